@@ -0,0 +1,22 @@
+// benches/stack_bench.rs
+//
+// Run with `cargo bench`. Set GIT_TRAIN_BENCH_BRANCHES to change the stack size.
+
+use anyhow::Result;
+use gittrain::bench::{print_report, run_benchmark};
+use gittrain::config::TrainConfig;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let branches = std::env::var("GIT_TRAIN_BENCH_BRANCHES")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(25);
+
+    let mut config = TrainConfig::default();
+    config.editor.default_editor = "true".to_string();
+
+    let report = run_benchmark(&config, branches).await?;
+    print_report(&report);
+    Ok(())
+}
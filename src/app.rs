@@ -1,10 +1,21 @@
 use anyhow::Result;
+use std::path::Path;
 
 use crate::{
-    cli::{Cli, Commands, ConfigCommands},
-    config::{ConfigManager, TrainConfig},
-    stack::StackManager,
-    ui,
+    bench,
+    bump::BumpLevel,
+    cli::{BranchCommands, Cli, Commands, ConfigCommands, MrCommands, ReviewNotesCommands},
+    config::{self, ConfigManager, MergeMethod, TrainConfig, UpdateStrategy},
+    conflict::ConflictResolver,
+    git::{self, GitRepository},
+    hooks::{self, HookInstall},
+    ids::MrIid,
+    init, serve,
+    stack::{
+        BranchRange, DeleteOptions, LandOptions, ListOptions, MrMergeOptions, PushOptions,
+        ReviewNoteAction, StackManager, SyncOptions,
+    },
+    ui, utils,
 };
 
 /// The main application context.
@@ -21,6 +32,7 @@ impl AppContext {
     pub fn new() -> Result<Self> {
         let config_manager = ConfigManager::new()?;
         let config = config_manager.get_config().clone();
+        git::set_command_timeout(config.timeouts.git());
         Ok(Self {
             config,
             config_manager,
@@ -31,14 +43,153 @@ impl AppContext {
     pub async fn run(&mut self, cli: Cli) -> Result<()> {
         match cli.command {
             Commands::Config(config_cmd) => self.handle_config_commands(&config_cmd).await,
+            Commands::Init => init::run_wizard(&mut self.config_manager).await,
             Commands::Health => {
                 let mut stack_manager = self.get_stack_manager().await?;
                 Self::handle_health_command(&mut stack_manager).await
             }
+            Commands::InstallHooks { force } => {
+                let git_repo = GitRepository::new_from_current_dir()?;
+                ui::print_train_header("Installing Git Hooks");
+                for (hook, outcome) in hooks::install(&git_repo, force)? {
+                    match outcome {
+                        HookInstall::Installed => ui::print_success(&format!("Installed {}", hook)),
+                        HookInstall::Updated => ui::print_success(&format!("Updated {}", hook)),
+                        HookInstall::Replaced => ui::print_success(&format!(
+                            "Installed {} (your hook was kept as {}.pre-git-train and still runs)",
+                            hook, hook
+                        )),
+                        HookInstall::Skipped => ui::print_warning(&format!(
+                            "Skipped {}: a hook not written by git-train is already there (use --force to chain it)",
+                            hook
+                        )),
+                    }
+                }
+                Ok(())
+            }
+            Commands::Conflicts { json } => {
+                // No stack or GitLab needed, so tools get clean output
+                let git_repo = GitRepository::new_from_current_dir()?;
+                let resolver = ConflictResolver::new(
+                    self.config.clone(),
+                    git_repo.git_dir().to_path_buf(),
+                    git_repo,
+                );
+                let report = resolver.conflict_report()?;
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&report)?);
+                    return Ok(());
+                }
+
+                ui::print_train_header("Conflicts");
+                let Some(operation) = &report.operation else {
+                    ui::print_info("No rebase, merge or cherry-pick in progress");
+                    return Ok(());
+                };
+                if report.files.is_empty() {
+                    ui::print_info(&format!("The {} has no unresolved conflicts", operation));
+                    return Ok(());
+                }
+                ui::print_warning(&format!(
+                    "{} conflicted files in the {}:",
+                    report.files.len(),
+                    operation
+                ));
+                for file in &report.files {
+                    ui::print_info(&format!("  📄 {} ({:?})", file.path, file.status));
+                }
+                ui::print_info("Resolve them, then run 'git-train continue'");
+                Ok(())
+            }
+            Commands::Hook { name } => {
+                // Hooks run unattended: no GitLab, no prompts
+                let git_repo = GitRepository::new_from_current_dir()?;
+                let mut stack_manager =
+                    StackManager::new_offline(self.config.clone(), git_repo).await?;
+                stack_manager.refresh_from_hook(&name)
+            }
+            Commands::Prompt => {
+                // Runs before every shell prompt: stay offline and silent, and leave
+                // repositories that never used git-train untouched
+                let Ok(git_repo) = GitRepository::new_from_current_dir() else {
+                    return Ok(());
+                };
+                if !git_repo.train_dir().exists() {
+                    return Ok(());
+                }
+                let stack_manager =
+                    StackManager::new_offline(self.config.clone(), git_repo).await?;
+                if let Some(summary) = stack_manager.prompt_summary() {
+                    println!("{}", summary);
+                }
+                Ok(())
+            }
+            Commands::Serve { socket } => {
+                // One manager for the whole session, so GitLab is only detected once
+                let socket = match socket {
+                    Some(socket) => socket,
+                    None => GitRepository::new_from_current_dir()?
+                        .git_dir()
+                        .join("git-train.sock"),
+                };
+                let stack_manager = self.get_stack_manager().await?;
+                serve::serve(stack_manager, &socket).await
+            }
+            Commands::Bench { branches } => {
+                let report = bench::run_benchmark(&self.config, branches).await?;
+                bench::print_report(&report);
+                Ok(())
+            }
             _ => {
+                // Commands that pick their own stack don't need HEAD reconciliation
+                let picks_own_stack = matches!(
+                    cli.command,
+                    Commands::Create { .. }
+                        | Commands::Switch { .. }
+                        | Commands::List { .. }
+                        | Commands::Delete { .. }
+                        | Commands::Recover { .. }
+                        | Commands::Sync { all: true, .. }
+                        | Commands::History { all: true, .. }
+                );
+                // Read-only commands neither wait for nor block commands that change branches.
+                // Plain status records MR states, so only watch (which locks each refresh's
+                // writes itself) skips the lock here
+                let read_only = matches!(
+                    cli.command,
+                    Commands::Status { watch: true, .. }
+                        | Commands::List { .. }
+                        | Commands::History { .. }
+                        | Commands::Summary { .. }
+                        | Commands::Env { .. }
+                        | Commands::Blame { .. }
+                );
                 let mut stack_manager = self.get_stack_manager().await?;
-                self.handle_stack_commands(cli.command, &mut stack_manager)
-                    .await
+                let _lock = if read_only {
+                    None
+                } else {
+                    let operation = std::env::args().skip(1).collect::<Vec<_>>().join(" ");
+                    Some(stack_manager.lock_operation(&operation, cli.steal_lock)?)
+                };
+                if let Some(target) = &cli.target_stack {
+                    if picks_own_stack {
+                        ui::print_error("--stack can't be combined with this command");
+                        return Ok(());
+                    }
+                    stack_manager.pin_stack(target)?;
+                } else if !picks_own_stack {
+                    if let Err(e) = stack_manager.follow_head_to_owning_stack() {
+                        ui::print_warning(&format!("Could not check which stack owns HEAD: {}", e));
+                    }
+                }
+                let result = self
+                    .handle_stack_commands(cli.command, &mut stack_manager)
+                    .await;
+                // Keep the stack's current branch in step with HEAD, even after failures
+                if let Err(e) = stack_manager.record_current_branch() {
+                    ui::print_warning(&format!("Could not record current branch: {}", e));
+                }
+                result
             }
         }
     }
@@ -55,19 +206,290 @@ impl AppContext {
         stack_manager: &mut StackManager,
     ) -> Result<()> {
         match command {
-            Commands::Create { name } => stack_manager.create_stack(&name).await,
-            Commands::Commit { message } => stack_manager.commit_changes(&message).await,
-            Commands::Amend { message } => stack_manager.amend_changes(message.as_deref()).await,
+            Commands::Create { name, base } => {
+                stack_manager
+                    .create_stack_with_base(&name, base.as_deref())
+                    .await
+            }
+            Commands::ForkStack {
+                name,
+                prefix,
+                suffix,
+            } => {
+                stack_manager
+                    .fork_stack(&name, prefix.as_deref(), suffix.as_deref())
+                    .await
+            }
+            Commands::Commit {
+                message,
+                file,
+                reword,
+            } => {
+                let message = message_or_file(message, file.as_deref())?;
+                stack_manager
+                    .commit_changes(message.as_deref(), reword)
+                    .await
+            }
+            Commands::Amend {
+                message,
+                file,
+                branch,
+            } => {
+                let message = message_or_file(message, file.as_deref())?;
+                match branch {
+                    Some(branch) => {
+                        stack_manager
+                            .amend_branch(&branch, message.as_deref())
+                            .await
+                    }
+                    None => stack_manager.amend_changes(message.as_deref()).await,
+                }
+            }
             Commands::Add { parent } => stack_manager.add_branch_to_stack(parent.as_deref()).await,
-            Commands::Status => stack_manager.show_status().await,
-            Commands::List => stack_manager.list_stacks().await,
-            Commands::Switch { stack } => stack_manager.switch_stack(&stack).await,
+            Commands::Branch(BranchCommands::Create {
+                description,
+                parent,
+            }) => {
+                stack_manager
+                    .create_branch(&description, parent.as_deref())
+                    .await
+            }
+            Commands::Branch(BranchCommands::Freeze { name }) => {
+                stack_manager.set_branch_frozen(&name, true).await
+            }
+            Commands::Branch(BranchCommands::Unfreeze { name }) => {
+                stack_manager.set_branch_frozen(&name, false).await
+            }
+            Commands::Branch(BranchCommands::Pause { name }) => {
+                stack_manager.set_branch_paused(&name, true).await
+            }
+            Commands::Branch(BranchCommands::Resume { name }) => {
+                stack_manager.set_branch_paused(&name, false).await
+            }
+            Commands::Branch(BranchCommands::Private { name }) => {
+                stack_manager.set_branch_private(&name).await
+            }
+            Commands::Publish { branch } => stack_manager.publish_branch(&branch).await,
+            Commands::Bump { level, no_push } => {
+                let Some(level) = BumpLevel::from_name(&level) else {
+                    ui::print_error("Invalid bump level. Use 'patch', 'minor' or 'major'");
+                    return Ok(());
+                };
+                stack_manager.bump_version(level, no_push).await
+            }
+            Commands::Pick { commit, onto } => {
+                stack_manager.pick_commit(&commit, onto.as_deref()).await
+            }
+            Commands::Edit { branch } => stack_manager.edit_branch(branch.as_deref()).await,
+            Commands::Bisect { test, top } => stack_manager
+                .bisect_stack(&test, top.as_deref())
+                .await
+                .map(|_| ()),
+            Commands::Verify { test } => stack_manager
+                .verify_stack(test.as_deref())
+                .await
+                .map(|_| ()),
+            Commands::Blame { file, branch } => stack_manager
+                .blame_file(&file, branch.as_deref())
+                .await
+                .map(|_| ()),
+            Commands::Continue => stack_manager.continue_conflict_resolution().await,
+            Commands::Status { watch: false, .. } => stack_manager.show_status().await,
+            Commands::Status {
+                watch: true,
+                interval,
+            } => {
+                stack_manager
+                    .watch_status(std::time::Duration::from_secs(interval))
+                    .await
+            }
+            Commands::List {
+                mine,
+                author,
+                archived,
+            } => {
+                stack_manager
+                    .list_stacks(&ListOptions {
+                        mine,
+                        author,
+                        archived,
+                    })
+                    .await
+            }
+            Commands::Switch {
+                stack,
+                checkout,
+                no_checkout,
+            } => {
+                let checkout = match (checkout, no_checkout) {
+                    (true, _) => Some(true),
+                    (_, true) => Some(false),
+                    _ => None,
+                };
+                stack_manager.switch_stack(&stack, checkout).await
+            }
             Commands::Navigate => stack_manager.navigate_stack_interactively().await,
-            Commands::Delete { stack, force } => stack_manager.delete_stack(&stack, force).await,
-            Commands::Push => stack_manager.push_stack().await,
-            Commands::Sync => stack_manager.sync_with_remote().await,
+            Commands::Top => stack_manager.checkout_top().await.map(|_| ()),
+            Commands::Bottom => stack_manager.checkout_bottom().await.map(|_| ()),
+            Commands::Delete {
+                stack,
+                force,
+                branches,
+                close_mrs,
+                dry_run,
+            } => {
+                stack_manager
+                    .delete_stack(
+                        &stack,
+                        &DeleteOptions {
+                            force,
+                            branches,
+                            close_mrs,
+                            dry_run,
+                        },
+                    )
+                    .await
+            }
+            Commands::Push {
+                template,
+                no_mr,
+                milestone,
+                up_to,
+                only,
+                sync,
+            } => {
+                stack_manager
+                    .push_stack_with_options(&PushOptions {
+                        template,
+                        no_mr,
+                        milestone,
+                        range: branch_range(up_to, only),
+                        sync,
+                    })
+                    .await
+            }
+            Commands::Mr(MrCommands::UpdateAll { template }) => {
+                stack_manager.update_all_mrs(template.as_deref()).await
+            }
+            Commands::Mr(MrCommands::Merge {
+                branch,
+                method,
+                delete_source_branch,
+                squash_message,
+            }) => {
+                let method = match method.as_deref().map(MergeMethod::from_name) {
+                    None => None,
+                    Some(Some(method)) => Some(method),
+                    Some(None) => {
+                        ui::print_error("Invalid merge method. Use 'merge', 'squash' or 'rebase'");
+                        return Ok(());
+                    }
+                };
+                stack_manager
+                    .merge_branch_mr(
+                        &branch,
+                        &MrMergeOptions {
+                            method,
+                            delete_source_branch,
+                            squash_message,
+                        },
+                    )
+                    .await
+            }
+            Commands::Land {
+                squash_all,
+                per_level,
+                squash,
+                delete_source_branch,
+            } => {
+                stack_manager
+                    .land_stack(&LandOptions {
+                        squash_all,
+                        per_level,
+                        squash,
+                        delete_source_branch,
+                    })
+                    .await
+            }
+            Commands::Template {
+                name,
+                branch,
+                clear,
+            } => {
+                stack_manager
+                    .set_mr_template(name.as_deref(), branch.as_deref(), clear)
+                    .await
+            }
+            Commands::UpdateStrategy { strategy } => {
+                let strategy = match strategy.as_deref().map(UpdateStrategy::from_name) {
+                    None => None,
+                    Some(Some(strategy)) => Some(strategy),
+                    Some(None) => {
+                        ui::print_error("Invalid strategy. Use 'rebase' or 'merge'");
+                        return Ok(());
+                    }
+                };
+                stack_manager.set_update_strategy(strategy).await
+            }
+            Commands::SetBase { branch } => stack_manager.move_stack_base(&branch).await,
+            Commands::ReviewNotes { branch, action } => {
+                let action = match action.unwrap_or(ReviewNotesCommands::Show) {
+                    ReviewNotesCommands::Show => ReviewNoteAction::Show,
+                    ReviewNotesCommands::Add { text, todo } => ReviewNoteAction::Add { text, todo },
+                    ReviewNotesCommands::Todo { text } => {
+                        ReviewNoteAction::Add { text, todo: true }
+                    }
+                    ReviewNotesCommands::Check { number } => ReviewNoteAction::Check(number),
+                    ReviewNotesCommands::Uncheck { number } => ReviewNoteAction::Uncheck(number),
+                    ReviewNotesCommands::Remove { number } => ReviewNoteAction::Remove(number),
+                    ReviewNotesCommands::Clear => ReviewNoteAction::Clear,
+                };
+                stack_manager.review_notes(action, branch.as_deref()).await
+            }
+            Commands::Sync {
+                all,
+                prune,
+                up_to,
+                only,
+                when_behind,
+            } => {
+                let options = SyncOptions {
+                    prune,
+                    range: branch_range(up_to, only),
+                    when_behind,
+                };
+                if all {
+                    stack_manager.sync_all_stacks(&options).await
+                } else {
+                    stack_manager.sync_with_remote_with_options(&options).await
+                }
+            }
+            Commands::History { limit, all } => stack_manager.show_history(limit, all).await,
+            Commands::Env { output } => stack_manager.debug_dump(output.as_deref()).await,
+            Commands::Summary { output } => stack_manager
+                .summarize_stack(output.as_deref())
+                .await
+                .map(|_| ()),
+            Commands::Restore { dry_run } => stack_manager.restore_branches(dry_run).await,
+            Commands::Recover { from_mr, name } => {
+                stack_manager
+                    .recover_from_mr(MrIid::from(from_mr), name.as_deref())
+                    .await
+            }
+            Commands::Repair { restore, remove } => {
+                stack_manager.repair_stack(restore, remove).await
+            }
+            Commands::Undo => stack_manager.undo_mr_update().await,
             // These are handled in run()
-            Commands::Config(_) | Commands::Health => Ok(()),
+            Commands::Config(_)
+            | Commands::Health
+            | Commands::InstallHooks { .. }
+            | Commands::Hook { .. }
+            | Commands::Prompt
+            | Commands::Conflicts { .. }
+            | Commands::Init
+            | Commands::Serve { .. }
+            | Commands::Bench { .. } => Ok(()),
         }
     }
 
@@ -101,11 +523,181 @@ impl AppContext {
                         .auto_force_push_after_rebase
                         .to_string(),
                 );
+                let path_rules: Vec<String> = config
+                    .conflict_resolution
+                    .path_rules
+                    .iter()
+                    .map(|(pattern, resolution)| format!("{} = {}", pattern, resolution.as_str()))
+                    .collect();
+                ui::print_config_item(
+                    "Conflict path rules",
+                    &if path_rules.is_empty() {
+                        "none".to_string()
+                    } else {
+                        path_rules.join(", ")
+                    },
+                );
                 ui::print_config_item("Auto-stash", &config.git.auto_stash.to_string());
+                ui::print_config_item(
+                    "Checkout on switch",
+                    &config.git.checkout_on_switch.to_string(),
+                );
+                ui::print_config_item(
+                    "Auto-switch stack",
+                    &format!("{:?}", config.git.auto_switch_stack),
+                );
+                ui::print_config_item(
+                    "Merge commits in stack levels",
+                    &format!("{:?}", config.git.merge_commits),
+                );
+                ui::print_config_item(
+                    "Commit identity",
+                    &config.git.commit_identity.as_ref().map_or_else(
+                        || "git user.name/user.email".to_string(),
+                        |identity| match (identity.name.as_str(), identity.email.as_str()) {
+                            (name, "") => format!("{} (git user.email)", name),
+                            ("", email) => format!("git user.name <{}>", email),
+                            (name, email) => format!("{} <{}>", name, email),
+                        },
+                    ),
+                );
+                ui::print_config_item(
+                    "Update strategy for new stacks",
+                    config.git.update_strategy.as_str(),
+                );
+                ui::print_config_item("Commit trailers", &config.git.commit_trailers.to_string());
+                ui::print_config_item("Set upstream on push", &config.git.set_upstream.to_string());
+                ui::print_config_item(
+                    "Base branch candidates",
+                    &config.git.base_branch_candidates.join(", "),
+                );
                 ui::print_config_item(
                     "Default rebase strategy",
                     &format!("{:?}", config.git.default_rebase_strategy),
                 );
+                ui::print_config_item(
+                    "Default MR template",
+                    config.mr.default_template.as_deref().unwrap_or("(none)"),
+                );
+                ui::print_config_item("MR title sync", config.mr.title_sync.as_str());
+                ui::print_config_item("MR merge method", config.mr.merge_method.as_str());
+                ui::print_config_item("MR dependency links", config.mr.dependency_links.as_str());
+                ui::print_config_item(
+                    "MR landing instructions",
+                    if config.mr.landing_instructions {
+                        "yes"
+                    } else {
+                        "no"
+                    },
+                );
+                ui::print_config_item(
+                    "Delete source branch on merge",
+                    if config.mr.delete_source_branch {
+                        "yes"
+                    } else {
+                        "no"
+                    },
+                );
+                ui::print_config_item(
+                    "Archive fully merged stacks",
+                    if config.mr.archive_merged_stacks {
+                        "yes"
+                    } else {
+                        "no"
+                    },
+                );
+                ui::print_config_item(
+                    "Default MR milestone",
+                    config.mr.default_milestone.as_deref().unwrap_or("(none)"),
+                );
+                ui::print_config_item(
+                    "Sync review notes to MRs",
+                    &config.mr.sync_review_notes.to_string(),
+                );
+                ui::print_config_item(
+                    "Max changed lines per level",
+                    &config.mr.max_changed_lines.to_string(),
+                );
+                ui::print_config_item(
+                    "Max changed files per level",
+                    &config.mr.max_changed_files.to_string(),
+                );
+                ui::print_config_item(
+                    "CODEOWNERS reviewers",
+                    &config.mr.codeowner_reviewers.to_string(),
+                );
+                ui::print_config_item("Sync history", &config.history.enabled.to_string());
+                ui::print_config_item(
+                    "Branch naming scheme",
+                    config.branch.name_template.as_deref().unwrap_or("(none)"),
+                );
+                ui::print_config_item(
+                    "Branch name enforcement",
+                    &format!("{:?}", config.branch.enforcement),
+                );
+                ui::print_config_item(
+                    "Name sanitization",
+                    &format!(
+                        "{} letters, '{}' replacement, {} max length, slashes {}",
+                        config.naming.charset.as_str(),
+                        config.naming.replacement,
+                        match config.naming.max_length {
+                            0 => "no".to_string(),
+                            max => max.to_string(),
+                        },
+                        if config.naming.preserve_slashes {
+                            "kept"
+                        } else {
+                            "replaced"
+                        }
+                    ),
+                );
+                let columns: Vec<&str> = config
+                    .stack_table
+                    .columns
+                    .iter()
+                    .map(|c| c.as_str())
+                    .collect();
+                ui::print_config_item(
+                    "Stack table columns",
+                    &if columns.is_empty() {
+                        "(none)".to_string()
+                    } else {
+                        columns.join(", ")
+                    },
+                );
+                ui::print_config_item(
+                    "Stack table badges",
+                    &format!("{:?}", config.stack_table.badges),
+                );
+                ui::print_config_item(
+                    "Compact stack table up to",
+                    &format!("{} branches", config.stack_table.compact_max_branches),
+                );
+                let seconds = |secs: u64| match secs {
+                    0 => "none".to_string(),
+                    secs => format!("{}s", secs),
+                };
+                ui::print_config_item("Git command timeout", &seconds(config.timeouts.git_secs));
+                ui::print_config_item(
+                    "GitLab request timeout",
+                    &seconds(config.timeouts.http_secs),
+                );
+                ui::print_config_item(
+                    "Verify command",
+                    config.verify.command.as_deref().unwrap_or("(none)"),
+                );
+                ui::print_config_item(
+                    "Sync before push",
+                    if config.push.auto_sync { "yes" } else { "no" },
+                );
+                ui::print_config_item(
+                    "Restack when the base is ahead by more than",
+                    &match config.sync.when_behind {
+                        0 => "0 commits (always)".to_string(),
+                        n => format!("{} commits, or on overlapping changes", n),
+                    },
+                );
             }
             ConfigCommands::Setup => {
                 self.config_manager.configure_interactive()?;
@@ -166,6 +758,260 @@ impl AppContext {
                     _ => {}
                 }
             }
+            ConfigCommands::SetAutoSwitch { mode } => {
+                use crate::config::AutoSwitchMode;
+
+                let new_mode = match mode.to_lowercase().as_str() {
+                    "never" => AutoSwitchMode::Never,
+                    "prompt" => AutoSwitchMode::Prompt,
+                    "always" => AutoSwitchMode::Always,
+                    _ => {
+                        ui::print_error("Invalid mode. Use 'never', 'prompt', or 'always'");
+                        return Ok(());
+                    }
+                };
+
+                self.config_manager.update_config(|config| {
+                    config.git.auto_switch_stack = new_mode;
+                })?;
+
+                ui::print_success(&format!("Set stack auto-switch to: {}", mode));
+            }
+            ConfigCommands::SetUpdateStrategy { strategy } => {
+                let Some(new_strategy) = UpdateStrategy::from_name(strategy) else {
+                    ui::print_error("Invalid strategy. Use 'rebase' or 'merge'");
+                    return Ok(());
+                };
+
+                self.config_manager.update_config(|config| {
+                    config.git.update_strategy = new_strategy;
+                })?;
+
+                ui::print_success(&format!(
+                    "Set update strategy for new stacks to: {}",
+                    new_strategy.as_str()
+                ));
+                ui::print_info(
+                    "Existing stacks keep their strategy; change one with 'git-train update-strategy'",
+                );
+            }
+            ConfigCommands::SetBranchTemplate { template } => {
+                let new_template = match template.as_str() {
+                    "none" | "" => None,
+                    template if !template.contains("{slug}") => {
+                        ui::print_error("Template must contain {slug}");
+                        return Ok(());
+                    }
+                    template => Some(template.to_string()),
+                };
+
+                self.config_manager.update_config(|config| {
+                    config.branch.name_template = new_template.clone();
+                })?;
+
+                match new_template {
+                    Some(template) => {
+                        ui::print_success(&format!("Set branch naming scheme to: {}", template))
+                    }
+                    None => ui::print_success("Cleared branch naming scheme"),
+                }
+            }
+            ConfigCommands::SetBranchEnforcement { mode } => {
+                use crate::config::NameEnforcement;
+
+                let new_mode = match mode.to_lowercase().as_str() {
+                    "warn" => NameEnforcement::Warn,
+                    "reject" => NameEnforcement::Reject,
+                    "fix" => NameEnforcement::Fix,
+                    _ => {
+                        ui::print_error("Invalid mode. Use 'warn', 'reject', or 'fix'");
+                        return Ok(());
+                    }
+                };
+
+                self.config_manager.update_config(|config| {
+                    config.branch.enforcement = new_mode;
+                })?;
+
+                ui::print_success(&format!("Set branch name enforcement to: {}", mode));
+            }
+            ConfigCommands::SetTableColumns { columns } => {
+                use crate::config::TableColumn;
+
+                let mut new_columns = Vec::new();
+                if !matches!(columns.as_str(), "none" | "") {
+                    for name in columns.split(',').map(str::trim) {
+                        match TableColumn::from_name(name) {
+                            Some(column) if !new_columns.contains(&column) => {
+                                new_columns.push(column)
+                            }
+                            Some(_) => {}
+                            None => {
+                                ui::print_error(&format!(
+                                    "Invalid column '{}'. Use 'state', 'pipeline', 'target', 'commits', or 'milestone'",
+                                    name
+                                ));
+                                return Ok(());
+                            }
+                        }
+                    }
+                }
+
+                self.config_manager.update_config(|config| {
+                    config.stack_table.columns = new_columns.clone();
+                })?;
+
+                if new_columns.is_empty() {
+                    ui::print_success("Cleared extra stack table columns");
+                } else {
+                    let names: Vec<&str> = new_columns.iter().map(|c| c.as_str()).collect();
+                    ui::print_success(&format!("Set stack table columns to: {}", names.join(", ")));
+                }
+            }
+            ConfigCommands::SetBaseBranches { branches } => {
+                let candidates: Vec<String> = branches
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                if candidates.is_empty() {
+                    ui::print_error("Give at least one branch name or glob, e.g. 'main,master'");
+                    return Ok(());
+                }
+
+                self.config_manager.update_config(|config| {
+                    config.git.base_branch_candidates = candidates.clone();
+                })?;
+                ui::print_success(&format!(
+                    "Set base branch candidates to: {}",
+                    candidates.join(", ")
+                ));
+            }
+            ConfigCommands::SetTableBadges { style } => {
+                use crate::config::BadgeStyle;
+
+                let new_style = match style.to_lowercase().as_str() {
+                    "emoji" => BadgeStyle::Emoji,
+                    "text" => BadgeStyle::Text,
+                    _ => {
+                        ui::print_error("Invalid style. Use 'emoji' or 'text'");
+                        return Ok(());
+                    }
+                };
+
+                self.config_manager.update_config(|config| {
+                    config.stack_table.badges = new_style;
+                })?;
+
+                ui::print_success(&format!("Set stack table badges to: {}", style));
+            }
+            ConfigCommands::SetTableCompact { max_branches } => {
+                let max_branches = *max_branches;
+                self.config_manager.update_config(|config| {
+                    config.stack_table.compact_max_branches = max_branches;
+                })?;
+
+                if max_branches == 0 {
+                    ui::print_success("Stack tables will always use the full layout");
+                } else {
+                    ui::print_success(&format!(
+                        "Stacks with up to {} branches will use the single-line layout",
+                        max_branches
+                    ));
+                }
+            }
+            ConfigCommands::SetSizeLimit { lines, files } => {
+                if lines.is_none() && files.is_none() {
+                    ui::print_error("Give --lines and/or --files");
+                    return Ok(());
+                }
+
+                self.config_manager.update_config(|config| {
+                    if let Some(lines) = lines {
+                        config.mr.max_changed_lines = *lines;
+                    }
+                    if let Some(files) = files {
+                        config.mr.max_changed_files = *files;
+                    }
+                })?;
+
+                let config = self.config_manager.get_config();
+                ui::print_success(&format!(
+                    "Levels over {} lines or {} files will be flagged",
+                    config.mr.max_changed_lines, config.mr.max_changed_files
+                ));
+            }
+            ConfigCommands::SetCodeownerReviewers { count } => {
+                let count = *count;
+                self.config_manager.update_config(|config| {
+                    config.mr.codeowner_reviewers = count;
+                })?;
+
+                if count == 0 {
+                    ui::print_success("New MRs will not request reviews from CODEOWNERS");
+                } else {
+                    ui::print_success(&format!(
+                        "New MRs will request reviews from up to {} CODEOWNERS",
+                        count
+                    ));
+                }
+            }
+            ConfigCommands::SetHistory { mode } => {
+                let enabled = match mode.to_lowercase().as_str() {
+                    "on" => true,
+                    "off" => false,
+                    _ => {
+                        ui::print_error("Invalid mode. Use 'on' or 'off'");
+                        return Ok(());
+                    }
+                };
+
+                self.config_manager.update_config(|config| {
+                    config.history.enabled = enabled;
+                })?;
+
+                ui::print_success(&format!("Set sync history to: {}", mode));
+                if enabled {
+                    ui::print_info(
+                        "Sync snapshots are stored locally in .git/train/history.jsonl and never sent anywhere",
+                    );
+                }
+            }
+            ConfigCommands::SetCommitTrailers { mode } => {
+                let enabled = match mode.to_lowercase().as_str() {
+                    "on" => true,
+                    "off" => false,
+                    _ => {
+                        ui::print_error("Invalid mode. Use 'on' or 'off'");
+                        return Ok(());
+                    }
+                };
+
+                self.config_manager.update_config(|config| {
+                    config.git.commit_trailers = enabled;
+                })?;
+
+                ui::print_success(&format!("Set commit trailers to: {}", mode));
+            }
+            ConfigCommands::Get { key } => match self.config_manager.get_value(key) {
+                Ok(value) => println!("{}", value),
+                Err(e) => ui::print_error(&e.to_string()),
+            },
+            ConfigCommands::Set { key, value } => {
+                if let Err(e) = self.config_manager.set_value(key, value) {
+                    ui::print_error(&e.to_string());
+                    return Ok(());
+                }
+                let value = self.config_manager.get_value(key)?;
+                ui::print_success(&format!("Set {} to: {}", key, value));
+            }
+            ConfigCommands::Keys => {
+                ui::print_train_header("Config Keys");
+                for (key, value) in config::config_keys(self.config_manager.get_config()) {
+                    ui::print_config_item(&key, &value);
+                }
+            }
         }
         Ok(())
     }
@@ -235,6 +1081,37 @@ impl AppContext {
                         ui::print_info("You can add it with: git-train add");
                     }
                 }
+
+                let deleted = stack_manager.deleted_local_branches(&stack);
+                if !deleted.is_empty() {
+                    ui::print_warning(&format!(
+                        "⚠️ Stack branches deleted locally: {}",
+                        deleted.join(", ")
+                    ));
+                    ui::print_info("Restore or remove them with: git-train repair");
+                }
+
+                for retarget in stack_manager.find_unexpected_retargets(&stack).await {
+                    ui::print_warning(&format!(
+                        "⚠️ MR !{} for '{}' was retargeted to '{}' outside git-train (expected '{}')",
+                        retarget.mr_iid, retarget.branch, retarget.actual, retarget.expected
+                    ));
+                }
+
+                // Check for MRs opened outside git-train
+                match stack_manager.find_untracked_mrs(&stack).await {
+                    Ok(untracked) => {
+                        for (branch, mr) in untracked {
+                            ui::print_warning(&format!(
+                                "⚠️ Branch '{}' has open MR !{} that the stack does not track",
+                                branch, mr.iid
+                            ));
+                        }
+                    }
+                    Err(e) => {
+                        ui::print_warning(&format!("⚠️ Could not query merge requests: {}", e));
+                    }
+                }
             }
             Err(_) => {
                 ui::print_warning("⚠️ No active stack found");
@@ -259,3 +1136,19 @@ impl AppContext {
         Ok(())
     }
 }
+
+/// The stack range selected by `--up-to`/`--only` (clap keeps them exclusive)
+fn branch_range(up_to: Option<String>, only: Option<String>) -> Option<BranchRange> {
+    up_to
+        .map(BranchRange::UpTo)
+        .or_else(|| only.as_deref().map(BranchRange::only))
+}
+
+/// The commit message given with `-m`, or read from `-F` (clap keeps them exclusive).
+/// Neither means the editor opens.
+fn message_or_file(message: Option<String>, file: Option<&Path>) -> Result<Option<String>> {
+    match file {
+        Some(path) => utils::read_message_file(path).map(Some),
+        None => Ok(message),
+    }
+}
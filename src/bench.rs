@@ -0,0 +1,139 @@
+use anyhow::Result;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use crate::config::TrainConfig;
+use crate::errors::TrainError;
+use crate::git::GitRepository;
+use crate::stack::StackManager;
+use crate::ui;
+
+/// Timings collected for a single synthetic benchmark run.
+#[derive(Debug, Clone)]
+pub struct BenchReport {
+    pub branches: usize,
+    pub timings: Vec<(String, Duration)>,
+}
+
+/// Generate a synthetic stack of `branches` levels in a temporary repository and
+/// measure how long the main stack operations take against a local bare remote.
+pub async fn run_benchmark(config: &TrainConfig, branches: usize) -> Result<BenchReport> {
+    let workspace = tempfile::tempdir()?;
+    let local_path = workspace.path().join("local");
+    let remote_path = workspace.path().join("remote.git");
+    fs::create_dir_all(&local_path)?;
+    fs::create_dir_all(&remote_path)?;
+
+    init_repo(&local_path, &remote_path)?;
+    let repo = GitRepository::new(&local_path)?;
+
+    // Benchmarks must never block on prompts
+    let mut config = config.clone();
+    config.conflict_resolution.auto_force_push_after_rebase = true;
+    config.conflict_resolution.prompt_before_force_push = false;
+
+    let mut stack_manager = StackManager::new_offline(config, repo.clone()).await?;
+    let mut timings = Vec::new();
+
+    let started = Instant::now();
+    for level in 1..=branches.max(1) {
+        let branch_name = format!("bench-level-{}", level);
+        repo.run(&["checkout", "-b", &branch_name])?;
+        let file_name = format!("level-{}.txt", level);
+        fs::write(local_path.join(&file_name), format!("content {}\n", level))?;
+        repo.run(&["add", &file_name])?;
+        repo.run(&["commit", "-m", &format!("bench: add level {}", level)])?;
+
+        if level == 1 {
+            stack_manager.create_stack("bench").await?;
+        } else {
+            let parent = format!("bench-level-{}", level - 1);
+            stack_manager.add_branch_to_stack(Some(&parent)).await?;
+        }
+    }
+    timings.push(("build".to_string(), started.elapsed()));
+
+    let started = Instant::now();
+    stack_manager.show_status().await?;
+    timings.push(("status".to_string(), started.elapsed()));
+
+    let started = Instant::now();
+    stack_manager.push_stack().await?;
+    timings.push(("push".to_string(), started.elapsed()));
+
+    // Advance the base so sync has to restack every level
+    let top_branch = repo.get_current_branch()?;
+    repo.run(&["checkout", "main"])?;
+    fs::write(local_path.join("base.txt"), "base moved\n")?;
+    repo.run(&["add", "base.txt"])?;
+    repo.run(&["commit", "-m", "bench: advance base"])?;
+    repo.run(&["push", "origin", "main"])?;
+    repo.run(&["checkout", &top_branch])?;
+
+    let started = Instant::now();
+    stack_manager.sync_with_remote().await?;
+    timings.push(("sync".to_string(), started.elapsed()));
+
+    let started = Instant::now();
+    stack_manager.push_stack().await?;
+    timings.push(("push (after sync)".to_string(), started.elapsed()));
+
+    Ok(BenchReport {
+        branches: branches.max(1),
+        timings,
+    })
+}
+
+/// Print a benchmark report in the same key/value style as `config show`.
+pub fn print_report(report: &BenchReport) {
+    ui::print_train_header(&format!("Benchmark: {} branches", report.branches));
+    for (phase, duration) in &report.timings {
+        let per_branch = duration.as_secs_f64() * 1000.0 / report.branches as f64;
+        ui::print_config_item(
+            phase,
+            &format!(
+                "{:.1} ms ({:.1} ms/branch)",
+                duration.as_secs_f64() * 1000.0,
+                per_branch
+            ),
+        );
+    }
+}
+
+fn init_repo(local_path: &Path, remote_path: &Path) -> Result<()> {
+    // A fixture that failed to build would make every timing meaningless
+    let git = |args: &[&str], cwd: &Path| -> Result<()> {
+        let output = Command::new("git").args(args).current_dir(cwd).output()?;
+        if !output.status.success() {
+            return Err(TrainError::GitError {
+                message: format!(
+                    "git {} failed while setting up the benchmark: {}",
+                    args.join(" "),
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            }
+            .into());
+        }
+        Ok(())
+    };
+
+    git(&["init", "--bare"], remote_path)?;
+    git(&["init", "-b", "main"], local_path)?;
+    git(
+        &["config", "user.email", "bench@git-train.local"],
+        local_path,
+    )?;
+    git(&["config", "user.name", "git-train bench"], local_path)?;
+    git(
+        &["commit", "--allow-empty", "-m", "initial commit"],
+        local_path,
+    )?;
+    git(
+        &["remote", "add", "origin", &remote_path.to_string_lossy()],
+        local_path,
+    )?;
+    git(&["push", "-u", "origin", "main"], local_path)?;
+    Ok(())
+}
@@ -0,0 +1,147 @@
+//! `git-train bump`: finding and rewriting the version field of manifests such as
+//! `Cargo.toml` and `package.json` for a release level on top of a stack.
+
+use regex::Regex;
+use std::fmt;
+use std::ops::Range;
+
+/// Which part of a `major.minor.patch` version a bump increments
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BumpLevel {
+    Patch,
+    Minor,
+    Major,
+}
+
+impl BumpLevel {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "patch" => Some(BumpLevel::Patch),
+            "minor" => Some(BumpLevel::Minor),
+            "major" => Some(BumpLevel::Major),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BumpLevel::Patch => "patch",
+            BumpLevel::Minor => "minor",
+            BumpLevel::Major => "major",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl Version {
+    /// Parse `1.2.3`, ignoring a pre-release or build suffix such as `-rc.1`
+    pub fn parse(text: &str) -> Option<Self> {
+        let core = text.split(['-', '+']).next()?;
+        let mut parts = core.split('.').map(|part| part.parse::<u64>().ok());
+        let version = Version {
+            major: parts.next()??,
+            minor: parts.next()??,
+            patch: parts.next()??,
+        };
+        parts.next().is_none().then_some(version)
+    }
+
+    /// The next release at `level`; a pre-release suffix is dropped along the way
+    pub fn bump(&self, level: BumpLevel) -> Self {
+        match level {
+            BumpLevel::Patch => Version {
+                patch: self.patch + 1,
+                ..*self
+            },
+            BumpLevel::Minor => Version {
+                major: self.major,
+                minor: self.minor + 1,
+                patch: 0,
+            },
+            BumpLevel::Major => Version {
+                major: self.major + 1,
+                minor: 0,
+                patch: 0,
+            },
+        }
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Byte range of the version string in a manifest, picked by its file name: the
+/// `version` of `[package]` or `[workspace.package]` in TOML files, the first
+/// `"version"` key in JSON files
+fn version_range(file_name: &str, contents: &str) -> Option<Range<usize>> {
+    if file_name.ends_with(".json") {
+        let re = Regex::new(r#""version"\s*:\s*"([^"]*)""#).ok()?;
+        return re.captures(contents)?.get(1).map(|m| m.range());
+    }
+    if !file_name.ends_with(".toml") {
+        return None;
+    }
+
+    let re = Regex::new(r#"^\s*version\s*=\s*"([^"]*)""#).ok()?;
+    let mut in_package = false;
+    let mut offset = 0;
+    for line in contents.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            in_package = trimmed == "[package]" || trimmed == "[workspace.package]";
+        } else if in_package {
+            if let Some(version) = re.captures(line).and_then(|c| c.get(1)) {
+                return Some(offset + version.start()..offset + version.end());
+            }
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// The version a manifest declares, as written
+pub fn read_version<'a>(file_name: &str, contents: &'a str) -> Option<&'a str> {
+    version_range(file_name, contents).map(|range| &contents[range])
+}
+
+/// `contents` with its version field replaced by `version`, everything else untouched
+pub fn write_version(file_name: &str, contents: &str, version: &Version) -> Option<String> {
+    let range = version_range(file_name, contents)?;
+    let mut updated = contents.to_string();
+    updated.replace_range(range, &version.to_string());
+    Some(updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bump_manifest_versions() {
+        let version = Version::parse("1.4.2-rc.1").unwrap();
+        assert_eq!(version.bump(BumpLevel::Patch).to_string(), "1.4.3");
+        assert_eq!(version.bump(BumpLevel::Minor).to_string(), "1.5.0");
+        assert_eq!(version.bump(BumpLevel::Major).to_string(), "2.0.0");
+        assert!(Version::parse("1.4").is_none());
+
+        // Dependency versions and other tables are left alone
+        let cargo = "[dependencies]\nserde = { version = \"1.0\" }\n\n[package]\nname = \"app\"\nversion = \"0.3.9\" # released\n";
+        assert_eq!(read_version("Cargo.toml", cargo), Some("0.3.9"));
+        let bumped = write_version("Cargo.toml", cargo, &Version::parse("0.4.0").unwrap());
+        assert_eq!(bumped.unwrap(), cargo.replace("0.3.9", "0.4.0"));
+
+        let package =
+            "{\n  \"name\": \"app\",\n  \"version\": \"2.0.1\",\n  \"dependencies\": {}\n}\n";
+        assert_eq!(read_version("web/package.json", package), Some("2.0.1"));
+        assert_eq!(read_version("VERSION.txt", "1.0.0"), None);
+    }
+}
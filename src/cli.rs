@@ -1,25 +1,91 @@
-use clap::{Parser, Subcommand};
+use clap::{ArgAction, Parser, Subcommand};
+use std::path::PathBuf;
+
+use crate::ui::Verbosity;
 
 #[derive(Parser)]
 #[command(name = "git-train", version, about = "Simple stack diff CLI tool")]
 pub struct Cli {
+    /// Show more detail (-v logs every git command with timings, -vv enables debug logs)
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Only print warnings, errors and command results
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Emit newline-delimited JSON progress events on stderr during sync and push
+    #[arg(long, global = true)]
+    pub progress_json: bool,
+
+    /// Operate on this stack (name or id prefix) without changing the active stack
+    #[arg(long = "stack", global = true, value_name = "STACK")]
+    pub target_stack: Option<String>,
+
+    /// Run as if git-train was started in this directory (like `git -C`)
+    #[arg(long, global = true, value_name = "PATH")]
+    pub cwd: Option<PathBuf>,
+
+    /// Take over the repository lock of a git-train command that is no longer running
+    #[arg(long, global = true)]
+    pub steal_lock: bool,
+
     #[command(subcommand)]
     pub command: Commands,
 }
 
+impl Cli {
+    pub fn verbosity(&self) -> Verbosity {
+        match (self.quiet, self.verbose) {
+            (true, _) => Verbosity::Quiet,
+            (false, 0) => Verbosity::Normal,
+            (false, 1) => Verbosity::Verbose,
+            (false, _) => Verbosity::Debug,
+        }
+    }
+}
+
 #[derive(Subcommand)]
 pub enum Commands {
     /// Create a new stack from current branch
     Create {
         /// Stack name
         name: String,
+        /// Base branch, local or remote-only (e.g. 'main' or 'origin/main'); detected if omitted
+        #[arg(short, long)]
+        base: Option<String>,
+    },
+
+    /// Duplicate the current stack under a new name, with new branches at the same commits
+    ForkStack {
+        /// Name of the new stack
+        name: String,
+        /// Name the copied branches `<prefix><branch>`
+        #[arg(long, conflicts_with = "suffix")]
+        prefix: Option<String>,
+        /// Name the copied branches `<branch><suffix>` (default: `-<name>`)
+        #[arg(long)]
+        suffix: Option<String>,
     },
 
     /// Add current changes to the stack
     Commit {
-        /// Commit message
+        /// Commit message; without it the editor opens on a template naming the
+        /// branch's stack position and parent
         #[arg(short, long)]
-        message: String,
+        message: Option<String>,
+        /// Read the commit message from a file, or from stdin with '-'
+        #[arg(
+            short = 'F',
+            long,
+            visible_alias = "message-file",
+            value_name = "PATH",
+            conflicts_with = "message"
+        )]
+        file: Option<PathBuf>,
+        /// Start the editor from the branch's latest commit message
+        #[arg(long, conflicts_with_all = ["message", "file"])]
+        reword: bool,
     },
 
     /// Amend the current commit and resync downstream branches
@@ -27,6 +93,18 @@ pub enum Commands {
         /// Updated commit message (optional)
         #[arg(short, long)]
         message: Option<String>,
+        /// Read the updated commit message from a file, or from stdin with '-'
+        #[arg(
+            short = 'F',
+            long,
+            visible_alias = "message-file",
+            value_name = "PATH",
+            conflicts_with = "message"
+        )]
+        file: Option<PathBuf>,
+        /// Amend this stack branch instead of the current one, then return here
+        #[arg(short, long)]
+        branch: Option<String>,
     },
 
     /// Add current branch to the stack
@@ -36,42 +114,450 @@ pub enum Commands {
         parent: Option<String>,
     },
 
+    /// Create stack branches that follow the configured naming scheme
+    #[command(subcommand)]
+    Branch(BranchCommands),
+
+    /// Add a level on top of the stack bumping the version in `bump.files`, then push
+    /// it and open its MR
+    Bump {
+        /// 'patch', 'minor' or 'major'
+        #[arg(long, default_value = "patch")]
+        level: String,
+        /// Commit the bump without pushing or opening its MR
+        #[arg(long)]
+        no_push: bool,
+    },
+
+    /// Publish a private branch and the private levels below it, then push them and
+    /// open their MRs
+    Publish {
+        /// Highest stack branch to publish
+        branch: String,
+    },
+
+    /// Cherry-pick a commit onto a stack branch and restack its descendants
+    Pick {
+        /// Commit to cherry-pick
+        commit: String,
+        /// Stack branch to pick onto (defaults to the current branch)
+        #[arg(long)]
+        onto: Option<String>,
+    },
+
+    /// Interactively rebase (reword/squash/drop) one branch's commits, then restack its descendants
+    Edit {
+        /// Stack branch to edit (defaults to the current branch)
+        branch: Option<String>,
+    },
+
+    /// Find which stack level introduced a regression by bisecting across branch tips
+    Bisect {
+        /// Shell command that exits 0 when the code is good
+        #[arg(short, long)]
+        test: String,
+        /// Highest stack branch to consider (defaults to the current branch)
+        #[arg(long)]
+        top: Option<String>,
+    },
+
+    /// Run a test command on every stack level in a temporary worktree and report each result
+    Verify {
+        /// Shell command that exits 0 when a level is good (default: verify.command)
+        #[arg(short, long)]
+        test: Option<String>,
+    },
+
+    /// Show which stack level last changed each line of a file
+    Blame {
+        /// File to annotate
+        file: String,
+        /// Stack branch whose version of the file to annotate (defaults to the current branch)
+        #[arg(short, long)]
+        branch: Option<String>,
+    },
+
+    /// Resume an interrupted conflict resolution from the first unresolved file
+    Continue,
+
+    /// List conflicted files of the rebase, merge or cherry-pick in progress
+    Conflicts {
+        /// Print a JSON report with the base/ours/theirs blob ids of every file
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Set up git-train for this repository: detect settings, write the config and
+    /// optionally adopt an existing chain of branches as a stack
+    Init,
+
     /// Show stack status
-    Status,
+    Status {
+        /// Keep refreshing the status, highlighting what changed since the last refresh
+        #[arg(long)]
+        watch: bool,
+        /// Seconds between refreshes in watch mode
+        #[arg(long, default_value_t = 10, requires = "watch", value_parser = clap::value_parser!(u64).range(1..))]
+        interval: u64,
+    },
 
     /// List all stacks
-    List,
+    List {
+        /// Only stacks created with your git user.email
+        #[arg(long)]
+        mine: bool,
+        /// Only stacks created by this git email
+        #[arg(long, conflicts_with = "mine")]
+        author: Option<String>,
+        /// Include stacks archived after all their levels were merged
+        #[arg(long)]
+        archived: bool,
+    },
 
     /// Switch to a different stack
     Switch {
         /// Stack name or ID
         stack: String,
+        /// Also check out the stack's current branch (or its tip)
+        #[arg(long, overrides_with = "no_checkout")]
+        checkout: bool,
+        /// Only switch the active stack, leaving HEAD alone
+        #[arg(long)]
+        no_checkout: bool,
     },
 
     /// Interactive navigation through the stack
     Navigate,
 
+    /// Check out the top of the current chain (asks which one when the stack branches)
+    Top,
+
+    /// Check out the bottom of the current chain, the level right above the base
+    Bottom,
+
     /// Delete a stack
     Delete {
         /// Stack name or ID
         stack: String,
-        /// Skip confirmation prompt
+        /// Skip confirmation prompts
         #[arg(short, long)]
         force: bool,
+        /// Also delete the stack's local and remote branches
+        #[arg(long)]
+        branches: bool,
+        /// Also close the stack's open MRs with a comment
+        #[arg(long)]
+        close_mrs: bool,
+        /// Only list what would be deleted or closed
+        #[arg(long)]
+        dry_run: bool,
     },
 
     /// Push stack to remote
-    Push,
+    Push {
+        /// MR template to use for merge requests created by this push
+        #[arg(long)]
+        template: Option<String>,
+        /// Only push branches; leave merge requests to 'git-train mr update-all'
+        #[arg(long, conflicts_with_all = ["template", "milestone"])]
+        no_mr: bool,
+        /// Assign every MR of the stack to this milestone (default: mr.default_milestone)
+        #[arg(long)]
+        milestone: Option<String>,
+        /// Only push this branch and the stack branches below it
+        #[arg(long, value_name = "BRANCH", conflicts_with = "only")]
+        up_to: Option<String>,
+        /// Only push a chain of branches, e.g. 'feature-2..feature-4' or a single branch
+        #[arg(long, value_name = "RANGE")]
+        only: Option<String>,
+        /// Fetch the base first and restack branches that fell behind (default: push.auto_sync)
+        #[arg(long)]
+        sync: bool,
+    },
+
+    /// Maintain the stack's merge requests without pushing
+    #[command(subcommand)]
+    Mr(MrCommands),
+
+    /// Merge the bottom MR once approved, or land the whole stack with --squash-all
+    Land {
+        /// Collapse the whole stack onto the base and merge it through the bottom MR,
+        /// closing the other MRs
+        #[arg(long)]
+        squash_all: bool,
+        /// With --squash-all, keep one commit per stack level instead of a single commit
+        #[arg(long, requires = "squash_all")]
+        per_level: bool,
+        /// Squash the bottom MR into one commit whose message is the MR title and
+        /// description, without the stack table
+        #[arg(long, conflicts_with = "squash_all")]
+        squash: bool,
+        /// Delete the bottom branch on the remote once the MRs above it are retargeted
+        /// (default: mr.delete_source_branch)
+        #[arg(long, conflicts_with = "squash_all")]
+        delete_source_branch: bool,
+    },
+
+    /// Select the MR template for the stack or a single branch (lists templates if no name)
+    Template {
+        /// Template name from .gitlab/merge_request_templates/
+        name: Option<String>,
+        /// Apply to this branch only instead of the whole stack
+        #[arg(short, long)]
+        branch: Option<String>,
+        /// Clear the selection instead of setting it
+        #[arg(long, conflicts_with = "name")]
+        clear: bool,
+    },
+
+    /// Show or set how sync updates the stack: rebase children or merge parents into them
+    UpdateStrategy {
+        /// Strategy: 'rebase' or 'merge'
+        strategy: Option<String>,
+    },
+
+    /// Move the stack onto a different base branch and retarget its root MRs
+    SetBase {
+        /// New base branch, e.g. after the default branch moved from master to main
+        branch: String,
+    },
+
+    /// Keep local review notes and TODO checklists for a stack branch
+    ReviewNotes {
+        /// Stack branch to annotate (defaults to the current branch)
+        #[arg(short, long, global = true)]
+        branch: Option<String>,
+        #[command(subcommand)]
+        action: Option<ReviewNotesCommands>,
+    },
 
     /// Sync with remote (pull latest and rebase)
-    Sync,
+    Sync {
+        /// Sync every stored stack, not just the current one
+        #[arg(long)]
+        all: bool,
+        /// Remove branches deleted on the remote from the stack, reparenting their children
+        #[arg(long)]
+        prune: bool,
+        /// Only restack this branch and the stack branches below it
+        #[arg(long, value_name = "BRANCH", conflicts_with_all = ["only", "all"])]
+        up_to: Option<String>,
+        /// Only restack a chain of branches, e.g. 'feature-2..feature-4' or a single branch
+        #[arg(long, value_name = "RANGE", conflicts_with = "all")]
+        only: Option<String>,
+        /// Leave levels on their base until it is more than N commits ahead, unless its
+        /// new commits touch files the stack changes (default: sync.when_behind)
+        #[arg(long, value_name = "N")]
+        when_behind: Option<usize>,
+    },
+
+    /// Show recorded sync history and trends for the stack
+    History {
+        /// Number of most recent syncs to list
+        #[arg(short = 'n', long, default_value_t = 10)]
+        limit: usize,
+        /// Include syncs of every stack, not just the current one
+        #[arg(long)]
+        all: bool,
+    },
+
+    /// Recreate stack branches missing locally (e.g. after a re-clone) from origin or
+    /// their recorded commits
+    Restore {
+        /// Only list what would be recreated
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Rebuild lost stack metadata from the stack table in an MR description
+    Recover {
+        /// IID of any MR in the stack whose description has the stack table
+        #[arg(long, value_name = "IID")]
+        from_mr: u64,
+        /// Stack name (defaults to the name in the MR title's `[Stack: ...]` prefix)
+        #[arg(long)]
+        name: Option<String>,
+    },
+
+    /// Restore or remove stack branches that were deleted locally with plain git
+    Repair {
+        /// Restore every deleted branch from origin or its recorded commit
+        #[arg(long)]
+        restore: bool,
+        /// Remove every deleted branch from the stack, reparenting its children
+        #[arg(long, conflicts_with = "restore")]
+        remove: bool,
+    },
 
     /// Configuration management
     #[command(subcommand)]
     Config(ConfigCommands),
 
+    /// Restore MR titles, descriptions and targets saved before the last bulk MR update
+    Undo,
+
+    /// Print a Markdown summary of every stack level for release notes or an umbrella issue
+    Summary {
+        /// Write the summary to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+
     /// Check repository and stack health
     Health,
+
+    /// Print a redacted diagnostics bundle to attach to bug reports
+    #[command(alias = "debug-dump")]
+    Env {
+        /// Write the bundle to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+
+    /// Install git hooks that keep stack metadata fresh when committing or rebasing outside git-train
+    InstallHooks {
+        /// Move existing hooks aside to `<hook>.pre-git-train` (they keep running) instead of skipping them
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Print a short stack summary for PS1/starship, e.g. `my-stack 3/5 ↑2` (no network)
+    Prompt,
+
+    /// Answer JSON requests (status, restack, push) over a local socket for editor plugins
+    Serve {
+        /// Socket path (defaults to `git-train.sock` in the git directory)
+        #[arg(long)]
+        socket: Option<PathBuf>,
+    },
+
+    /// Refresh stack metadata; run by the hooks from `install-hooks`
+    #[command(hide = true)]
+    Hook {
+        /// Name of the git hook that fired
+        name: String,
+    },
+
+    /// Measure sync/push/status timings on a synthetic stack
+    #[command(hide = true)]
+    Bench {
+        /// Number of stacked branches to generate
+        #[arg(long, default_value_t = 20)]
+        branches: usize,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum BranchCommands {
+    /// Create a branch named from `branch.name_template` and add it to the stack
+    Create {
+        /// Short description used for the {slug} part of the name
+        description: String,
+        /// Stack branch to start from (defaults to the current stack branch or the base)
+        #[arg(short, long)]
+        parent: Option<String>,
+    },
+
+    /// Mark a branch as pushed by a teammate: git-train never rebases or force-pushes
+    /// it, and sync restacks its children onto its remote tip
+    Freeze {
+        /// Stack branch to freeze
+        name: String,
+    },
+
+    /// Let git-train rebase and push a frozen branch again
+    Unfreeze {
+        /// Stack branch to unfreeze
+        name: String,
+    },
+
+    /// Stop pushing a published branch and updating its MR, e.g. while reviewers are
+    /// mid-review; sync still restacks it locally
+    Pause {
+        /// Stack branch to pause
+        name: String,
+    },
+
+    /// Push a paused branch and update its MR again
+    Resume {
+        /// Stack branch to resume
+        name: String,
+    },
+
+    /// Keep a branch and every level above it local: no push, no MR, left out of stack
+    /// tables until 'git-train publish'
+    Private {
+        /// Stack branch to make private
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum MrCommands {
+    /// Create missing MRs, retarget existing ones and refresh their descriptions
+    UpdateAll {
+        /// MR template to use for merge requests created by this run
+        #[arg(long)]
+        template: Option<String>,
+    },
+
+    /// Merge one branch's MR once its parent's MR is merged, then retarget its children
+    Merge {
+        /// Stack branch whose MR to merge
+        branch: String,
+        /// 'merge', 'squash' or 'rebase' (default: mr.merge_method)
+        #[arg(long)]
+        method: Option<String>,
+        /// Delete the source branch once the child MRs are retargeted (default:
+        /// mr.delete_source_branch)
+        #[arg(long)]
+        delete_source_branch: bool,
+        /// Squash commit message instead of the MR title and description
+        #[arg(long)]
+        squash_message: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ReviewNotesCommands {
+    /// Show the branch's notes (default)
+    Show,
+
+    /// Add a free-form note
+    Add {
+        /// Note text
+        text: String,
+        /// Add it as a TODO checklist item
+        #[arg(short, long)]
+        todo: bool,
+    },
+
+    /// Add a TODO checklist item
+    Todo {
+        /// TODO text
+        text: String,
+    },
+
+    /// Mark a TODO item as done
+    Check {
+        /// Item number as shown by `review-notes show`
+        number: usize,
+    },
+
+    /// Mark a TODO item as not done
+    Uncheck {
+        /// Item number as shown by `review-notes show`
+        number: usize,
+    },
+
+    /// Remove a note or TODO item
+    Remove {
+        /// Item number as shown by `review-notes show`
+        number: usize,
+    },
+
+    /// Remove all notes from the branch
+    Clear,
 }
 
 #[derive(Subcommand)]
@@ -99,4 +585,97 @@ pub enum ConfigCommands {
         /// Mode: 'auto', 'prompt', or 'never'
         mode: String,
     },
+
+    /// Set what happens when HEAD belongs to a different stack than the active one
+    SetAutoSwitch {
+        /// Mode: 'never', 'prompt', or 'always'
+        mode: String,
+    },
+
+    /// Set the update strategy given to new stacks
+    SetUpdateStrategy {
+        /// Strategy: 'rebase' or 'merge'
+        strategy: String,
+    },
+
+    /// Set the branch naming scheme, e.g. '{user}/{stack}/{slug}' ('none' to clear)
+    SetBranchTemplate {
+        /// Template using {user}, {stack} and {slug}
+        template: String,
+    },
+
+    /// Set what `add` does with branches that don't follow the naming scheme
+    SetBranchEnforcement {
+        /// Mode: 'warn', 'reject', or 'fix'
+        mode: String,
+    },
+
+    /// Set the extra columns of the MR stack table, e.g. 'state,pipeline' ('none' to clear)
+    SetTableColumns {
+        /// Comma-separated list of 'state', 'pipeline', 'target', 'commits' and 'milestone'
+        columns: String,
+    },
+
+    /// Set the branches probed for the base of a new stack, e.g. 'develop,main,release/*'
+    SetBaseBranches {
+        /// Comma-separated branch names or globs, tried in order after origin's HEAD
+        branches: String,
+    },
+
+    /// Set how MR state and pipeline badges are rendered in the stack table
+    SetTableBadges {
+        /// Style: 'emoji' or 'text'
+        style: String,
+    },
+
+    /// Render stacks with at most this many branches as a single line instead of a table
+    SetTableCompact {
+        /// Branch limit for the compact layout (0 to always use a table)
+        max_branches: usize,
+    },
+
+    /// Set the size above which a stack level is flagged as too big to review (0 disables)
+    SetSizeLimit {
+        /// Maximum changed lines per level
+        #[arg(long)]
+        lines: Option<usize>,
+        /// Maximum changed files per level
+        #[arg(long)]
+        files: Option<usize>,
+    },
+
+    /// Ask up to this many CODEOWNERS of a level's changed files to review its new MR (0 disables)
+    SetCodeownerReviewers {
+        /// Number of reviewers to request per MR
+        count: usize,
+    },
+
+    /// Enable or disable local sync history
+    SetHistory {
+        /// Mode: 'on' or 'off'
+        mode: String,
+    },
+
+    /// Add Stack-Id/Stack-Position trailers to commits made by git-train
+    SetCommitTrailers {
+        /// Mode: 'on' or 'off'
+        mode: String,
+    },
+
+    /// Print the value of a config key, e.g. 'git.auto_stash'
+    Get {
+        /// Dotted key (see 'config keys')
+        key: String,
+    },
+
+    /// Set any config key, e.g. 'git-train config set git.auto_stash true'
+    Set {
+        /// Dotted key (see 'config keys')
+        key: String,
+        /// New value; lists are comma-separated and 'none' clears optional values
+        value: String,
+    },
+
+    /// List every config key with its current value
+    Keys,
 }
@@ -1,7 +1,9 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::PathBuf;
+use std::time::Duration;
 use tracing::info;
 
 use crate::errors::TrainError;
@@ -12,6 +14,26 @@ pub struct TrainConfig {
     pub editor: EditorConfig,
     pub conflict_resolution: ConflictResolutionConfig,
     pub git: GitConfig,
+    #[serde(default)]
+    pub mr: MrConfig,
+    #[serde(default)]
+    pub history: HistoryConfig,
+    #[serde(default)]
+    pub branch: BranchConfig,
+    #[serde(default)]
+    pub stack_table: StackTableConfig,
+    #[serde(default)]
+    pub timeouts: TimeoutConfig,
+    #[serde(default)]
+    pub verify: VerifyConfig,
+    #[serde(default)]
+    pub push: PushConfig,
+    #[serde(default)]
+    pub naming: NamingConfig,
+    #[serde(default)]
+    pub bump: BumpConfig,
+    #[serde(default)]
+    pub sync: SyncConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -29,6 +51,54 @@ pub struct ConflictResolutionConfig {
     pub prompt_before_force_push: bool,
     #[serde(default)]
     pub auto_force_push_after_rebase: bool,
+    /// How conflicted files are resolved by path pattern, e.g. `"*.snap" = "ours"`,
+    /// before `.gitattributes` or the interactive resolver get a look. A pattern
+    /// without `/` matches the file name in any directory; when several match, an
+    /// exact path wins over a glob and a longer glob over a shorter one.
+    #[serde(default)]
+    pub path_rules: BTreeMap<String, PathResolution>,
+}
+
+impl ConflictResolutionConfig {
+    /// The most specific `path_rules` entry matching `path`, with its pattern
+    pub fn path_rule(&self, path: &str) -> Option<(&str, PathResolution)> {
+        self.path_rules
+            .iter()
+            .filter(|(pattern, _)| crate::utils::path_matches_glob(pattern, path))
+            .max_by_key(|(pattern, _)| {
+                (
+                    !pattern.contains(['*', '?']),
+                    pattern.contains('/'),
+                    pattern.len(),
+                )
+            })
+            .map(|(pattern, resolution)| (pattern.as_str(), *resolution))
+    }
+}
+
+/// Resolution for conflicted files matching a `conflict_resolution.path_rules` pattern.
+///
+/// Sides are named as git names them: while restacking, "ours" is the parent the level
+/// is being rebased onto and "theirs" the level's own commit.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PathResolution {
+    /// Keep the version being rebased onto (`git checkout --ours`)
+    Ours,
+    /// Keep the version of the commit being replayed (`git checkout --theirs`)
+    Theirs,
+    /// Keep the lines of both sides, like `merge=union` in `.gitattributes`
+    Union,
+}
+
+impl PathResolution {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PathResolution::Ours => "ours",
+            PathResolution::Theirs => "theirs",
+            PathResolution::Union => "union",
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,6 +106,470 @@ pub struct GitConfig {
     pub default_rebase_strategy: RebaseStrategy,
     pub auto_stash: bool,
     pub verify_signatures: bool,
+    /// Check out the stack's branch when running `switch` without `--checkout`/`--no-checkout`
+    #[serde(default)]
+    pub checkout_on_switch: bool,
+    /// What to do when HEAD is on a branch owned by a stack other than the active one
+    #[serde(default)]
+    pub auto_switch_stack: AutoSwitchMode,
+    /// Update strategy given to newly created stacks
+    #[serde(default)]
+    pub update_strategy: UpdateStrategy,
+    /// Append `Stack-Id`/`Stack-Position` trailers to commits git-train creates or amends
+    #[serde(default)]
+    pub commit_trailers: bool,
+    /// Point each pushed stack branch's upstream at `origin/<branch>` so plain
+    /// `git pull`/`git status` work on it
+    #[serde(default = "default_set_upstream")]
+    pub set_upstream: bool,
+    /// Branches probed, in order, for the base of a new stack when origin's HEAD is
+    /// unknown; entries may be globs such as `release/*`
+    #[serde(default = "default_base_branch_candidates")]
+    pub base_branch_candidates: Vec<String>,
+    /// What a rebase does with merge commits inside a stack level
+    #[serde(default)]
+    pub merge_commits: MergeCommitHandling,
+    /// Identity for commits git-train creates (restacks, squashes, merges) in place of
+    /// the local `user.name`/`user.email`, e.g. a bot account in automation
+    #[serde(default)]
+    pub commit_identity: Option<CommitIdentity>,
+}
+
+/// Name and email git-train authors and commits as. An empty field keeps git's own
+/// `user.name`/`user.email` for that part.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct CommitIdentity {
+    pub name: String,
+    pub email: String,
+}
+
+fn default_set_upstream() -> bool {
+    true
+}
+
+fn default_base_branch_candidates() -> Vec<String> {
+    vec!["main".to_string(), "master".to_string()]
+}
+
+/// How `sync` brings each stack branch up to date with its parent
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateStrategy {
+    /// Rebase each child onto its parent (linear history, needs force-pushes)
+    #[default]
+    Rebase,
+    /// Merge each parent into its child (history is never rewritten, plain pushes only)
+    Merge,
+}
+
+impl UpdateStrategy {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "rebase" => Some(UpdateStrategy::Rebase),
+            "merge" => Some(UpdateStrategy::Merge),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UpdateStrategy::Rebase => "rebase",
+            UpdateStrategy::Merge => "merge",
+        }
+    }
+}
+
+/// What restacking does with a stack level that contains its own merge commits,
+/// e.g. after merging the base into it by hand
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum MergeCommitHandling {
+    /// Ask whether to linearize the level; keep the merges when declined
+    #[default]
+    Prompt,
+    /// Keep the merges by rebasing with `--rebase-merges`
+    Preserve,
+    /// Drop the merge commits and replay the level as a linear history
+    Linearize,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub enum AutoSwitchMode {
+    /// Only warn that the active stack does not own HEAD
+    Never,
+    /// Ask before switching to the stack that owns HEAD
+    #[default]
+    Prompt,
+    /// Switch to the stack that owns HEAD without asking
+    Always,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MrConfig {
+    /// Template name from `.gitlab/merge_request_templates/` used for new MRs
+    #[serde(default)]
+    pub default_template: Option<String>,
+    /// Mirror local review notes into a collapsed section of each MR description
+    #[serde(default)]
+    pub sync_review_notes: bool,
+    /// Warn when a stack level changes more lines than this (0 disables the check)
+    #[serde(default = "default_max_changed_lines")]
+    pub max_changed_lines: usize,
+    /// Warn when a stack level touches more files than this (0 disables the check)
+    #[serde(default = "default_max_changed_files")]
+    pub max_changed_files: usize,
+    /// Whether `push` rewrites existing MR titles when commit subjects change
+    #[serde(default)]
+    pub title_sync: TitleSync,
+    /// Milestone `push` assigns to every MR of the stack unless `--milestone` is given
+    #[serde(default)]
+    pub default_milestone: Option<String>,
+    /// How `mr merge` merges an MR unless `--method` is given
+    #[serde(default)]
+    pub merge_method: MergeMethod,
+    /// Whether `mr merge` and `land` delete the merged source branch, once the MRs
+    /// above it are retargeted
+    #[serde(default)]
+    pub delete_source_branch: bool,
+    /// Archive a stack once the MRs of all its levels are merged
+    #[serde(default = "default_archive_merged_stacks")]
+    pub archive_merged_stacks: bool,
+    /// How each MR is linked to the MRs of its parent and child levels
+    #[serde(default)]
+    pub dependency_links: DependencyLinks,
+    /// Ask up to this many CODEOWNERS of a level's changed files to review its new MR
+    /// (0 disables the assignment)
+    #[serde(default)]
+    pub codeowner_reviewers: usize,
+    /// End each MR description with landing instructions: which MR to merge first and
+    /// which ones wait for it
+    #[serde(default)]
+    pub landing_instructions: bool,
+}
+
+fn default_archive_merged_stacks() -> bool {
+    true
+}
+
+/// How `mr merge` asks GitLab to merge an MR
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeMethod {
+    /// Merge as is, with a merge commit unless the project fast-forwards
+    #[default]
+    Merge,
+    /// Squash the MR into one commit without the stack table in its message
+    Squash,
+    /// Let GitLab rebase the source branch onto the target first
+    Rebase,
+}
+
+impl MergeMethod {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "merge" => Some(MergeMethod::Merge),
+            "squash" => Some(MergeMethod::Squash),
+            "rebase" => Some(MergeMethod::Rebase),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MergeMethod::Merge => "merge",
+            MergeMethod::Squash => "squash",
+            MergeMethod::Rebase => "rebase",
+        }
+    }
+}
+
+/// How `push` keeps existing MR titles in step with their branch's commit subject
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TitleSync {
+    /// Always rewrite the title to `[Stack: name] <subject>`, replacing manual edits
+    FollowCommit,
+    /// Never touch a title after the MR is created
+    Manual,
+    /// Rewrite titles that still carry the `[Stack: name]` prefix, keep the others
+    #[default]
+    PrefixOnly,
+}
+
+impl TitleSync {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TitleSync::FollowCommit => "follow_commit",
+            TitleSync::Manual => "manual",
+            TitleSync::PrefixOnly => "prefix_only",
+        }
+    }
+}
+
+/// Dependency references between stacked MRs, on top of the stack table
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DependencyLinks {
+    /// Only the stack table
+    #[default]
+    Off,
+    /// "Depends on !12" / "Required by !14" lines in each MR description
+    Description,
+    /// GitLab blocking-MR links (needs GitLab Premium)
+    Api,
+}
+
+impl DependencyLinks {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DependencyLinks::Off => "off",
+            DependencyLinks::Description => "description",
+            DependencyLinks::Api => "api",
+        }
+    }
+}
+
+fn default_max_changed_lines() -> usize {
+    400
+}
+
+fn default_max_changed_files() -> usize {
+    30
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct HistoryConfig {
+    /// Record a local snapshot of every sync for `git-train history` (never uploaded)
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct VerifyConfig {
+    /// Shell command `git-train verify` runs on every stack level when `--test` is not given
+    #[serde(default)]
+    pub command: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct PushConfig {
+    /// Restack branches that fell behind the base before every push, as `push --sync` does
+    #[serde(default)]
+    pub auto_sync: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct SyncConfig {
+    /// Leave levels on their base until it is more than this many commits ahead of
+    /// them, unless its new commits touch files the stack changes (0 always restacks);
+    /// `sync --when-behind` overrides it
+    #[serde(default)]
+    pub when_behind: usize,
+}
+
+/// How stack names and the `{slug}` of branch names are cleaned up
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NamingConfig {
+    /// Letters and digits kept as typed (lowercased)
+    #[serde(default)]
+    pub charset: NameCharset,
+    /// Stands in for every other character except `-`; runs collapse into one
+    #[serde(default = "default_name_replacement")]
+    pub replacement: String,
+    /// Longest name produced, in characters (0 for no limit)
+    #[serde(default = "default_name_max_length")]
+    pub max_length: usize,
+    /// Keep `/` in stack names so hierarchies like `feat/login` survive
+    #[serde(default = "default_preserve_slashes")]
+    pub preserve_slashes: bool,
+}
+
+/// What `git-train bump` rewrites and how it describes the release
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BumpConfig {
+    /// Manifests whose version gets bumped, relative to the repository root; missing
+    /// ones are skipped
+    #[serde(default = "default_bump_files")]
+    pub files: Vec<String>,
+    /// Commit subject, and so MR title, of the bump level; `{version}`, `{previous}`
+    /// and `{level}` are filled in
+    #[serde(default = "default_bump_message")]
+    pub message_template: String,
+}
+
+fn default_bump_files() -> Vec<String> {
+    vec!["Cargo.toml".to_string(), "package.json".to_string()]
+}
+
+fn default_bump_message() -> String {
+    "chore: release {version}".to_string()
+}
+
+impl Default for BumpConfig {
+    fn default() -> Self {
+        Self {
+            files: default_bump_files(),
+            message_template: default_bump_message(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum NameCharset {
+    /// `a-z` and `0-9` only
+    Ascii,
+    /// Letters and digits of any script
+    #[default]
+    Unicode,
+}
+
+impl NameCharset {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NameCharset::Ascii => "ascii",
+            NameCharset::Unicode => "unicode",
+        }
+    }
+}
+
+fn default_name_replacement() -> String {
+    "-".to_string()
+}
+
+fn default_name_max_length() -> usize {
+    50
+}
+
+fn default_preserve_slashes() -> bool {
+    true
+}
+
+impl Default for NamingConfig {
+    fn default() -> Self {
+        Self {
+            charset: NameCharset::default(),
+            replacement: default_name_replacement(),
+            max_length: default_name_max_length(),
+            preserve_slashes: default_preserve_slashes(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeoutConfig {
+    /// Seconds a git subprocess may run before it is killed (0 waits forever)
+    #[serde(default = "default_git_timeout_secs")]
+    pub git_secs: u64,
+    /// Seconds a GitLab API request may take before it is abandoned (0 waits forever)
+    #[serde(default = "default_http_timeout_secs")]
+    pub http_secs: u64,
+}
+
+impl TimeoutConfig {
+    pub fn git(&self) -> Option<Duration> {
+        (self.git_secs > 0).then(|| Duration::from_secs(self.git_secs))
+    }
+
+    pub fn http(&self) -> Option<Duration> {
+        (self.http_secs > 0).then(|| Duration::from_secs(self.http_secs))
+    }
+}
+
+fn default_git_timeout_secs() -> u64 {
+    600
+}
+
+fn default_http_timeout_secs() -> u64 {
+    60
+}
+
+/// Template used by `branch create` when no `name_template` is configured
+pub const DEFAULT_BRANCH_TEMPLATE: &str = "{user}/{stack}/{slug}";
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct BranchConfig {
+    /// Team branch naming scheme; `{user}`, `{stack}` and `{slug}` are filled in.
+    /// When set, `add` checks branch names against it.
+    #[serde(default)]
+    pub name_template: Option<String>,
+    /// What `add` does with a branch whose name doesn't match `name_template`
+    #[serde(default)]
+    pub enforcement: NameEnforcement,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub enum NameEnforcement {
+    /// Add the branch but warn about the name
+    #[default]
+    Warn,
+    /// Refuse to add the branch
+    Reject,
+    /// Rename the branch to match the scheme, keeping its last path segment as the slug
+    Fix,
+}
+
+/// Layout of the stack table written into MR descriptions
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct StackTableConfig {
+    /// Extra columns shown after the merge request link, in order
+    #[serde(default)]
+    pub columns: Vec<TableColumn>,
+    /// How MR state and pipeline status are rendered
+    #[serde(default)]
+    pub badges: BadgeStyle,
+    /// Render stacks with at most this many branches as a single line (0 always uses a table)
+    #[serde(default)]
+    pub compact_max_branches: usize,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TableColumn {
+    /// MR state (open, merged, closed)
+    State,
+    /// Status of the MR's head pipeline
+    Pipeline,
+    /// Branch the MR targets
+    Target,
+    /// Number of commits the branch adds on top of its parent
+    Commits,
+    /// Milestone the MR is assigned to
+    Milestone,
+}
+
+impl TableColumn {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "state" => Some(TableColumn::State),
+            "pipeline" => Some(TableColumn::Pipeline),
+            "target" => Some(TableColumn::Target),
+            "commits" => Some(TableColumn::Commits),
+            "milestone" => Some(TableColumn::Milestone),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TableColumn::State => "state",
+            TableColumn::Pipeline => "pipeline",
+            TableColumn::Target => "target",
+            TableColumn::Commits => "commits",
+            TableColumn::Milestone => "milestone",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BadgeStyle {
+    /// Emoji such as 🟢 and ✅
+    #[default]
+    Emoji,
+    /// Plain words such as `open` and `passed`
+    Text,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -91,6 +625,35 @@ impl Default for ConflictResolutionConfig {
             max_retry_attempts: 3,
             prompt_before_force_push: true,
             auto_force_push_after_rebase: false,
+            path_rules: BTreeMap::new(),
+        }
+    }
+}
+
+impl Default for MrConfig {
+    fn default() -> Self {
+        Self {
+            default_template: None,
+            sync_review_notes: false,
+            max_changed_lines: default_max_changed_lines(),
+            max_changed_files: default_max_changed_files(),
+            title_sync: TitleSync::default(),
+            default_milestone: None,
+            merge_method: MergeMethod::default(),
+            delete_source_branch: false,
+            archive_merged_stacks: default_archive_merged_stacks(),
+            dependency_links: DependencyLinks::default(),
+            codeowner_reviewers: 0,
+            landing_instructions: false,
+        }
+    }
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        Self {
+            git_secs: default_git_timeout_secs(),
+            http_secs: default_http_timeout_secs(),
         }
     }
 }
@@ -101,6 +664,14 @@ impl Default for GitConfig {
             default_rebase_strategy: RebaseStrategy::Standard,
             auto_stash: true,
             verify_signatures: false,
+            checkout_on_switch: false,
+            auto_switch_stack: AutoSwitchMode::default(),
+            update_strategy: UpdateStrategy::default(),
+            commit_trailers: false,
+            set_upstream: default_set_upstream(),
+            base_branch_candidates: default_base_branch_candidates(),
+            merge_commits: MergeCommitHandling::default(),
+            commit_identity: None,
         }
     }
 }
@@ -230,6 +801,80 @@ impl ConfigManager {
         Ok(())
     }
 
+    /// Current value of a dotted config key such as `git.auto_stash`
+    pub fn get_value(&self, key: &str) -> Result<String> {
+        let tree = serde_json::to_value(&self.config)?;
+        let value = lookup_key(&tree, key).ok_or_else(|| unknown_key(key))?;
+        Ok(display_value(value))
+    }
+
+    /// Set a dotted config key from its command-line form.
+    ///
+    /// The text is read according to the key's current type (`true`/`false`, a number,
+    /// a comma-separated list, `none` to clear an optional value), and the updated
+    /// config has to deserialize again before it is saved, so enum values and list
+    /// entries are checked against the schema. Setting a field of an unset optional
+    /// section, e.g. `git.commit_identity.name`, creates the section.
+    pub fn set_value(&mut self, key: &str, value: &str) -> Result<()> {
+        let mut tree = serde_json::to_value(&self.config)?;
+        let mut slot = &mut tree;
+        let mut created = false;
+        for part in key.split('.') {
+            if slot.is_null() {
+                *slot = serde_json::Value::Object(serde_json::Map::new());
+                created = true;
+            }
+            let fields = slot.as_object_mut().ok_or_else(|| unknown_key(key))?;
+            slot = if created {
+                fields
+                    .entry(part.to_string())
+                    .or_insert(serde_json::Value::Null)
+            } else {
+                fields.get_mut(part).ok_or_else(|| unknown_key(key))?
+            };
+        }
+        // Sections can only be cleared, and only optional ones survive the schema check
+        if slot.is_object() && value != "none" {
+            return Err(unknown_key(key).into());
+        }
+        let invalid = |reason: String| TrainError::InvalidState {
+            message: format!("Invalid value '{}' for '{}': {}", value, key, reason),
+        };
+
+        *slot = match slot {
+            serde_json::Value::Bool(_) => match value.to_lowercase().as_str() {
+                "true" | "on" | "yes" => serde_json::Value::Bool(true),
+                "false" | "off" | "no" => serde_json::Value::Bool(false),
+                _ => return Err(invalid("expected 'true' or 'false'".to_string()).into()),
+            },
+            serde_json::Value::Number(_) => value
+                .parse::<u64>()
+                .map(serde_json::Value::from)
+                .map_err(|_| invalid("expected a non-negative number".to_string()))?,
+            serde_json::Value::Array(_) => serde_json::Value::from(
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty() && *item != "none")
+                    .collect::<Vec<_>>(),
+            ),
+            serde_json::Value::Null | serde_json::Value::Object(_) if value == "none" => {
+                serde_json::Value::Null
+            }
+            _ => serde_json::Value::from(value),
+        };
+
+        let config: TrainConfig =
+            serde_json::from_value(tree).map_err(|e| invalid(e.to_string()))?;
+        // A created section only accepts the fields its schema kept
+        if created && lookup_key(&serde_json::to_value(&config)?, key).is_none() {
+            return Err(unknown_key(key).into());
+        }
+        self.config = config;
+        Self::save_config(&self.config_path, &self.config)?;
+        Ok(())
+    }
+
     fn load_config(path: &PathBuf) -> Result<TrainConfig> {
         let content = fs::read_to_string(path)?;
         let config: TrainConfig =
@@ -249,6 +894,60 @@ impl ConfigManager {
     }
 }
 
+/// Every key `config get`/`config set` accept, in dotted form, with its current value
+pub fn config_keys(config: &TrainConfig) -> Vec<(String, String)> {
+    fn walk(prefix: &str, value: &serde_json::Value, keys: &mut Vec<(String, String)>) {
+        match value {
+            serde_json::Value::Object(fields) => {
+                for (name, field) in fields {
+                    let key = if prefix.is_empty() {
+                        name.clone()
+                    } else {
+                        format!("{}.{}", prefix, name)
+                    };
+                    walk(&key, field, keys);
+                }
+            }
+            leaf => keys.push((prefix.to_string(), display_value(leaf))),
+        }
+    }
+
+    let mut keys = Vec::new();
+    if let Ok(tree) = serde_json::to_value(config) {
+        walk("", &tree, &mut keys);
+    }
+    keys.sort();
+    keys
+}
+
+fn lookup_key<'a>(tree: &'a serde_json::Value, key: &str) -> Option<&'a serde_json::Value> {
+    key.split('.')
+        .try_fold(tree, |node, part| node.get(part))
+        .filter(|value| !value.is_object())
+}
+
+fn display_value(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Null => "none".to_string(),
+        serde_json::Value::String(text) => text.clone(),
+        serde_json::Value::Array(items) => items
+            .iter()
+            .map(display_value)
+            .collect::<Vec<_>>()
+            .join(","),
+        other => other.to_string(),
+    }
+}
+
+fn unknown_key(key: &str) -> TrainError {
+    TrainError::InvalidState {
+        message: format!(
+            "Unknown config key '{}'; run 'git-train config keys' to list them",
+            key
+        ),
+    }
+}
+
 // Helper function to check if a command exists
 mod which {
     use std::process::Command;
@@ -267,3 +966,74 @@ mod which {
             })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_get_and_set_by_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = ConfigManager {
+            config_path: dir.path().join("config.toml"),
+            config: TrainConfig::default(),
+        };
+
+        let keys = config_keys(manager.get_config());
+        assert!(keys.iter().any(|(key, _)| key == "git.auto_stash"));
+        assert!(keys
+            .iter()
+            .any(|(key, value)| key == "mr.default_template" && value == "none"));
+
+        manager.set_value("git.auto_stash", "false").unwrap();
+        manager.set_value("mr.max_changed_lines", "250").unwrap();
+        manager
+            .set_value("stack_table.columns", "state, pipeline")
+            .unwrap();
+        manager.set_value("git.update_strategy", "merge").unwrap();
+        assert_eq!(manager.get_value("git.auto_stash").unwrap(), "false");
+        assert_eq!(
+            manager.get_value("stack_table.columns").unwrap(),
+            "state,pipeline"
+        );
+        assert_eq!(
+            manager.get_config().git.update_strategy,
+            UpdateStrategy::Merge
+        );
+
+        // The schema rejects what it can't deserialize and the config stays untouched
+        assert!(manager.set_value("git.update_strategy", "squash").is_err());
+        assert!(manager
+            .set_value("stack_table.columns", "state,bogus")
+            .is_err());
+        assert!(manager.set_value("mr.max_changed_lines", "-1").is_err());
+        assert!(manager.set_value("git.no_such_key", "true").is_err());
+        assert!(manager.set_value("git", "true").is_err());
+        assert!(manager.set_value("git", "none").is_err());
+        assert_eq!(manager.get_config().mr.max_changed_lines, 250);
+
+        let saved = ConfigManager::load_config(&manager.config_path).unwrap();
+        assert!(!saved.git.auto_stash);
+
+        // Unset optional sections are created by setting one of their fields
+        assert!(manager
+            .set_value("git.commit_identity.nickname", "bot")
+            .is_err());
+        assert!(manager.get_config().git.commit_identity.is_none());
+        manager
+            .set_value("git.commit_identity.name", "Release Bot")
+            .unwrap();
+        manager
+            .set_value("git.commit_identity.email", "bot@example.com")
+            .unwrap();
+        assert_eq!(
+            manager.get_config().git.commit_identity,
+            Some(CommitIdentity {
+                name: "Release Bot".to_string(),
+                email: "bot@example.com".to_string(),
+            })
+        );
+        manager.set_value("git.commit_identity", "none").unwrap();
+        assert!(manager.get_config().git.commit_identity.is_none());
+    }
+}
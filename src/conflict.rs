@@ -1,8 +1,11 @@
 use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
 use std::path::PathBuf;
 use std::process::{Command, ExitStatus};
 
-use crate::config::TrainConfig;
+use crate::config::{PathResolution, TrainConfig};
 use crate::errors::TrainError;
 use crate::git::GitRepository;
 use crate::ui;
@@ -34,7 +37,29 @@ pub struct ConflictFile {
     pub status: ConflictStatus,
 }
 
-#[derive(Debug, Clone)]
+/// A conflicted file with the blob ids of its index stages, for tools that drive
+/// their own merge UI
+#[derive(Debug, Clone, Serialize)]
+pub struct ConflictDetails {
+    pub path: String,
+    pub status: ConflictStatus,
+    /// Blob of the common ancestor (stage 1), absent when both sides added the file
+    pub base: Option<String>,
+    /// Blob on the side being rebased onto / merged into (stage 2)
+    pub ours: Option<String>,
+    /// Blob on the side being applied (stage 3)
+    pub theirs: Option<String>,
+}
+
+/// Everything `git-train conflicts --json` reports
+#[derive(Debug, Clone, Serialize)]
+pub struct ConflictReport {
+    /// Operation stopped on the conflicts (rebase, merge, cherry-pick), `None` when git is clean
+    pub operation: Option<String>,
+    pub files: Vec<ConflictDetails>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ConflictStatus {
     BothModified,
     AddedByUs,
@@ -43,6 +68,48 @@ pub enum ConflictStatus {
     DeletedByThem,
 }
 
+/// How `.gitattributes` asks for a path to be merged
+#[derive(Debug, Clone, PartialEq)]
+enum MergeAttribute {
+    /// No merge attribute, or the built-in text merge
+    Default,
+    /// `merge=union`: keep the lines of both sides
+    Union,
+    /// `-merge` or `merge=binary`: never merge, leave it to the user
+    Binary,
+    /// `merge=<name>` with a `merge.<name>.driver` command configured
+    Driver { name: String, command: String },
+}
+
+/// Progress through a manual conflict resolution, saved after every file so an
+/// interrupted session can be picked up again with `git-train continue`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConflictSession {
+    pub started_at: DateTime<Utc>,
+    /// Git operation the conflicts belong to (rebase, merge or cherry-pick)
+    pub operation: String,
+    pub files: Vec<SessionFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionFile {
+    pub path: String,
+    pub status: ConflictStatus,
+    /// Conflict markers are gone and the file has been staged
+    pub resolved: bool,
+    /// The working copy had conflict markers when the file became conflicted. Files
+    /// without them (binary and delete conflicts) are only resolved once the user
+    /// stages or removes them.
+    #[serde(default)]
+    pub markers: bool,
+}
+
+impl ConflictSession {
+    pub fn resolved_count(&self) -> usize {
+        self.files.iter().filter(|f| f.resolved).count()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum GitState {
     Clean,
@@ -52,6 +119,18 @@ pub enum GitState {
     Conflicted,
 }
 
+impl GitState {
+    /// Name of the operation git is in the middle of
+    pub fn operation(&self) -> &'static str {
+        match self {
+            GitState::Rebasing => "rebase",
+            GitState::Merging => "merge",
+            GitState::CherryPicking => "cherry-pick",
+            GitState::Conflicted | GitState::Clean => "operation",
+        }
+    }
+}
+
 pub struct ConflictResolver {
     config: TrainConfig,
     git_dir: PathBuf,
@@ -136,6 +215,148 @@ impl ConflictResolver {
         Ok(has_unmerged)
     }
 
+    #[cfg(test)]
+    pub fn set_editor_launcher(&mut self, launcher: Box<dyn EditorLauncher>) {
+        self.editor_launcher = launcher;
+    }
+
+    fn session_path(&self) -> PathBuf {
+        self.git_repo.train_dir().join("conflict-session.json")
+    }
+
+    /// The saved conflict session, if a manual resolution was started and not finished
+    pub fn load_session(&self) -> Result<Option<ConflictSession>> {
+        let path = self.session_path();
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_str(&fs::read_to_string(path)?)?))
+    }
+
+    fn save_session(&self, session: &ConflictSession) -> Result<()> {
+        let path = self.session_path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(session)?)?;
+        Ok(())
+    }
+
+    fn clear_session(&self) -> Result<()> {
+        let path = self.session_path();
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    /// Load the session for the operation in progress, adding any newly conflicted files
+    fn start_session(&self, conflict_info: &ConflictInfo) -> Result<ConflictSession> {
+        let operation = self.get_git_state()?.operation();
+
+        let mut session = match self.load_session()? {
+            Some(session) if session.operation == operation => session,
+            _ => ConflictSession {
+                started_at: Utc::now(),
+                operation: operation.to_string(),
+                files: Vec::new(),
+            },
+        };
+        for conflict_file in &conflict_info.files {
+            let markers = fs::read_to_string(self.git_repo.root().join(&conflict_file.path))
+                .is_ok_and(|contents| has_conflict_markers(&contents));
+            match session
+                .files
+                .iter_mut()
+                .find(|f| f.path == conflict_file.path)
+            {
+                // Conflicted again (e.g. the next commit of a rebase)
+                Some(existing) => {
+                    existing.resolved = false;
+                    existing.markers = markers;
+                }
+                None => session.files.push(SessionFile {
+                    path: conflict_file.path.clone(),
+                    status: conflict_file.status.clone(),
+                    resolved: false,
+                    markers,
+                }),
+            }
+        }
+        self.save_session(&session)?;
+        Ok(session)
+    }
+
+    /// Stage every unresolved file whose conflict markers are gone and save the session.
+    ///
+    /// Files that never had markers can't be judged from their contents, so they count
+    /// as resolved only once the user has staged or removed them.
+    fn refresh_session(&self, session: &mut ConflictSession) -> Result<()> {
+        for file in session.files.iter_mut().filter(|f| !f.resolved) {
+            if !file.markers {
+                // Already staged by the user, or still waiting for them
+                file.resolved = self
+                    .git_repo
+                    .run(&["ls-files", "--unmerged", "--", &file.path])?
+                    .is_empty();
+                continue;
+            }
+            let path = self.git_repo.root().join(&file.path);
+            let resolved = match fs::read_to_string(&path) {
+                Ok(contents) => !has_conflict_markers(&contents),
+                // Deleted to resolve a delete/modify conflict
+                Err(_) => !path.exists(),
+            };
+            if resolved {
+                self.git_repo.run(&["add", "-A", "--", &file.path])?;
+                file.resolved = true;
+            }
+        }
+        self.save_session(session)
+    }
+
+    /// Pick up an interrupted manual resolution from the first unresolved file, then
+    /// continue the git operation
+    pub async fn resume_session(&self) -> Result<()> {
+        let Some(session) = self.load_session()? else {
+            return Err(TrainError::InvalidState {
+                message: "No conflict resolution to resume".to_string(),
+            }
+            .into());
+        };
+
+        let state = self.get_git_state()?;
+        if matches!(state, GitState::Clean) {
+            self.clear_session()?;
+            ui::print_info(&format!(
+                "The {} is no longer in progress; nothing to resume",
+                session.operation
+            ));
+            return Ok(());
+        }
+
+        ui::print_info(&format!(
+            "Resuming {}: {}/{} files resolved",
+            session.operation,
+            session.resolved_count(),
+            session.files.len()
+        ));
+        let original = ConflictInfo {
+            files: session
+                .files
+                .iter()
+                .map(|f| ConflictFile {
+                    path: f.path.clone(),
+                    status: f.status.clone(),
+                })
+                .collect(),
+        };
+        if let Some(conflicts) = self.detect_conflicts()? {
+            self.open_editor_for_conflicts(&conflicts).await?;
+        }
+        self.verify_conflicts_resolved(&original, state).await
+    }
+
     /// Clean up stale rebase state using `git rebase --abort`
     #[cfg(test)]
     pub fn cleanup_stale_rebase_files(&self) -> Result<()> {
@@ -162,10 +383,202 @@ impl ConflictResolver {
         }
     }
 
-    /// Attempt to resolve conflicts automatically based on configuration
-    pub async fn auto_resolve_conflicts(&self, _conflict_info: &ConflictInfo) -> Result<bool> {
-        ui::print_info("Automatic conflict resolution is disabled");
-        Ok(false)
+    /// Attempt to resolve conflicts automatically based on configuration.
+    ///
+    /// Files matching a `conflict_resolution.path_rules` pattern are resolved as the
+    /// rule says. Otherwise files whose `.gitattributes` request `merge=union` or a
+    /// configured custom merge driver are merged that way and staged. Everything else
+    /// is left for the user. Returns true only when no conflicts remain.
+    pub async fn auto_resolve_conflicts(&self, conflict_info: &ConflictInfo) -> Result<bool> {
+        let mut unresolved = 0;
+
+        for conflict_file in &conflict_info.files {
+            if let Some((pattern, resolution)) = self
+                .config
+                .conflict_resolution
+                .path_rule(&conflict_file.path)
+            {
+                if self.apply_path_rule(&conflict_file.path, resolution)? {
+                    ui::print_success(&format!(
+                        "Resolved {} with '{}' ({})",
+                        conflict_file.path,
+                        resolution.as_str(),
+                        pattern
+                    ));
+                    continue;
+                }
+            }
+
+            if !matches!(conflict_file.status, ConflictStatus::BothModified) {
+                unresolved += 1;
+                continue;
+            }
+
+            let resolved = match self.merge_attribute(&conflict_file.path)? {
+                MergeAttribute::Union => self.merge_with_stages(&conflict_file.path, None)?,
+                MergeAttribute::Driver { name, command } => {
+                    ui::print_info(&format!(
+                        "Running merge driver '{}' for {}",
+                        name, conflict_file.path
+                    ));
+                    self.merge_with_stages(&conflict_file.path, Some(&command))?
+                }
+                MergeAttribute::Default | MergeAttribute::Binary => false,
+            };
+
+            if resolved {
+                self.git_repo.run(&["add", "--", &conflict_file.path])?;
+                ui::print_success(&format!(
+                    "Resolved {} using its .gitattributes merge setting",
+                    conflict_file.path
+                ));
+            } else {
+                unresolved += 1;
+            }
+        }
+
+        Ok(unresolved == 0)
+    }
+
+    /// Resolve `path` as a `path_rules` entry asks and stage it. Taking a side that
+    /// deleted the file deletes it; returns false when the rule can't settle the
+    /// conflict, e.g. a union merge of a file one side deleted.
+    fn apply_path_rule(&self, path: &str, resolution: PathResolution) -> Result<bool> {
+        // "<mode> <blob> <stage>\t<path>" for each stage present in the index
+        let stages = self.git_repo.run(&["ls-files", "--unmerged", "--", path])?;
+        let has_stage = |stage: &str| {
+            stages
+                .lines()
+                .any(|line| line.split_whitespace().nth(2) == Some(stage))
+        };
+
+        let (side, stage) = match resolution {
+            PathResolution::Union => {
+                if !self.merge_with_stages(path, None)? {
+                    return Ok(false);
+                }
+                self.git_repo.run(&["add", "--", path])?;
+                return Ok(true);
+            }
+            PathResolution::Ours => ("--ours", "2"),
+            PathResolution::Theirs => ("--theirs", "3"),
+        };
+        if has_stage(stage) {
+            self.git_repo.run(&["checkout", side, "--", path])?;
+            self.git_repo.run(&["add", "--", path])?;
+        } else {
+            self.git_repo.run(&["rm", "--quiet", "--", path])?;
+        }
+        Ok(true)
+    }
+
+    /// Look up the `merge` attribute for `path`
+    fn merge_attribute(&self, path: &str) -> Result<MergeAttribute> {
+        let output = self.git_repo.run(&["check-attr", "merge", "--", path])?;
+        // Output is "<path>: merge: <value>"
+        let value = output.rsplit(": ").next().unwrap_or("unspecified").trim();
+
+        Ok(match value {
+            "unspecified" | "set" | "text" => MergeAttribute::Default,
+            "union" => MergeAttribute::Union,
+            "unset" | "binary" => MergeAttribute::Binary,
+            name => {
+                // Git silently falls back to a text merge for undefined drivers
+                match self
+                    .git_repo
+                    .run(&["config", "--get", &format!("merge.{}.driver", name)])
+                {
+                    Ok(command) if !command.is_empty() => MergeAttribute::Driver {
+                        name: name.to_string(),
+                        command,
+                    },
+                    _ => MergeAttribute::Default,
+                }
+            }
+        })
+    }
+
+    /// Re-merge `path` from its index stages with `git merge-file --union`, or with
+    /// `driver` (a `merge.<name>.driver` command using %O/%A/%B/%L/%P placeholders).
+    ///
+    /// On success the merged result is written to `path`; returns false if the
+    /// merge still conflicts.
+    fn merge_with_stages(&self, path: &str, driver: Option<&str>) -> Result<bool> {
+        let root = self.git_repo.root();
+
+        // Writes each stage to a temp file in the work tree: "<base> <ours> <theirs>\t<path>"
+        let output = self
+            .git_repo
+            .run(&["checkout-index", "--stage=all", "--temp", "--", path])?;
+        let stages: Vec<&str> = output
+            .split('\t')
+            .next()
+            .unwrap_or_default()
+            .split_whitespace()
+            .collect();
+        let [base, ours, theirs] = stages[..] else {
+            return Ok(false);
+        };
+        let temp_files: Vec<PathBuf> = [base, ours, theirs]
+            .iter()
+            .filter(|name| **name != ".")
+            .map(|name| root.join(name))
+            .collect();
+
+        let result = (|| -> Result<bool> {
+            if ours == "." || theirs == "." {
+                return Ok(false);
+            }
+            // Both sides added the file: merge against an empty base
+            let base_path = if base == "." {
+                let empty = root.join(format!("{}.git-train-empty-base", ours));
+                std::fs::write(&empty, "")?;
+                empty
+            } else {
+                root.join(base)
+            };
+            let ours_path = root.join(ours);
+            let theirs_path = root.join(theirs);
+
+            let status = match driver {
+                None => Command::new("git")
+                    .arg("merge-file")
+                    .arg("--union")
+                    .arg(&ours_path)
+                    .arg(&base_path)
+                    .arg(&theirs_path)
+                    .current_dir(root)
+                    .status()?,
+                Some(driver) => {
+                    // Quoted like git does, so paths can't split or inject into the command
+                    let command = driver
+                        .replace("%O", &shell_quote(&base_path.to_string_lossy()))
+                        .replace("%A", &shell_quote(&ours_path.to_string_lossy()))
+                        .replace("%B", &shell_quote(&theirs_path.to_string_lossy()))
+                        .replace("%L", "7")
+                        .replace("%P", &shell_quote(path));
+                    Command::new("sh")
+                        .arg("-c")
+                        .arg(&command)
+                        .current_dir(root)
+                        .status()?
+                }
+            };
+
+            if base == "." {
+                let _ = std::fs::remove_file(&base_path);
+            }
+            if !status.success() {
+                return Ok(false);
+            }
+            std::fs::copy(&ours_path, root.join(path))?;
+            Ok(true)
+        })();
+
+        for temp_file in temp_files {
+            let _ = std::fs::remove_file(temp_file);
+        }
+        result
     }
 
     /// Handle conflicts with user intervention
@@ -232,22 +645,47 @@ impl ConflictResolver {
         }
     }
 
-    /// Open the configured editor for manual conflict resolution
+    /// Open the configured editor on each unresolved file, one at a time.
+    ///
+    /// Progress is saved to the conflict session after every file, so an interrupted
+    /// resolution resumes with `git-train continue` from the first unresolved file.
     async fn open_editor_for_conflicts(&self, conflict_info: &ConflictInfo) -> Result<()> {
         let editor_config = &self.config.editor;
 
-        ui::print_info("Opening editor(s) to resolve conflicts...");
+        let mut session = self.start_session(conflict_info)?;
+        self.refresh_session(&mut session)?;
+        let pending: Vec<String> = session
+            .files
+            .iter()
+            .filter(|f| !f.resolved)
+            .map(|f| f.path.clone())
+            .collect();
+        if pending.is_empty() {
+            return Ok(());
+        }
 
-        for conflict_file in &conflict_info.files {
+        ui::print_info(&format!(
+            "Opening editor(s) to resolve conflicts ({} of {} files left)...",
+            pending.len(),
+            session.files.len()
+        ));
+
+        for path in &pending {
             ui::print_info(&format!(
                 "Opening {} in {}",
-                conflict_file.path, editor_config.default_editor
+                path, editor_config.default_editor
             ));
+            if session.files.iter().any(|f| &f.path == path && !f.markers) {
+                ui::print_info(&format!(
+                    "{} has no conflict markers: keep a version with 'git add' or 'git rm'",
+                    path
+                ));
+            }
 
             match self.editor_launcher.launch(
                 &editor_config.default_editor,
                 &editor_config.editor_args,
-                &conflict_file.path,
+                path,
             ) {
                 Ok(status) => {
                     if !status.success() {
@@ -257,11 +695,11 @@ impl ConflictResolver {
                         ));
 
                         // Check if user wants to continue with other files or abort
-                        if conflict_info.files.len() > 1 {
+                        if pending.len() > 1 {
                             let continue_choice =
                                 ui::confirm_action("Continue editing other files?")?;
                             if !continue_choice {
-                                ui::print_info("Please resolve conflicts manually and re-run 'git-train sync' when ready.");
+                                ui::print_info("Resolve the remaining files and run 'git-train continue' when ready.");
                                 return Err(TrainError::InvalidState {
                                     message: "Manual conflict resolution interrupted".to_string(),
                                 }
@@ -277,24 +715,29 @@ impl ConflictResolver {
                     ));
                     ui::print_info("You can:");
                     ui::print_info("• Resolve conflicts manually in your preferred editor");
-                    ui::print_info("• Re-run 'git-train sync' when done");
+                    ui::print_info("• Run 'git-train continue' when done");
                     return Err(TrainError::GitError {
                         message: format!("Could not launch editor: {}", e),
                     }
                     .into());
                 }
             }
-        }
 
-        // Wait for user confirmation that they've finished resolving conflicts
-        ui::print_info("");
-        ui::print_success("Editor(s) have been opened for conflict resolution.");
-        ui::print_info("After resolving all conflicts in your editor(s), come back here.");
+            if editor_config.wait_for_editor {
+                self.refresh_session(&mut session)?;
+            }
+        }
 
-        // Simple confirmation prompt
-        ui::print_info("Press Enter when you have finished resolving all conflicts...");
-        let mut input = String::new();
-        std::io::stdin().read_line(&mut input)?;
+        if !editor_config.wait_for_editor {
+            // The editors returned immediately, so wait for the user to finish
+            ui::print_info("");
+            ui::print_success("Editor(s) have been opened for conflict resolution.");
+            ui::print_info("After resolving all conflicts in your editor(s), come back here.");
+            ui::print_info("Press Enter when you have finished resolving all conflicts...");
+            let mut input = String::new();
+            std::io::stdin().read_line(&mut input)?;
+            self.refresh_session(&mut session)?;
+        }
 
         Ok(())
     }
@@ -349,15 +792,23 @@ impl ConflictResolver {
             }
         }
 
+        self.clear_session()?;
         Ok(())
     }
 
     fn analyze_conflicts(&self) -> Result<Option<ConflictInfo>> {
-        let status_output = self.git_repo.run(&["status", "--porcelain"])?;
+        // -z leaves paths with spaces or quotes unquoted
+        let status_output = self.git_repo.run(&["status", "--porcelain", "-z"])?;
         let mut conflict_files = Vec::new();
 
-        for line in status_output.lines() {
-            if let Some(conflict_file) = self.parse_conflict_line(line)? {
+        let mut entries = status_output.split('\0');
+        while let Some(entry) = entries.next() {
+            // Renames and copies are followed by their source path
+            if entry.starts_with(['R', 'C']) {
+                entries.next();
+                continue;
+            }
+            if let Some(conflict_file) = self.parse_conflict_line(entry)? {
                 conflict_files.push(conflict_file);
             }
         }
@@ -396,6 +847,55 @@ impl ConflictResolver {
         }))
     }
 
+    /// Conflicted files with their status and the blob id of each side
+    pub fn conflict_report(&self) -> Result<ConflictReport> {
+        let state = self.get_git_state()?;
+        let operation = match state {
+            GitState::Clean => None,
+            _ => Some(state.operation().to_string()),
+        };
+        let Some(conflict_info) = self.detect_conflicts()? else {
+            return Ok(ConflictReport {
+                operation,
+                files: Vec::new(),
+            });
+        };
+
+        let mut files = Vec::new();
+        for conflict_file in conflict_info.files {
+            // Lines are "<mode> <blob> <stage>\t<path>"
+            let stages =
+                self.git_repo
+                    .run(&["ls-files", "--unmerged", "--", &conflict_file.path])?;
+            let mut blobs: [Option<String>; 3] = Default::default();
+            for line in stages.lines() {
+                let mut fields = line.split('\t').next().unwrap_or_default().split(' ');
+                let (Some(_), Some(blob), Some(stage)) =
+                    (fields.next(), fields.next(), fields.next())
+                else {
+                    continue;
+                };
+                if let Some(slot) = stage
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|stage| blobs.get_mut(stage.wrapping_sub(1)))
+                {
+                    *slot = Some(blob.to_string());
+                }
+            }
+            let [base, ours, theirs] = blobs;
+            files.push(ConflictDetails {
+                path: conflict_file.path,
+                status: conflict_file.status,
+                base,
+                ours,
+                theirs,
+            });
+        }
+
+        Ok(ConflictReport { operation, files })
+    }
+
     pub fn print_conflict_summary(&self, conflict_info: &ConflictInfo) {
         ui::print_warning(&format!(
             "Found {} conflicted files:",
@@ -430,10 +930,29 @@ impl ConflictResolver {
                 ui::print_warning("No operation to abort");
             }
         }
+        self.clear_session()?;
         Ok(())
     }
 }
 
+/// Whether `contents` still has an unresolved `<<<<<<<` ... `>>>>>>>` block
+fn has_conflict_markers(contents: &str) -> bool {
+    let mut open = false;
+    for line in contents.lines() {
+        if line.starts_with("<<<<<<<") {
+            open = true;
+        } else if open && line.starts_with(">>>>>>>") {
+            return true;
+        }
+    }
+    false
+}
+
+/// `value` as a single-quoted `sh` word
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -510,4 +1029,313 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn conflict_report_lists_blob_ids() -> Result<()> {
+        let (tmp, repo, git_dir) = init_repo()?;
+        let git = |args: &[&str]| repo.run(args);
+        std::fs::write(tmp.path().join("file.txt"), "base\n")?;
+        git(&["add", "."])?;
+        git(&["commit", "-m", "base"])?;
+        git(&["checkout", "-b", "theirs"])?;
+        std::fs::write(tmp.path().join("file.txt"), "theirs\n")?;
+        git(&["commit", "-am", "theirs"])?;
+        git(&["checkout", "-"])?;
+        std::fs::write(tmp.path().join("file.txt"), "ours\n")?;
+        git(&["commit", "-am", "ours"])?;
+
+        let resolver = ConflictResolver::new(TrainConfig::default(), git_dir, repo.clone());
+        assert!(resolver.conflict_report()?.operation.is_none());
+
+        assert!(git(&["merge", "theirs"]).is_err());
+        let report = resolver.conflict_report()?;
+        assert_eq!(report.operation.as_deref(), Some("merge"));
+        assert_eq!(report.files.len(), 1);
+        let file = &report.files[0];
+        assert_eq!(file.path, "file.txt");
+        assert_eq!(file.base, Some(git(&["rev-parse", "HEAD~1:file.txt"])?));
+        assert_eq!(file.ours, Some(git(&["rev-parse", "HEAD:file.txt"])?));
+        assert_eq!(file.theirs, Some(git(&["rev-parse", "theirs:file.txt"])?));
+        Ok(())
+    }
+
+    /// Editor stand-in that records what it opened and resolves the files it's allowed to
+    struct ScriptedEditor {
+        root: PathBuf,
+        resolves: Vec<&'static str>,
+        opened: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    impl EditorLauncher for ScriptedEditor {
+        fn launch(&self, _editor: &str, _args: &[String], file: &str) -> Result<ExitStatus> {
+            self.opened.lock().unwrap().push(file.to_string());
+            if self.resolves.contains(&file) {
+                std::fs::write(self.root.join(file), "resolved\n")?;
+            }
+            Ok(Command::new("true").status()?)
+        }
+    }
+
+    #[tokio::test]
+    async fn conflict_session_resumes_from_first_unresolved_file() -> Result<()> {
+        let (tmp, repo, git_dir) = init_repo()?;
+        let git = |args: &[&str]| repo.run(args);
+
+        for name in ["a.txt", "b.txt"] {
+            std::fs::write(tmp.path().join(name), "base\n")?;
+        }
+        git(&["add", "."])?;
+        git(&["commit", "-m", "init"])?;
+        let main = git(&["rev-parse", "--abbrev-ref", "HEAD"])?;
+        git(&["checkout", "-b", "feature"])?;
+        for name in ["a.txt", "b.txt"] {
+            std::fs::write(tmp.path().join(name), "feature\n")?;
+        }
+        git(&["commit", "-am", "feature"])?;
+        git(&["checkout", &main])?;
+        for name in ["a.txt", "b.txt"] {
+            std::fs::write(tmp.path().join(name), "main\n")?;
+        }
+        git(&["commit", "-am", "main"])?;
+        assert!(git(&["merge", "feature"]).is_err());
+
+        // First run only gets through a.txt before being interrupted
+        let opened = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut resolver =
+            ConflictResolver::new(TrainConfig::default(), git_dir.clone(), repo.clone());
+        resolver.set_editor_launcher(Box::new(ScriptedEditor {
+            root: tmp.path().to_path_buf(),
+            resolves: vec!["a.txt"],
+            opened: opened.clone(),
+        }));
+        let info = resolver.detect_conflicts()?.expect("conflicts");
+        resolver.open_editor_for_conflicts(&info).await?;
+
+        let session = resolver.load_session()?.expect("session saved");
+        assert_eq!(session.operation, "merge");
+        assert_eq!(session.resolved_count(), 1);
+        assert_eq!(git(&["diff", "--name-only", "--diff-filter=U"])?, "b.txt");
+
+        // `continue` reopens only b.txt, then finishes the merge
+        opened.lock().unwrap().clear();
+        let mut resolver =
+            ConflictResolver::new(TrainConfig::default(), git_dir.clone(), repo.clone());
+        resolver.set_editor_launcher(Box::new(ScriptedEditor {
+            root: tmp.path().to_path_buf(),
+            resolves: vec!["b.txt"],
+            opened: opened.clone(),
+        }));
+        resolver.resume_session().await?;
+
+        assert_eq!(*opened.lock().unwrap(), vec!["b.txt".to_string()]);
+        assert!(!git_dir.join("MERGE_HEAD").exists());
+        assert!(resolver.load_session()?.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn binary_and_delete_conflicts_wait_for_the_user() -> Result<()> {
+        let (tmp, repo, git_dir) = init_repo()?;
+        let git = |args: &[&str]| repo.run(args);
+
+        std::fs::write(tmp.path().join("image.bin"), b"\0base\xff")?;
+        std::fs::write(tmp.path().join("notes.txt"), "base\n")?;
+        git(&["add", "."])?;
+        git(&["commit", "-m", "init"])?;
+        let main = git(&["rev-parse", "--abbrev-ref", "HEAD"])?;
+        git(&["checkout", "-b", "feature"])?;
+        std::fs::write(tmp.path().join("image.bin"), b"\0feature\xff")?;
+        git(&["rm", "--quiet", "notes.txt"])?;
+        git(&["commit", "-am", "feature"])?;
+        git(&["checkout", &main])?;
+        std::fs::write(tmp.path().join("image.bin"), b"\0main\xff")?;
+        std::fs::write(tmp.path().join("notes.txt"), "main\n")?;
+        git(&["commit", "-am", "main"])?;
+        assert!(git(&["merge", "feature"]).is_err());
+
+        let opened = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut resolver =
+            ConflictResolver::new(TrainConfig::default(), git_dir.clone(), repo.clone());
+        resolver.set_editor_launcher(Box::new(ScriptedEditor {
+            root: tmp.path().to_path_buf(),
+            resolves: Vec::new(),
+            opened: opened.clone(),
+        }));
+        let info = resolver.detect_conflicts()?.expect("conflicts");
+        resolver.open_editor_for_conflicts(&info).await?;
+
+        // Neither file has markers to remove, so both are offered and left unmerged
+        assert_eq!(opened.lock().unwrap().len(), 2);
+        let mut session = resolver.load_session()?.expect("session saved");
+        assert_eq!(session.resolved_count(), 0);
+        assert_eq!(
+            git(&["diff", "--name-only", "--diff-filter=U"])?,
+            "image.bin\nnotes.txt"
+        );
+
+        // Choosing a side explicitly resolves them
+        git(&["checkout", "--theirs", "--", "image.bin"])?;
+        git(&["add", "image.bin"])?;
+        git(&["rm", "--quiet", "notes.txt"])?;
+        resolver.refresh_session(&mut session)?;
+        assert_eq!(session.resolved_count(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn conflict_markers_need_a_closed_block() {
+        assert!(has_conflict_markers(
+            "<<<<<<< HEAD\na\n=======\nb\n>>>>>>> x\n"
+        ));
+        assert!(!has_conflict_markers("resolved\n"));
+        assert!(!has_conflict_markers("<<<<<<< only an opening line\n"));
+    }
+
+    #[tokio::test]
+    async fn auto_resolve_honors_union_merge_attribute() -> Result<()> {
+        let (tmp, repo, git_dir) = init_repo()?;
+        let git = |args: &[&str]| repo.run(args);
+
+        std::fs::write(tmp.path().join("CHANGELOG.md"), "base\n")?;
+        std::fs::write(tmp.path().join("code.txt"), "base\n")?;
+        git(&["add", "."])?;
+        git(&["commit", "-m", "init"])?;
+        let main = git(&["rev-parse", "--abbrev-ref", "HEAD"])?;
+
+        git(&["checkout", "-b", "feature"])?;
+        std::fs::write(tmp.path().join("CHANGELOG.md"), "base\nfeature entry\n")?;
+        std::fs::write(tmp.path().join("code.txt"), "feature\n")?;
+        git(&["commit", "-am", "feature"])?;
+
+        git(&["checkout", &main])?;
+        std::fs::write(tmp.path().join("CHANGELOG.md"), "base\nmain entry\n")?;
+        std::fs::write(tmp.path().join("code.txt"), "main\n")?;
+        git(&["commit", "-am", "main"])?;
+        assert!(git(&["merge", "feature"]).is_err());
+
+        // Declared after the fact so git's own merge didn't already apply it
+        std::fs::write(
+            tmp.path().join(".gitattributes"),
+            "CHANGELOG.md merge=union\n",
+        )?;
+
+        let resolver = ConflictResolver::new(TrainConfig::default(), git_dir, repo.clone());
+        let info = resolver.detect_conflicts()?.expect("conflicts");
+        assert_eq!(info.files.len(), 2);
+
+        // code.txt has no merge attribute and stays conflicted
+        assert!(!resolver.auto_resolve_conflicts(&info).await?);
+
+        let changelog = std::fs::read_to_string(tmp.path().join("CHANGELOG.md"))?;
+        assert_eq!(changelog, "base\nmain entry\nfeature entry\n");
+        let unmerged = git(&["diff", "--name-only", "--diff-filter=U"])?;
+        assert_eq!(unmerged, "code.txt");
+        let untracked = git(&["ls-files", "--others", "--exclude-standard"])?;
+        assert_eq!(untracked, ".gitattributes");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn merge_driver_gets_quoted_paths() -> Result<()> {
+        let (tmp, repo, git_dir) = init_repo()?;
+        let git = |args: &[&str]| repo.run(args);
+        let name = "my notes;touch pwned.txt";
+
+        std::fs::write(tmp.path().join(name), "base\n")?;
+        git(&["add", "."])?;
+        git(&["commit", "-m", "init"])?;
+        let main = git(&["rev-parse", "--abbrev-ref", "HEAD"])?;
+
+        git(&["checkout", "-b", "feature"])?;
+        std::fs::write(tmp.path().join(name), "feature\n")?;
+        git(&["commit", "-am", "feature"])?;
+        git(&["checkout", &main])?;
+        std::fs::write(tmp.path().join(name), "main\n")?;
+        git(&["commit", "-am", "main"])?;
+        assert!(git(&["merge", "feature"]).is_err());
+
+        git(&[
+            "config",
+            "merge.append.driver",
+            "cat %B >> %A && test -f %P",
+        ])?;
+        std::fs::write(tmp.path().join(".gitattributes"), "*.txt merge=append\n")?;
+
+        let resolver = ConflictResolver::new(TrainConfig::default(), git_dir, repo.clone());
+        let info = resolver.detect_conflicts()?.expect("conflicts");
+        assert!(resolver.auto_resolve_conflicts(&info).await?);
+
+        let merged = std::fs::read_to_string(tmp.path().join(name))?;
+        assert_eq!(merged, "main\nfeature\n");
+        assert!(!tmp.path().join("pwned.txt").exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn auto_resolve_applies_path_rules() -> Result<()> {
+        let (tmp, repo, git_dir) = init_repo()?;
+        let git = |args: &[&str]| repo.run(args);
+        let write = |name: &str, contents: &str| std::fs::write(tmp.path().join(name), contents);
+
+        std::fs::create_dir(tmp.path().join("web"))?;
+        for name in [
+            "CHANGELOG.md",
+            "web/package-lock.json",
+            "app.snap",
+            "code.txt",
+        ] {
+            write(name, "base\n")?;
+        }
+        git(&["add", "."])?;
+        git(&["commit", "-m", "init"])?;
+        let main = git(&["rev-parse", "--abbrev-ref", "HEAD"])?;
+
+        git(&["checkout", "-b", "feature"])?;
+        write("CHANGELOG.md", "base\nfeature entry\n")?;
+        write("web/package-lock.json", "feature lock\n")?;
+        write("code.txt", "feature\n")?;
+        git(&["rm", "--quiet", "app.snap"])?;
+        git(&["commit", "-am", "feature"])?;
+
+        git(&["checkout", &main])?;
+        write("CHANGELOG.md", "base\nmain entry\n")?;
+        write("web/package-lock.json", "main lock\n")?;
+        write("app.snap", "main snapshot\n")?;
+        write("code.txt", "main\n")?;
+        git(&["commit", "-am", "main"])?;
+        assert!(git(&["merge", "feature"]).is_err());
+
+        let mut config = TrainConfig::default();
+        for (pattern, resolution) in [
+            ("CHANGELOG.md", PathResolution::Union),
+            ("package-lock.json", PathResolution::Theirs),
+            ("*.json", PathResolution::Ours),
+            ("*.snap", PathResolution::Theirs),
+        ] {
+            config
+                .conflict_resolution
+                .path_rules
+                .insert(pattern.to_string(), resolution);
+        }
+        let resolver = ConflictResolver::new(config, git_dir, repo.clone());
+        let info = resolver.detect_conflicts()?.expect("conflicts");
+        assert_eq!(info.files.len(), 4);
+
+        // code.txt matches no rule and stays conflicted
+        assert!(!resolver.auto_resolve_conflicts(&info).await?);
+        let unmerged = git(&["diff", "--name-only", "--diff-filter=U"])?;
+        assert_eq!(unmerged, "code.txt");
+
+        let read = |name: &str| std::fs::read_to_string(tmp.path().join(name));
+        assert_eq!(read("CHANGELOG.md")?, "base\nmain entry\nfeature entry\n");
+        // The exact name beats the shorter glob
+        assert_eq!(read("web/package-lock.json")?, "feature lock\n");
+        // Theirs deleted the snapshot
+        assert!(!tmp.path().join("app.snap").exists());
+        assert!(git(&["ls-files", "--", "app.snap"])?.is_empty());
+
+        Ok(())
+    }
 }
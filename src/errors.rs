@@ -8,6 +8,13 @@ pub enum TrainError {
     #[error("GitLab API error: {message}")]
     GitLabError { message: String },
 
+    #[error("GitLab rejected the token ({status}) for {endpoint}: {hint}")]
+    GitLabAuthError {
+        status: u16,
+        endpoint: String,
+        hint: String,
+    },
+
     #[error("Stack error: {message}")]
     StackError { message: String },
 
@@ -22,4 +29,20 @@ pub enum TrainError {
 
     #[error("Invalid state: {message}")]
     InvalidState { message: String },
+
+    #[error("Interrupted: {message}")]
+    Interrupted { message: String },
+
+    #[error("Locked: {message}")]
+    Locked { message: String },
+}
+
+/// Whether `error`, or an error it wraps, is GitLab rejecting the token
+pub fn is_gitlab_auth_error(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        matches!(
+            cause.downcast_ref(),
+            Some(TrainError::GitLabAuthError { .. })
+        )
+    })
 }
@@ -0,0 +1,72 @@
+//! Newline-delimited JSON progress events on stderr (`--progress-json`), so GUI
+//! wrappers can follow `sync` and `push` without parsing the human-readable output.
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::ids::MrIid;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// One step of a long-running operation
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    OperationStarted {
+        operation: &'a str,
+        stack: &'a str,
+    },
+    OperationFinished {
+        operation: &'a str,
+        success: bool,
+    },
+    BranchRebased {
+        branch: &'a str,
+        onto: &'a str,
+    },
+    BranchPushed {
+        branch: &'a str,
+        forced: bool,
+    },
+    ConflictDetected {
+        operation: &'a str,
+        files: Vec<&'a str>,
+    },
+    MrUpdated {
+        branch: &'a str,
+        iid: MrIid,
+        created: bool,
+    },
+}
+
+/// Write `event` as a single JSON line to stderr when `--progress-json` is on
+pub fn emit(event: &Event) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    if let Ok(line) = serde_json::to_string(event) {
+        eprintln!("{}", line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_are_tagged_json_lines() {
+        let event = Event::MrUpdated {
+            branch: "feature-1",
+            iid: MrIid::from(7),
+            created: true,
+        };
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            r#"{"event":"mr_updated","branch":"feature-1","iid":7,"created":true}"#
+        );
+    }
+}
@@ -1,14 +1,91 @@
 use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
-use std::process::Command;
-use tracing::{error, info};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{debug, info};
+use wait_timeout::ChildExt;
 
+use crate::config::CommitIdentity;
 use crate::errors::TrainError;
+use crate::utils::redact_secrets;
+
+/// Audit log size that triggers rotation to `audit.log.1`
+const AUDIT_LOG_MAX_BYTES: u64 = 1024 * 1024;
+/// Characters of command output kept per audit entry
+const AUDIT_OUTPUT_LIMIT: usize = 300;
+
+/// Set on every git process git-train spawns so the installed hooks can tell its own
+/// commits and checkouts apart from the user's.
+pub const INTERNAL_ENV: &str = "GIT_TRAIN_INTERNAL";
+
+/// How often a running git command checks whether it was cancelled
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Seconds a git subprocess may run before it is killed (0 waits forever)
+static COMMAND_TIMEOUT_SECS: AtomicU64 = AtomicU64::new(0);
+/// Set by Ctrl+C: the running git command is killed and no further one is started
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+/// Kill git subprocesses that run longer than `timeout`; set once at startup from config.
+pub fn set_command_timeout(timeout: Option<Duration>) {
+    COMMAND_TIMEOUT_SECS.store(timeout.map_or(0, |t| t.as_secs()), Ordering::SeqCst);
+}
+
+/// Abort the git command in flight and refuse to start new ones for the rest of the
+/// process. Returns `false` if cancellation had already been requested.
+pub fn cancel() -> bool {
+    !CANCELLED.swap(true, Ordering::SeqCst)
+}
+
+/// A git-train command that was stopped with Ctrl+C, recorded for `git-train continue`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InterruptedCommand {
+    pub interrupted_at: DateTime<Utc>,
+    /// The git-train invocation, e.g. `git-train sync`
+    pub command: String,
+    /// The git command that was running or about to run
+    pub git_command: String,
+}
+
+/// Short description of a single commit.
+#[derive(Debug, Clone)]
+pub struct CommitSummary {
+    pub hash: String,
+    pub author_name: String,
+    pub author_email: String,
+    pub subject: String,
+}
+
+/// Lines changed in one file, from `git diff --numstat` (binary files count as 0).
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileDiffStat {
+    pub path: String,
+    pub added: usize,
+    pub deleted: usize,
+}
+
+impl FileDiffStat {
+    pub fn changed(&self) -> usize {
+        self.added + self.deleted
+    }
+}
 
 /// A wrapper around the git command line tool.
 #[derive(Clone)]
 pub struct GitRepository {
     repo_path: PathBuf,
+    git_dir: PathBuf,
+    common_dir: PathBuf,
+    current_stack_file: PathBuf,
+    audit_log: PathBuf,
+    commit_identity: Option<CommitIdentity>,
 }
 
 impl GitRepository {
@@ -21,23 +98,281 @@ impl GitRepository {
             }
             .into());
         }
-        Ok(Self { repo_path })
+
+        // Resolve once so no caller depends on the process working directory.
+        // `--git-common-dir` is shared by linked worktrees, while `--git-path` of a
+        // non-shared file lands in this worktree's own git dir; both may be relative.
+        let output = run_cmd(
+            &[
+                "rev-parse",
+                "--absolute-git-dir",
+                "--git-common-dir",
+                "--git-path",
+                "train/current.json",
+            ],
+            &repo_path,
+            None,
+            None,
+        )?;
+        let mut lines = output.lines();
+        let git_dir = PathBuf::from(lines.next().unwrap_or(".git").trim());
+        let mut resolve = |fallback: PathBuf| match lines.next().map(|l| PathBuf::from(l.trim())) {
+            Some(path) if path.is_absolute() => path,
+            Some(path) => repo_path.join(path),
+            None => fallback,
+        };
+        let common_dir = resolve(git_dir.clone());
+        let current_stack_file = resolve(git_dir.join("train").join("current.json"));
+        let audit_log = common_dir.join("train").join("audit.log");
+
+        Ok(Self {
+            repo_path,
+            git_dir,
+            common_dir,
+            current_stack_file,
+            audit_log,
+            commit_identity: None,
+        })
+    }
+
+    /// Author and commit as `identity` in every git command run through this repository
+    /// (rebased commits keep their author and only take it as committer).
+    pub fn with_commit_identity(mut self, identity: Option<CommitIdentity>) -> Self {
+        self.commit_identity = identity;
+        self
+    }
+
+    /// Root of the working tree.
+    pub fn root(&self) -> &Path {
+        &self.repo_path
+    }
+
+    /// Absolute path of this working tree's git directory (where REBASE_HEAD etc. live).
+    pub fn git_dir(&self) -> &Path {
+        &self.git_dir
+    }
+
+    /// Directory holding git-train's stack metadata, shared by all worktrees.
+    pub fn train_dir(&self) -> PathBuf {
+        self.common_dir.join("train")
+    }
+
+    /// File recording the active stack. Unlike the stacks themselves it is kept per
+    /// worktree, so linked worktrees can each work on a different stack.
+    pub fn current_stack_file(&self) -> &Path {
+        &self.current_stack_file
     }
 
     /// Find the git repository root and create a new `GitRepository` instance.
     pub fn new_from_current_dir() -> Result<Self> {
-        let output = run_cmd(&["rev-parse", "--show-toplevel"], ".")?;
-        let repo_path = PathBuf::from(output.trim());
-        Self::new(&repo_path)
+        Self::discover(Path::new("."))
+    }
+
+    /// Open the repository containing `path`, which may be any subdirectory of the
+    /// working tree (this is what lets `git train` run from anywhere in a checkout).
+    pub fn discover(path: &Path) -> Result<Self> {
+        let output =
+            run_cmd(&["rev-parse", "--show-toplevel"], path, None, None).map_err(|_| {
+                TrainError::GitError {
+                    message: "Not a git repository (or any parent directory)".to_string(),
+                }
+            })?;
+        Self::new(Path::new(output.trim()))
     }
 
     /// Run a git command and return its output.
     pub fn run(&self, args: &[&str]) -> Result<String> {
-        run_cmd(args, &self.repo_path)
+        run_cmd(
+            args,
+            &self.repo_path,
+            Some(&self.audit_log),
+            self.commit_identity.as_ref(),
+        )
     }
 
+    /// Run a git command attached to the terminal so git can open editors and prompt.
+    pub fn run_interactive(&self, args: &[&str]) -> Result<()> {
+        let args_str = args.join(" ");
+        let started = Instant::now();
+        let mut command = Command::new("git");
+        command
+            .args(args)
+            .env(INTERNAL_ENV, "1")
+            .current_dir(&self.repo_path);
+        apply_commit_identity(&mut command, self.commit_identity.as_ref());
+        let status = command.status()?;
+
+        let entry = AuditEntry {
+            args: &args_str,
+            cwd: &self.repo_path,
+            elapsed: started.elapsed(),
+            exit_code: status.code(),
+            output: "(interactive)",
+        };
+        if let Err(e) = append_audit_entry(&self.audit_log, &entry) {
+            debug!("Could not write git audit log: {}", e);
+        }
+
+        if status.success() {
+            info!("git {} ({:.1?})", args_str, started.elapsed());
+            Ok(())
+        } else {
+            Err(anyhow!(TrainError::GitError {
+                message: format!("git {} exited with {}", args_str, status),
+            }))
+        }
+    }
+
+    /// Path of the git command audit log.
+    pub fn audit_log_path(&self) -> &Path {
+        &self.audit_log
+    }
+
+    /// The command recorded by the last Ctrl+C, if any; reading it clears the record.
+    pub fn take_interrupted(&self) -> Result<Option<InterruptedCommand>> {
+        let path = interrupted_path(&self.audit_log);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let interrupted = serde_json::from_str(&fs::read_to_string(&path)?).ok();
+        fs::remove_file(path)?;
+        Ok(interrupted)
+    }
+
+    /// Name of the checked-out branch; fails with a remediation hint on a detached HEAD.
     pub fn get_current_branch(&self) -> Result<String> {
-        self.run(&["branch", "--show-current"])
+        let branch = self.run(&["branch", "--show-current"])?;
+        if !branch.is_empty() {
+            return Ok(branch);
+        }
+
+        let head = self
+            .run(&["rev-parse", "--short", "HEAD"])
+            .unwrap_or_else(|_| "an unborn commit".to_string());
+        let message = if self.git_dir.join("rebase-merge").exists()
+            || self.git_dir.join("rebase-apply").exists()
+        {
+            format!(
+                "HEAD is detached at {} because a rebase is in progress. Finish it with `git rebase --continue` or undo it with `git rebase --abort`.",
+                head
+            )
+        } else {
+            format!(
+                "HEAD is detached at {}. Check out a stack branch with `git switch <branch>`, or keep these commits on a new branch with `git switch -c <name>`.",
+                head
+            )
+        };
+        Err(TrainError::InvalidState { message }.into())
+    }
+
+    /// Branch that `refs/remotes/origin/HEAD` points at, if the remote HEAD is known
+    /// locally (set by `clone` or `git remote set-head origin --auto`).
+    pub fn remote_head_branch(&self) -> Option<String> {
+        let head = self
+            .run(&[
+                "symbolic-ref",
+                "--quiet",
+                "--short",
+                "refs/remotes/origin/HEAD",
+            ])
+            .ok()?;
+        head.trim()
+            .strip_prefix("origin/")
+            .filter(|branch| !branch.is_empty())
+            .map(str::to_string)
+    }
+
+    /// Local and origin branches matching the glob `pattern` (e.g. `release/*`), most
+    /// recently committed first, without duplicates.
+    pub fn branches_matching(&self, pattern: &str) -> Vec<String> {
+        let output = self
+            .run(&[
+                "for-each-ref",
+                "--sort=-committerdate",
+                "--format=%(refname)",
+                &format!("refs/heads/{}", pattern),
+                &format!("refs/remotes/origin/{}", pattern),
+            ])
+            .unwrap_or_default();
+
+        let mut branches: Vec<String> = Vec::new();
+        for refname in output.lines() {
+            let branch = refname
+                .strip_prefix("refs/heads/")
+                .or_else(|| refname.strip_prefix("refs/remotes/origin/"));
+            if let Some(branch) = branch {
+                if branch != "HEAD" && !branches.iter().any(|b| b == branch) {
+                    branches.push(branch.to_string());
+                }
+            }
+        }
+        branches
+    }
+
+    /// Names of all local branches
+    pub fn local_branches(&self) -> Vec<String> {
+        self.run(&["for-each-ref", "--format=%(refname:short)", "refs/heads/"])
+            .unwrap_or_default()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Whether `refs/heads/<branch>` exists.
+    pub fn has_local_branch(&self, branch: &str) -> bool {
+        self.run(&[
+            "show-ref",
+            "--verify",
+            "--quiet",
+            &format!("refs/heads/{}", branch),
+        ])
+        .is_ok()
+    }
+
+    /// Whether the commit `hash` is in the local object database.
+    pub fn has_commit(&self, hash: &str) -> bool {
+        self.run(&["cat-file", "-e", &format!("{}^{{commit}}", hash)])
+            .is_ok()
+    }
+
+    /// Whether `refs/remotes/origin/<branch>` exists.
+    pub fn has_remote_branch(&self, branch: &str) -> bool {
+        self.run(&[
+            "show-ref",
+            "--verify",
+            "--quiet",
+            &format!("refs/remotes/origin/{}", branch),
+        ])
+        .is_ok()
+    }
+
+    /// Ref to hand git for `branch`: the local branch when there is one, otherwise its
+    /// `origin/` remote-tracking branch (fresh clones often only have the latter).
+    pub fn resolve_branch(&self, branch: &str) -> String {
+        if !self.has_local_branch(branch) && self.has_remote_branch(branch) {
+            format!("origin/{}", branch)
+        } else {
+            branch.to_string()
+        }
+    }
+
+    /// Create a local branch tracking `origin/<branch>` if only the remote one exists.
+    ///
+    /// Returns whether a branch was created.
+    pub fn ensure_local_branch(&self, branch: &str) -> Result<bool> {
+        if self.has_local_branch(branch) || !self.has_remote_branch(branch) {
+            return Ok(false);
+        }
+        self.run(&["branch", "--track", branch, &format!("origin/{}", branch)])?;
+        Ok(true)
+    }
+
+    /// Short hash HEAD is detached at, or `None` when a branch is checked out.
+    pub fn detached_head(&self) -> Result<Option<String>> {
+        if !self.run(&["branch", "--show-current"])?.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(self.run(&["rev-parse", "--short", "HEAD"])?))
     }
 
     pub fn get_current_commit_hash(&self) -> Result<String> {
@@ -55,35 +390,337 @@ impl GitRepository {
         Ok(output.trim().to_string())
     }
 
+    /// Create a commit with the tree of `tree_of` on top of `parent`, without touching
+    /// the working tree or any ref. Returns the new commit's hash.
+    pub fn commit_tree(&self, tree_of: &str, parent: &str, message: &str) -> Result<String> {
+        let tree = format!("{}^{{tree}}", tree_of);
+        let output = self.run(&["commit-tree", &tree, "-p", parent, "-m", message])?;
+        Ok(output.trim().to_string())
+    }
+
+    /// Check whether `ancestor` is reachable from `descendant`.
+    pub fn is_ancestor(&self, ancestor: &str, descendant: &str) -> bool {
+        self.run(&["merge-base", "--is-ancestor", ancestor, descendant])
+            .is_ok()
+    }
+
+    /// Merge commits reachable from `theirs` but not from `ours`
+    pub fn merge_commits_in(&self, ours: &str, theirs: &str) -> Result<Vec<String>> {
+        let output = self.run(&["rev-list", "--merges", &format!("{}..{}", ours, theirs)])?;
+        Ok(output.lines().map(str::to_string).collect())
+    }
+
+    /// List commits reachable from `theirs` but not from `ours`, newest first.
+    ///
+    /// For `ours = branch` and `theirs = origin/branch` these are the commits a
+    /// force-push of `branch` would discard from the remote.
+    pub fn commits_only_in(&self, ours: &str, theirs: &str) -> Result<Vec<CommitSummary>> {
+        let output = self.run(&[
+            "log",
+            "--format=%H%x1f%an%x1f%ae%x1f%s",
+            &format!("{}..{}", ours, theirs),
+        ])?;
+
+        Ok(output
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.splitn(4, '\x1f');
+                Some(CommitSummary {
+                    hash: fields.next()?.to_string(),
+                    author_name: fields.next()?.to_string(),
+                    author_email: fields.next()?.to_string(),
+                    subject: fields.next().unwrap_or_default().to_string(),
+                })
+            })
+            .collect())
+    }
+
+    /// Blame `file` at `rev`, optionally limited to the 1-based inclusive line `range`.
+    /// Returns the commit that last changed each line, with the line's content.
+    pub fn blame(
+        &self,
+        rev: &str,
+        file: &str,
+        range: Option<(usize, usize)>,
+    ) -> Result<Vec<(String, String)>> {
+        let line_range = range.map(|(start, end)| format!("{},{}", start, end));
+        let mut args = vec!["blame", "--porcelain"];
+        if let Some(line_range) = &line_range {
+            args.extend_from_slice(&["-L", line_range]);
+        }
+        args.extend_from_slice(&[rev, "--", file]);
+        let output = self.run(&args)?;
+
+        // Each line is a `<hash> <orig> <final> [<count>]` header, optional commit
+        // metadata, then the content prefixed with a tab
+        let mut lines = Vec::new();
+        let mut commit = String::new();
+        for line in output.lines() {
+            if let Some(content) = line.strip_prefix('\t') {
+                lines.push((commit.clone(), content.to_string()));
+            } else if let Some(hash) = line.split(' ').next() {
+                if hash.len() == 40 && hash.chars().all(|c| c.is_ascii_hexdigit()) {
+                    commit = hash.to_string();
+                }
+            }
+        }
+        Ok(lines)
+    }
+
+    /// Per-file changes `branch` makes since it forked from `base`.
+    pub fn diff_stat(&self, base: &str, branch: &str) -> Result<Vec<FileDiffStat>> {
+        let output = self.run(&["diff", "--numstat", &format!("{}...{}", base, branch)])?;
+        Ok(output
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.splitn(3, '\t');
+                let added = fields.next()?.parse().unwrap_or(0);
+                let deleted = fields.next()?.parse().unwrap_or(0);
+                Some(FileDiffStat {
+                    path: fields.next()?.to_string(),
+                    added,
+                    deleted,
+                })
+            })
+            .collect())
+    }
+
+    /// Email configured for the current user, if any.
+    pub fn user_email(&self) -> Option<String> {
+        self.run(&["config", "user.email"])
+            .ok()
+            .map(|email| email.trim().to_string())
+            .filter(|email| !email.is_empty())
+    }
+
     pub fn has_uncommitted_changes(&self) -> Result<bool> {
         let output = self.run(&["status", "--porcelain"])?;
         Ok(!output.is_empty())
     }
 }
 
+/// Make `command` author and commit as `identity` rather than the configured user
+fn apply_commit_identity(command: &mut Command, identity: Option<&CommitIdentity>) {
+    if let Some(identity) = identity {
+        if !identity.name.is_empty() {
+            command
+                .env("GIT_AUTHOR_NAME", &identity.name)
+                .env("GIT_COMMITTER_NAME", &identity.name);
+        }
+        if !identity.email.is_empty() {
+            command
+                .env("GIT_AUTHOR_EMAIL", &identity.email)
+                .env("GIT_COMMITTER_EMAIL", &identity.email);
+        }
+    }
+}
+
 /// Helper function to run a git command.
-fn run_cmd<P: AsRef<Path>>(args: &[&str], cwd: P) -> Result<String> {
+fn run_cmd<P: AsRef<Path>>(
+    args: &[&str],
+    cwd: P,
+    audit_log: Option<&Path>,
+    identity: Option<&CommitIdentity>,
+) -> Result<String> {
     let args_str = args.join(" ");
-    info!(
+    debug!(
         "Running git command: `git {}` in `{:?}`",
         args_str,
         cwd.as_ref()
     );
 
-    let output = Command::new("git")
+    if CANCELLED.load(Ordering::SeqCst) {
+        return Err(interrupted(&args_str, audit_log));
+    }
+
+    let started = Instant::now();
+    let mut command = Command::new("git");
+    command
         .args(args)
+        .env(INTERNAL_ENV, "1")
         .current_dir(cwd.as_ref())
-        .output()?;
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    apply_commit_identity(&mut command, identity);
+    let mut child = command.spawn()?;
 
-    if output.status.success() {
-        let stdout = String::from_utf8(output.stdout)?.trim().to_string();
-        Ok(stdout)
+    // Drain both pipes on their own threads so a chatty command can't block on a full pipe
+    let drain = |pipe: Option<Box<dyn Read + Send>>| {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut buffer = Vec::new();
+            if let Some(mut pipe) = pipe {
+                let _ = pipe.read_to_end(&mut buffer);
+            }
+            let _ = sender.send(buffer);
+        });
+        receiver
+    };
+    let stdout_reader = drain(
+        child
+            .stdout
+            .take()
+            .map(|p| Box::new(p) as Box<dyn Read + Send>),
+    );
+    let stderr_reader = drain(
+        child
+            .stderr
+            .take()
+            .map(|p| Box::new(p) as Box<dyn Read + Send>),
+    );
+
+    let timeout = match COMMAND_TIMEOUT_SECS.load(Ordering::SeqCst) {
+        0 => None,
+        secs => Some(Duration::from_secs(secs)),
+    };
+    let status = loop {
+        if let Some(status) = child.wait_timeout(CANCEL_POLL_INTERVAL)? {
+            break Some(status);
+        }
+        let timed_out = timeout.is_some_and(|timeout| started.elapsed() >= timeout);
+        if timed_out || CANCELLED.load(Ordering::SeqCst) {
+            let _ = child.kill();
+            let _ = child.wait();
+            break None;
+        }
+    };
+    let elapsed = started.elapsed();
+
+    // Hooks spawned by a killed git may still hold the pipes open; don't wait for them
+    let collect = |reader: mpsc::Receiver<Vec<u8>>| match status {
+        Some(_) => reader.recv().unwrap_or_default(),
+        None => reader
+            .recv_timeout(CANCEL_POLL_INTERVAL)
+            .unwrap_or_default(),
+    };
+    let stdout_bytes = collect(stdout_reader);
+    let stderr_bytes = collect(stderr_reader);
+    let stdout = String::from_utf8_lossy(&stdout_bytes);
+    let stderr = String::from_utf8_lossy(&stderr_bytes);
+    let success = status.is_some_and(|status| status.success());
+    if let Some(audit_log) = audit_log {
+        let shown = match status {
+            Some(_) if success => &stdout,
+            Some(_) => &stderr,
+            None => "(killed)",
+        };
+        let entry = AuditEntry {
+            args: &args_str,
+            cwd: cwd.as_ref(),
+            elapsed,
+            exit_code: status.and_then(|status| status.code()),
+            output: shown,
+        };
+        // Auditing must never break the command itself
+        if let Err(e) = append_audit_entry(audit_log, &entry) {
+            debug!("Could not write git audit log: {}", e);
+        }
+    }
+
+    // A git process killed by the terminal's Ctrl+C also counts as interrupted
+    if !success && CANCELLED.load(Ordering::SeqCst) {
+        return Err(interrupted(&args_str, audit_log));
+    }
+    if status.is_none() {
+        return Err(anyhow!(TrainError::GitError {
+            message: format!(
+                "git {} timed out after {:.0?}; raise it with 'git-train config set timeouts.git_secs <seconds>' (0 disables)",
+                args_str, elapsed
+            ),
+        }));
+    }
+
+    if success {
+        info!("git {} ({:.1?})", args_str, elapsed);
+        Ok(stdout.trim().to_string())
     } else {
-        let stderr = String::from_utf8(output.stderr)?;
-        error!(
-            "Git command `git {}` failed with stderr: {}",
-            args_str, stderr
-        );
-        Err(anyhow!(TrainError::GitError { message: stderr }))
+        // Many callers probe with git and handle the failure themselves, so only
+        // surface it in verbose mode; the returned error carries stderr
+        info!("git {} failed ({:.1?})", args_str, elapsed);
+        debug!("stderr: {}", stderr.trim());
+        Err(anyhow!(TrainError::GitError {
+            message: stderr.to_string()
+        }))
     }
 }
+
+fn interrupted_path(audit_log: &Path) -> PathBuf {
+    audit_log.with_file_name("interrupted.json")
+}
+
+/// Record the interruption for `git-train continue` and build the error to return
+fn interrupted(args_str: &str, audit_log: Option<&Path>) -> anyhow::Error {
+    if let Some(audit_log) = audit_log {
+        let path = interrupted_path(audit_log);
+        // Keep the first record: that is where the command actually stopped
+        if !path.exists() {
+            let record = InterruptedCommand {
+                interrupted_at: Utc::now(),
+                command: format!(
+                    "git-train {}",
+                    std::env::args().skip(1).collect::<Vec<_>>().join(" ")
+                ),
+                git_command: format!("git {}", args_str),
+            };
+            if let Ok(json) = serde_json::to_string_pretty(&record) {
+                if let Err(e) = fs::write(&path, json) {
+                    debug!("Could not record the interrupted command: {}", e);
+                }
+            }
+        }
+    }
+    anyhow!(TrainError::Interrupted {
+        message: format!(
+            "stopped while running 'git {}'; run 'git-train continue' to see where it left off",
+            args_str
+        ),
+    })
+}
+
+struct AuditEntry<'a> {
+    args: &'a str,
+    cwd: &'a Path,
+    elapsed: Duration,
+    exit_code: Option<i32>,
+    output: &'a str,
+}
+
+/// Append one line per command to the audit log, rotating it once it grows too large.
+fn append_audit_entry(path: &Path, entry: &AuditEntry) -> Result<()> {
+    let Some(dir) = path.parent() else {
+        return Ok(());
+    };
+    fs::create_dir_all(dir)?;
+
+    if fs::metadata(path).is_ok_and(|m| m.len() > AUDIT_LOG_MAX_BYTES) {
+        fs::rename(path, path.with_extension("log.1"))?;
+    }
+
+    let mut output: String = entry
+        .output
+        .trim()
+        .chars()
+        .take(AUDIT_OUTPUT_LIMIT)
+        .collect();
+    if entry.output.trim().chars().count() > AUDIT_OUTPUT_LIMIT {
+        output.push('…');
+    }
+
+    let line = format!(
+        "{} exit={} {:.1?} cwd={} git {} | {}",
+        Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+        entry
+            .exit_code
+            .map(|code| code.to_string())
+            .unwrap_or_else(|| "signal".to_string()),
+        entry.elapsed,
+        entry.cwd.display(),
+        entry.args,
+        output.replace('\n', "\\n")
+    );
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", redact_secrets(&line))?;
+    Ok(())
+}
@@ -1,21 +1,74 @@
-use crate::errors::TrainError;
+use crate::errors::{is_gitlab_auth_error, TrainError};
 use crate::git::GitRepository;
+use crate::gitlab::cache::EtagCache;
+use crate::ids::MrIid;
 use anyhow::Result;
 use async_trait::async_trait;
-use reqwest::Client;
+use reqwest::header::{HeaderMap, ETAG, IF_NONE_MATCH};
+use reqwest::{Client, Response, StatusCode};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 use tokio::sync::RwLock;
+use tracing::info;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct MergeRequest {
     pub id: u64,
-    pub iid: u64,
+    pub iid: MrIid,
     pub title: String,
     pub description: Option<String>,
     pub source_branch: String,
     pub target_branch: String,
+    /// Project the source branch lives in (differs from the target project for forks)
+    #[serde(default)]
+    pub source_project_id: Option<u64>,
     pub state: String,
     pub web_url: String,
+    /// Latest pipeline for the source branch (only returned when fetching a single MR)
+    #[serde(default)]
+    pub head_pipeline: Option<Pipeline>,
+    /// GitLab can't merge the source into the target branch without conflicts
+    #[serde(default)]
+    pub has_conflicts: bool,
+    /// e.g. `mergeable`, `conflict`, `checking`, `need_rebase`
+    #[serde(default)]
+    pub detailed_merge_status: Option<String>,
+    /// Head commit of the source branch as GitLab last saw it
+    #[serde(default)]
+    pub sha: Option<String>,
+    #[serde(default)]
+    pub milestone: Option<Milestone>,
+}
+
+impl MergeRequest {
+    /// Whether GitLab reports conflicts with the MR's current target, which can happen
+    /// even when the branch is cleanly stacked locally (e.g. after a retarget)
+    pub fn conflicts_with_target(&self) -> bool {
+        self.has_conflicts || self.detailed_merge_status.as_deref() == Some("conflict")
+    }
+}
+
+/// How [`GitLabApi::merge_merge_request`] accepts an MR
+#[derive(Debug, Clone, Default)]
+pub struct MergeParams {
+    /// Squash the MR's commits into one commit with this message
+    pub squash_message: Option<String>,
+    /// Delete the source branch once the MR is merged
+    pub remove_source_branch: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Milestone {
+    pub id: u64,
+    pub title: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Pipeline {
+    pub id: u64,
+    /// e.g. `running`, `success`, `failed`, `canceled`
+    pub status: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -38,14 +91,52 @@ pub struct GitLabNamespace {
     pub path: String,
 }
 
+/// Approval state of a merge request
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MergeRequestApprovals {
+    #[serde(default)]
+    pub approved: bool,
+    #[serde(default)]
+    pub approvals_left: u32,
+}
+
+/// A deployment environment, such as the review app a pipeline deploys for a branch
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Environment {
+    pub name: String,
+    /// Where the environment is reachable, if the deploy job sets a URL
+    #[serde(default)]
+    pub external_url: Option<String>,
+}
+
+/// A GitLab user account
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GitLabUser {
+    pub id: u64,
+    pub username: String,
+}
+
 #[derive(Debug, Serialize)]
 pub struct CreateMergeRequestRequest {
     pub source_branch: String,
     pub target_branch: String,
     pub title: String,
     pub description: Option<String>,
+    /// Users asked to review the new MR
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub reviewer_ids: Vec<u64>,
 }
 
+/// Page size requested from list endpoints (GitLab's maximum)
+const PER_PAGE: u32 = 100;
+/// Upper bound on pages fetched by a single list call
+const MAX_PAGES: u32 = 50;
+/// Seconds `rebase_merge_request` waits for GitLab to finish a rebase
+const REBASE_POLL_ATTEMPTS: usize = 60;
+
+/// Remote that holds the project MRs are opened in when `origin` is a fork of it
+const UPSTREAM_REMOTE: &str = "upstream";
+
 #[derive(Debug, Clone)]
 pub struct ProjectInfo {
     pub host: String,
@@ -56,89 +147,266 @@ pub struct ProjectInfo {
 #[async_trait]
 pub trait GitLabApi: Send + Sync {
     async fn detect_and_cache_project(&self) -> Result<GitLabProject>;
+    /// Fork that branches are pushed to when MRs are opened in the project it was
+    /// forked from; `None` when both live in the same project
+    async fn fork_project(&self) -> Result<Option<GitLabProject>>;
     async fn create_merge_request(
         &self,
         request: CreateMergeRequestRequest,
     ) -> Result<MergeRequest>;
     async fn update_merge_request(
         &self,
-        iid: u64,
+        iid: MrIid,
         title: Option<String>,
         description: Option<String>,
     ) -> Result<MergeRequest>;
     async fn update_merge_request_with_target(
         &self,
-        iid: u64,
+        iid: MrIid,
         title: Option<String>,
         description: Option<String>,
         target_branch: Option<String>,
     ) -> Result<MergeRequest>;
-    async fn get_merge_request(&self, iid: u64) -> Result<MergeRequest>;
+    async fn get_merge_request(&self, iid: MrIid) -> Result<MergeRequest>;
+    /// List MRs whose source branch is `source_branch`, optionally filtered by state
+    async fn list_merge_requests_by_source_branch(
+        &self,
+        source_branch: &str,
+        state: Option<&str>,
+    ) -> Result<Vec<MergeRequest>>;
+    async fn get_merge_request_approvals(&self, iid: MrIid) -> Result<MergeRequestApprovals>;
+    /// Active milestone titled exactly `title`, in the project or one of its groups
+    async fn find_milestone(&self, title: &str) -> Result<Option<Milestone>>;
+    async fn set_merge_request_milestone(
+        &self,
+        iid: MrIid,
+        milestone_id: u64,
+    ) -> Result<MergeRequest>;
+    /// Available environments deployed for `branch`, such as `review/<branch>` review apps
+    async fn list_branch_environments(&self, branch: &str) -> Result<Vec<Environment>>;
+    /// Post a comment on the MR
+    async fn create_merge_request_note(&self, iid: MrIid, body: &str) -> Result<()>;
+    async fn close_merge_request(&self, iid: MrIid) -> Result<MergeRequest>;
+    /// Accept (merge) the MR as described by `params`
+    async fn merge_merge_request(&self, iid: MrIid, params: &MergeParams) -> Result<MergeRequest>;
+    /// Have GitLab rebase the MR's source branch onto its target, waiting until done
+    async fn rebase_merge_request(&self, iid: MrIid) -> Result<()>;
+    /// MRs that must merge before `iid` can (GitLab Premium blocking MRs)
+    async fn list_blocking_merge_requests(&self, iid: MrIid) -> Result<Vec<MrIid>>;
+    /// Make `blocking` a blocking MR of `iid`
+    async fn add_blocking_merge_request(&self, iid: MrIid, blocking: &MergeRequest) -> Result<()>;
+    /// The user the token belongs to
+    async fn current_user(&self) -> Result<GitLabUser>;
+    /// The user named `username`, `None` when there is none (e.g. it names a group)
+    async fn find_user(&self, username: &str) -> Result<Option<GitLabUser>>;
+}
+
+/// Credentials for the GitLab API
+#[derive(Debug, Clone, PartialEq)]
+pub enum GitLabToken {
+    /// Personal, project or group access token from `GITLAB_TOKEN`
+    Access(String),
+    /// `CI_JOB_TOKEN` of the running GitLab CI job
+    Job(String),
+}
+
+impl GitLabToken {
+    fn header_name(&self) -> &'static str {
+        match self {
+            GitLabToken::Access(_) => "Authorization",
+            GitLabToken::Job(_) => "JOB-TOKEN",
+        }
+    }
+
+    fn header_value(&self) -> String {
+        match self {
+            GitLabToken::Access(token) => format!("Bearer {}", token),
+            GitLabToken::Job(token) => token.clone(),
+        }
+    }
+
+    /// What to do about a 401/403 from GitLab with this kind of token
+    fn scope_hint(&self, status: StatusCode) -> String {
+        match (self, status) {
+            (GitLabToken::Job(_), _) => "CI_JOB_TOKEN can't call this endpoint; set GITLAB_TOKEN to a token with the 'api' scope".to_string(),
+            (GitLabToken::Access(_), StatusCode::UNAUTHORIZED) => "GITLAB_TOKEN is invalid, expired or revoked; create a token with the 'api' scope (and 'write_repository' to push over HTTPS)".to_string(),
+            (GitLabToken::Access(_), _) => "GITLAB_TOKEN needs the 'api' scope (and 'write_repository' to push over HTTPS), and at least the Developer role in the project".to_string(),
+        }
+    }
+
+    /// Error for a failed response: a [`TrainError::GitLabAuthError`] when GitLab
+    /// rejected the credentials, otherwise `action` with the response body.
+    async fn error_for(&self, response: Response, action: &str) -> anyhow::Error {
+        let status = response.status();
+        if matches!(status, StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) {
+            return TrainError::GitLabAuthError {
+                status: status.as_u16(),
+                endpoint: response.url().path().to_string(),
+                hint: self.scope_hint(status),
+            }
+            .into();
+        }
+        match response.text().await {
+            Ok(error_text) => TrainError::GitLabError {
+                message: format!("{}: {}", action, error_text),
+            }
+            .into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+/// Where to reach GitLab and how to authenticate, resolved from the environment
+#[derive(Debug, Clone, PartialEq)]
+pub struct GitLabSettings {
+    pub base_url: String,
+    pub token: GitLabToken,
+    /// Project to use when it can't be detected from the git remotes
+    pub project_id: Option<String>,
+}
+
+impl GitLabSettings {
+    /// Resolve settings from `GITLAB_*` variables, falling back to the variables GitLab
+    /// CI predefines (`CI_SERVER_URL`, `CI_PROJECT_ID`, `CI_JOB_TOKEN`) inside a job.
+    pub fn from_env(var: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let var = |name: &str| var(name).filter(|value| !value.is_empty());
+        let in_ci = var("GITLAB_CI").as_deref() == Some("true");
+        let ci_var = |name: &str| if in_ci { var(name) } else { None };
+
+        let token = match (var("GITLAB_TOKEN"), ci_var("CI_JOB_TOKEN")) {
+            (Some(token), _) => GitLabToken::Access(token),
+            (None, Some(job_token)) => GitLabToken::Job(job_token),
+            (None, None) => {
+                return Err(TrainError::SecurityError {
+                    message: "GITLAB_TOKEN environment variable not set".to_string(),
+                }
+                .into())
+            }
+        };
+        let base_url = var("GITLAB_URL")
+            .or_else(|| ci_var("CI_SERVER_URL"))
+            .unwrap_or_else(|| "https://gitlab.com".to_string());
+        let project_id = var("GITLAB_PROJECT_ID").or_else(|| ci_var("CI_PROJECT_ID"));
+
+        Ok(Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            token,
+            project_id,
+        })
+    }
 }
 
 pub struct GitLabClient {
     client: Client,
     base_url: String,
-    token: String,
+    token: GitLabToken,
+    project_id: Option<String>,
     project_info: RwLock<Option<ProjectInfo>>,
     project_details: RwLock<Option<GitLabProject>>,
+    fork_details: RwLock<Option<GitLabProject>>,
+    /// Bodies of MR and project GETs, revalidated with `If-None-Match`
+    etags: EtagCache,
     git_repo: GitRepository,
 }
 
 impl GitLabClient {
-    pub async fn new(git_repo: GitRepository) -> Result<Self> {
-        let token = std::env::var("GITLAB_TOKEN").map_err(|_| TrainError::SecurityError {
-            message: "GITLAB_TOKEN environment variable not set".to_string(),
-        })?;
-
-        let base_url =
-            std::env::var("GITLAB_URL").unwrap_or_else(|_| "https://gitlab.com".to_string());
+    /// `timeout` bounds every API request (`None` waits forever)
+    pub async fn new(git_repo: GitRepository, timeout: Option<Duration>) -> Result<Self> {
+        let settings = GitLabSettings::from_env(|name| std::env::var(name).ok())?;
+        if matches!(settings.token, GitLabToken::Job(_)) {
+            info!("Using CI_JOB_TOKEN for GitLab at {}", settings.base_url);
+        }
 
-        let client = Client::new();
+        let mut builder = Client::builder();
+        if let Some(timeout) = timeout {
+            builder = builder.timeout(timeout);
+        }
+        let client = builder.build()?;
 
         Ok(Self {
             client,
-            base_url,
-            token,
+            base_url: settings.base_url,
+            token: settings.token,
+            project_id: settings.project_id,
             project_info: RwLock::new(None),
             project_details: RwLock::new(None),
+            fork_details: RwLock::new(None),
+            etags: EtagCache::load(&git_repo.train_dir()),
             git_repo,
         })
     }
 
-    async fn detect_project_from_remotes(&self) -> Result<(ProjectInfo, GitLabProject)> {
-        // Get all git remotes
+    /// Project MRs are opened in, plus the fork branches are pushed to when `origin`
+    /// and `upstream` point at different projects
+    async fn detect_project_from_remotes(
+        &self,
+    ) -> Result<(ProjectInfo, GitLabProject, Option<GitLabProject>)> {
+        // Get all git remotes, keeping the first GitLab URL of each
         let remotes_output = self.git_repo.run(&["remote", "-v"])?;
+        let host = self.base_url.replace("https://", "").replace("http://", "");
+        let mut remotes: Vec<(String, ProjectInfo)> = Vec::new();
 
         for line in remotes_output.lines() {
+            let Some(name) = line.split_whitespace().next() else {
+                continue;
+            };
+            if remotes.iter().any(|(seen, _)| seen == name) {
+                continue;
+            }
             if let Some(project_info) = Self::parse_gitlab_remote(line)? {
                 // Verify this matches our GitLab instance
-                if project_info.host == self.base_url.replace("https://", "").replace("http://", "")
+                if project_info.host == host
                     || (self.base_url.contains("gitlab.com") && project_info.host == "gitlab.com")
                 {
-                    // Fetch project details from GitLab API
-                    let project_path =
-                        format!("{}/{}", project_info.namespace, project_info.project);
-                    if let Ok(project_details) = Self::get_project_by_path(
-                        &self.base_url,
-                        &self.token,
-                        &self.client,
-                        &project_path,
-                    )
-                    .await
-                    {
-                        return Ok((project_info, project_details));
-                    }
+                    remotes.push((name.to_string(), project_info));
+                }
+            }
+        }
+
+        let remote = |name: &str| {
+            remotes
+                .iter()
+                .find(|(remote, _)| remote == name)
+                .map(|(_, info)| info)
+        };
+        if let (Some(upstream), Some(origin)) = (remote(UPSTREAM_REMOTE), remote("origin")) {
+            if let (Some(upstream_details), Some(fork_details)) = (
+                self.lookup_project(upstream).await?,
+                self.lookup_project(origin).await?,
+            ) {
+                if upstream_details.id != fork_details.id {
+                    info!(
+                        "Opening MRs in {} from the fork {}",
+                        upstream_details.web_url, fork_details.web_url
+                    );
+                    return Ok((upstream.clone(), upstream_details, Some(fork_details)));
                 }
             }
         }
 
+        for (_, project_info) in &remotes {
+            if let Some(project_details) = self.lookup_project(project_info).await? {
+                return Ok((project_info.clone(), project_details, None));
+            }
+        }
+
         Err(TrainError::GitLabError {
             message: "Could not detect GitLab project from git remotes".to_string(),
         }
         .into())
     }
 
+    /// Project behind a remote, `None` when GitLab doesn't know it
+    async fn lookup_project(&self, project_info: &ProjectInfo) -> Result<Option<GitLabProject>> {
+        let project_path = format!("{}/{}", project_info.namespace, project_info.project);
+        match self.get_project_by_path(&project_path).await {
+            Ok(project_details) => Ok(Some(project_details)),
+            // Another remote won't fare better with the same token
+            Err(e) if is_gitlab_auth_error(&e) => Err(e),
+            Err(_) => Ok(None),
+        }
+    }
+
     fn parse_gitlab_remote(remote_line: &str) -> Result<Option<ProjectInfo>> {
         // Parse lines like:
         // origin  git@gitlab.com:namespace/project.git (fetch)
@@ -196,61 +464,98 @@ impl GitLabClient {
         Ok(None)
     }
 
-    async fn get_project_by_path(
-        base_url: &str,
-        token: &str,
-        client: &Client,
-        project_path: &str,
-    ) -> Result<GitLabProject> {
+    async fn get_project_by_path(&self, project_path: &str) -> Result<GitLabProject> {
         // URL encode the project path for the API
         let encoded_path = urlencoding::encode(project_path);
-        let url = format!("{}/api/v4/projects/{}", base_url, encoded_path);
+        let url = format!("{}/api/v4/projects/{}", self.base_url, encoded_path);
+        self.get_json_cached(
+            &url,
+            &format!("Failed to get project by path {}", project_path),
+        )
+        .await
+    }
 
-        let response = client
-            .get(&url)
-            .header("Authorization", format!("Bearer {}", token))
-            .send()
-            .await?;
+    async fn get_project_by_id(&self, project_id: &str) -> Result<GitLabProject> {
+        let url = format!("{}/api/v4/projects/{}", self.base_url, project_id);
+        self.get_json_cached(&url, &format!("Failed to get project by ID {}", project_id))
+            .await
+    }
 
-        if response.status().is_success() {
-            let project: GitLabProject = response.json().await?;
-            Ok(project)
-        } else {
-            let error_text = response.text().await?;
-            Err(TrainError::GitLabError {
-                message: format!(
-                    "Failed to get project by path {}: {}",
-                    project_path, error_text
-                ),
+    /// GET a single resource, sending the ETag of the cached copy so an unchanged
+    /// resource comes back as a bodyless `304 Not Modified`
+    async fn get_json_cached<T: DeserializeOwned>(&self, url: &str, action: &str) -> Result<T> {
+        let cached = self.etags.get(url);
+        let mut request = self
+            .client
+            .get(url)
+            .header(self.token.header_name(), self.token.header_value());
+        if let Some(cached) = &cached {
+            request = request.header(IF_NONE_MATCH, &cached.etag);
+        }
+        let response = request.send().await?;
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            if let Some(cached) = cached {
+                self.etags.touch(url);
+                return Ok(serde_json::from_str(&cached.body)?);
             }
-            .into())
         }
+        if !response.status().is_success() {
+            return Err(self.token.error_for(response, action).await);
+        }
+
+        let etag = response
+            .headers()
+            .get(ETAG)
+            .and_then(|etag| etag.to_str().ok())
+            .map(str::to_string);
+        let body = response.text().await?;
+        let value = serde_json::from_str(&body)?;
+        if let Some(etag) = etag {
+            self.etags.store(url, &etag, &body);
+        }
+        Ok(value)
     }
 
-    async fn get_project_by_id(
-        base_url: &str,
-        token: &str,
-        client: &Client,
-        project_id: &str,
-    ) -> Result<GitLabProject> {
-        let url = format!("{}/api/v4/projects/{}", base_url, project_id);
+    /// GET every page of a list endpoint, following GitLab's `X-Next-Page` header.
+    async fn get_paginated<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        query: &[(&str, &str)],
+    ) -> Result<Vec<T>> {
+        let mut items = Vec::new();
+        let mut page = 1;
 
-        let response = client
-            .get(&url)
-            .header("Authorization", format!("Bearer {}", token))
-            .send()
-            .await?;
+        loop {
+            let page_param = page.to_string();
+            let per_page_param = PER_PAGE.to_string();
+            let response = self
+                .client
+                .get(url)
+                .header(self.token.header_name(), self.token.header_value())
+                .query(query)
+                .query(&[("page", &page_param), ("per_page", &per_page_param)])
+                .send()
+                .await?;
 
-        if response.status().is_success() {
-            let project: GitLabProject = response.json().await?;
-            Ok(project)
-        } else {
-            let error_text = response.text().await?;
-            Err(TrainError::GitLabError {
-                message: format!("Failed to get project by ID {}: {}", project_id, error_text),
+            if !response.status().is_success() {
+                return Err(self
+                    .token
+                    .error_for(response, &format!("Failed to list {}", url))
+                    .await);
+            }
+
+            let next = next_page(response.headers());
+            let mut batch: Vec<T> = response.json().await?;
+            items.append(&mut batch);
+
+            match next {
+                Some(next) if next > page && next <= MAX_PAGES => page = next,
+                _ => break,
             }
-            .into())
         }
+
+        Ok(items)
     }
 
     async fn get_project_id_for_api(&self) -> Result<String> {
@@ -281,29 +586,33 @@ impl GitLabApi for GitLabClient {
 
         // Try to auto-detect project from git remotes
         match self.detect_project_from_remotes().await {
-            Ok((info, details)) => {
+            Ok((info, details, fork)) => {
                 // Cache both project info and details
                 {
                     let mut project_info = self.project_info.write().await;
                     *project_info = Some(info);
                 }
+                {
+                    let mut fork_details = self.fork_details.write().await;
+                    *fork_details = fork;
+                }
                 {
                     let mut project_details = self.project_details.write().await;
                     *project_details = Some(details.clone());
                 }
                 Ok(details)
             }
+            Err(e) if is_gitlab_auth_error(&e) => Err(e),
             Err(_) => {
-                // Fall back to environment variables if available
-                if let Ok(project_id) = std::env::var("GITLAB_PROJECT_ID") {
-                    if let Ok(project_details) = Self::get_project_by_id(
-                        &self.base_url,
-                        &self.token,
-                        &self.client,
-                        &project_id,
-                    )
-                    .await
-                    {
+                // Fall back to GITLAB_PROJECT_ID / CI_PROJECT_ID if available
+                if let Some(project_id) = &self.project_id {
+                    let found = self.get_project_by_id(project_id).await;
+                    if let Err(e) = &found {
+                        if is_gitlab_auth_error(e) {
+                            return found;
+                        }
+                    }
+                    if let Ok(project_details) = found {
                         // Cache the project details
                         {
                             let mut cached_details = self.project_details.write().await;
@@ -315,7 +624,7 @@ impl GitLabApi for GitLabClient {
 
                 Err(TrainError::GitLabError {
                     message:
-                        "Could not detect GitLab project from git remotes or GITLAB_PROJECT_ID"
+                        "Could not detect GitLab project from git remotes, GITLAB_PROJECT_ID or CI_PROJECT_ID"
                             .to_string(),
                 }
                 .into())
@@ -323,21 +632,36 @@ impl GitLabApi for GitLabClient {
         }
     }
 
+    async fn fork_project(&self) -> Result<Option<GitLabProject>> {
+        self.detect_and_cache_project().await?;
+        Ok(self.fork_details.read().await.clone())
+    }
+
     async fn create_merge_request(
         &self,
         request: CreateMergeRequestRequest,
     ) -> Result<MergeRequest> {
-        let project_id = self.get_project_id_for_api().await?;
+        let project = self.detect_and_cache_project().await?;
+        let mut body = serde_json::to_value(&request)?;
+
+        // From a fork, the MR is created in the fork and pointed at the upstream project
+        let source_project_id = match self.fork_project().await? {
+            Some(fork) => {
+                body["target_project_id"] = project.id.into();
+                fork.id
+            }
+            None => project.id,
+        };
         let url = format!(
             "{}/api/v4/projects/{}/merge_requests",
-            self.base_url, project_id
+            self.base_url, source_project_id
         );
 
         let response = self
             .client
             .post(&url)
-            .header("Authorization", format!("Bearer {}", self.token))
-            .json(&request)
+            .header(self.token.header_name(), self.token.header_value())
+            .json(&body)
             .send()
             .await?;
 
@@ -345,17 +669,13 @@ impl GitLabApi for GitLabClient {
             let mr: MergeRequest = response.json().await?;
             Ok(mr)
         } else {
-            let error_text = response.text().await?;
-            Err(TrainError::GitLabError {
-                message: format!("Failed to create MR: {}", error_text),
-            }
-            .into())
+            Err(self.token.error_for(response, "Failed to create MR").await)
         }
     }
 
     async fn update_merge_request(
         &self,
-        iid: u64,
+        iid: MrIid,
         title: Option<String>,
         description: Option<String>,
     ) -> Result<MergeRequest> {
@@ -379,7 +699,7 @@ impl GitLabApi for GitLabClient {
         let response = self
             .client
             .put(&url)
-            .header("Authorization", format!("Bearer {}", self.token))
+            .header(self.token.header_name(), self.token.header_value())
             .json(&params)
             .send()
             .await?;
@@ -388,17 +708,13 @@ impl GitLabApi for GitLabClient {
             let mr: MergeRequest = response.json().await?;
             Ok(mr)
         } else {
-            let error_text = response.text().await?;
-            Err(TrainError::GitLabError {
-                message: format!("Failed to update MR: {}", error_text),
-            }
-            .into())
+            Err(self.token.error_for(response, "Failed to update MR").await)
         }
     }
 
     async fn update_merge_request_with_target(
         &self,
-        iid: u64,
+        iid: MrIid,
         title: Option<String>,
         description: Option<String>,
         target_branch: Option<String>,
@@ -429,7 +745,7 @@ impl GitLabApi for GitLabClient {
         let response = self
             .client
             .put(&url)
-            .header("Authorization", format!("Bearer {}", self.token))
+            .header(self.token.header_name(), self.token.header_value())
             .json(&params)
             .send()
             .await?;
@@ -438,15 +754,195 @@ impl GitLabApi for GitLabClient {
             let mr: MergeRequest = response.json().await?;
             Ok(mr)
         } else {
-            let error_text = response.text().await?;
-            Err(TrainError::GitLabError {
-                message: format!("Failed to update MR with target: {}", error_text),
-            }
-            .into())
+            Err(self
+                .token
+                .error_for(response, "Failed to update MR with target")
+                .await)
+        }
+    }
+
+    async fn get_merge_request(&self, iid: MrIid) -> Result<MergeRequest> {
+        let project_id = self.get_project_id_for_api().await?;
+        let url = format!(
+            "{}/api/v4/projects/{}/merge_requests/{}",
+            self.base_url, project_id, iid
+        );
+        self.get_json_cached(&url, "Failed to get MR").await
+    }
+
+    async fn list_merge_requests_by_source_branch(
+        &self,
+        source_branch: &str,
+        state: Option<&str>,
+    ) -> Result<Vec<MergeRequest>> {
+        let project_id = self.get_project_id_for_api().await?;
+        let url = format!(
+            "{}/api/v4/projects/{}/merge_requests",
+            self.base_url, project_id
+        );
+
+        let mut query = vec![("source_branch", source_branch)];
+        if let Some(state) = state {
+            query.push(("state", state));
+        }
+        self.get_paginated(&url, &query).await
+    }
+
+    async fn find_milestone(&self, title: &str) -> Result<Option<Milestone>> {
+        let project_id = self.get_project_id_for_api().await?;
+        let url = format!(
+            "{}/api/v4/projects/{}/milestones",
+            self.base_url, project_id
+        );
+
+        let milestones: Vec<Milestone> = self
+            .get_paginated(
+                &url,
+                &[
+                    ("title", title),
+                    ("state", "active"),
+                    ("include_ancestors", "true"),
+                ],
+            )
+            .await?;
+        Ok(milestones.into_iter().find(|m| m.title == title))
+    }
+
+    async fn set_merge_request_milestone(
+        &self,
+        iid: MrIid,
+        milestone_id: u64,
+    ) -> Result<MergeRequest> {
+        let project_id = self.get_project_id_for_api().await?;
+        let url = format!(
+            "{}/api/v4/projects/{}/merge_requests/{}",
+            self.base_url, project_id, iid
+        );
+
+        let response = self
+            .client
+            .put(&url)
+            .header(self.token.header_name(), self.token.header_value())
+            .json(&serde_json::json!({ "milestone_id": milestone_id }))
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            let mr: MergeRequest = response.json().await?;
+            Ok(mr)
+        } else {
+            Err(self
+                .token
+                .error_for(response, "Failed to set MR milestone")
+                .await)
         }
     }
 
-    async fn get_merge_request(&self, iid: u64) -> Result<MergeRequest> {
+    async fn list_branch_environments(&self, branch: &str) -> Result<Vec<Environment>> {
+        let project_id = self.get_project_id_for_api().await?;
+        let url = format!(
+            "{}/api/v4/projects/{}/environments",
+            self.base_url, project_id
+        );
+
+        // Review apps are conventionally named after $CI_COMMIT_REF_SLUG, so search for
+        // the slug and keep the environments whose last path segment is the branch
+        let slug = ref_slug(branch);
+        let environments: Vec<Environment> = self
+            .get_paginated(&url, &[("search", &slug), ("states", "available")])
+            .await?;
+        Ok(environments
+            .into_iter()
+            .filter(|env| {
+                let leaf = env.name.rsplit('/').next().unwrap_or(&env.name);
+                leaf == slug || leaf == branch
+            })
+            .collect())
+    }
+
+    async fn list_blocking_merge_requests(&self, iid: MrIid) -> Result<Vec<MrIid>> {
+        #[derive(Deserialize)]
+        struct Block {
+            blocking_merge_request: BlockRef,
+        }
+        #[derive(Deserialize)]
+        struct BlockRef {
+            iid: MrIid,
+        }
+
+        let project_id = self.get_project_id_for_api().await?;
+        let url = format!(
+            "{}/api/v4/projects/{}/merge_requests/{}/blocks",
+            self.base_url, project_id, iid
+        );
+        let blocks: Vec<Block> = self.get_paginated(&url, &[]).await?;
+        Ok(blocks
+            .into_iter()
+            .map(|block| block.blocking_merge_request.iid)
+            .collect())
+    }
+
+    async fn add_blocking_merge_request(&self, iid: MrIid, blocking: &MergeRequest) -> Result<()> {
+        let project_id = self.get_project_id_for_api().await?;
+        let url = format!(
+            "{}/api/v4/projects/{}/merge_requests/{}/blocks",
+            self.base_url, project_id, iid
+        );
+
+        let response = self
+            .client
+            .post(&url)
+            .header(self.token.header_name(), self.token.header_value())
+            .query(&[("blocking_merge_request_id", blocking.id)])
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(self
+                .token
+                .error_for(response, "Failed to add a blocking MR")
+                .await)
+        }
+    }
+
+    async fn get_merge_request_approvals(&self, iid: MrIid) -> Result<MergeRequestApprovals> {
+        let project_id = self.get_project_id_for_api().await?;
+        let url = format!(
+            "{}/api/v4/projects/{}/merge_requests/{}/approvals",
+            self.base_url, project_id, iid
+        );
+        self.get_json_cached(&url, "Failed to get MR approvals")
+            .await
+    }
+
+    async fn create_merge_request_note(&self, iid: MrIid, body: &str) -> Result<()> {
+        let project_id = self.get_project_id_for_api().await?;
+        let url = format!(
+            "{}/api/v4/projects/{}/merge_requests/{}/notes",
+            self.base_url, project_id, iid
+        );
+
+        let response = self
+            .client
+            .post(&url)
+            .header(self.token.header_name(), self.token.header_value())
+            .json(&serde_json::json!({ "body": body }))
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(self
+                .token
+                .error_for(response, "Failed to comment on MR")
+                .await)
+        }
+    }
+
+    async fn close_merge_request(&self, iid: MrIid) -> Result<MergeRequest> {
         let project_id = self.get_project_id_for_api().await?;
         let url = format!(
             "{}/api/v4/projects/{}/merge_requests/{}",
@@ -455,8 +951,39 @@ impl GitLabApi for GitLabClient {
 
         let response = self
             .client
-            .get(&url)
-            .header("Authorization", format!("Bearer {}", self.token))
+            .put(&url)
+            .header(self.token.header_name(), self.token.header_value())
+            .json(&serde_json::json!({ "state_event": "close" }))
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            let mr: MergeRequest = response.json().await?;
+            Ok(mr)
+        } else {
+            Err(self.token.error_for(response, "Failed to close MR").await)
+        }
+    }
+
+    async fn merge_merge_request(&self, iid: MrIid, params: &MergeParams) -> Result<MergeRequest> {
+        let project_id = self.get_project_id_for_api().await?;
+        let url = format!(
+            "{}/api/v4/projects/{}/merge_requests/{}/merge",
+            self.base_url, project_id, iid
+        );
+        let mut body = serde_json::json!({
+            "squash": params.squash_message.is_some(),
+            "should_remove_source_branch": params.remove_source_branch,
+        });
+        if let Some(message) = &params.squash_message {
+            body["squash_commit_message"] = serde_json::json!(message);
+        }
+
+        let response = self
+            .client
+            .put(&url)
+            .header(self.token.header_name(), self.token.header_value())
+            .json(&body)
             .send()
             .await?;
 
@@ -464,11 +991,231 @@ impl GitLabApi for GitLabClient {
             let mr: MergeRequest = response.json().await?;
             Ok(mr)
         } else {
-            let error_text = response.text().await?;
-            Err(TrainError::GitLabError {
-                message: format!("Failed to get MR: {}", error_text),
+            Err(self.token.error_for(response, "Failed to merge MR").await)
+        }
+    }
+
+    async fn current_user(&self) -> Result<GitLabUser> {
+        let url = format!("{}/api/v4/user", self.base_url);
+        self.get_json_cached(&url, "Failed to get the current user")
+            .await
+    }
+
+    async fn find_user(&self, username: &str) -> Result<Option<GitLabUser>> {
+        let url = format!("{}/api/v4/users", self.base_url);
+        let users: Vec<GitLabUser> = self.get_paginated(&url, &[("username", username)]).await?;
+        Ok(users.into_iter().next())
+    }
+
+    async fn rebase_merge_request(&self, iid: MrIid) -> Result<()> {
+        let project_id = self.get_project_id_for_api().await?;
+        let url = format!(
+            "{}/api/v4/projects/{}/merge_requests/{}",
+            self.base_url, project_id, iid
+        );
+
+        let response = self
+            .client
+            .put(format!("{}/rebase", url))
+            .header(self.token.header_name(), self.token.header_value())
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(self.token.error_for(response, "Failed to rebase MR").await);
+        }
+
+        // The rebase runs in the background; poll until GitLab reports it finished
+        for _ in 0..REBASE_POLL_ATTEMPTS {
+            let response = self
+                .client
+                .get(&url)
+                .query(&[("include_rebase_in_progress", "true")])
+                .header(self.token.header_name(), self.token.header_value())
+                .send()
+                .await?;
+            if !response.status().is_success() {
+                return Err(self.token.error_for(response, "Failed to get MR").await);
             }
-            .into())
+            let status: serde_json::Value = response.json().await?;
+            if status["rebase_in_progress"].as_bool() != Some(true) {
+                return match status["merge_error"].as_str() {
+                    Some(error) => Err(TrainError::GitLabError {
+                        message: format!("GitLab could not rebase MR !{}: {}", iid, error),
+                    }
+                    .into()),
+                    None => Ok(()),
+                };
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+        Err(TrainError::GitLabError {
+            message: format!("GitLab is still rebasing MR !{}; try again later", iid),
+        }
+        .into())
+    }
+}
+
+/// GitLab CI's `CI_COMMIT_REF_SLUG` for `branch`: lowercased, anything outside
+/// `a-z0-9` replaced by `-`, cut to 63 bytes and without leading or trailing `-`.
+fn ref_slug(branch: &str) -> String {
+    let slug: String = branch
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .take(63)
+        .collect();
+    slug.trim_matches('-').to_string()
+}
+
+/// Parse GitLab's `X-Next-Page` header; empty or missing means this was the last page.
+fn next_page(headers: &HeaderMap) -> Option<u32> {
+    headers
+        .get("x-next-page")?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_ref_slug_matches_gitlab_ci() {
+        assert_eq!(ref_slug("feature/Add-Login"), "feature-add-login");
+        assert_eq!(ref_slug("-fix_bug-"), "fix-bug");
+        assert_eq!(ref_slug(&"a".repeat(70)).len(), 63);
+    }
+
+    #[test]
+    fn test_next_page_header() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(next_page(&headers), None);
+
+        headers.insert("x-next-page", HeaderValue::from_static(""));
+        assert_eq!(next_page(&headers), None);
+
+        headers.insert("x-next-page", HeaderValue::from_static("3"));
+        assert_eq!(next_page(&headers), Some(3));
+    }
+
+    #[test]
+    fn test_settings_from_ci_environment() {
+        let env = |vars: &[(&str, &str)]| {
+            let vars: std::collections::HashMap<String, String> = vars
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+            move |name: &str| vars.get(name).cloned()
+        };
+
+        assert!(GitLabSettings::from_env(env(&[])).is_err());
+
+        let ci = [
+            ("GITLAB_CI", "true"),
+            ("CI_SERVER_URL", "https://gitlab.example.com/"),
+            ("CI_PROJECT_ID", "42"),
+            ("CI_JOB_TOKEN", "job-secret"),
+        ];
+        let settings = GitLabSettings::from_env(env(&ci)).unwrap();
+        assert_eq!(settings.base_url, "https://gitlab.example.com");
+        assert_eq!(settings.token, GitLabToken::Job("job-secret".to_string()));
+        assert_eq!(settings.project_id.as_deref(), Some("42"));
+
+        // Explicit configuration wins over the CI defaults
+        let mut explicit = ci.to_vec();
+        explicit.push(("GITLAB_TOKEN", "personal"));
+        explicit.push(("GITLAB_URL", "https://other.example.com"));
+        let settings = GitLabSettings::from_env(env(&explicit)).unwrap();
+        assert_eq!(settings.base_url, "https://other.example.com");
+        assert_eq!(settings.token, GitLabToken::Access("personal".to_string()));
+
+        // CI variables are ignored outside of a GitLab CI job
+        let settings = GitLabSettings::from_env(env(&[
+            ("GITLAB_TOKEN", "personal"),
+            ("CI_PROJECT_ID", "42"),
+        ]))
+        .unwrap();
+        assert_eq!(settings.base_url, "https://gitlab.com");
+        assert_eq!(settings.project_id, None);
+    }
+
+    #[test]
+    fn test_scope_hint_depends_on_token_kind() {
+        let access = GitLabToken::Access("glpat".to_string());
+        assert!(access
+            .scope_hint(StatusCode::UNAUTHORIZED)
+            .contains("expired"));
+        assert!(access
+            .scope_hint(StatusCode::FORBIDDEN)
+            .contains("'api' scope"));
+        let job = GitLabToken::Job("job".to_string());
+        assert!(job
+            .scope_hint(StatusCode::FORBIDDEN)
+            .starts_with("CI_JOB_TOKEN"));
+    }
+
+    #[tokio::test]
+    async fn test_unchanged_resources_are_revalidated_with_etags() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for _ in 0..2 {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buf = vec![0; 4096];
+                let n = stream.read(&mut buf).await.unwrap();
+                let request = String::from_utf8_lossy(&buf[..n]).to_lowercase();
+                let response = if request.contains("if-none-match: \"v1\"") {
+                    "HTTP/1.1 304 Not Modified\r\netag: \"v1\"\r\nconnection: close\r\n\r\n"
+                        .to_string()
+                } else {
+                    let body = r#"{"approved":true,"approvals_left":0}"#;
+                    format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\netag: \"v1\"\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    )
+                };
+                stream.write_all(response.as_bytes()).await.unwrap();
+                requests.push(request);
+            }
+            requests
+        });
+
+        let dir = tempfile::tempdir().unwrap();
+        std::process::Command::new("git")
+            .arg("init")
+            .current_dir(dir.path())
+            .output()
+            .unwrap();
+        let git_repo = GitRepository::new(dir.path()).unwrap();
+        let client = GitLabClient {
+            client: Client::new(),
+            base_url: base_url.clone(),
+            token: GitLabToken::Access("secret".to_string()),
+            project_id: None,
+            project_info: RwLock::new(None),
+            project_details: RwLock::new(None),
+            fork_details: RwLock::new(None),
+            etags: EtagCache::load(&git_repo.train_dir()),
+            git_repo,
+        };
+
+        let url = format!("{}/api/v4/projects/1/merge_requests/1/approvals", base_url);
+        for _ in 0..2 {
+            let approvals: MergeRequestApprovals =
+                client.get_json_cached(&url, "Failed").await.unwrap();
+            assert!(approvals.approved);
         }
+
+        let requests = server.await.unwrap();
+        assert!(!requests[0].contains("if-none-match"));
+        assert!(requests[1].contains("if-none-match: \"v1\""));
     }
 }
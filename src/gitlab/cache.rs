@@ -0,0 +1,191 @@
+//! Responses to GitLab GETs kept with their `ETag`, so repeated reads (`status`,
+//! `watch`, every MR of a large stack) revalidate with `If-None-Match` and GitLab
+//! answers `304 Not Modified` instead of sending the resource again.
+//!
+//! The cache lives in the train directory next to the stacks, so it outlives a single
+//! command. Changes are written when the cache is dropped at the end of a command (and
+//! at most once a minute by `serve` and `watch`), replacing the file atomically so
+//! concurrent git-train processes never see it half written.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// File inside the train directory
+const CACHE_FILE: &str = "gitlab-etags.json";
+/// Responses kept; the least recently validated ones go first
+const MAX_ENTRIES: usize = 500;
+/// Long-running commands write pending changes at most this often
+const SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedResponse {
+    pub etag: String,
+    pub body: String,
+    /// Last time GitLab sent or confirmed this body
+    pub validated_at: DateTime<Utc>,
+}
+
+/// ETag-validated response bodies keyed by request URL
+#[derive(Debug, Default)]
+pub struct EtagCache {
+    /// Where entries are saved; `None` keeps them in memory only
+    path: Option<PathBuf>,
+    state: Mutex<CacheState>,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    entries: HashMap<String, CachedResponse>,
+    /// Entries changed since they were last written
+    dirty: bool,
+    saved_at: Option<Instant>,
+}
+
+impl EtagCache {
+    /// Load the cache kept in `train_dir`. A missing or unreadable file starts empty.
+    pub fn load(train_dir: &Path) -> Self {
+        let path = train_dir.join(CACHE_FILE);
+        let entries = fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+        Self {
+            path: Some(path),
+            state: Mutex::new(CacheState {
+                entries,
+                dirty: false,
+                saved_at: Some(Instant::now()),
+            }),
+        }
+    }
+
+    pub fn get(&self, url: &str) -> Option<CachedResponse> {
+        self.lock().entries.get(url).cloned()
+    }
+
+    /// Remember `body` as the content of `url` for `etag`
+    pub fn store(&self, url: &str, etag: &str, body: &str) {
+        let mut state = self.lock();
+        let entries = &mut state.entries;
+        entries.insert(
+            url.to_string(),
+            CachedResponse {
+                etag: etag.to_string(),
+                body: body.to_string(),
+                validated_at: Utc::now(),
+            },
+        );
+        if entries.len() > MAX_ENTRIES {
+            let mut by_age: Vec<(String, DateTime<Utc>)> = entries
+                .iter()
+                .map(|(url, entry)| (url.clone(), entry.validated_at))
+                .collect();
+            by_age.sort_by_key(|(_, validated_at)| *validated_at);
+            for (url, _) in by_age.into_iter().take(entries.len() - MAX_ENTRIES) {
+                entries.remove(&url);
+            }
+        }
+        state.dirty = true;
+        self.save_if_due(&mut state);
+    }
+
+    /// GitLab confirmed the cached body of `url` is still current
+    pub fn touch(&self, url: &str) {
+        let mut state = self.lock();
+        if let Some(entry) = state.entries.get_mut(url) {
+            entry.validated_at = Utc::now();
+            state.dirty = true;
+            self.save_if_due(&mut state);
+        }
+    }
+
+    /// Write pending changes now
+    pub fn flush(&self) {
+        let mut state = self.lock();
+        self.save(&mut state);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, CacheState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn save_if_due(&self, state: &mut CacheState) {
+        if state
+            .saved_at
+            .is_some_and(|saved_at| saved_at.elapsed() >= SAVE_INTERVAL)
+        {
+            self.save(state);
+        }
+    }
+
+    /// Best effort: a cache that can't be written only costs full responses later
+    fn save(&self, state: &mut CacheState) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        if !state.dirty {
+            return;
+        }
+        // Written beside the cache and renamed over it, so readers and concurrent
+        // writers only ever see a complete file
+        let temp_path = path.with_extension(format!("json.{}.tmp", std::process::id()));
+        let result = serde_json::to_string(&state.entries)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| {
+                if let Some(dir) = path.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+                fs::write(&temp_path, json)?;
+                fs::rename(&temp_path, path)?;
+                Ok(())
+            });
+        match result {
+            Ok(()) => state.dirty = false,
+            Err(e) => {
+                let _ = fs::remove_file(&temp_path);
+                debug!("Could not save the GitLab ETag cache: {}", e);
+            }
+        }
+        state.saved_at = Some(Instant::now());
+    }
+}
+
+impl Drop for EtagCache {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_survives_reload_and_drops_oldest() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let cache = EtagCache::load(dir.path());
+        assert!(cache.get("https://gitlab/mr/1").is_none());
+        cache.store("https://gitlab/mr/1", "W/\"abc\"", "{\"iid\":1}");
+        // Nothing is written until the command is done with the cache
+        assert!(!dir.path().join(CACHE_FILE).exists());
+        drop(cache);
+
+        let reloaded = EtagCache::load(dir.path());
+        let entry = reloaded.get("https://gitlab/mr/1").unwrap();
+        assert_eq!(entry.etag, "W/\"abc\"");
+        assert_eq!(entry.body, "{\"iid\":1}");
+
+        for i in 0..MAX_ENTRIES {
+            reloaded.store(&format!("https://gitlab/mr/{}", i + 2), "e", "{}");
+        }
+        assert!(reloaded.get("https://gitlab/mr/1").is_none());
+        assert!(reloaded.get("https://gitlab/mr/2").is_some());
+    }
+}
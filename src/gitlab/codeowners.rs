@@ -0,0 +1,170 @@
+//! GitLab `CODEOWNERS` files: which owners GitLab expects to review the files an MR
+//! changes.
+//!
+//! Within a section the last matching entry wins, like in GitLab; every section
+//! contributes its own owners. A section's default owners apply to its entries that
+//! don't name any.
+
+use regex::Regex;
+
+use crate::utils::glob_regex;
+
+/// Where GitLab looks for the file, in order; the first one found is used
+pub const CODEOWNERS_PATHS: [&str; 3] = ["CODEOWNERS", "docs/CODEOWNERS", ".gitlab/CODEOWNERS"];
+
+#[derive(Debug)]
+struct Entry {
+    /// Index of the section the entry belongs to (0 for entries before any section)
+    section: usize,
+    pattern: Regex,
+    owners: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct CodeOwners {
+    entries: Vec<Entry>,
+}
+
+impl CodeOwners {
+    pub fn parse(contents: &str) -> Self {
+        let mut entries = Vec::new();
+        let mut section = 0;
+        let mut section_owners: Vec<String> = Vec::new();
+
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            // `[Section]`, `^[Optional section]` or `[Section][2]`, optionally followed
+            // by default owners
+            if let Some(header) = line.strip_prefix('^').unwrap_or(line).strip_prefix('[') {
+                let Some(end) = header.find(']') else {
+                    continue;
+                };
+                let rest = header[end + 1..].trim_start();
+                let rest = match rest.strip_prefix('[') {
+                    Some(count) => count.split_once(']').map_or("", |(_, rest)| rest),
+                    None => rest,
+                };
+                section += 1;
+                section_owners = owners(rest);
+                continue;
+            }
+
+            let mut parts = line.split_whitespace();
+            let Some(pattern) = parts.next() else {
+                continue;
+            };
+            let mut entry_owners = owners(&parts.collect::<Vec<_>>().join(" "));
+            if entry_owners.is_empty() {
+                entry_owners = section_owners.clone();
+            }
+            if let Some(pattern) = pattern_regex(pattern) {
+                entries.push(Entry {
+                    section,
+                    pattern,
+                    owners: entry_owners,
+                });
+            }
+        }
+
+        Self { entries }
+    }
+
+    /// Owners of `paths`, in the order they first appear in the file, without duplicates
+    pub fn owners_for(&self, paths: &[String]) -> Vec<String> {
+        let mut matched: Vec<usize> = Vec::new();
+        for path in paths {
+            let mut last_per_section: Vec<(usize, usize)> = Vec::new();
+            for (index, entry) in self.entries.iter().enumerate() {
+                if !entry.pattern.is_match(path) {
+                    continue;
+                }
+                match last_per_section
+                    .iter_mut()
+                    .find(|(section, _)| *section == entry.section)
+                {
+                    Some(last) => last.1 = index,
+                    None => last_per_section.push((entry.section, index)),
+                }
+            }
+            matched.extend(last_per_section.into_iter().map(|(_, index)| index));
+        }
+        matched.sort();
+        matched.dedup();
+
+        let mut owners: Vec<String> = Vec::new();
+        for index in matched {
+            for owner in &self.entries[index].owners {
+                if !owners.contains(owner) {
+                    owners.push(owner.clone());
+                }
+            }
+        }
+        owners
+    }
+}
+
+/// `@user`, `@group/subgroup` and email owners in `text`
+fn owners(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .filter(|owner| owner.contains('@'))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Regex for a CODEOWNERS pattern, matched against repository paths: a leading `/` or
+/// an inner `/` anchors it at the root, otherwise it matches at any depth; a matched
+/// directory covers everything below it, and a trailing `/` matches directories only
+fn pattern_regex(pattern: &str) -> Option<Regex> {
+    let directory_only = pattern.ends_with('/');
+    let trimmed = pattern.trim_end_matches('/');
+    let anchored = trimmed.starts_with('/') || trimmed.contains('/');
+    let body = glob_regex(trimmed.trim_start_matches('/'));
+    if body.is_empty() {
+        return None;
+    }
+
+    let prefix = if anchored { "^" } else { "^(?:.*/)?" };
+    let suffix = if directory_only { "/.*$" } else { "(?:/.*)?$" };
+    Regex::new(&format!("{}{}{}", prefix, body, suffix)).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_owners_of_changed_files() {
+        let codeowners = CodeOwners::parse(
+            "# Default owners\n\
+             * @lead\n\
+             *.rs @rustacean @lead\n\
+             /docs/ @writer\n\
+             src/gitlab/ @api-team\n\
+             \n\
+             [Database] @dba\n\
+             migrations/\n\
+             ^[Optional][2]\n\
+             Cargo.lock @release-bot\n",
+        );
+        let owners = |paths: &[&str]| {
+            codeowners.owners_for(&paths.iter().map(|p| p.to_string()).collect::<Vec<_>>())
+        };
+
+        assert_eq!(owners(&["README.md"]), ["@lead"]);
+        assert_eq!(owners(&["src/main.rs"]), ["@rustacean", "@lead"]);
+        // The later, more specific entry wins within a section
+        assert_eq!(owners(&["src/gitlab/api.rs"]), ["@api-team"]);
+        assert_eq!(owners(&["docs/guide.md"]), ["@writer"]);
+        assert_eq!(owners(&["web/docs/guide.md"]), ["@lead"]);
+        // Sections add their owners; section defaults fill in entries without owners
+        assert_eq!(owners(&["db/migrations/001.sql"]), ["@lead", "@dba"]);
+        assert_eq!(owners(&["Cargo.lock"]), ["@lead", "@release-bot"]);
+        assert_eq!(
+            owners(&["docs/guide.md", "src/main.rs"]),
+            ["@rustacean", "@lead", "@writer"]
+        );
+    }
+}
@@ -1,47 +1,116 @@
+use crate::config::{BadgeStyle, StackTableConfig, TableColumn, UpdateStrategy};
+use crate::git::FileDiffStat;
 use crate::gitlab::api::MergeRequest;
-use crate::stack::types::Stack;
+use crate::ids::{BranchName, MrIid};
+use crate::stack::types::{BranchState, Stack, StackBranch};
+use regex::Regex;
 use std::collections::HashMap;
 
 const STACK_TABLE_START: &str = "<!-- git-train-stack-start -->";
 const STACK_TABLE_END: &str = "<!-- git-train-stack-end -->";
+const REVIEW_NOTES_START: &str = "<!-- git-train-review-notes-start -->";
+const REVIEW_NOTES_END: &str = "<!-- git-train-review-notes-end -->";
+const DEPENDENCIES_START: &str = "<!-- git-train-dependencies-start -->";
+const DEPENDENCIES_END: &str = "<!-- git-train-dependencies-end -->";
+const LANDING_START: &str = "<!-- git-train-landing-start -->";
+const LANDING_END: &str = "<!-- git-train-landing-end -->";
+/// Largest files listed per level by `build_stack_summary`
+const SUMMARY_KEY_FILES: usize = 5;
 
-pub fn build_stack_table(stack: &Stack, mrs: &HashMap<u64, MergeRequest>) -> String {
+/// Build the stack table for MR descriptions, laid out according to `options`.
+/// `commit_counts` maps branch names to the commits they add on top of their parent
+/// and is only needed for the `commits` column.
+pub fn build_stack_table(
+    stack: &Stack,
+    mrs: &HashMap<MrIid, MergeRequest>,
+    options: &StackTableConfig,
+    commit_counts: &HashMap<String, usize>,
+) -> String {
     let mut table = String::new();
 
     table.push_str(STACK_TABLE_START);
     table.push_str("\n\n");
-    table.push_str("### MR Train\n\n");
-    table.push_str("| Position | Branch | Merge Request |\n");
-    table.push_str("|---|---|---|\n");
 
     // Collect all branches in hierarchical order (depth-first traversal)
     let branches_in_order = collect_branches_in_order(stack);
 
-    for (i, branch) in branches_in_order.iter().enumerate() {
-        let position = format!("{}", i + 1);
+    if !branches_in_order.is_empty() && branches_in_order.len() <= options.compact_max_branches {
+        let entries: Vec<String> = branches_in_order
+            .iter()
+            .enumerate()
+            .map(|(i, branch)| {
+                let mr = branch.mr_iid.and_then(|iid| mrs.get(&iid));
+                let mut entry = format!("#{} {}", i + 1, mr_link(branch, mrs));
+                for column in &options.columns {
+                    match column {
+                        TableColumn::State => {
+                            entry.push(' ');
+                            entry.push_str(&state_badge(mr, options.badges));
+                        }
+                        TableColumn::Pipeline => {
+                            entry.push(' ');
+                            entry.push_str(&pipeline_badge(mr, options.badges));
+                        }
+                        TableColumn::Target | TableColumn::Commits | TableColumn::Milestone => {}
+                    }
+                }
+                entry
+            })
+            .collect();
+        table.push_str(&format!("**MR Train:** {}\n", entries.join(" → ")));
+    } else {
+        table.push_str("### MR Train\n\n");
+        table.push_str("| Position | Branch | Merge Request |");
+        for column in &options.columns {
+            table.push_str(&format!(" {} |", column_header(*column)));
+        }
+        table.push('\n');
+        table.push_str(&"|---".repeat(3 + options.columns.len()));
+        table.push_str("|\n");
 
-        let mr_link = if let Some(iid) = branch.mr_iid {
-            if let Some(mr) = mrs.get(&iid) {
-                // Append '+' to the URL to get a rich link in GitLab
-                format!("{}+", mr.web_url)
-            } else {
-                "N/A (MR not found)".to_string()
+        for (i, branch) in branches_in_order.iter().enumerate() {
+            let mr = branch.mr_iid.and_then(|iid| mrs.get(&iid));
+            table.push_str(&format!(
+                "| #{} | `{}` | {} |",
+                i + 1,
+                branch.name,
+                mr_link(branch, mrs)
+            ));
+            for column in &options.columns {
+                let cell = match column {
+                    TableColumn::State => state_badge(mr, options.badges),
+                    TableColumn::Pipeline => pipeline_badge(mr, options.badges),
+                    TableColumn::Target => mr
+                        .map(|mr| mr.target_branch.clone())
+                        .or_else(|| branch.parent.as_ref().map(|parent| parent.to_string()))
+                        .map(|target| format!("`{}`", target))
+                        .unwrap_or_else(|| "-".to_string()),
+                    TableColumn::Commits => commit_counts
+                        .get(branch.name.as_str())
+                        .map(|count| count.to_string())
+                        .unwrap_or_else(|| "-".to_string()),
+                    TableColumn::Milestone => mr
+                        .and_then(|mr| mr.milestone.as_ref())
+                        .map(|milestone| milestone.title.clone())
+                        .unwrap_or_else(|| "-".to_string()),
+                };
+                table.push_str(&format!(" {} |", cell));
             }
-        } else {
-            "N/A".to_string()
-        };
-
-        table.push_str(&format!(
-            "| {} | `{}` | {} |\n",
-            position, branch.name, mr_link
-        ));
-    }
+            table.push('\n');
+        }
 
-    if branches_in_order.is_empty() {
-        table.push_str("| | | |\n")
+        if branches_in_order.is_empty() {
+            table.push_str(&"| ".repeat(3 + options.columns.len()));
+            table.push_str("|\n");
+        }
     }
 
     table.push('\n');
+    if stack.update_strategy == UpdateStrategy::Merge {
+        table.push_str(
+            "*This stack is updated by merging each parent into its child, so history is never rewritten and branches are never force-pushed.*\n\n",
+        );
+    }
     table.push_str("---\n");
     table.push_str("*Created by [gitTrain](https://github.com/michaelangeloio/gitTrain)*\n\n");
     table.push_str(STACK_TABLE_END);
@@ -49,6 +118,79 @@ pub fn build_stack_table(stack: &Stack, mrs: &HashMap<u64, MergeRequest>) -> Str
     table
 }
 
+fn mr_link(branch: &StackBranch, mrs: &HashMap<MrIid, MergeRequest>) -> String {
+    match branch.mr_iid {
+        // Append '+' to the URL to get a rich link in GitLab
+        Some(iid) => match mrs.get(&iid) {
+            Some(mr) => format!("[{}]({}+)", escape_link_text(&mr.title), mr.web_url),
+            None => "N/A (MR not found)".to_string(),
+        },
+        None => "N/A".to_string(),
+    }
+}
+
+/// Escape an MR title for link text inside a table cell, where `]` would end the link
+/// and `|` the cell
+fn escape_link_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '[' | ']' | '|') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn column_header(column: TableColumn) -> &'static str {
+    match column {
+        TableColumn::State => "State",
+        TableColumn::Pipeline => "Pipeline",
+        TableColumn::Target => "Target",
+        TableColumn::Commits => "Commits",
+        TableColumn::Milestone => "Milestone",
+    }
+}
+
+fn state_badge(mr: Option<&MergeRequest>, style: BadgeStyle) -> String {
+    let Some(mr) = mr else {
+        return "-".to_string();
+    };
+    let (emoji, text) = match mr.state.as_str() {
+        "opened" => ("🟢", "open"),
+        "merged" => ("🟣", "merged"),
+        "closed" => ("🔴", "closed"),
+        "locked" => ("🔒", "locked"),
+        other => return other.to_string(),
+    };
+    match style {
+        BadgeStyle::Emoji => emoji.to_string(),
+        BadgeStyle::Text => text.to_string(),
+    }
+}
+
+fn pipeline_badge(mr: Option<&MergeRequest>, style: BadgeStyle) -> String {
+    let Some(pipeline) = mr.and_then(|mr| mr.head_pipeline.as_ref()) else {
+        return "-".to_string();
+    };
+    let (emoji, text) = match pipeline.status.as_str() {
+        "success" => ("✅", "passed"),
+        "failed" => ("❌", "failed"),
+        "running" => ("🔄", "running"),
+        "created" | "pending" | "waiting_for_resource" | "preparing" | "scheduled" => {
+            ("⏳", "pending")
+        }
+        "canceled" => ("🚫", "canceled"),
+        "skipped" => ("⏭️", "skipped"),
+        "manual" => ("✋", "manual"),
+        other => return other.to_string(),
+    };
+    match style {
+        BadgeStyle::Emoji => emoji.to_string(),
+        BadgeStyle::Text => text.to_string(),
+    }
+}
+
 /// Collect all branches in the stack in hierarchical order
 /// This performs a depth-first traversal starting from branches that have the base branch as parent
 fn collect_branches_in_order(stack: &Stack) -> Vec<crate::stack::types::StackBranch> {
@@ -56,7 +198,7 @@ fn collect_branches_in_order(stack: &Stack) -> Vec<crate::stack::types::StackBra
     let mut visited = std::collections::HashSet::new();
 
     // Build a parent -> children mapping for efficient traversal
-    let mut hierarchy: HashMap<String, Vec<String>> = HashMap::new();
+    let mut hierarchy: HashMap<BranchName, Vec<BranchName>> = HashMap::new();
     for (branch_name, branch) in &stack.branches {
         if let Some(parent) = &branch.parent {
             hierarchy
@@ -86,16 +228,18 @@ fn collect_branches_in_order(stack: &Stack) -> Vec<crate::stack::types::StackBra
         }
     }
 
+    // Private levels sit above every published one and stay out of anything shared
+    result.retain(|branch| branch.state != BranchState::LocalOnly);
     result
 }
 
 /// Recursively collect branches in depth-first order
 fn collect_branch_recursive(
     stack: &Stack,
-    hierarchy: &HashMap<String, Vec<String>>,
+    hierarchy: &HashMap<BranchName, Vec<BranchName>>,
     branch_name: &str,
     result: &mut Vec<crate::stack::types::StackBranch>,
-    visited: &mut std::collections::HashSet<String>,
+    visited: &mut std::collections::HashSet<BranchName>,
 ) {
     // Avoid infinite loops
     if visited.contains(branch_name) {
@@ -103,7 +247,7 @@ fn collect_branch_recursive(
     }
 
     if let Some(branch) = stack.branches.get(branch_name) {
-        visited.insert(branch_name.to_string());
+        visited.insert(BranchName::from(branch_name));
         result.push(branch.clone());
 
         // Recursively collect children
@@ -115,25 +259,363 @@ fn collect_branch_recursive(
     }
 }
 
+/// The stack table section of an MR description, markers included
+pub fn stack_table_section(description: &Option<String>) -> Option<&str> {
+    let description = description.as_deref()?;
+    let start = description.find(STACK_TABLE_START)?;
+    let end = description[start..].find(STACK_TABLE_END)? + start + STACK_TABLE_END.len();
+    Some(&description[start..end])
+}
+
+/// IIDs of the MRs linked from the stack table in an MR description, in table order
+pub fn stack_table_mr_iids(description: &Option<String>) -> Vec<MrIid> {
+    let Some(section) = stack_table_section(description) else {
+        return Vec::new();
+    };
+    let link = Regex::new(r"/merge_requests/(\d+)\+?\)").expect("valid MR link regex");
+    let mut iids = Vec::new();
+    for captures in link.captures_iter(section) {
+        if let Ok(iid) = captures[1].parse::<u64>() {
+            let iid = MrIid::from(iid);
+            if !iids.contains(&iid) {
+                iids.push(iid);
+            }
+        }
+    }
+    iids
+}
+
+/// Stack name from an MR title carrying the `[Stack: name]` prefix git-train adds
+pub fn stack_name_from_title(title: &str) -> Option<&str> {
+    let rest = title.strip_prefix("[Stack: ")?;
+    rest.split_once(']')
+        .map(|(name, _)| name.trim())
+        .filter(|name| !name.is_empty())
+}
+
+/// Stable fingerprint (64-bit FNV-1a) of a stack table, kept in the stack metadata to
+/// notice when the copy on GitLab goes stale
+pub fn table_hash(table: &str) -> String {
+    let hash = table
+        .trim()
+        .bytes()
+        .fold(0xcbf29ce484222325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+        });
+    format!("{:016x}", hash)
+}
+
 pub fn update_description(current_description: &Option<String>, new_table: &str) -> String {
+    replace_section(
+        current_description,
+        STACK_TABLE_START,
+        STACK_TABLE_END,
+        Some(new_table),
+    )
+}
+
+/// Squash commit message for an MR: its title without the `[Stack: name]` prefix and
+/// its description without the sections git-train injects
+pub fn squash_commit_message(mr: &MergeRequest) -> String {
+    let body = authored_description(&mr.description);
+
+    let mut message = without_stack_prefix(&mr.title).to_string();
+    if !body.is_empty() {
+        message.push_str("\n\n");
+        message.push_str(&body);
+    }
+    message.push_str(&format!("\n\nSee merge request !{}", mr.iid));
+    message
+}
+
+/// An MR title without the `[Stack: name]` prefix git-train adds
+fn without_stack_prefix(title: &str) -> &str {
+    let title = match title.strip_prefix("[Stack: ") {
+        Some(rest) => rest.split_once("] ").map_or(title, |(_, t)| t),
+        None => title,
+    };
+    title.trim()
+}
+
+/// The part of an MR description written by people: everything but the sections
+/// git-train injects
+fn authored_description(description: &Option<String>) -> String {
+    let without_table = replace_section(description, STACK_TABLE_START, STACK_TABLE_END, None);
+    let without_dependencies = update_dependencies(&Some(without_table), None);
+    let without_landing = update_landing_instructions(&Some(without_dependencies), None);
+    update_review_notes(&Some(without_landing), None)
+        .trim()
+        .to_string()
+}
+
+/// Markdown summary of the whole stack for release notes or an umbrella issue: one
+/// section per level, in stack table order, with its MR, description and largest
+/// changes. `diff_stats` maps branch names to the files they change on top of their
+/// parent.
+pub fn build_stack_summary(
+    stack: &Stack,
+    mrs: &HashMap<MrIid, MergeRequest>,
+    diff_stats: &HashMap<String, Vec<FileDiffStat>>,
+) -> String {
+    let branches = collect_branches_in_order(stack);
+    let mut summary = format!(
+        "# Stack: {}\n\n{} levels on `{}`.\n",
+        stack.name,
+        branches.len(),
+        stack.base_branch
+    );
+
+    for (i, branch) in branches.iter().enumerate() {
+        let mr = branch.mr_iid.and_then(|iid| mrs.get(&iid));
+        let title = mr
+            .map(|mr| mr.title.as_str())
+            .or(branch.mr_title.as_deref())
+            .map(without_stack_prefix)
+            .unwrap_or(branch.name.as_str());
+        summary.push_str(&format!("\n## {}. {}", i + 1, title));
+        match (mr, branch.mr_iid) {
+            (Some(mr), _) => summary.push_str(&format!(" ([!{}]({}))", mr.iid, mr.web_url)),
+            (None, Some(iid)) => summary.push_str(&format!(" (!{})", iid)),
+            (None, None) => {}
+        }
+        summary.push_str("\n\n");
+
+        let files = diff_stats
+            .get(branch.name.as_str())
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let added: usize = files.iter().map(|f| f.added).sum();
+        let deleted: usize = files.iter().map(|f| f.deleted).sum();
+        summary.push_str(&format!(
+            "`{}` · {} files, +{} −{}\n",
+            branch.name,
+            files.len(),
+            added,
+            deleted
+        ));
+
+        let description = mr
+            .map(|mr| authored_description(&mr.description))
+            .unwrap_or_default();
+        if !description.is_empty() {
+            summary.push_str(&format!("\n{}\n", description));
+        }
+
+        if !files.is_empty() {
+            let mut largest: Vec<&FileDiffStat> = files.iter().collect();
+            largest.sort_by(|a, b| b.changed().cmp(&a.changed()).then(a.path.cmp(&b.path)));
+            summary.push_str("\nKey changes:\n");
+            for file in largest.iter().take(SUMMARY_KEY_FILES) {
+                summary.push_str(&format!(
+                    "- `{}` (+{} −{})\n",
+                    file.path, file.added, file.deleted
+                ));
+            }
+            if files.len() > SUMMARY_KEY_FILES {
+                summary.push_str(&format!(
+                    "- …and {} more files\n",
+                    files.len() - SUMMARY_KEY_FILES
+                ));
+            }
+        }
+    }
+
+    summary
+}
+
+/// Build the collapsed review-notes section for a branch, or `None` if it has no notes
+pub fn build_review_notes_section(branch: &StackBranch) -> Option<String> {
+    if branch.review_notes.is_empty() {
+        return None;
+    }
+
+    let mut section = String::new();
+    section.push_str(REVIEW_NOTES_START);
+    section.push_str("\n<details>\n");
+
+    let (done, total) = branch.todo_progress();
+    if total > 0 {
+        section.push_str(&format!(
+            "<summary>Review notes ({}/{} done)</summary>\n\n",
+            done, total
+        ));
+    } else {
+        section.push_str("<summary>Review notes</summary>\n\n");
+    }
+
+    for note in &branch.review_notes {
+        let marker = match (note.todo, note.done) {
+            (true, true) => "- [x] ",
+            (true, false) => "- [ ] ",
+            (false, _) => "- ",
+        };
+        section.push_str(marker);
+        section.push_str(&note.text);
+        section.push('\n');
+    }
+
+    section.push_str("\n</details>\n");
+    section.push_str(REVIEW_NOTES_END);
+    Some(section)
+}
+
+/// Insert, replace or (with `None`) remove the review-notes section of a description
+pub fn update_review_notes(current_description: &Option<String>, section: Option<&str>) -> String {
+    replace_section(
+        current_description,
+        REVIEW_NOTES_START,
+        REVIEW_NOTES_END,
+        section,
+    )
+}
+
+/// Build the "Depends on !12" / "Required by !14" references for `branch`, or `None`
+/// when neither its parent nor any child has an MR
+pub fn build_dependency_section(stack: &Stack, branch: &StackBranch) -> Option<String> {
+    let parent_iid = branch
+        .parent
+        .as_ref()
+        .and_then(|parent| stack.branches.get(parent))
+        .and_then(|parent| parent.mr_iid);
+    let mut child_iids: Vec<MrIid> = stack
+        .branches
+        .values()
+        .filter(|child| child.parent.as_ref() == Some(&branch.name))
+        .filter_map(|child| child.mr_iid)
+        .collect();
+    child_iids.sort();
+
+    let mut lines = Vec::new();
+    if let Some(iid) = parent_iid {
+        lines.push(format!("Depends on !{}", iid));
+    }
+    if !child_iids.is_empty() {
+        let refs: Vec<String> = child_iids.iter().map(|iid| format!("!{}", iid)).collect();
+        lines.push(format!("Required by {}", refs.join(", ")));
+    }
+    if lines.is_empty() {
+        return None;
+    }
+
+    Some(format!(
+        "{}\n{}\n{}",
+        DEPENDENCIES_START,
+        lines.join("\n\n"),
+        DEPENDENCIES_END
+    ))
+}
+
+/// Insert, replace or (with `None`) remove the dependency references of a description
+pub fn update_dependencies(current_description: &Option<String>, section: Option<&str>) -> String {
+    replace_section(
+        current_description,
+        DEPENDENCIES_START,
+        DEPENDENCIES_END,
+        section,
+    )
+}
+
+/// Build the footer telling reviewers new to stacked MRs where `branch`'s MR sits in
+/// the landing order: which MR to merge first and which ones wait for it. `None` for
+/// levels left out of the stack table.
+pub fn build_landing_section(
+    stack: &Stack,
+    branch: &StackBranch,
+    mrs: &HashMap<MrIid, MergeRequest>,
+) -> Option<String> {
+    let levels = collect_branches_in_order(stack);
+    let position = levels.iter().position(|level| level.name == branch.name)?;
+
+    let reference = |level: &StackBranch| -> Option<String> {
+        let iid = level.mr_iid?;
+        Some(match mrs.get(&iid) {
+            Some(mr) => format!("[{}]({}+)", escape_link_text(&mr.title), mr.web_url),
+            None => format!("!{}", iid),
+        })
+    };
+    let merged = |level: &StackBranch| match level.mr_iid.and_then(|iid| mrs.get(&iid)) {
+        Some(mr) => mr.state == "merged",
+        None => level.state == BranchState::MrMerged,
+    };
+    let parent = branch
+        .parent
+        .as_ref()
+        .and_then(|parent| stack.branches.get(parent))
+        .filter(|parent| !merged(parent));
+    let children: Vec<String> = levels
+        .iter()
+        .filter(|level| level.parent.as_ref() == Some(&branch.name))
+        .filter_map(&reference)
+        .collect();
+
+    let mut section = String::new();
+    section.push_str(LANDING_START);
+    section.push_str("\n#### Landing this stack\n\n");
+    section.push_str(&format!(
+        "This MR is **{} of {}** in a stack of dependent merge requests. Merge them from the bottom up, one at a time:\n\n",
+        position + 1,
+        levels.len()
+    ));
+    match parent.and_then(reference) {
+        Some(parent) => section.push_str(&format!("- ⬇️ Merge first: {}\n", parent)),
+        None => section
+            .push_str("- ⬇️ Nothing below this MR is left to merge: it can go in once approved\n"),
+    }
+    if !children.is_empty() {
+        section.push_str(&format!(
+            "- ⬆️ Merge after this one: {}\n",
+            children.join(", ")
+        ));
+    }
+    if parent.is_some() {
+        section.push_str(&format!(
+            "\nThe diff only shows this level's changes because the MR targets the branch below it; once that is merged, git-train retargets it to `{}`.\n",
+            stack.base_branch
+        ));
+    }
+    section.push_str(LANDING_END);
+    Some(section)
+}
+
+/// Insert, replace or (with `None`) remove the landing instructions of a description
+pub fn update_landing_instructions(
+    current_description: &Option<String>,
+    section: Option<&str>,
+) -> String {
+    replace_section(current_description, LANDING_START, LANDING_END, section)
+}
+
+/// Replace the text between `start` and `end` markers, appending it if missing
+fn replace_section(
+    current_description: &Option<String>,
+    start: &str,
+    end: &str,
+    new_section: Option<&str>,
+) -> String {
     let current_desc = current_description.as_deref().unwrap_or("").trim();
 
-    if let (Some(start_idx), Some(end_idx)) = (
-        current_desc.find(STACK_TABLE_START),
-        current_desc.find(STACK_TABLE_END),
-    ) {
-        // Table exists, replace it
+    if let (Some(start_idx), Some(end_idx)) = (current_desc.find(start), current_desc.find(end)) {
+        // Section exists, replace it
         let mut new_desc = String::new();
-        new_desc.push_str(&current_desc[..start_idx]);
-        new_desc.push_str(new_table);
-        new_desc.push_str(&current_desc[end_idx + STACK_TABLE_END.len()..]);
+        new_desc.push_str(current_desc[..start_idx].trim_end());
+        if let Some(section) = new_section {
+            if start_idx > 0 {
+                new_desc.push_str("\n\n");
+            }
+            new_desc.push_str(section);
+        }
+        let rest = current_desc[end_idx + end.len()..].trim_start();
+        if !rest.is_empty() {
+            new_desc.push_str("\n\n");
+            new_desc.push_str(rest);
+        }
         new_desc.trim().to_string()
     } else {
-        // Table does not exist, append it
-        if current_desc.is_empty() {
-            new_table.to_string()
-        } else {
-            format!("{}\n\n{}", current_desc, new_table)
+        // Section does not exist, append it
+        match new_section {
+            None => current_desc.to_string(),
+            Some(section) if current_desc.is_empty() => section.to_string(),
+            Some(section) => format!("{}\n\n{}", current_desc, section),
         }
     }
 }
@@ -142,198 +624,278 @@ pub fn update_description(current_description: &Option<String>, new_table: &str)
 mod tests {
     use super::*;
     use crate::gitlab::api::MergeRequest;
-    use crate::stack::types::{Stack, StackBranch};
+    use crate::stack::types::{BranchState, Stack, StackBranch};
     use chrono::Utc;
 
-    fn create_test_stack_and_mrs() -> (Stack, HashMap<u64, MergeRequest>) {
+    fn create_test_stack_and_mrs() -> (Stack, HashMap<MrIid, MergeRequest>) {
         let mut branches = HashMap::new();
         branches.insert(
-            "feature-1".to_string(),
+            "feature-1".into(),
             StackBranch {
-                name: "feature-1".to_string(),
-                parent: Some("main".to_string()),
-                children: vec!["feature-2".to_string()],
+                name: "feature-1".into(),
+                parent: Some("main".into()),
+                children: vec!["feature-2".into()],
                 commit_hash: "hash1".to_string(),
-                mr_iid: Some(101),
+                mr_iid: Some(MrIid::from(101)),
                 mr_title: Some("Feat: part 1".to_string()),
+                mr_template: None,
+                review_notes: Vec::new(),
+                target_history: Vec::new(),
+                description_hash: None,
+                remote_sha: None,
+                state: BranchState::MrOpen,
                 created_at: Utc::now(),
                 updated_at: Utc::now(),
             },
         );
         branches.insert(
-            "feature-2".to_string(),
+            "feature-2".into(),
             StackBranch {
-                name: "feature-2".to_string(),
-                parent: Some("feature-1".to_string()),
+                name: "feature-2".into(),
+                parent: Some("feature-1".into()),
                 children: vec![],
                 commit_hash: "hash2".to_string(),
-                mr_iid: Some(102),
+                mr_iid: Some(MrIid::from(102)),
                 mr_title: Some("Feat: part 2".to_string()),
+                mr_template: None,
+                review_notes: Vec::new(),
+                target_history: Vec::new(),
+                description_hash: None,
+                remote_sha: None,
+                state: BranchState::MrOpen,
                 created_at: Utc::now(),
                 updated_at: Utc::now(),
             },
         );
 
         let stack = Stack {
-            id: "stack-1".to_string(),
+            id: "stack-1".into(),
             name: "test-stack".to_string(),
-            base_branch: "main".to_string(),
+            base_branch: "main".into(),
             branches,
-            current_branch: Some("feature-2".to_string()),
+            current_branch: Some("feature-2".into()),
             gitlab_project: None,
+            mr_template: None,
+            update_strategy: UpdateStrategy::default(),
+            owner: None,
+            archived: false,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         };
 
         let mut mrs = HashMap::new();
         mrs.insert(
-            101,
+            MrIid::from(101),
             MergeRequest {
                 id: 1,
-                iid: 101,
+                iid: MrIid::from(101),
                 title: "Feat: part 1".to_string(),
                 description: Some("".to_string()),
                 source_branch: "feature-1".to_string(),
                 target_branch: "main".to_string(),
+                source_project_id: None,
                 state: "opened".to_string(),
                 web_url: "https://gitlab.com/test/repo/-/merge_requests/101".to_string(),
+                head_pipeline: None,
+                has_conflicts: false,
+                detailed_merge_status: None,
+                sha: None,
+                milestone: None,
             },
         );
         mrs.insert(
-            102,
+            MrIid::from(102),
             MergeRequest {
                 id: 2,
-                iid: 102,
+                iid: MrIid::from(102),
                 title: "Feat: part 2".to_string(),
                 description: Some("".to_string()),
                 source_branch: "feature-2".to_string(),
                 target_branch: "feature-1".to_string(),
+                source_project_id: None,
                 state: "opened".to_string(),
                 web_url: "https://gitlab.com/test/repo/-/merge_requests/102".to_string(),
+                head_pipeline: None,
+                has_conflicts: false,
+                detailed_merge_status: None,
+                sha: None,
+                milestone: None,
             },
         );
 
         (stack, mrs)
     }
 
-    fn create_complex_test_stack_and_mrs() -> (Stack, HashMap<u64, MergeRequest>) {
+    fn create_complex_test_stack_and_mrs() -> (Stack, HashMap<MrIid, MergeRequest>) {
         let mut branches = HashMap::new();
         // Create a more complex stack with multiple branches
         branches.insert(
-            "feature-1".to_string(),
+            "feature-1".into(),
             StackBranch {
-                name: "feature-1".to_string(),
-                parent: Some("main".to_string()),
-                children: vec!["feature-2".to_string(), "feature-3".to_string()],
+                name: "feature-1".into(),
+                parent: Some("main".into()),
+                children: vec!["feature-2".into(), "feature-3".into()],
                 commit_hash: "hash1".to_string(),
-                mr_iid: Some(101),
+                mr_iid: Some(MrIid::from(101)),
                 mr_title: Some("Feat: part 1".to_string()),
+                mr_template: None,
+                review_notes: Vec::new(),
+                target_history: Vec::new(),
+                description_hash: None,
+                remote_sha: None,
+                state: BranchState::MrOpen,
                 created_at: Utc::now(),
                 updated_at: Utc::now(),
             },
         );
         branches.insert(
-            "feature-2".to_string(),
+            "feature-2".into(),
             StackBranch {
-                name: "feature-2".to_string(),
-                parent: Some("feature-1".to_string()),
+                name: "feature-2".into(),
+                parent: Some("feature-1".into()),
                 children: vec![],
                 commit_hash: "hash2".to_string(),
-                mr_iid: Some(102),
+                mr_iid: Some(MrIid::from(102)),
                 mr_title: Some("Feat: part 2".to_string()),
+                mr_template: None,
+                review_notes: Vec::new(),
+                target_history: Vec::new(),
+                description_hash: None,
+                remote_sha: None,
+                state: BranchState::MrOpen,
                 created_at: Utc::now(),
                 updated_at: Utc::now(),
             },
         );
         branches.insert(
-            "feature-3".to_string(),
+            "feature-3".into(),
             StackBranch {
-                name: "feature-3".to_string(),
-                parent: Some("feature-1".to_string()),
+                name: "feature-3".into(),
+                parent: Some("feature-1".into()),
                 children: vec![],
                 commit_hash: "hash3".to_string(),
-                mr_iid: Some(103),
+                mr_iid: Some(MrIid::from(103)),
                 mr_title: Some("Feat: part 3".to_string()),
+                mr_template: None,
+                review_notes: Vec::new(),
+                target_history: Vec::new(),
+                description_hash: None,
+                remote_sha: None,
+                state: BranchState::MrOpen,
                 created_at: Utc::now(),
                 updated_at: Utc::now(),
             },
         );
         branches.insert(
-            "feature-4".to_string(),
+            "feature-4".into(),
             StackBranch {
-                name: "feature-4".to_string(),
-                parent: Some("main".to_string()),
+                name: "feature-4".into(),
+                parent: Some("main".into()),
                 children: vec![],
                 commit_hash: "hash4".to_string(),
-                mr_iid: Some(104),
+                mr_iid: Some(MrIid::from(104)),
                 mr_title: Some("Feat: part 4".to_string()),
+                mr_template: None,
+                review_notes: Vec::new(),
+                target_history: Vec::new(),
+                description_hash: None,
+                remote_sha: None,
+                state: BranchState::MrOpen,
                 created_at: Utc::now(),
                 updated_at: Utc::now(),
             },
         );
 
         let stack = Stack {
-            id: "stack-1".to_string(),
+            id: "stack-1".into(),
             name: "test-stack".to_string(),
-            base_branch: "main".to_string(),
+            base_branch: "main".into(),
             branches,
-            current_branch: Some("feature-2".to_string()),
+            current_branch: Some("feature-2".into()),
             gitlab_project: None,
+            mr_template: None,
+            update_strategy: UpdateStrategy::default(),
+            owner: None,
+            archived: false,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         };
 
         let mut mrs = HashMap::new();
         mrs.insert(
-            101,
+            MrIid::from(101),
             MergeRequest {
                 id: 1,
-                iid: 101,
+                iid: MrIid::from(101),
                 title: "Feat: part 1".to_string(),
                 description: Some("".to_string()),
                 source_branch: "feature-1".to_string(),
                 target_branch: "main".to_string(),
+                source_project_id: None,
                 state: "opened".to_string(),
                 web_url: "https://gitlab.com/test/repo/-/merge_requests/101".to_string(),
+                head_pipeline: None,
+                has_conflicts: false,
+                detailed_merge_status: None,
+                sha: None,
+                milestone: None,
             },
         );
         mrs.insert(
-            102,
+            MrIid::from(102),
             MergeRequest {
                 id: 2,
-                iid: 102,
+                iid: MrIid::from(102),
                 title: "Feat: part 2".to_string(),
                 description: Some("".to_string()),
                 source_branch: "feature-2".to_string(),
                 target_branch: "feature-1".to_string(),
+                source_project_id: None,
                 state: "opened".to_string(),
                 web_url: "https://gitlab.com/test/repo/-/merge_requests/102".to_string(),
+                head_pipeline: None,
+                has_conflicts: false,
+                detailed_merge_status: None,
+                sha: None,
+                milestone: None,
             },
         );
         mrs.insert(
-            103,
+            MrIid::from(103),
             MergeRequest {
                 id: 3,
-                iid: 103,
+                iid: MrIid::from(103),
                 title: "Feat: part 3".to_string(),
                 description: Some("".to_string()),
                 source_branch: "feature-3".to_string(),
                 target_branch: "feature-1".to_string(),
+                source_project_id: None,
                 state: "opened".to_string(),
                 web_url: "https://gitlab.com/test/repo/-/merge_requests/103".to_string(),
+                head_pipeline: None,
+                has_conflicts: false,
+                detailed_merge_status: None,
+                sha: None,
+                milestone: None,
             },
         );
         mrs.insert(
-            104,
+            MrIid::from(104),
             MergeRequest {
                 id: 4,
-                iid: 104,
+                iid: MrIid::from(104),
                 title: "Feat: part 4".to_string(),
                 description: Some("".to_string()),
                 source_branch: "feature-4".to_string(),
                 target_branch: "main".to_string(),
+                source_project_id: None,
                 state: "opened".to_string(),
                 web_url: "https://gitlab.com/test/repo/-/merge_requests/104".to_string(),
+                head_pipeline: None,
+                has_conflicts: false,
+                detailed_merge_status: None,
+                sha: None,
+                milestone: None,
             },
         );
 
@@ -343,18 +905,124 @@ mod tests {
     #[test]
     fn test_build_stack_table() {
         let (stack, mrs) = create_test_stack_and_mrs();
-        let table = build_stack_table(&stack, &mrs);
+        let table = build_stack_table(&stack, &mrs, &StackTableConfig::default(), &HashMap::new());
 
         assert!(table.contains(STACK_TABLE_START));
         assert!(table.contains(STACK_TABLE_END));
         assert!(table.contains("| #1 | `feature-1` | [Feat: part 1](https://gitlab.com/test/repo/-/merge_requests/101+) |"));
         assert!(table.contains("| #2 | `feature-2` | [Feat: part 2](https://gitlab.com/test/repo/-/merge_requests/102+) |"));
+        assert!(!table.contains("never force-pushed"));
+    }
+
+    #[test]
+    fn test_build_stack_table_escapes_titles() {
+        let (stack, mut mrs) = create_test_stack_and_mrs();
+        mrs.get_mut(&MrIid::from(101)).unwrap().title = "[Stack: s] a|b [wip]".to_string();
+        let table = build_stack_table(&stack, &mrs, &StackTableConfig::default(), &HashMap::new());
+
+        assert!(table.contains(
+            "| #1 | `feature-1` | [\\[Stack: s\\] a\\|b \\[wip\\]](https://gitlab.com/test/repo/-/merge_requests/101+) |"
+        ));
+    }
+
+    #[test]
+    fn test_build_stack_table_notes_merge_strategy() {
+        let (mut stack, mrs) = create_test_stack_and_mrs();
+        stack.update_strategy = UpdateStrategy::Merge;
+        let table = build_stack_table(&stack, &mrs, &StackTableConfig::default(), &HashMap::new());
+
+        assert!(table.contains("merging each parent into its child"));
+        assert!(table.contains("never force-pushed"));
+    }
+
+    #[test]
+    fn test_build_stack_table_optional_columns() {
+        let (stack, mut mrs) = create_test_stack_and_mrs();
+        mrs.get_mut(&MrIid::from(101)).unwrap().state = "merged".to_string();
+        mrs.get_mut(&MrIid::from(102)).unwrap().head_pipeline =
+            Some(crate::gitlab::api::Pipeline {
+                id: 7,
+                status: "failed".to_string(),
+            });
+        let options = StackTableConfig {
+            columns: vec![
+                TableColumn::State,
+                TableColumn::Pipeline,
+                TableColumn::Target,
+                TableColumn::Commits,
+            ],
+            ..Default::default()
+        };
+        let commit_counts = HashMap::from([("feature-2".to_string(), 3)]);
+
+        let table = build_stack_table(&stack, &mrs, &options, &commit_counts);
+        assert!(table.contains(
+            "| Position | Branch | Merge Request | State | Pipeline | Target | Commits |"
+        ));
+        assert!(table.contains("|---|---|---|---|---|---|---|"));
+        assert!(table.contains("merge_requests/101+) | 🟣 | - | `main` | - |"));
+        assert!(table.contains("merge_requests/102+) | 🟢 | ❌ | `feature-1` | 3 |"));
+
+        let text = StackTableConfig {
+            badges: BadgeStyle::Text,
+            ..options
+        };
+        let table = build_stack_table(&stack, &mrs, &text, &commit_counts);
+        assert!(table.contains("| open | failed |"));
+    }
+
+    #[test]
+    fn test_build_stack_table_compact() {
+        let (stack, mrs) = create_test_stack_and_mrs();
+        let options = StackTableConfig {
+            columns: vec![TableColumn::State, TableColumn::Commits],
+            compact_max_branches: 2,
+            ..Default::default()
+        };
+
+        let table = build_stack_table(&stack, &mrs, &options, &HashMap::new());
+        assert!(!table.contains("| Position |"));
+        assert!(table.contains(
+            "**MR Train:** #1 [Feat: part 1](https://gitlab.com/test/repo/-/merge_requests/101+) 🟢 → #2 [Feat: part 2](https://gitlab.com/test/repo/-/merge_requests/102+) 🟢"
+        ));
+
+        // Stacks taller than the limit still get a table
+        let options = StackTableConfig {
+            compact_max_branches: 1,
+            ..options
+        };
+        let table = build_stack_table(&stack, &mrs, &options, &HashMap::new());
+        assert!(table.contains("| Position |"));
+    }
+
+    #[test]
+    fn test_stack_table_mr_iids_in_table_order() {
+        let (stack, mrs) = create_test_stack_and_mrs();
+        for compact_max_branches in [0, 5] {
+            let options = StackTableConfig {
+                compact_max_branches,
+                ..Default::default()
+            };
+            let table = build_stack_table(&stack, &mrs, &options, &HashMap::new());
+            let description = Some(format!("Intro\n\n{}\n\nSee !7/merge_requests/9)", table));
+            assert_eq!(
+                stack_table_mr_iids(&description),
+                vec![MrIid::from(101), MrIid::from(102)]
+            );
+        }
+        assert!(stack_table_mr_iids(&Some("No table".to_string())).is_empty());
+
+        assert_eq!(
+            stack_name_from_title("[Stack: payments] Add refunds"),
+            Some("payments")
+        );
+        assert_eq!(stack_name_from_title("Add refunds"), None);
     }
 
     #[test]
     fn test_build_stack_table_includes_all_branches() {
         let (stack, mrs) = create_complex_test_stack_and_mrs();
-        let table = build_stack_table(&stack, &mrs);
+        let table = build_stack_table(&stack, &mrs, &StackTableConfig::default(), &HashMap::new());
 
         assert!(table.contains(STACK_TABLE_START));
         assert!(table.contains(STACK_TABLE_END));
@@ -411,7 +1079,8 @@ mod tests {
     fn test_update_description_no_existing_table() {
         let description = Some("Initial description.".to_string());
         let (stack, mrs) = create_test_stack_and_mrs();
-        let new_table = build_stack_table(&stack, &mrs);
+        let new_table =
+            build_stack_table(&stack, &mrs, &StackTableConfig::default(), &HashMap::new());
 
         let updated_description = update_description(&description, &new_table);
 
@@ -429,7 +1098,8 @@ mod tests {
         ));
 
         let (stack, mrs) = create_test_stack_and_mrs();
-        let new_table = build_stack_table(&stack, &mrs);
+        let new_table =
+            build_stack_table(&stack, &mrs, &StackTableConfig::default(), &HashMap::new());
 
         let updated_description = update_description(&description, &new_table);
 
@@ -444,10 +1114,161 @@ mod tests {
     fn test_update_description_empty_description() {
         let description = None;
         let (stack, mrs) = create_test_stack_and_mrs();
-        let new_table = build_stack_table(&stack, &mrs);
+        let new_table =
+            build_stack_table(&stack, &mrs, &StackTableConfig::default(), &HashMap::new());
         let updated_description = update_description(&description, &new_table);
 
         assert!(updated_description.contains(STACK_TABLE_START));
         assert!(!updated_description.starts_with("\n\n"));
     }
+
+    #[test]
+    fn test_squash_commit_message_drops_injected_sections() {
+        let (stack, mrs) = create_test_stack_and_mrs();
+        let table = build_stack_table(&stack, &mrs, &StackTableConfig::default(), &HashMap::new());
+        let mut mr = mrs.values().next().unwrap().clone();
+        mr.title = "[Stack: my-stack] feat: add login".to_string();
+        mr.description = Some(update_description(
+            &Some("Adds the login form.".to_string()),
+            &table,
+        ));
+
+        let message = squash_commit_message(&mr);
+        assert!(message.starts_with("feat: add login\n\nAdds the login form.\n\n"));
+        assert!(message.ends_with(&format!("See merge request !{}", mr.iid)));
+        assert!(!message.contains(STACK_TABLE_START));
+    }
+
+    #[test]
+    fn test_stack_summary_follows_table_order() {
+        let (stack, mut mrs) = create_test_stack_and_mrs();
+        let table = build_stack_table(&stack, &mrs, &StackTableConfig::default(), &HashMap::new());
+        mrs.get_mut(&MrIid::from(102)).unwrap().description = Some(update_description(
+            &Some("Wires the API into the form.".to_string()),
+            &table,
+        ));
+        let mut diff_stats = HashMap::new();
+        diff_stats.insert(
+            "feature-2".to_string(),
+            (1..=7)
+                .map(|n| FileDiffStat {
+                    path: format!("src/file{}.rs", n),
+                    added: n * 10,
+                    deleted: 1,
+                })
+                .collect(),
+        );
+
+        let summary = build_stack_summary(&stack, &mrs, &diff_stats);
+        let part1 = summary.find("## 1. Feat: part 1").unwrap();
+        let part2 = summary.find("## 2. Feat: part 2").unwrap();
+        assert!(part1 < part2);
+        assert!(summary.contains("`feature-2` · 7 files, +280 −7"));
+        assert!(summary.contains("Wires the API into the form."));
+        assert!(!summary.contains(STACK_TABLE_START));
+        // Largest files first, the rest folded into a count
+        let largest = summary.find("src/file7.rs").unwrap();
+        assert!(largest < summary.find("src/file3.rs").unwrap());
+        assert!(!summary.contains("src/file2.rs"));
+        assert!(summary.contains("…and 2 more files"));
+    }
+
+    #[test]
+    fn test_stack_table_section_hash_roundtrip() {
+        let (stack, mrs) = create_test_stack_and_mrs();
+        let table = build_stack_table(&stack, &mrs, &StackTableConfig::default(), &HashMap::new());
+        let description = Some(update_description(&Some("Intro".to_string()), &table));
+
+        let section = stack_table_section(&description).unwrap();
+        assert_eq!(table_hash(section), table_hash(&table));
+        assert_ne!(table_hash(section), table_hash("something else"));
+        assert!(stack_table_section(&Some("No table here".to_string())).is_none());
+    }
+
+    #[test]
+    fn test_review_notes_section_roundtrip() {
+        let (mut stack, mrs) = create_test_stack_and_mrs();
+        let branch = stack.branches.get_mut("feature-1").unwrap();
+        branch.review_notes = vec![
+            crate::stack::types::ReviewNote {
+                text: "Check migration order".to_string(),
+                todo: true,
+                done: true,
+                created_at: Utc::now(),
+            },
+            crate::stack::types::ReviewNote {
+                text: "Waiting on design sign-off".to_string(),
+                todo: false,
+                done: false,
+                created_at: Utc::now(),
+            },
+        ];
+        let section = build_review_notes_section(branch).unwrap();
+        assert!(section.contains("Review notes (1/1 done)"));
+        assert!(section.contains("- [x] Check migration order"));
+        assert!(section.contains("- Waiting on design sign-off"));
+
+        let table = build_stack_table(&stack, &mrs, &StackTableConfig::default(), &HashMap::new());
+        let description = Some(update_description(&Some("Intro".to_string()), &table));
+        let with_notes = update_review_notes(&description, Some(&section));
+        assert!(with_notes.starts_with("Intro"));
+        assert!(with_notes.contains(STACK_TABLE_END));
+        assert!(with_notes.contains(REVIEW_NOTES_START));
+
+        // Refreshing the stack table keeps the notes, removing the notes keeps the table
+        let refreshed = update_description(&Some(with_notes), &table);
+        assert!(refreshed.contains("Check migration order"));
+        let without_notes = update_review_notes(&Some(refreshed), None);
+        assert!(!without_notes.contains(REVIEW_NOTES_START));
+        assert!(without_notes.contains(STACK_TABLE_END));
+    }
+
+    #[test]
+    fn test_dependency_section_links_parent_and_children() {
+        let (stack, mrs) = create_test_stack_and_mrs();
+        let bottom = build_dependency_section(&stack, &stack.branches["feature-1"]).unwrap();
+        assert!(bottom.contains("Required by !102"));
+        assert!(!bottom.contains("Depends on"));
+        let top = build_dependency_section(&stack, &stack.branches["feature-2"]).unwrap();
+        assert!(top.contains("Depends on !101"));
+
+        let mut mr = mrs[&MrIid::from(102)].clone();
+        mr.description = Some(update_dependencies(&Some("Intro".to_string()), Some(&top)));
+        assert!(!squash_commit_message(&mr).contains("Depends on"));
+        mr.description = Some(update_dependencies(&mr.description, None));
+        assert_eq!(mr.description.as_deref(), Some("Intro"));
+    }
+
+    #[test]
+    fn test_landing_section_orders_parent_and_children() {
+        let (mut stack, mut mrs) = create_test_stack_and_mrs();
+        let bottom = build_landing_section(&stack, &stack.branches["feature-1"], &mrs).unwrap();
+        assert!(bottom.contains("**1 of 2**"), "{}", bottom);
+        assert!(bottom.contains("Nothing below this MR is left to merge"));
+        assert!(bottom.contains(
+            "Merge after this one: [Feat: part 2](https://gitlab.com/test/repo/-/merge_requests/102+)"
+        ));
+        let top = build_landing_section(&stack, &stack.branches["feature-2"], &mrs).unwrap();
+        assert!(top.contains("**2 of 2**"));
+        assert!(top.contains("Merge first: [Feat: part 1]"));
+        assert!(top.contains("retargets it to `main`"));
+        assert!(!top.contains("Merge after"));
+
+        // A merged parent no longer holds the level back
+        mrs.get_mut(&MrIid::from(101)).unwrap().state = "merged".to_string();
+        let top = build_landing_section(&stack, &stack.branches["feature-2"], &mrs).unwrap();
+        assert!(top.contains("Nothing below this MR is left to merge"));
+
+        // The footer goes below everything else and stays out of squash commits
+        let description = update_description(&Some("Intro".to_string()), "table");
+        let description = update_landing_instructions(&Some(description), Some(&top));
+        assert!(description.starts_with("Intro\n\ntable"));
+        assert!(description.ends_with(LANDING_END));
+        let mut mr = mrs[&MrIid::from(102)].clone();
+        mr.description = Some(description);
+        assert!(!squash_commit_message(&mr).contains("Landing this stack"));
+
+        stack.branches.get_mut("feature-2").unwrap().state = BranchState::LocalOnly;
+        assert!(build_landing_section(&stack, &stack.branches["feature-2"], &mrs).is_none());
+    }
 }
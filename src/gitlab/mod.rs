@@ -1,2 +1,4 @@
 pub mod api;
+pub mod cache;
+pub mod codeowners;
 pub mod markdown;
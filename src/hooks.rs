@@ -0,0 +1,98 @@
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::git::{GitRepository, INTERNAL_ENV};
+
+/// Hooks that keep stack metadata in step with commits, checkouts and rewrites
+/// made outside git-train.
+pub const HOOKS: &[&str] = &["post-commit", "post-checkout", "post-rewrite"];
+
+/// First line after the shebang of every hook git-train writes
+const HOOK_MARKER: &str = "# Installed by git-train install-hooks";
+
+/// Suffix given to a user's own hook when `--force` replaces it
+const BACKUP_SUFFIX: &str = ".pre-git-train";
+
+/// What `install` did with one hook
+#[derive(Debug, Clone, PartialEq)]
+pub enum HookInstall {
+    Installed,
+    /// A git-train hook was already there and has been rewritten
+    Updated,
+    /// The user's hook was moved aside to `<name>.pre-git-train` and is chained from ours
+    Replaced,
+    /// A foreign hook is in the way and `force` wasn't given
+    Skipped,
+}
+
+/// Install the git-train hooks into the repository's hooks directory
+/// (honouring `core.hooksPath`).
+pub fn install(repo: &GitRepository, force: bool) -> Result<Vec<(String, HookInstall)>> {
+    let hooks_dir = hooks_dir(repo)?;
+    fs::create_dir_all(&hooks_dir)?;
+
+    let mut results = Vec::new();
+    for name in HOOKS {
+        let path = hooks_dir.join(name);
+        let outcome = match fs::read_to_string(&path) {
+            Ok(existing) if existing.contains(HOOK_MARKER) => HookInstall::Updated,
+            Ok(_) if !force => {
+                results.push((name.to_string(), HookInstall::Skipped));
+                continue;
+            }
+            Ok(_) => {
+                fs::rename(&path, hooks_dir.join(format!("{}{}", name, BACKUP_SUFFIX)))?;
+                HookInstall::Replaced
+            }
+            Err(_) => HookInstall::Installed,
+        };
+        fs::write(&path, hook_script(name))?;
+        make_executable(&path)?;
+        results.push((name.to_string(), outcome));
+    }
+    Ok(results)
+}
+
+fn hooks_dir(repo: &GitRepository) -> Result<PathBuf> {
+    let path = PathBuf::from(repo.run(&["rev-parse", "--git-path", "hooks"])?);
+    Ok(if path.is_absolute() {
+        path
+    } else {
+        repo.root().join(path)
+    })
+}
+
+fn hook_script(name: &str) -> String {
+    format!(
+        r#"#!/bin/sh
+{marker}
+# Keeps stack metadata fresh when you commit, check out or rebase outside git-train.
+if [ -x "$0{backup}" ]; then
+    "$0{backup}" "$@" || exit $?
+fi
+[ -n "${internal}" ] && exit 0
+command -v git-train >/dev/null 2>&1 || exit 0
+git-train hook {name} >/dev/null 2>&1 </dev/null
+exit 0
+"#,
+        marker = HOOK_MARKER,
+        backup = BACKUP_SUFFIX,
+        internal = INTERNAL_ENV,
+        name = name,
+    )
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mut permissions = fs::metadata(path)?.permissions();
+    permissions.set_mode(0o755);
+    fs::set_permissions(path, permissions)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}
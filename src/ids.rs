@@ -0,0 +1,250 @@
+//! Newtypes for the identifiers git-train passes around, so a stack id, a branch name
+//! and an MR iid can't be swapped for one another. All of them serialize as the bare
+//! string or number, keeping stored stacks and GitLab payloads unchanged.
+
+use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
+use std::fmt;
+use std::ops::Deref;
+use uuid::Uuid;
+
+use crate::config::NamingConfig;
+use crate::errors::TrainError;
+use crate::utils::sanitize_name;
+
+/// A git branch name.
+///
+/// `From` conversions trust their input (names read from git or from stored stacks);
+/// names typed by users or built from templates go through [`BranchName::new`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct BranchName(String);
+
+impl BranchName {
+    /// Validate `name` against git's ref name rules.
+    pub fn new(name: impl Into<String>) -> Result<Self, TrainError> {
+        let name = name.into();
+        let invalid = |reason: &str| TrainError::InvalidState {
+            message: format!("'{}' is not a valid branch name: {}", name, reason),
+        };
+
+        if name.is_empty() {
+            return Err(invalid("it is empty"));
+        }
+        if name.starts_with('-') || name.starts_with('/') || name.ends_with('/') {
+            return Err(invalid("it can't start with '-' or start or end with '/'"));
+        }
+        if name.ends_with('.') || name.ends_with(".lock") {
+            return Err(invalid("it can't end with '.' or '.lock'"));
+        }
+        if name.contains("..") || name.contains("//") || name.contains("@{") || name == "@" {
+            return Err(invalid("it can't contain '..', '//' or '@{'"));
+        }
+        if name
+            .chars()
+            .any(|c| c.is_ascii_control() || " ~^:?*[\\".contains(c))
+        {
+            return Err(invalid("it can't contain spaces or any of ~^:?*[\\"));
+        }
+        if name.split('/').any(|part| part.starts_with('.')) {
+            return Err(invalid("no path component can start with '.'"));
+        }
+
+        Ok(Self(name))
+    }
+
+    /// Turn free-form text into a name following the `naming` policy
+    /// ("Feature Branch" -> "feature-branch"), keeping `/` when the policy says so.
+    pub fn sanitized(text: &str, naming: &NamingConfig) -> Self {
+        Self(sanitize_name(text, naming, naming.preserve_slashes))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Identifier of a stored stack (a UUID).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct StackId(String);
+
+impl StackId {
+    pub fn generate() -> Self {
+        Self(Uuid::new_v4().to_string())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// First eight characters, as shown in listings
+    pub fn short(&self) -> &str {
+        &self.0[..8.min(self.0.len())]
+    }
+}
+
+/// Project-scoped merge request number (the `!123` users see).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct MrIid(u64);
+
+macro_rules! string_id {
+    ($name:ident) => {
+        impl Deref for $name {
+            type Target = str;
+
+            fn deref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl Borrow<str> for $name {
+            fn borrow(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl Borrow<String> for $name {
+            fn borrow(&self) -> &String {
+                &self.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(value: String) -> Self {
+                Self(value)
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(value: &str) -> Self {
+                Self(value.to_string())
+            }
+        }
+
+        impl From<&String> for $name {
+            fn from(value: &String) -> Self {
+                Self(value.clone())
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+
+        impl PartialEq<str> for $name {
+            fn eq(&self, other: &str) -> bool {
+                self.0 == other
+            }
+        }
+
+        impl PartialEq<&str> for $name {
+            fn eq(&self, other: &&str) -> bool {
+                self.0 == *other
+            }
+        }
+
+        impl PartialEq<String> for $name {
+            fn eq(&self, other: &String) -> bool {
+                &self.0 == other
+            }
+        }
+
+        impl PartialEq<$name> for String {
+            fn eq(&self, other: &$name) -> bool {
+                self == &other.0
+            }
+        }
+
+        impl PartialEq<$name> for str {
+            fn eq(&self, other: &$name) -> bool {
+                self == other.0
+            }
+        }
+
+        impl PartialEq<$name> for &str {
+            fn eq(&self, other: &$name) -> bool {
+                *self == other.0
+            }
+        }
+    };
+}
+
+string_id!(BranchName);
+string_id!(StackId);
+
+impl fmt::Display for MrIid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<u64> for MrIid {
+    fn from(value: u64) -> Self {
+        Self(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitized_branch_name() {
+        let naming = NamingConfig::default();
+        assert_eq!(
+            BranchName::sanitized("Feature Branch", &naming),
+            "feature-branch"
+        );
+        assert_eq!(BranchName::sanitized("fix/bug#123", &naming), "fix/bug-123");
+        assert_eq!(BranchName::sanitized("--start--", &naming), "start");
+        let flat = NamingConfig {
+            preserve_slashes: false,
+            ..NamingConfig::default()
+        };
+        assert_eq!(BranchName::sanitized("fix/bug#123", &flat), "fix-bug-123");
+    }
+
+    #[test]
+    fn test_branch_name_validation() {
+        for valid in ["feature-1", "alice/stack/add-login", "v1.2"] {
+            assert!(BranchName::new(valid).is_ok(), "{}", valid);
+        }
+        for invalid in [
+            "",
+            "-x",
+            "a..b",
+            "a b",
+            "x.lock",
+            "a/.hidden",
+            "dir/",
+            "what?",
+        ] {
+            assert!(BranchName::new(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_ids_serialize_transparently() {
+        let branch = BranchName::from("feature-1");
+        assert_eq!(serde_json::to_string(&branch).unwrap(), "\"feature-1\"");
+        let iid: MrIid = serde_json::from_str("42").unwrap();
+        assert_eq!(iid, MrIid::from(42));
+        assert_eq!(format!("!{}", iid), "!42");
+    }
+}
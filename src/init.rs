@@ -0,0 +1,154 @@
+//! `git-train init`: first-run setup for a repository. Detects what it can, asks about
+//! the rest, writes the config and can turn an existing chain of branches into a stack.
+
+use anyhow::Result;
+
+use crate::config::{ConfigManager, TrainConfig};
+use crate::errors::TrainError;
+use crate::git::GitRepository;
+use crate::gitlab::api::{GitLabApi, GitLabClient, GitLabSettings};
+use crate::stack::StackManager;
+use crate::ui;
+
+/// How restacked branches get force-pushed, as offered by the wizard
+const FORCE_PUSH_MODES: [&str; 3] = [
+    "Ask before each force-push",
+    "Force-push automatically (with lease) after restacking",
+    "Never force-push; I'll push rewritten branches myself",
+];
+
+pub async fn run_wizard(config_manager: &mut ConfigManager) -> Result<()> {
+    ui::print_train_header("Welcome to git-train");
+
+    let git_repo = GitRepository::new_from_current_dir().map_err(|_| TrainError::InvalidState {
+        message: "Run 'git-train init' inside a git repository".to_string(),
+    })?;
+    ui::print_success(&format!("Repository: {}", git_repo.root().display()));
+
+    let config = config_manager.get_config().clone();
+    let stack_manager =
+        StackManager::new_with_config(config.clone(), Some(git_repo.clone()), None).await?;
+
+    // Base branch
+    let detected_base = stack_manager.detect_base_branch();
+    let base = ui::get_user_input("Base branch for new stacks", detected_base.as_deref())?
+        .trim()
+        .to_string();
+    let mut base_candidates = config.git.base_branch_candidates.clone();
+    if detected_base.as_deref() != Some(base.as_str()) && !base_candidates.contains(&base) {
+        base_candidates.insert(0, base.clone());
+    }
+
+    check_gitlab(&git_repo, &config).await;
+
+    // Editor and force-push preference
+    let editor = ui::get_user_input(
+        "Editor for conflicts and MR descriptions",
+        Some(&config.editor.default_editor),
+    )?;
+    let force_push = ui::select_from_list(
+        &FORCE_PUSH_MODES,
+        "How should restacked branches be force-pushed?",
+    )?;
+    let (auto_force_push, prompt_before_force_push) = match force_push {
+        0 => (false, true),
+        1 => (true, false),
+        _ => (false, false),
+    };
+
+    config_manager.update_config(|config| {
+        config.git.base_branch_candidates = base_candidates;
+        config.conflict_resolution.auto_force_push_after_rebase = auto_force_push;
+        config.conflict_resolution.prompt_before_force_push = prompt_before_force_push;
+    })?;
+    if editor.trim() != config.editor.default_editor {
+        config_manager.set_default_editor(editor.trim())?;
+    }
+    ui::print_success("Saved the git-train configuration");
+
+    // Existing branches stacked on each other can become a stack right away
+    let mut stack_manager = StackManager::new_with_config(
+        config_manager.get_config().clone(),
+        Some(git_repo.clone()),
+        None,
+    )
+    .await?;
+    if let Ok(current) = git_repo.get_current_branch() {
+        if current != base {
+            match stack_manager.detect_branch_chain(&current, &base) {
+                Ok(chain) => {
+                    ui::print_info(&format!(
+                        "'{}' sits on a chain of {} branch(es): {}",
+                        current,
+                        chain.len(),
+                        chain.join(" → ")
+                    ));
+                    if ui::confirm_action("Adopt them as a stack?")? {
+                        let name = ui::get_user_input("Stack name", Some(&chain[0]))?;
+                        stack_manager
+                            .adopt_branch_chain(name.trim(), &chain, &base)
+                            .await?;
+                    }
+                }
+                Err(e) => ui::print_info(&format!("Not adopting '{}': {}", current, e)),
+            }
+        }
+    }
+
+    print_cheatsheet();
+    Ok(())
+}
+
+/// Report whether a token is set and which project the remotes point at
+async fn check_gitlab(git_repo: &GitRepository, config: &TrainConfig) {
+    let settings = match GitLabSettings::from_env(|name| std::env::var(name).ok()) {
+        Ok(settings) => settings,
+        Err(_) => {
+            ui::print_warning("No GitLab token found; git-train will work git-only");
+            ui::print_info(
+                "Export GITLAB_TOKEN (a token with the 'api' scope) and, for self-managed instances, GITLAB_URL",
+            );
+            return;
+        }
+    };
+    ui::print_success(&format!("GitLab token found for {}", settings.base_url));
+
+    let project = match GitLabClient::new(git_repo.clone(), config.timeouts.http()).await {
+        Ok(client) => client.detect_and_cache_project().await,
+        Err(e) => Err(e),
+    };
+    match project {
+        Ok(project) => ui::print_success(&format!(
+            "GitLab project: {}/{} ({})",
+            project.namespace.path, project.path, project.web_url
+        )),
+        Err(e) => ui::print_warning(&format!("Could not detect the GitLab project: {}", e)),
+    }
+}
+
+fn print_cheatsheet() {
+    ui::print_train_header("Quickstart");
+    for (command, what) in [
+        (
+            "git-train create <name>",
+            "start a stack from the current branch",
+        ),
+        (
+            "git checkout -b <branch> && git-train add",
+            "stack a new level on top",
+        ),
+        ("git-train status", "levels, MRs and pipelines at a glance"),
+        ("git-train sync", "restack every level onto its parent"),
+        (
+            "git-train push",
+            "push all levels and open or update their MRs",
+        ),
+        ("git-train navigate", "jump between levels"),
+        (
+            "git-train land",
+            "merge the bottom MRs once they're approved",
+        ),
+    ] {
+        ui::print_config_item(command, what);
+    }
+}
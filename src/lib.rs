@@ -1,10 +1,18 @@
 pub mod app;
+pub mod bench;
+pub mod bump;
 pub mod cli;
 pub mod config;
 pub mod conflict;
 pub mod errors;
+pub mod events;
 pub mod git;
 pub mod gitlab;
+pub mod hooks;
+pub mod ids;
+pub mod init;
+pub mod lock;
+pub mod serve;
 pub mod stack;
 pub mod ui;
 pub mod utils;
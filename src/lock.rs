@@ -0,0 +1,199 @@
+//! Repository-wide lock held while a git-train command changes branches or stack
+//! metadata, so two commands in the same repository (or its worktrees) can't interleave.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::errors::TrainError;
+use crate::ui;
+
+/// Lock file inside the train directory
+const LOCK_FILE: &str = "operation.lock";
+
+/// Lock file held by this process, for [`release_held`]
+static HELD: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Who holds the lock, as written into the lock file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockHolder {
+    pub pid: u32,
+    /// Command line of the holder, e.g. `sync --all`
+    pub operation: String,
+    pub started_at: DateTime<Utc>,
+}
+
+impl LockHolder {
+    fn describe(&self) -> String {
+        format!(
+            "'git-train {}' (pid {}) since {}",
+            self.operation,
+            self.pid,
+            self.started_at.format("%Y-%m-%d %H:%M:%S UTC")
+        )
+    }
+
+    /// Whether the holding process is still running on this machine
+    fn is_running(&self) -> bool {
+        process_running(self.pid)
+    }
+}
+
+/// Held for as long as the value lives; dropping it releases the lock
+#[derive(Debug)]
+pub struct OperationLock {
+    path: PathBuf,
+}
+
+impl OperationLock {
+    /// Take the lock in `train_dir` for `operation`.
+    ///
+    /// When another command holds it, fail with the holder's details. With `steal`, a
+    /// lock whose holder is no longer running is taken over; a live holder never is.
+    pub fn acquire(train_dir: &Path, operation: &str, steal: bool) -> Result<Self> {
+        fs::create_dir_all(train_dir)?;
+        let path = train_dir.join(LOCK_FILE);
+        let holder = LockHolder {
+            pid: std::process::id(),
+            operation: operation.to_string(),
+            started_at: Utc::now(),
+        };
+
+        // One retry, after stealing a stale lock
+        for _ in 0..2 {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    file.write_all(serde_json::to_string(&holder)?.as_bytes())?;
+                    *HELD.lock().unwrap_or_else(|e| e.into_inner()) = Some(path.clone());
+                    return Ok(Self { path });
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    let existing = read_holder(&path);
+                    let description = existing.as_ref().map_or_else(
+                        || "a git-train command that is still starting".to_string(),
+                        LockHolder::describe,
+                    );
+                    if !steal {
+                        return Err(TrainError::Locked {
+                            message: format!(
+                                "{} is changing this repository. Wait for it to finish, or if it crashed, rerun with --steal-lock",
+                                description
+                            ),
+                        }
+                        .into());
+                    }
+                    if existing.as_ref().is_some_and(LockHolder::is_running) {
+                        return Err(TrainError::Locked {
+                            message: format!(
+                                "{} is still running; stop it before stealing its lock",
+                                description
+                            ),
+                        }
+                        .into());
+                    }
+                    ui::print_warning(&format!("Stealing the lock held by {}", description));
+                    fs::remove_file(&path)?;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Err(TrainError::Locked {
+            message: "Another git-train command took the lock first".to_string(),
+        }
+        .into())
+    }
+}
+
+impl Drop for OperationLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+        let mut held = HELD.lock().unwrap_or_else(|e| e.into_inner());
+        if held.as_ref() == Some(&self.path) {
+            *held = None;
+        }
+    }
+}
+
+/// Release the lock this process holds, for exits that skip destructors
+/// (`std::process::exit` after a second Ctrl+C)
+pub fn release_held() {
+    if let Some(path) = HELD.lock().unwrap_or_else(|e| e.into_inner()).take() {
+        let _ = fs::remove_file(path);
+    }
+}
+
+/// The current holder, or `None` when the file is gone or still being written
+fn read_holder(path: &Path) -> Option<LockHolder> {
+    let contents = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+#[cfg(unix)]
+fn process_running(pid: u32) -> bool {
+    let proc = Path::new("/proc");
+    if proc.is_dir() {
+        return proc.join(pid.to_string()).exists();
+    }
+    std::process::Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stderr(std::process::Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Other processes can't be inspected here; trust the user's --steal-lock
+#[cfg(not(unix))]
+fn process_running(_pid: u32) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_excludes_other_commands_until_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let lock = OperationLock::acquire(dir.path(), "sync", false).unwrap();
+        let error = OperationLock::acquire(dir.path(), "push", false).unwrap_err();
+        let message = error.to_string();
+        assert!(message.contains("'git-train sync'"), "{}", message);
+        assert!(message.contains(&format!("pid {}", std::process::id())));
+
+        // The holder is alive, so even --steal-lock leaves it alone
+        assert!(OperationLock::acquire(dir.path(), "push", true).is_err());
+
+        drop(lock);
+        assert!(OperationLock::acquire(dir.path(), "push", false).is_ok());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_steal_lock_of_a_finished_process() {
+        let dir = tempfile::tempdir().unwrap();
+        let crashed = LockHolder {
+            pid: u32::MAX,
+            operation: "land".to_string(),
+            started_at: Utc::now(),
+        };
+        fs::write(
+            dir.path().join(LOCK_FILE),
+            serde_json::to_string(&crashed).unwrap(),
+        )
+        .unwrap();
+
+        let error = OperationLock::acquire(dir.path(), "sync", false).unwrap_err();
+        assert!(error.to_string().contains("--steal-lock"));
+
+        let _lock = OperationLock::acquire(dir.path(), "sync", true).unwrap();
+        let holder = read_holder(&dir.path().join(LOCK_FILE)).unwrap();
+        assert_eq!(holder.pid, std::process::id());
+        assert_eq!(holder.operation, "sync");
+    }
+}
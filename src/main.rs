@@ -2,25 +2,67 @@ use anyhow::Result;
 use clap::Parser;
 
 mod app;
+mod bench;
+mod bump;
 mod cli;
 mod config;
 mod conflict;
 mod errors;
+mod events;
 mod git;
 mod gitlab;
+mod hooks;
+mod ids;
+mod init;
+mod lock;
+mod serve;
 mod stack;
 mod ui;
 mod utils;
 
 use app::AppContext;
 use cli::Cli;
+use tracing::level_filters::LevelFilter;
+use ui::Verbosity;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    tracing_subscriber::fmt::init();
-
     let cli = Cli::parse();
 
+    let verbosity = cli.verbosity();
+    ui::set_verbosity(verbosity);
+    events::set_enabled(cli.progress_json);
+    tracing_subscriber::fmt()
+        .with_max_level(match verbosity {
+            Verbosity::Quiet => LevelFilter::ERROR,
+            Verbosity::Normal => LevelFilter::WARN,
+            Verbosity::Verbose => LevelFilter::INFO,
+            Verbosity::Debug => LevelFilter::DEBUG,
+        })
+        .with_writer(std::io::stderr)
+        .init();
+
+    // Like `git -C`: the repository, its train dir, relative paths and editors are all
+    // resolved from the requested directory
+    if let Some(dir) = &cli.cwd {
+        std::env::set_current_dir(dir).map_err(|e| errors::TrainError::IoError {
+            message: format!("Cannot change to '{}': {}", dir.display(), e),
+        })?;
+    }
+
+    // The first Ctrl+C stops the running git command and lets the current step unwind;
+    // a second one quits on the spot
+    tokio::spawn(async {
+        while tokio::signal::ctrl_c().await.is_ok() {
+            if git::cancel() {
+                ui::print_warning("Interrupting... press Ctrl+C again to quit immediately");
+            } else {
+                lock::release_held();
+                std::process::exit(130);
+            }
+        }
+    });
+
     // Initialize and run the application context
     AppContext::new()?.run(cli).await
 }
@@ -0,0 +1,213 @@
+//! `git-train serve`: a long-lived process answering newline-delimited JSON requests on
+//! a Unix socket, so editor plugins skip startup and GitLab detection on every call.
+//!
+//! Each line is a request `{"id": 1, "method": "status", "params": {}}` and gets exactly
+//! one response line, `{"id": 1, "result": ...}` or `{"id": 1, "error": {"message": ...}}`.
+//! Requests are handled one at a time, in the order they arrive.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::Path;
+
+use crate::stack::types::BranchState;
+use crate::stack::{PushOptions, StackManager, SyncOptions};
+use crate::ui;
+
+/// One JSON request line
+#[derive(Debug, Deserialize)]
+pub struct Request {
+    #[serde(default)]
+    pub id: Value,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+/// The answer to a [`Request`], echoing its `id`
+#[derive(Debug, Serialize)]
+pub struct Response {
+    pub id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ResponseError>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResponseError {
+    pub message: String,
+}
+
+impl Response {
+    fn ok(id: Value, result: Value) -> Self {
+        Self {
+            id,
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    fn error(id: Value, message: impl Into<String>) -> Self {
+        Self {
+            id,
+            result: None,
+            error: Some(ResponseError {
+                message: message.into(),
+            }),
+        }
+    }
+}
+
+/// Answer one raw request line. Returns the response and whether the client asked the
+/// server to shut down.
+pub async fn handle_line(stack_manager: &mut StackManager, line: &str) -> (Response, bool) {
+    let request: Request = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(e) => {
+            return (
+                Response::error(Value::Null, format!("Invalid request: {}", e)),
+                false,
+            )
+        }
+    };
+    if request.method == "shutdown" {
+        return (Response::ok(request.id, json!({ "ok": true })), true);
+    }
+
+    // Requests that change branches wait their turn like any other git-train command
+    let _lock = match request.method.as_str() {
+        "restack" | "push" => {
+            match stack_manager.lock_operation(&format!("serve {}", request.method), false) {
+                Ok(lock) => Some(lock),
+                Err(e) => return (Response::error(request.id, e.to_string()), false),
+            }
+        }
+        _ => None,
+    };
+
+    // Other git-train processes may have changed the stack since the last request
+    stack_manager.forget_current_stack();
+    if let Err(e) = stack_manager.follow_head_to_owning_stack() {
+        ui::print_warning(&format!("Could not check which stack owns HEAD: {}", e));
+    }
+    let result = dispatch(stack_manager, &request.method, &request.params).await;
+    if let Err(e) = stack_manager.record_current_branch() {
+        ui::print_warning(&format!("Could not record current branch: {}", e));
+    }
+
+    let response = match result {
+        Ok(result) => Response::ok(request.id, result),
+        Err(e) => Response::error(request.id, e.to_string()),
+    };
+    (response, false)
+}
+
+async fn dispatch(stack_manager: &mut StackManager, method: &str, params: &Value) -> Result<Value> {
+    match method {
+        "status" => status(stack_manager),
+        "restack" => {
+            stack_manager
+                .sync_with_remote_with_options(&SyncOptions {
+                    prune: params["prune"].as_bool().unwrap_or(false),
+                    when_behind: params["when_behind"].as_u64().map(|n| n as usize),
+                    ..Default::default()
+                })
+                .await?;
+            Ok(json!({ "ok": true }))
+        }
+        "push" => {
+            stack_manager
+                .push_stack_with_options(&PushOptions {
+                    no_mr: params["no_mr"].as_bool().unwrap_or(false),
+                    ..Default::default()
+                })
+                .await?;
+            Ok(json!({ "ok": true }))
+        }
+        other => Err(anyhow::anyhow!(
+            "Unknown method '{}' (expected status, restack, push or shutdown)",
+            other
+        )),
+    }
+}
+
+/// The current stack as JSON, levels listed parents first
+fn status(stack_manager: &mut StackManager) -> Result<Value> {
+    let stack = stack_manager.get_or_load_current_stack()?;
+    let (merged, total) = stack.merge_progress();
+    let branches: Vec<Value> = stack_manager
+        .levels_in_order(&stack)
+        .into_iter()
+        .filter_map(|(name, depth)| {
+            let branch = stack.branches.get(name.as_str())?;
+            Some(json!({
+                "name": branch.name,
+                "parent": branch.parent,
+                "depth": depth,
+                "mr_iid": branch.mr_iid,
+                "mr_title": branch.mr_title,
+                "state": branch.state.as_str(),
+                "merged": branch.state == BranchState::MrMerged,
+                "frozen": branch.state == BranchState::Frozen,
+                "private": branch.state == BranchState::LocalOnly,
+            }))
+        })
+        .collect();
+
+    Ok(json!({
+        "id": stack.id,
+        "name": stack.name,
+        "base_branch": stack.base_branch,
+        "current_branch": stack.current_branch,
+        "merged": merged,
+        "total": total,
+        "branches": branches,
+    }))
+}
+
+/// Listen on `socket` until a client sends `shutdown`
+#[cfg(unix)]
+pub async fn serve(mut stack_manager: StackManager, socket: &Path) -> Result<()> {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::UnixListener;
+
+    // A socket left behind by a server that didn't shut down cleanly
+    if socket.exists() {
+        std::fs::remove_file(socket)?;
+    }
+    let listener = UnixListener::bind(socket)?;
+    ui::print_info(&format!("Listening on {}", socket.display()));
+
+    'connections: loop {
+        let (stream, _) = listener.accept().await?;
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+
+        while let Ok(Some(line)) = lines.next_line().await {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (response, shutdown) = handle_line(&mut stack_manager, &line).await;
+            let mut payload = serde_json::to_string(&response)?;
+            payload.push('\n');
+            // A client hanging up only ends its own connection, not the server
+            if writer.write_all(payload.as_bytes()).await.is_err() {
+                break;
+            }
+            if shutdown {
+                break 'connections;
+            }
+        }
+    }
+
+    std::fs::remove_file(socket)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub async fn serve(_stack_manager: StackManager, _socket: &Path) -> Result<()> {
+    Err(anyhow::anyhow!(
+        "'git-train serve' needs Unix domain sockets, which this platform doesn't support"
+    ))
+}
@@ -1,32 +1,226 @@
 use anyhow::Result;
 use chrono::Utc;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
 use tracing::info;
-use uuid::Uuid;
 
-use crate::config::TrainConfig;
-use crate::conflict::{ConflictResolver, GitState};
-use crate::errors::TrainError;
-use crate::git::GitRepository;
-use crate::gitlab::api::{CreateMergeRequestRequest, GitLabApi, GitLabClient, MergeRequest};
+use crate::bump::{self, BumpLevel, Version};
+use crate::config::{
+    DependencyLinks, MergeCommitHandling, MergeMethod, NameEnforcement, TableColumn, TitleSync,
+    TrainConfig, UpdateStrategy, DEFAULT_BRANCH_TEMPLATE,
+};
+use crate::conflict::{ConflictInfo, ConflictResolver, GitState};
+use crate::errors::{is_gitlab_auth_error, TrainError};
+use crate::events::{self, Event};
+use crate::git::{FileDiffStat, GitRepository};
+use crate::gitlab::api::{
+    CreateMergeRequestRequest, Environment, GitLabApi, GitLabClient, MergeParams, MergeRequest,
+};
+use crate::gitlab::codeowners::{CodeOwners, CODEOWNERS_PATHS};
 use crate::gitlab::markdown;
+use crate::ids::{BranchName, MrIid, StackId};
+use crate::lock::OperationLock;
 use crate::stack::state::StackState;
-use crate::stack::types::{Stack, StackBranch};
+use crate::stack::types::{
+    BranchState, MrOperation, MrSnapshot, ReviewNote, Stack, StackBranch, SyncSnapshot,
+};
 use crate::ui::{
     self, confirm_action, get_user_input, print_error, print_info, print_success,
     print_train_header, print_warning, MrStatusInfo,
 };
-use crate::utils::{create_backup_name, sanitize_branch_name};
-use futures::future;
+use crate::utils::{
+    branch_name_matches, create_backup_name, render_branch_name, slugify, split_hunks,
+    suggest_split_points,
+};
+use console::style;
+use futures::{future, stream, StreamExt};
+
+/// MR status requests sent to GitLab at once
+const MR_STATUS_CONCURRENCY: usize = 8;
+/// Force-push attempts per branch when the lease keeps going stale
+const MAX_LEASE_ATTEMPTS: usize = 3;
+/// How long `navigate` shows MR statuses before refreshing them in the background
+const MR_STATUS_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// What `status --watch` compares between refreshes to highlight changes
+#[derive(Debug, Clone, Default)]
+pub struct WatchSnapshot {
+    /// Tip of the remote base branch
+    base_head: Option<String>,
+    /// Local tip of every stack branch
+    branch_heads: HashMap<String, String>,
+    /// MR iid and GitLab state per branch
+    mr_states: HashMap<String, (MrIid, String)>,
+}
+
+/// Options controlling a `push` run
+#[derive(Debug, Clone, Default)]
+pub struct PushOptions {
+    /// MR template for merge requests created during this push
+    pub template: Option<String>,
+    /// Only push branches, without talking to GitLab
+    pub no_mr: bool,
+    /// Milestone to assign to every MR, overriding `mr.default_milestone`
+    pub milestone: Option<String>,
+    /// Only push (and open MRs for) this part of the stack
+    pub range: Option<BranchRange>,
+    /// Restack branches that are behind the base before pushing, overriding `push.auto_sync`
+    pub sync: bool,
+}
+
+/// Which stacks `list` shows
+#[derive(Debug, Clone, Default)]
+pub struct ListOptions {
+    /// Only stacks created with the current git `user.email`
+    pub mine: bool,
+    /// Only stacks created by this git email
+    pub author: Option<String>,
+    /// Include stacks archived after all their levels were merged
+    pub archived: bool,
+}
+
+/// Part of a stack that `push` or `sync` is limited to
+#[derive(Debug, Clone, PartialEq)]
+pub enum BranchRange {
+    /// The branch and every stack branch below it (`--up-to`)
+    UpTo(String),
+    /// The chain from `from` up to `to`, both included (`--only from..to`, or a
+    /// single branch when they are equal)
+    Only { from: String, to: String },
+}
+
+impl BranchRange {
+    /// Parse an `--only` argument: `feature-2..feature-4` or a single branch
+    pub fn only(spec: &str) -> Self {
+        match spec.split_once("..") {
+            Some((from, to)) => BranchRange::Only {
+                from: from.to_string(),
+                to: to.to_string(),
+            },
+            None => BranchRange::Only {
+                from: spec.to_string(),
+                to: spec.to_string(),
+            },
+        }
+    }
+}
+
+/// Options controlling a `land` run
+#[derive(Debug, Clone, Default)]
+pub struct LandOptions {
+    /// Collapse the whole stack onto the base and merge it through the bottom MR
+    pub squash_all: bool,
+    /// With `squash_all`, keep one commit per stack level instead of a single commit
+    pub per_level: bool,
+    /// Squash the bottom MR with a message built from its title and description,
+    /// leaving out the stack table
+    pub squash: bool,
+    /// Delete the bottom branch on the remote once its children are retargeted, on
+    /// top of `mr.delete_source_branch`
+    pub delete_source_branch: bool,
+}
+
+/// Options controlling `mr merge`
+#[derive(Debug, Clone, Default)]
+pub struct MrMergeOptions {
+    /// Merge method, overriding `mr.merge_method`
+    pub method: Option<MergeMethod>,
+    /// Delete the source branch after merging, on top of `mr.delete_source_branch`
+    pub delete_source_branch: bool,
+    /// Squash commit message used instead of the MR title and description
+    pub squash_message: Option<String>,
+}
+
+/// Options controlling a `sync` run
+#[derive(Debug, Clone, Default)]
+pub struct SyncOptions {
+    /// Remove branches deleted on the remote from the stack instead of only warning
+    pub prune: bool,
+    /// Only restack this part of the stack
+    pub range: Option<BranchRange>,
+    /// Overrides `sync.when_behind`
+    pub when_behind: Option<usize>,
+}
+
+/// Options controlling a `delete` run
+#[derive(Debug, Clone, Default)]
+pub struct DeleteOptions {
+    /// Skip all confirmation prompts
+    pub force: bool,
+    /// Also delete the stack's local and remote branches
+    pub branches: bool,
+    /// Also close the stack's open MRs, leaving a comment
+    pub close_mrs: bool,
+    /// Only list what would be deleted or closed
+    pub dry_run: bool,
+}
+
+/// An MR whose target branch was changed on GitLab after git-train last set it
+#[derive(Debug, Clone)]
+pub struct UnexpectedRetarget {
+    pub branch: String,
+    pub mr_iid: MrIid,
+    /// Target git-train last set
+    pub expected: String,
+    /// Target currently on GitLab
+    pub actual: String,
+}
+
+/// A way a level's local state differs from its MR on GitLab, i.e. something
+/// `push` would change or would have to deal with
+#[derive(Debug, Clone, PartialEq)]
+pub enum MrDrift {
+    /// The MR targets another branch than the level's parent
+    Target { gitlab: String },
+    /// The MR title differs from the one git-train last set
+    Title,
+    /// The local branch has commits the MR doesn't; push sends them
+    Unpushed,
+    /// The MR has commits the local branch doesn't
+    Behind,
+    /// Local and MR histories diverged; push would force-push
+    Diverged,
+}
+
+impl MrDrift {
+    fn marker(&self) -> String {
+        match self {
+            MrDrift::Target { gitlab } => style(format!("⇄ {}", gitlab)).yellow(),
+            MrDrift::Title => style("✎ title".to_string()).yellow(),
+            MrDrift::Unpushed => style("↑ unpushed".to_string()).cyan(),
+            MrDrift::Behind => style("↓ behind MR".to_string()).red(),
+            MrDrift::Diverged => style("± diverged".to_string()).red(),
+        }
+        .to_string()
+    }
+}
+
+/// Operations on a branch's local review notes
+#[derive(Debug, Clone)]
+pub enum ReviewNoteAction {
+    Show,
+    Add { text: String, todo: bool },
+    Check(usize),
+    Uncheck(usize),
+    Remove(usize),
+    Clear,
+}
 
 pub struct StackManager {
     stack_state: StackState,
     current_stack: Option<Stack>,
-    gitlab_client: Option<Box<dyn GitLabApi + Send + Sync>>,
+    /// Created on first use, so local-only commands never wait on GitLab detection
+    gitlab_client: OnceCell<Option<Box<dyn GitLabApi + Send + Sync>>>,
     config: TrainConfig,
     conflict_resolver: ConflictResolver,
     git_repo: GitRepository,
+    /// Rebase conflicts hit during the current command, for sync history
+    conflicts_seen: AtomicUsize,
+    /// Set once GitLab rejects the token; the rest of the command runs git-only
+    gitlab_disabled: AtomicBool,
 }
 
 impl StackManager {
@@ -36,18 +230,22 @@ impl StackManager {
         gitlab_client: Option<Box<dyn GitLabApi + Send + Sync>>,
     ) -> Result<Self> {
         let git_repo = git_repo.unwrap_or_else(|| GitRepository::new_from_current_dir().unwrap());
-        Self::new_with_services(config, git_repo, gitlab_client).await
+        Self::new_with_services(config, git_repo, gitlab_client, true).await
+    }
+
+    /// Create a manager that never attempts to reach GitLab, e.g. for synthetic benchmark repos
+    pub async fn new_offline(config: TrainConfig, git_repo: GitRepository) -> Result<Self> {
+        Self::new_with_services(config, git_repo, None, false).await
     }
 
     async fn new_with_services(
         config: TrainConfig,
         git_repo: GitRepository,
         gitlab_client: Option<Box<dyn GitLabApi + Send + Sync>>,
+        detect_gitlab: bool,
     ) -> Result<Self> {
-        let git_dir = git_repo.run(&["rev-parse", "--git-dir"])?;
-        let git_dir_path = std::path::PathBuf::from(git_dir.trim());
-
-        let train_dir = git_dir_path.join("train");
+        let git_repo = git_repo.with_commit_identity(config.git.commit_identity.clone());
+        let train_dir = git_repo.train_dir();
 
         // Create train directory if it doesn't exist
         if !train_dir.exists() {
@@ -55,26 +253,20 @@ impl StackManager {
             info!("Created train directory: {:?}", train_dir);
         }
 
-        let gitlab_client = if gitlab_client.is_none() {
-            // Try to initialize GitLab client
-            match GitLabClient::new(git_repo.clone()).await {
-                Ok(client) => {
-                    print_info("GitLab integration initialized");
-                    Some(Box::new(client) as Box<dyn GitLabApi + Send + Sync>)
-                }
-                Err(e) => {
-                    print_warning(&format!("GitLab integration not available: {}", e));
-                    None
-                }
-            }
-        } else {
-            gitlab_client
+        // Without an injected client, detection waits for the first command that needs GitLab
+        let gitlab_client = match gitlab_client {
+            Some(client) => OnceCell::new_with(Some(Some(client))),
+            None if detect_gitlab => OnceCell::new(),
+            None => OnceCell::new_with(Some(None)),
         };
 
         // Initialize conflict resolver
-        let conflict_resolver =
-            ConflictResolver::new(config.clone(), git_dir_path.clone(), git_repo.clone());
-        let stack_state = StackState::new(train_dir)?;
+        let conflict_resolver = ConflictResolver::new(
+            config.clone(),
+            git_repo.git_dir().to_path_buf(),
+            git_repo.clone(),
+        );
+        let stack_state = StackState::new(train_dir, git_repo.current_stack_file().to_path_buf())?;
 
         Ok(Self {
             stack_state,
@@ -83,6 +275,8 @@ impl StackManager {
             config,
             conflict_resolver,
             git_repo,
+            conflicts_seen: AtomicUsize::new(0),
+            gitlab_disabled: AtomicBool::new(false),
         })
     }
 
@@ -90,6 +284,43 @@ impl StackManager {
         &self.conflict_resolver
     }
 
+    /// The GitLab client, unless GitLab rejected the token earlier in this command.
+    ///
+    /// The first call detects the project from the git remotes; later calls reuse the result.
+    async fn gitlab(&self) -> Option<&(dyn GitLabApi + Send + Sync)> {
+        if self.gitlab_disabled.load(Ordering::Relaxed) {
+            return None;
+        }
+        self.gitlab_client
+            .get_or_init(|| async {
+                match GitLabClient::new(self.git_repo.clone(), self.config.timeouts.http()).await {
+                    Ok(client) => {
+                        print_info("GitLab integration initialized");
+                        Some(Box::new(client) as Box<dyn GitLabApi + Send + Sync>)
+                    }
+                    Err(e) => {
+                        print_warning(&format!("GitLab integration not available: {}", e));
+                        None
+                    }
+                }
+            })
+            .await
+            .as_deref()
+    }
+
+    /// When `error` is GitLab rejecting the token, report it once and carry on without
+    /// GitLab for the rest of the command. Returns whether that was the case.
+    fn disable_gitlab_on_auth_error(&self, error: &anyhow::Error) -> bool {
+        if !is_gitlab_auth_error(error) {
+            return false;
+        }
+        if !self.gitlab_disabled.swap(true, Ordering::Relaxed) {
+            print_error(&format!("{:#}", error));
+            print_warning("Continuing without GitLab for the rest of this command");
+        }
+        true
+    }
+
     /// Create a unique backup name that doesn't conflict with existing branches
     fn create_unique_backup_name(&self, prefix: &str) -> Result<String> {
         let base_name = create_backup_name(prefix);
@@ -122,8 +353,90 @@ impl StackManager {
         }
     }
 
-    /// Smart rebase that handles conflicts automatically when possible
-    async fn smart_rebase(&self, branch: &str, onto: &str) -> Result<()> {
+    /// Decide which commit a child should be replayed from when restacking onto `parent`.
+    ///
+    /// Returns the stored parent commit when the parent was rewritten (amended, rebased)
+    /// since git-train last recorded it, so only the child's own commits are replayed.
+    fn resolve_restack_base(
+        &self,
+        branch: &str,
+        parent: &str,
+        stored_parent_hash: Option<&str>,
+    ) -> Option<String> {
+        let stored = stored_parent_hash?;
+        let parent_tip = self.git_repo.get_commit_hash_for_branch(parent).ok()?;
+        if parent_tip == stored {
+            return None;
+        }
+
+        // Parent simply advanced: a plain rebase replays the right commits
+        if self.git_repo.is_ancestor(stored, &parent_tip) {
+            return None;
+        }
+
+        if self.git_repo.is_ancestor(stored, branch) {
+            print_warning(&format!(
+                "Parent '{}' was rewritten outside git-train (recorded {}, now {}). Replaying only the commits of '{}' on top of it.",
+                parent,
+                &stored[..8.min(stored.len())],
+                &parent_tip[..8],
+                branch
+            ));
+            Some(stored.to_string())
+        } else {
+            print_warning(&format!(
+                "Recorded commit {} of parent '{}' is not in the history of '{}'. Falling back to a plain rebase.",
+                &stored[..8.min(stored.len())],
+                parent,
+                branch
+            ));
+            None
+        }
+    }
+
+    /// Whether rebasing `branch` from `base` should keep its merge commits. A plain
+    /// rebase silently flattens them, so levels with merges are rebased with
+    /// `--rebase-merges` unless `git.merge_commits` (or the user) asks to linearize.
+    fn keep_merge_commits(&self, branch: &str, base: &str) -> bool {
+        let merges = self
+            .git_repo
+            .merge_commits_in(base, branch)
+            .unwrap_or_default();
+        if merges.is_empty() {
+            return false;
+        }
+        let linearize = match self.config.git.merge_commits {
+            MergeCommitHandling::Preserve => false,
+            MergeCommitHandling::Linearize => true,
+            MergeCommitHandling::Prompt => {
+                print_warning(&format!(
+                    "'{}' contains {} merge commit(s)",
+                    branch,
+                    merges.len()
+                ));
+                confirm_action("Convert it to a linear history? (otherwise the merges are kept)")
+                    .unwrap_or(false)
+            }
+        };
+        if linearize {
+            print_info(&format!(
+                "Linearizing '{}': its merge commits are dropped",
+                branch
+            ));
+        } else {
+            print_info(&format!(
+                "Keeping the merge commits of '{}' (rebase --rebase-merges)",
+                branch
+            ));
+        }
+        !linearize
+    }
+
+    /// Smart rebase that handles conflicts automatically when possible.
+    ///
+    /// When `old_base` is given, runs `git rebase --onto <onto> <old_base>` so that only
+    /// commits after the previous parent tip are replayed.
+    async fn smart_rebase(&self, branch: &str, onto: &str, old_base: Option<&str>) -> Result<()> {
         // First check if we're already in a conflict state
         let git_state = self.conflict_resolver.get_git_state()?;
         if !matches!(git_state, GitState::Clean) {
@@ -170,7 +483,15 @@ impl StackManager {
         }
 
         // Attempt the rebase
-        let rebase_result = self.git_repo.run(&["rebase", onto]);
+        let mut rebase_args = vec!["rebase"];
+        if self.keep_merge_commits(branch, old_base.unwrap_or(onto)) {
+            rebase_args.push("--rebase-merges");
+        }
+        match old_base {
+            Some(old_base) => rebase_args.extend(["--onto", onto, old_base]),
+            None => rebase_args.push(onto),
+        }
+        let rebase_result = self.git_repo.run(&rebase_args);
 
         // Restore stashed changes if we created a stash
         if stash_created {
@@ -187,75 +508,197 @@ impl StackManager {
                 Ok(())
             }
             Err(_rebase_err) => {
-                // Check if we're in a conflict state
-                let git_state = self.conflict_resolver.get_git_state()?;
+                self.handle_update_conflicts(
+                    UpdateStrategy::Rebase,
+                    &format!("rebase of {} onto {}", branch, onto),
+                )
+                .await
+            }
+        }
+    }
 
-                if matches!(git_state, GitState::Rebasing | GitState::Conflicted) {
-                    // We have conflicts during rebase
-                    let conflict_info = self.conflict_resolver.detect_conflicts()?;
-                    if let Some(conflict_info) = conflict_info {
-                        print_info(&format!(
-                            "Conflicts detected in {} files during rebase",
-                            conflict_info.files.len()
-                        ));
+    /// Merge `parent` into `branch`, leaving the branch's existing commits untouched.
+    ///
+    /// Used by stacks with `update_strategy = merge`; the result always fast-forwards
+    /// the remote branch, so pushing never needs `--force`.
+    async fn smart_merge(&self, branch: &str, parent: &str) -> Result<()> {
+        let git_state = self.conflict_resolver.get_git_state()?;
+        if !matches!(git_state, GitState::Clean) {
+            return Err(TrainError::InvalidState {
+                message: format!("Cannot merge: git is in state {:?}. Please run 'git-train sync' to handle conflicts.", git_state),
+            }.into());
+        }
 
-                        // Try to resolve conflicts automatically if enabled
-                        match self.config.conflict_resolution.auto_resolve_strategy {
-                            crate::config::AutoResolveStrategy::Never => {
-                                print_warning(
-                                    "Auto-resolution disabled. Please resolve conflicts manually:",
-                                );
-                                print_info("Re-run 'git-train sync' to continue with manual conflict resolution");
-                                Err(TrainError::InvalidState {
-                                    message: format!("Manual conflict resolution required for rebase of {} onto {}", branch, onto),
-                                }.into())
-                            }
-                            _ => {
-                                // Try auto-resolve conflicts
-                                match self
-                                    .conflict_resolver
-                                    .auto_resolve_conflicts(&conflict_info)
-                                    .await
-                                {
-                                    Ok(true) => {
-                                        print_success("Conflicts resolved automatically");
-                                        self.git_repo.run(&["rebase", "--continue"])?;
-                                        print_success(&format!(
-                                            "Completed rebase of {} onto {}",
-                                            branch, onto
-                                        ));
-                                        Ok(())
-                                    }
-                                    Ok(false) | Err(_) => {
-                                        print_warning("Automatic conflict resolution failed. Falling back to interactive resolution.");
-                                        self.conflict_resolver
-                                            .resolve_conflicts_interactively(&conflict_info)
-                                            .await?;
-                                        Ok(())
-                                    }
-                                }
-                            }
-                        }
-                    } else {
-                        // No conflicts detected but rebase failed - abort and return error
-                        self.git_repo.run(&["rebase", "--abort"]).ok(); // Best effort abort
-                        Err(TrainError::GitError {
-                            message: format!("Rebase of {} onto {} failed", branch, onto),
+        let has_changes = self.has_uncommitted_changes()?;
+        let stash_created = if has_changes {
+            self.git_repo.run(&[
+                "stash",
+                "push",
+                "-m",
+                &format!("git-train: auto-stash before merge {}", branch),
+            ])?;
+            print_info("Stashed uncommitted changes");
+            true
+        } else {
+            false
+        };
+
+        self.git_repo.run(&["checkout", branch])?;
+        let merge_result = self.git_repo.run(&["merge", "--no-edit", parent]);
+
+        if stash_created {
+            if self.git_repo.run(&["stash", "pop"]).is_err() {
+                print_warning("Could not automatically restore stashed changes. Run 'git stash pop' manually if needed.");
+            } else {
+                print_info("Restored stashed changes");
+            }
+        }
+
+        match merge_result {
+            Ok(_) => {
+                print_success(&format!("Merged {} into {} successfully", parent, branch));
+                Ok(())
+            }
+            Err(_merge_err) => {
+                self.handle_update_conflicts(
+                    UpdateStrategy::Merge,
+                    &format!("merge of {} into {}", parent, branch),
+                )
+                .await
+            }
+        }
+    }
+
+    /// Bring `branch` up to date with `onto` using the stack's update strategy.
+    ///
+    /// Merge-based stacks can only merge when the parent moved forward; if the parent
+    /// was rewritten (amend, edit) the child has to be rebased to drop the old commits.
+    async fn restack_branch(
+        &self,
+        stack: &Stack,
+        branch: &str,
+        onto: &str,
+        stored_parent_hash: Option<&str>,
+    ) -> Result<()> {
+        ensure_not_frozen(stack, branch)?;
+        let onto = &self.git_repo.resolve_branch(onto);
+        if stack.update_strategy == UpdateStrategy::Merge {
+            let rewritten =
+                stored_parent_hash.is_some_and(|stored| !self.git_repo.is_ancestor(stored, onto));
+            if !rewritten {
+                return self.smart_merge(branch, onto).await;
+            }
+            print_warning(&format!(
+                "Parent '{}' was rewritten, so '{}' is rebased instead of merged and will need a force-push",
+                onto, branch
+            ));
+        }
+
+        let old_base = self.resolve_restack_base(branch, onto, stored_parent_hash);
+        self.smart_rebase(branch, onto, old_base.as_deref()).await
+    }
+
+    /// Handle a failed rebase or merge: resolve conflicts automatically or interactively,
+    /// or abort and report when git stopped for another reason.
+    ///
+    /// `description` reads like "rebase of feature onto main" and is used in messages.
+    async fn handle_update_conflicts(
+        &self,
+        strategy: UpdateStrategy,
+        description: &str,
+    ) -> Result<()> {
+        let (operation, continue_args, abort_args): (_, &[&str], &[&str]) = match strategy {
+            UpdateStrategy::Rebase => ("rebase", &["rebase", "--continue"], &["rebase", "--abort"]),
+            UpdateStrategy::Merge => ("merge", &["commit", "--no-edit"], &["merge", "--abort"]),
+        };
+        let failed = || -> anyhow::Error {
+            let mut message = description.to_string();
+            if let Some(first) = message.get_mut(0..1) {
+                first.make_ascii_uppercase();
+            }
+            TrainError::GitError {
+                message: format!("{} failed", message),
+            }
+            .into()
+        };
+
+        // Check if we're in a conflict state
+        let git_state = self.conflict_resolver.get_git_state()?;
+
+        if matches!(
+            (strategy, &git_state),
+            (UpdateStrategy::Rebase, GitState::Rebasing)
+                | (UpdateStrategy::Merge, GitState::Merging)
+                | (_, GitState::Conflicted)
+        ) {
+            // We have conflicts during the operation
+            let conflict_info = self.conflict_resolver.detect_conflicts()?;
+            if let Some(conflict_info) = conflict_info {
+                self.conflicts_seen.fetch_add(1, Ordering::Relaxed);
+                print_info(&format!(
+                    "Conflicts detected in {} files during {}",
+                    conflict_info.files.len(),
+                    operation
+                ));
+                emit_conflict_event(description, &conflict_info);
+
+                // Try to resolve conflicts automatically if enabled
+                match self.config.conflict_resolution.auto_resolve_strategy {
+                    crate::config::AutoResolveStrategy::Never => {
+                        print_warning(
+                            "Auto-resolution disabled. Please resolve conflicts manually:",
+                        );
+                        print_info(
+                            "Re-run 'git-train sync' to continue with manual conflict resolution",
+                        );
+                        Err(TrainError::InvalidState {
+                            message: format!(
+                                "Manual conflict resolution required for {}",
+                                description
+                            ),
                         }
                         .into())
                     }
-                } else {
-                    // Rebase failed for other reasons
-                    Err(TrainError::GitError {
-                        message: format!("Rebase of {} onto {} failed", branch, onto),
+                    _ => {
+                        // Try auto-resolve conflicts
+                        match self
+                            .conflict_resolver
+                            .auto_resolve_conflicts(&conflict_info)
+                            .await
+                        {
+                            Ok(true) => {
+                                print_success("Conflicts resolved automatically");
+                                self.git_repo.run(continue_args)?;
+                                print_success(&format!("Completed {}", description));
+                                Ok(())
+                            }
+                            Ok(false) | Err(_) => {
+                                print_warning("Automatic conflict resolution failed. Falling back to interactive resolution.");
+                                self.conflict_resolver
+                                    .resolve_conflicts_interactively(&conflict_info)
+                                    .await?;
+                                Ok(())
+                            }
+                        }
                     }
-                    .into())
                 }
+            } else {
+                // No conflicts detected but the operation failed - abort and return error
+                self.git_repo.run(abort_args).ok(); // Best effort abort
+                Err(failed())
             }
+        } else {
+            // Failed for other reasons
+            Err(failed())
         }
     }
 
     pub async fn create_stack(&mut self, name: &str) -> Result<()> {
+        self.create_stack_with_base(name, None).await
+    }
+
+    /// Create a stack on `base` (`main` or `origin/main`), detecting the base when `None`
+    pub async fn create_stack_with_base(&mut self, name: &str, base: Option<&str>) -> Result<()> {
         print_train_header(&format!("Creating Stack: {}", name));
 
         // Ensure we're on a clean working directory
@@ -263,13 +706,16 @@ impl StackManager {
 
         let current_branch = self.git_repo.get_current_branch()?;
         let current_commit = self.git_repo.get_current_commit_hash()?;
-        let base_branch = self.determine_base_branch(&current_branch)?;
+        let base_branch = match base {
+            Some(base) => self.normalize_base_branch(base)?,
+            None => self.determine_base_branch(&current_branch)?,
+        };
 
-        let sanitized_name = sanitize_branch_name(name);
-        let stack_id = Uuid::new_v4().to_string();
+        let sanitized_name = BranchName::sanitized(name, &self.config.naming).to_string();
+        let stack_id = StackId::generate();
 
         // Get GitLab project information if available
-        let gitlab_project = if let Some(gitlab_client) = &mut self.gitlab_client {
+        let gitlab_project = if let Some(gitlab_client) = self.gitlab().await {
             print_info("Detecting GitLab project...");
             match gitlab_client.detect_and_cache_project().await {
                 Ok(project) => {
@@ -281,7 +727,9 @@ impl StackManager {
                     Some(project.clone())
                 }
                 Err(e) => {
-                    print_warning(&format!("GitLab project could not be auto-detected: {}", e));
+                    if !self.disable_gitlab_on_auth_error(&e) {
+                        print_warning(&format!("GitLab project could not be auto-detected: {}", e));
+                    }
                     None
                 }
             }
@@ -293,27 +741,37 @@ impl StackManager {
         let mut stack = Stack {
             id: stack_id.clone(),
             name: sanitized_name.clone(),
-            base_branch: base_branch.clone(),
+            base_branch: BranchName::from(&base_branch),
             branches: HashMap::new(),
-            current_branch: Some(current_branch.clone()),
+            current_branch: Some(BranchName::from(&current_branch)),
             gitlab_project,
+            mr_template: None,
+            update_strategy: self.config.git.update_strategy,
+            owner: self.git_repo.user_email(),
+            archived: false,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         };
 
         // Add the current branch to the stack
         let branch = StackBranch {
-            name: current_branch.clone(),
-            parent: Some(base_branch.clone()),
+            name: BranchName::from(&current_branch),
+            parent: Some(BranchName::from(&base_branch)),
             children: vec![],
             commit_hash: current_commit,
             mr_iid: None,
             mr_title: None,
+            mr_template: None,
+            review_notes: Vec::new(),
+            target_history: Vec::new(),
+            description_hash: None,
+            remote_sha: None,
+            state: BranchState::Unpushed,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         };
 
-        stack.branches.insert(current_branch.clone(), branch);
+        stack.branches.insert(branch.name.clone(), branch);
 
         // Save the stack
         self.stack_state.save_stack(&stack)?;
@@ -331,7 +789,140 @@ impl StackManager {
         Ok(())
     }
 
-    pub async fn commit_changes(&mut self, message: &str) -> Result<()> {
+    /// Duplicate the current stack under a new name with its own branches.
+    ///
+    /// Every branch gets a copy (`<prefix><branch>` or `<branch><suffix>`, defaulting to
+    /// `<branch>-<name>`) pointing at the same commit, so the fork can be reworked and
+    /// pushed as new MRs while the original stack and its MRs stay untouched.
+    pub async fn fork_stack(
+        &mut self,
+        name: &str,
+        prefix: Option<&str>,
+        suffix: Option<&str>,
+    ) -> Result<()> {
+        let original = self.get_or_load_current_stack()?;
+        let sanitized_name = BranchName::sanitized(name, &self.config.naming).to_string();
+        print_train_header(&format!(
+            "Forking Stack: {} -> {}",
+            original.name, sanitized_name
+        ));
+
+        if self.stack_state.find_by_identifier(&sanitized_name).is_ok() {
+            return Err(TrainError::StackError {
+                message: format!("A stack named '{}' already exists", sanitized_name),
+            }
+            .into());
+        }
+
+        let default_suffix = format!("-{}", sanitized_name);
+        let rename = |branch: &str| match (prefix, suffix) {
+            (Some(prefix), _) => format!("{}{}", prefix, branch),
+            (None, Some(suffix)) => format!("{}{}", branch, suffix),
+            (None, None) => format!("{}{}", branch, default_suffix),
+        };
+        let rename_ref = |branch: &str| {
+            if original.branches.contains_key(branch) {
+                BranchName::from(rename(branch))
+            } else {
+                BranchName::from(branch)
+            }
+        };
+
+        // Refuse before creating anything so a clash never leaves a half-made fork behind
+        for branch_name in original.branches.keys() {
+            let new_name = BranchName::new(rename(branch_name))?;
+            if self.git_repo.get_commit_hash_for_branch(&new_name).is_ok() {
+                return Err(TrainError::StackError {
+                    message: format!(
+                        "Branch '{}' already exists; pick another name, --prefix or --suffix",
+                        new_name
+                    ),
+                }
+                .into());
+            }
+        }
+
+        let mut fork = Stack {
+            id: StackId::generate(),
+            name: sanitized_name.clone(),
+            base_branch: original.base_branch.clone(),
+            branches: HashMap::new(),
+            current_branch: original.current_branch.as_deref().map(rename_ref),
+            gitlab_project: original.gitlab_project.clone(),
+            mr_template: original.mr_template.clone(),
+            update_strategy: original.update_strategy,
+            owner: self.git_repo.user_email(),
+            archived: false,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        for (branch_name, branch) in &original.branches {
+            let new_name = BranchName::from(rename(branch_name));
+            let commit_hash = self.git_repo.get_commit_hash_for_branch(branch_name)?;
+            self.git_repo.run(&["branch", &new_name, &commit_hash])?;
+
+            fork.branches.insert(
+                new_name.clone(),
+                StackBranch {
+                    name: new_name,
+                    parent: branch.parent.as_deref().map(rename_ref),
+                    children: branch.children.iter().map(|c| rename_ref(c)).collect(),
+                    commit_hash,
+                    // The fork gets its own MRs on its first push
+                    mr_iid: None,
+                    mr_title: None,
+                    mr_template: branch.mr_template.clone(),
+                    review_notes: branch.review_notes.clone(),
+                    target_history: Vec::new(),
+                    description_hash: None,
+                    remote_sha: None,
+                    state: if branch.state == BranchState::LocalOnly {
+                        BranchState::LocalOnly
+                    } else {
+                        BranchState::Unpushed
+                    },
+                    created_at: Utc::now(),
+                    updated_at: Utc::now(),
+                },
+            );
+        }
+
+        // Follow HEAD into the fork when it was on one of the original branches
+        let head = self.get_current_branch()?;
+        if original.branches.contains_key(&head) {
+            self.git_repo.run(&["checkout", &rename(&head)])?;
+        }
+
+        self.stack_state.save_stack(&fork)?;
+        print_success(&format!(
+            "Forked stack '{}' into '{}' with {} branches",
+            original.name,
+            fork.name,
+            fork.branches.len()
+        ));
+        let mut renamed: Vec<(&BranchName, String)> = original
+            .branches
+            .keys()
+            .map(|branch| (branch, rename(branch)))
+            .collect();
+        renamed.sort();
+        for (old, new) in renamed {
+            print_info(&format!("  {} -> {}", old, new));
+        }
+        print_info(&format!(
+            "'{}' is now active; switch back with 'git-train switch {}'",
+            fork.name, original.name
+        ));
+        self.current_stack = Some(fork);
+
+        Ok(())
+    }
+
+    /// Commit all changes on the current stack branch and propagate them downstream.
+    /// Without `message` the editor opens on [`Self::commit_message_template`], starting
+    /// from the branch's latest commit message when `reword` is set.
+    pub async fn commit_changes(&mut self, message: Option<&str>, reword: bool) -> Result<()> {
         print_train_header("Saving Changes");
 
         let stack = self.get_or_load_current_stack()?;
@@ -361,7 +952,29 @@ impl StackManager {
 
         // Commit the changes
         self.git_repo.run(&["add", "."])?;
-        self.git_repo.run(&["commit", "-m", message])?;
+        match message {
+            Some(message) => {
+                self.commit_with_trailers(&stack, &current_branch, &["-m", message])?
+            }
+            None => {
+                let previous_message = if reword {
+                    self.git_repo
+                        .run(&["log", "-1", "--format=%B", &current_branch])?
+                } else {
+                    String::new()
+                };
+                let template_path = tempfile::NamedTempFile::new()?.into_temp_path();
+                std::fs::write(
+                    &template_path,
+                    self.commit_message_template(&stack, &current_branch, &previous_message),
+                )?;
+                let template = template_path.to_string_lossy();
+                let command =
+                    self.commit_command(&stack, &current_branch, &["-e", "-F", &template]);
+                let command: Vec<&str> = command.iter().map(String::as_str).collect();
+                self.git_repo.run_interactive(&command)?;
+            }
+        }
 
         let new_commit_hash = self.get_current_commit_hash()?;
         print_success(&format!("Committed changes: {}", &new_commit_hash[..8]));
@@ -375,8 +988,16 @@ impl StackManager {
         updated_stack.updated_at = Utc::now();
 
         // Propagate changes to dependent branches
-        self.propagate_changes(&mut updated_stack, &current_branch)
-            .await?;
+        let previous_commit = stack
+            .branches
+            .get(&current_branch)
+            .map(|b| b.commit_hash.clone());
+        self.propagate_changes(
+            &mut updated_stack,
+            &current_branch,
+            previous_commit.as_deref(),
+        )
+        .await?;
 
         // Save the updated stack
         self.stack_state.save_stack(&updated_stack)?;
@@ -403,6 +1024,7 @@ impl StackManager {
             }
             .into());
         }
+        ensure_not_frozen(&stack, &current_branch)?;
 
         // Check if there are any files to amend
         let staged_output = self.git_repo.run(&["diff", "--cached", "--name-only"])?;
@@ -468,21 +1090,161 @@ impl StackManager {
             .await
     }
 
-    /// Detect which files in the current changes originally came from earlier branches in the stack
-    fn detect_files_from_earlier_branches(
-        &self,
-        stack: &Stack,
-        current_branch: &str,
-        modified_files: &[String],
-    ) -> Result<HashMap<String, Vec<String>>> {
-        let mut files_to_propagate: HashMap<String, Vec<String>> = HashMap::new();
+    /// Amend the tip commit of another stack branch without leaving the current one.
+    ///
+    /// Staged changes are moved onto `branch` (without staged changes or a message, the
+    /// editor opens on its commit message), its descendants are restacked, and unstaged
+    /// changes are put back on the original branch afterwards.
+    pub async fn amend_branch(&mut self, branch: &str, new_message: Option<&str>) -> Result<()> {
+        let original_branch = self.get_current_branch()?;
+        if branch == original_branch {
+            return self.amend_changes(new_message).await;
+        }
 
-        // Get all ancestor branches (earlier branches in the stack)
-        let ancestors = self.get_ancestor_branches(stack, current_branch);
+        print_train_header(&format!("Amending Branch: {}", branch));
 
-        for ancestor in ancestors {
-            // Get files that were introduced or modified in this ancestor branch
-            let ancestor_files = self.get_files_from_branch(stack, &ancestor)?;
+        let stack = self.get_or_load_current_stack()?;
+        if !stack.branches.contains_key(branch) {
+            return Err(TrainError::StackError {
+                message: format!("Branch '{}' is not part of the current stack", branch),
+            }
+            .into());
+        }
+        ensure_not_frozen(&stack, branch)?;
+
+        // Staged changes travel to `branch`; unstaged ones come back afterwards
+        let patch_dir = tempfile::tempdir()?;
+        let staged = self.git_repo.run(&["diff", "--cached", "--binary"])?;
+        let unstaged = self.git_repo.run(&["diff", "--binary"])?;
+        let staged_patch = patch_dir.path().join("staged.patch");
+        let unstaged_patch = patch_dir.path().join("unstaged.patch");
+        fs::write(&staged_patch, format!("{}\n", staged))?;
+        fs::write(&unstaged_patch, format!("{}\n", unstaged))?;
+
+        let stashed = !staged.is_empty() || !unstaged.is_empty();
+        if stashed {
+            self.git_repo.run(&[
+                "stash",
+                "push",
+                "-m",
+                &format!("git-train: amend --branch {}", branch),
+            ])?;
+        }
+
+        let result = self
+            .amend_stack_branch(
+                &stack,
+                branch,
+                new_message,
+                (!staged.is_empty()).then_some(staged_patch.as_path()),
+            )
+            .await;
+
+        if !matches!(self.conflict_resolver.get_git_state()?, GitState::Clean) {
+            if stashed {
+                print_info("Your uncommitted changes are stashed; restore them with 'git stash pop --index' once git is clean");
+            }
+            return result;
+        }
+        self.git_repo.run(&["checkout", &original_branch])?;
+        if stashed {
+            if result.is_err() {
+                self.git_repo.run(&["stash", "pop", "--index"])?;
+            } else if unstaged.is_empty()
+                || self
+                    .git_repo
+                    .run(&["apply", &unstaged_patch.to_string_lossy()])
+                    .is_ok()
+            {
+                // The staged part of the stash now lives in the stack
+                self.git_repo.run(&["stash", "drop"])?;
+            } else {
+                print_warning(
+                    "Unstaged changes no longer apply cleanly; they are kept in 'git stash list'",
+                );
+            }
+        }
+
+        result?;
+        print_success(&format!(
+            "Amended '{}' and restacked its descendants",
+            branch
+        ));
+        Ok(())
+    }
+
+    /// Check out `branch`, amend its tip with `staged_patch` and/or `new_message`, and
+    /// restack the branches above it
+    async fn amend_stack_branch(
+        &mut self,
+        stack: &Stack,
+        branch: &str,
+        new_message: Option<&str>,
+        staged_patch: Option<&std::path::Path>,
+    ) -> Result<()> {
+        self.git_repo.run(&["checkout", branch])?;
+        let previous_tip = self.get_current_commit_hash()?;
+        print_info(&format!(
+            "Original state can be recovered via git reflog (commit: {})",
+            &previous_tip[..8]
+        ));
+
+        if let Some(patch) = staged_patch {
+            self.git_repo
+                .run(&["apply", "--index", &patch.to_string_lossy()])
+                .map_err(|e| TrainError::GitError {
+                    message: format!("Staged changes don't apply to '{}': {}", branch, e),
+                })?;
+        }
+        match (new_message, staged_patch) {
+            (Some(message), _) => {
+                self.commit_with_trailers(stack, branch, &["--amend", "-m", message])?
+            }
+            (None, Some(_)) => {
+                self.commit_with_trailers(stack, branch, &["--amend", "--no-edit"])?
+            }
+            (None, None) => self.git_repo.run_interactive(&["commit", "--amend"])?,
+        }
+
+        let new_tip = self.get_current_commit_hash()?;
+        print_success(&format!("New commit hash: {}", &new_tip[..8]));
+
+        // Children are replayed from the tip the branch had before the amend
+        let mut original = stack.clone();
+        if let Some(b) = original.branches.get_mut(branch) {
+            b.commit_hash = previous_tip;
+        }
+        let mut updated_stack = stack.clone();
+        if let Some(b) = updated_stack.branches.get_mut(branch) {
+            b.commit_hash = new_tip;
+            b.updated_at = Utc::now();
+        }
+        updated_stack.updated_at = Utc::now();
+
+        print_info("Restacking descendant branches...");
+        self.rebase_downstream_branches_from(&mut updated_stack, &original, branch)
+            .await?;
+
+        self.stack_state.save_stack(&updated_stack)?;
+        self.current_stack = Some(updated_stack);
+        Ok(())
+    }
+
+    /// Detect which files in the current changes originally came from earlier branches in the stack
+    fn detect_files_from_earlier_branches(
+        &self,
+        stack: &Stack,
+        current_branch: &str,
+        modified_files: &[String],
+    ) -> Result<HashMap<String, Vec<String>>> {
+        let mut files_to_propagate: HashMap<String, Vec<String>> = HashMap::new();
+
+        // Get all ancestor branches (earlier branches in the stack)
+        let ancestors = self.get_ancestor_branches(stack, current_branch);
+
+        for ancestor in ancestors {
+            // Get files that were introduced or modified in this ancestor branch
+            let ancestor_files = self.get_files_from_branch(stack, &ancestor)?;
 
             // Check which modified files belong to this ancestor
             let matching_files: Vec<String> = modified_files
@@ -508,7 +1270,7 @@ impl StackManager {
         while let Some(stack_branch) = stack.branches.get(current) {
             if let Some(parent) = &stack_branch.parent {
                 if parent != &stack.base_branch {
-                    ancestors.push(parent.clone());
+                    ancestors.push(parent.to_string());
                 }
                 current = parent;
             } else {
@@ -519,6 +1281,58 @@ impl StackManager {
         ancestors
     }
 
+    /// Branches selected by `range`, resolved through the stack hierarchy; `None`
+    /// selects the whole stack
+    fn resolve_branch_range(
+        &self,
+        stack: &Stack,
+        range: Option<&BranchRange>,
+    ) -> Result<Option<HashSet<String>>> {
+        let Some(range) = range else {
+            return Ok(None);
+        };
+        let require = |branch: &str| -> Result<()> {
+            if stack.branches.contains_key(branch) {
+                Ok(())
+            } else {
+                Err(TrainError::StackError {
+                    message: format!("Branch '{}' is not part of the current stack", branch),
+                }
+                .into())
+            }
+        };
+
+        let selected: HashSet<String> = match range {
+            BranchRange::UpTo(top) => {
+                require(top)?;
+                let mut selected: HashSet<String> =
+                    self.get_ancestor_branches(stack, top).into_iter().collect();
+                selected.insert(top.clone());
+                selected
+            }
+            BranchRange::Only { from, to } => {
+                require(from)?;
+                require(to)?;
+                let mut selected = HashSet::from([to.clone()]);
+                if from != to {
+                    let ancestors = self.get_ancestor_branches(stack, to);
+                    let Some(end) = ancestors.iter().position(|b| b == from) else {
+                        return Err(TrainError::StackError {
+                            message: format!(
+                                "'{}' is not below '{}' in the stack; use --only <lower>..<upper>",
+                                from, to
+                            ),
+                        }
+                        .into());
+                    };
+                    selected.extend(ancestors[..=end].iter().cloned());
+                }
+                selected
+            }
+        };
+        Ok(Some(selected))
+    }
+
     /// Get files that were introduced or modified in a specific branch
     fn get_files_from_branch(&self, stack: &Stack, branch_name: &str) -> Result<Vec<String>> {
         let branch = stack
@@ -528,7 +1342,9 @@ impl StackManager {
                 message: format!("Branch '{}' not found in stack", branch_name),
             })?;
 
-        let parent = branch.parent.as_ref().unwrap_or(&stack.base_branch);
+        let parent = self
+            .git_repo
+            .resolve_branch(branch.parent.as_ref().unwrap_or(&stack.base_branch));
 
         // Get files that were introduced or modified in this branch
         let files_output = self.git_repo.run(&[
@@ -546,7 +1362,12 @@ impl StackManager {
             .collect())
     }
 
-    /// Handle propagation of changes back to earlier branches
+    /// Handle propagation of changes back to earlier branches.
+    ///
+    /// Every staged hunk goes to the branch that introduced the lines it touches: the
+    /// hunks are split into one commit per owning branch and cherry-picked onto it, so
+    /// the rest of the file on that branch is left alone. Hunks on lines introduced by
+    /// the current branch (or by the base) stay on the current branch.
     async fn handle_earlier_branch_propagation(
         &mut self,
         stack: &Stack,
@@ -554,20 +1375,17 @@ impl StackManager {
         files_to_propagate: HashMap<String, Vec<String>>,
         new_message: Option<&str>,
     ) -> Result<()> {
-        print_info("Detected files from earlier branches in the stack. Propagating changes...");
-
-        // Stash any uncommitted changes in working directory
-        let has_uncommitted = self.has_uncommitted_changes()?;
-        if has_uncommitted {
-            self.git_repo.run(&[
-                "stash",
-                "push",
-                "-m",
-                "git-train: auto-stash before propagation",
-            ])?;
-            print_info("Stashed uncommitted changes");
+        let mut patches = self.plan_hunk_propagation(stack, current_branch, &files_to_propagate)?;
+        let remaining_patch = patches.remove(current_branch);
+        if patches.is_empty() {
+            print_info("All staged hunks belong to the current branch");
+            return self
+                .perform_standard_amend(stack, current_branch, new_message)
+                .await;
         }
 
+        print_info("Detected changes to lines from earlier branches in the stack. Propagating...");
+
         // Log original state for recovery via reflog
         print_info(&format!(
             "Original state can be recovered via git reflog (branch: {}, commit: {})",
@@ -582,87 +1400,81 @@ impl StackManager {
             self.git_repo.run(&["log", "-1", "--pretty=%s"])?
         };
 
-        let mut updated_stack = stack.clone();
-
-        // Collect file contents before switching branches
-        let mut all_file_contents: HashMap<String, HashMap<String, String>> = HashMap::new();
-
-        for (target_branch, files) in &files_to_propagate {
-            let mut file_contents = HashMap::new();
-            for file in files {
-                // Read the file content from the current branch
-                match std::fs::read_to_string(format!(
-                    "{}/{}",
-                    self.git_repo.run(&["rev-parse", "--show-toplevel"])?.trim(),
-                    file
-                )) {
-                    Ok(content) => {
-                        file_contents.insert(file.clone(), content);
-                    }
-                    Err(e) => {
-                        print_warning(&format!("Could not read file '{}': {}", file, e));
-                    }
-                }
+        // Split the staged changes into one commit per branch, each on top of HEAD
+        let head = self.get_current_commit_hash()?;
+        let staged_tree = self.git_repo.run(&["write-tree"])?;
+        let patch_dir = tempfile::tempdir()?;
+        let mut split_commits: Vec<(String, String)> = Vec::new();
+        let mut remaining_commit = None;
+        let split_result = (|| -> Result<()> {
+            for (branch_name, patch) in &patches {
+                let commit = self.split_commit(
+                    &head,
+                    patch,
+                    &patch_dir.path().join(branch_name.replace('/', "_")),
+                )?;
+                split_commits.push((branch_name.clone(), commit));
             }
-            all_file_contents.insert(target_branch.clone(), file_contents);
-        }
+            if let Some(patch) = &remaining_patch {
+                remaining_commit =
+                    Some(self.split_commit(&head, patch, &patch_dir.path().join("remaining"))?);
+            }
+            Ok(())
+        })();
+        // Give the index back to the user before anything else can fail
+        self.git_repo.run(&["read-tree", &staged_tree])?;
+        split_result?;
+
+        // Put the working tree aside; the stash doubles as a recovery point
+        let had_unstaged = !self.git_repo.run(&["diff", "--name-only"])?.is_empty();
+        self.git_repo.run(&[
+            "stash",
+            "push",
+            "-m",
+            "git-train: auto-stash before propagation",
+        ])?;
 
-        // Sort branches by their depth (closer to base branch first)
-        let mut branches_to_update: Vec<(&String, &Vec<String>)> =
-            files_to_propagate.iter().collect();
-        branches_to_update.sort_by(|a, b| {
-            let depth_a = self.get_branch_depth_in_stack(stack, a.0);
-            let depth_b = self.get_branch_depth_in_stack(stack, b.0);
-            depth_a.cmp(&depth_b)
-        });
+        let mut updated_stack = stack.clone();
 
-        // Apply changes to each earlier branch
-        for (target_branch, files) in branches_to_update {
+        // Apply to earlier branches, closest to the base first
+        split_commits
+            .sort_by_key(|(branch_name, _)| self.get_branch_depth_in_stack(stack, branch_name));
+        for (target_branch, commit) in &split_commits {
             print_info(&format!(
                 "Applying changes to earlier branch: {} (reflog available for recovery)",
                 target_branch
             ));
 
-            // Switch to the target branch
             self.git_repo.run(&["checkout", target_branch])?;
-
-            // Apply the file changes using saved content
-            if let Some(file_contents) = all_file_contents.get(target_branch) {
-                for file in files {
-                    if let Some(content) = file_contents.get(file) {
-                        // Write the content to the file in the target branch
-                        let target_file_path = format!(
-                            "{}/{}",
-                            self.git_repo.run(&["rev-parse", "--show-toplevel"])?.trim(),
-                            file
-                        );
-                        std::fs::write(&target_file_path, content)?;
-
-                        // Stage the file
-                        self.git_repo.run(&["add", file])?;
-                    }
+            if let Err(e) = self.git_repo.run(&["cherry-pick", "--no-commit", commit]) {
+                let _ = self.git_repo.run(&["reset", "-q", "--merge"]);
+                let _ = self.git_repo.run(&["checkout", current_branch]);
+                let _ = self.git_repo.run(&["stash", "pop", "--index"]);
+                return Err(TrainError::StackError {
+                    message: format!(
+                        "Could not apply the changes meant for '{}' on top of it: {}. Your staged changes were restored on '{}'",
+                        target_branch, e, current_branch
+                    ),
                 }
+                .into());
             }
 
-            // Commit the changes
-            let propagated_message = format!(
-                "propagate: {} (from {})",
-                commit_message.trim(),
-                current_branch
-            );
-
             // Check if there are any staged changes before committing
             let staged_changes = self.git_repo.run(&["diff", "--cached", "--name-only"])?;
-
             if staged_changes.trim().is_empty() {
                 print_warning(&format!(
                     "No staged changes to commit for branch '{}'",
                     target_branch
                 ));
-                continue; // Skip this branch if no changes to commit
+                continue; // The branch already had these changes
             }
 
-            self.git_repo.run(&["commit", "-m", &propagated_message])?;
+            let propagated_message = format!(
+                "propagate: {} (from {})",
+                commit_message.trim(),
+                current_branch
+            );
+            self.commit_with_trailers(&updated_stack, target_branch, &["-m", &propagated_message])?;
 
             // Update the stack state
             if let Some(branch) = updated_stack.branches.get_mut(target_branch) {
@@ -676,42 +1488,23 @@ impl StackManager {
             ));
         }
 
-        // Return to current branch and remove the propagated changes
+        // Return to current branch and commit the hunks that stay there
         self.git_repo.run(&["checkout", current_branch])?;
 
-        // Remove the propagated files from the current branch's staged changes
-        let all_propagated_files: Vec<String> = files_to_propagate
-            .values()
-            .flat_map(|files| files.iter().cloned())
-            .collect();
-
-        for file in &all_propagated_files {
-            // Reset the file to its state before our changes
-            if let Ok(content) = self.git_repo.run(&["show", &format!("HEAD:{}", file)]) {
-                let file_path = self.git_repo.run(&["rev-parse", "--show-toplevel"])? + "/" + file;
-                std::fs::write(file_path.trim(), content)?;
-            }
-        }
-
-        // Re-stage all files and check if there are any remaining changes
-        self.git_repo.run(&["add", "."])?;
-        let remaining_staged = self.git_repo.run(&["diff", "--cached", "--name-only"])?;
-
-        if !remaining_staged.trim().is_empty() {
-            // There are still changes to commit on the current branch
+        if let Some(commit) = remaining_commit {
+            self.git_repo
+                .run(&["cherry-pick", "--no-commit", &commit])?;
             let remaining_message =
                 format!("Remove propagated changes (was: {})", commit_message.trim());
-            self.git_repo.run(&["commit", "-m", &remaining_message])?;
+            self.commit_with_trailers(stack, current_branch, &["-m", &remaining_message])?;
             print_info(&format!(
-                "Cleaned up propagated changes from '{}'",
+                "Kept the remaining changes on '{}'",
                 current_branch
             ));
-        } else {
-            // No remaining changes, just update the commit message if needed
-            if let Some(msg) = new_message {
-                self.git_repo.run(&["commit", "--amend", "-m", msg])?;
-                print_info(&format!("Updated commit message to: {}", msg));
-            }
+        } else if let Some(msg) = new_message {
+            // No remaining changes, just update the commit message
+            self.commit_with_trailers(stack, current_branch, &["--amend", "-m", msg])?;
+            print_info(&format!("Updated commit message to: {}", msg));
         }
 
         // Update current branch in stack
@@ -721,25 +1514,21 @@ impl StackManager {
         }
 
         // Rebase all branches that are downstream from the earliest modified branch
-        let earliest_branch = files_to_propagate.keys().min_by(|a, b| {
-            let depth_a = self.get_branch_depth_in_stack(stack, a);
-            let depth_b = self.get_branch_depth_in_stack(stack, b);
-            depth_a.cmp(&depth_b)
-        });
-
-        if let Some(earliest) = earliest_branch {
+        if let Some((earliest, _)) = split_commits.first() {
             print_info("Rebasing downstream branches...");
-            self.rebase_downstream_branches_from(&mut updated_stack, earliest)
+            self.rebase_downstream_branches_from(&mut updated_stack, stack, earliest)
                 .await?;
         }
 
-        // Restore stashed changes if we created a stash
-        if has_uncommitted {
+        // Everything staged is committed now; only bring back changes that weren't staged
+        if had_unstaged {
             if self.git_repo.run(&["stash", "pop"]).is_err() {
-                print_warning("Could not automatically restore stashed changes. Run 'git stash pop' manually if needed.");
+                print_warning("Could not automatically restore unstaged changes. Run 'git stash pop' manually if needed.");
             } else {
-                print_info("Restored stashed changes");
+                print_info("Restored unstaged changes");
             }
+        } else {
+            self.git_repo.run(&["stash", "drop"])?;
         }
 
         // Save the updated stack
@@ -753,6 +1542,146 @@ impl StackManager {
         Ok(())
     }
 
+    /// Group the staged hunks of every changed file into one patch per owning branch.
+    ///
+    /// A hunk belongs to the latest branch that introduced any of the lines it replaces
+    /// (found with `git blame` on HEAD); insertions are attributed to the line above
+    /// them. Hunks blamed on the base or on the current branch, and new files, map to
+    /// `current_branch`. Hunk-less diffs (binary files, mode changes) go to the closest
+    /// earlier branch that touched the file.
+    fn plan_hunk_propagation(
+        &self,
+        stack: &Stack,
+        current_branch: &str,
+        files_to_propagate: &HashMap<String, Vec<String>>,
+    ) -> Result<HashMap<String, Vec<u8>>> {
+        // Branches from the bottom of the stack up to the current one
+        let mut chain = self.get_ancestor_branches(stack, current_branch);
+        chain.reverse();
+        chain.push(current_branch.to_string());
+
+        let staged = self
+            .git_repo
+            .run(&["diff", "--cached", "--name-only", "-z"])?;
+        let patch_path = tempfile::NamedTempFile::new()?.into_temp_path();
+        let mut patches: HashMap<String, Vec<u8>> = HashMap::new();
+
+        for file in staged.split('\0').filter(|f| !f.is_empty()) {
+            self.git_repo.run(&[
+                "diff",
+                "--cached",
+                "--binary",
+                "--no-color",
+                "--no-ext-diff",
+                "-U0",
+                &format!("--output={}", patch_path.display()),
+                "--",
+                file,
+            ])?;
+            let (header, hunks) = split_hunks(&fs::read(&patch_path)?);
+
+            if hunks.is_empty() {
+                let owner = chain
+                    .iter()
+                    .rev()
+                    .find(|branch| {
+                        files_to_propagate
+                            .get(*branch)
+                            .is_some_and(|files| files.iter().any(|f| f == file))
+                    })
+                    .map(String::as_str)
+                    .unwrap_or(current_branch);
+                patches.entry(owner.to_string()).or_default().extend(header);
+                continue;
+            }
+
+            let mut file_patches: HashMap<&str, Vec<u8>> = HashMap::new();
+            for hunk in hunks {
+                let owner = if files_to_propagate.values().flatten().any(|f| f == file) {
+                    let (start, end) = match (hunk.old_start, hunk.old_count) {
+                        (0, _) => (1, 1),
+                        (start, 0) => (start, start),
+                        (start, count) => (start, start + count - 1),
+                    };
+                    self.blame_levels(stack, &chain, "HEAD", file, Some((start, end)))
+                        .unwrap_or_default()
+                        .into_iter()
+                        .filter_map(|(level, _)| level)
+                        .max()
+                        .map(|index| chain[index].as_str())
+                        .unwrap_or(current_branch)
+                } else {
+                    current_branch
+                };
+                file_patches
+                    .entry(owner)
+                    .or_insert_with(|| header.clone())
+                    .extend(hunk.text);
+            }
+            for (owner, patch) in file_patches {
+                patches.entry(owner.to_string()).or_default().extend(patch);
+            }
+        }
+
+        Ok(patches)
+    }
+
+    /// Blame `file` at `rev` (optionally only the line `range`) and attribute each line to
+    /// the stack level whose commits last changed it: an index into `levels` (bottom of
+    /// the stack first), or `None` for lines that come from the base.
+    fn blame_levels(
+        &self,
+        stack: &Stack,
+        levels: &[String],
+        rev: &str,
+        file: &str,
+        range: Option<(usize, usize)>,
+    ) -> Result<Vec<(Option<usize>, String)>> {
+        let base = self.git_repo.resolve_branch(&stack.base_branch);
+        let mut levels_by_commit: HashMap<String, Option<usize>> = HashMap::new();
+
+        Ok(self
+            .git_repo
+            .blame(rev, file, range)?
+            .into_iter()
+            .map(|(commit, content)| {
+                let level = *levels_by_commit.entry(commit).or_insert_with_key(|commit| {
+                    if self.git_repo.is_ancestor(commit, &base) {
+                        return None;
+                    }
+                    levels
+                        .iter()
+                        .position(|branch| self.git_repo.is_ancestor(commit, branch))
+                });
+                (level, content)
+            })
+            .collect())
+    }
+
+    /// Apply `patch` to the tree of `head` in the index and commit the result on top of
+    /// `head` without moving any ref. The index is left in an unspecified state.
+    fn split_commit(
+        &self,
+        head: &str,
+        patch: &[u8],
+        patch_path: &std::path::Path,
+    ) -> Result<String> {
+        fs::write(patch_path, patch)?;
+        let patch_file = patch_path.to_string_lossy();
+        self.git_repo.run(&["read-tree", head])?;
+        self.git_repo
+            .run(&["apply", "--cached", "--unidiff-zero", &patch_file])?;
+        let tree = self.git_repo.run(&["write-tree"])?;
+        self.git_repo.run(&[
+            "commit-tree",
+            &tree,
+            "-p",
+            head,
+            "-m",
+            "git-train: split changes",
+        ])
+    }
+
     /// Get the depth of a branch in the stack hierarchy
     fn get_branch_depth_in_stack(&self, stack: &Stack, branch_name: &str) -> usize {
         let mut depth = 0;
@@ -773,10 +1702,14 @@ impl StackManager {
         depth
     }
 
-    /// Rebase all branches downstream from a given branch
+    /// Rebase all branches downstream from a given branch.
+    ///
+    /// `original` is the stack as recorded before any rewrites, used to replay each
+    /// child from its parent's previous tip.
     async fn rebase_downstream_branches_from(
         &self,
         stack: &mut Stack,
+        original: &Stack,
         from_branch: &str,
     ) -> Result<()> {
         let hierarchy = self.build_branch_hierarchy(stack);
@@ -795,8 +1728,13 @@ impl StackManager {
         for branch_name in branches_to_rebase {
             if let Some(branch) = stack.branches.get(&branch_name) {
                 if let Some(parent) = &branch.parent {
-                    print_info(&format!("Rebasing '{}' onto '{}'", branch_name, parent));
-                    self.smart_rebase(&branch_name, parent).await?;
+                    print_info(&format!("Updating '{}' from '{}'", branch_name, parent));
+                    let stored_parent_hash = original
+                        .branches
+                        .get(parent)
+                        .map(|b| b.commit_hash.as_str());
+                    self.restack_branch(original, &branch_name, parent, stored_parent_hash)
+                        .await?;
 
                     // Update commit hash in stack
                     if let Some(branch_mut) = stack.branches.get_mut(&branch_name) {
@@ -841,19 +1779,19 @@ impl StackManager {
         // Amend the current commit
         if let Some(message) = new_message {
             // Amend with new message
-            self.git_repo.run(&["commit", "--amend", "-m", message])?;
+            self.commit_with_trailers(stack, current_branch, &["--amend", "-m", message])?;
             print_success(&format!("Amended commit with new message: {}", message));
         } else {
             // Check if there are staged changes to amend
             let staged_output = self.git_repo.run(&["diff", "--cached", "--name-only"])?;
             if staged_output.trim().is_empty() {
                 // No staged changes, just amend message
-                self.git_repo.run(&["commit", "--amend", "--no-edit"])?;
+                self.commit_with_trailers(stack, current_branch, &["--amend", "--no-edit"])?;
                 print_success("Amended commit (no changes)");
             } else {
                 // Stage all changes and amend
                 self.git_repo.run(&["add", "."])?;
-                self.git_repo.run(&["commit", "--amend", "--no-edit"])?;
+                self.commit_with_trailers(stack, current_branch, &["--amend", "--no-edit"])?;
                 print_success("Amended commit with staged changes");
             }
         }
@@ -871,8 +1809,16 @@ impl StackManager {
 
         // Propagate changes to dependent branches (resync downstream)
         print_info("Resyncing downstream branches...");
-        self.propagate_changes(&mut updated_stack, current_branch)
-            .await?;
+        let previous_commit = stack
+            .branches
+            .get(current_branch)
+            .map(|b| b.commit_hash.clone());
+        self.propagate_changes(
+            &mut updated_stack,
+            current_branch,
+            previous_commit.as_deref(),
+        )
+        .await?;
 
         // Save the updated stack
         self.stack_state.save_stack(&updated_stack)?;
@@ -883,1162 +1829,5813 @@ impl StackManager {
         Ok(())
     }
 
-    /// Intelligently detect the best parent branch by analyzing git history
-    async fn detect_smart_parent(&self, current_branch: &str, stack: &Stack) -> Result<String> {
-        // Get the commits in the current branch that are not in the base branch
-        let commits_output = self.git_repo.run(&[
-            "rev-list",
-            &format!("{}..{}", stack.base_branch, current_branch),
-            "--reverse",
-        ])?;
+    /// Route conflicts left behind by a git operation (cherry-pick, rebase) through the
+    /// conflict resolver according to the configured auto-resolve strategy
+    async fn resolve_operation_conflicts(&self, operation: &str) -> Result<()> {
+        let Some(conflict_info) = self.conflict_resolver.detect_conflicts()? else {
+            return Err(TrainError::GitError {
+                message: format!("{} failed without conflicts", operation),
+            }
+            .into());
+        };
 
-        let commits: Vec<&str> = commits_output.trim().lines().collect();
+        print_info(&format!(
+            "Conflicts detected in {} files during {}",
+            conflict_info.files.len(),
+            operation
+        ));
+        emit_conflict_event(operation, &conflict_info);
 
-        if commits.is_empty() {
-            // No commits beyond base branch, parent should be base branch
-            return Ok(stack.base_branch.clone());
+        if self.config.conflict_resolution.auto_resolve_strategy
+            == crate::config::AutoResolveStrategy::Never
+        {
+            print_warning("Auto-resolution disabled. Please resolve conflicts manually:");
+            print_info("Re-run 'git-train sync' to continue with manual conflict resolution");
+            return Err(TrainError::InvalidState {
+                message: format!("Manual conflict resolution required for {}", operation),
+            }
+            .into());
         }
 
-        // Check each stack branch to see which one contains the most commits from our branch
-        let mut best_parent = stack.base_branch.clone();
-        let mut max_shared_commits = 0;
+        match self
+            .conflict_resolver
+            .auto_resolve_conflicts(&conflict_info)
+            .await
+        {
+            Ok(true) => {
+                let state = self.conflict_resolver.get_git_state()?;
+                self.conflict_resolver
+                    .verify_conflicts_resolved(&conflict_info, state)
+                    .await
+            }
+            Ok(false) | Err(_) => {
+                print_warning(
+                    "Automatic conflict resolution failed. Falling back to interactive resolution.",
+                );
+                self.conflict_resolver
+                    .resolve_conflicts_interactively(&conflict_info)
+                    .await?;
 
-        for branch_name in stack.branches.keys() {
-            // Get commits in this stack branch
-            let branch_commits_output = self.git_repo.run(&[
-                "rev-list",
-                &format!("{}..{}", stack.base_branch, branch_name),
-            ])?;
+                // The user may have chosen to abort instead of resolving
+                if matches!(self.conflict_resolver.get_git_state()?, GitState::Clean) {
+                    Ok(())
+                } else {
+                    Err(TrainError::InvalidState {
+                        message: format!("{} was not completed", operation),
+                    }
+                    .into())
+                }
+            }
+        }
+    }
 
-            let branch_commits: std::collections::HashSet<&str> =
-                branch_commits_output.trim().lines().collect();
+    /// Run an interactive rebase over a single branch's commits and restack its descendants
+    pub async fn edit_branch(&mut self, branch: Option<&str>) -> Result<()> {
+        print_train_header("Editing Branch");
 
-            // Count how many of our commits are in this branch
-            let shared_commits = commits
-                .iter()
-                .filter(|commit| branch_commits.contains(*commit))
-                .count();
+        let stack = self.get_or_load_current_stack()?;
+        let original_branch = self.get_current_branch()?;
+        let branch_name = branch.unwrap_or(&original_branch).to_string();
 
-            // If this branch contains more of our commits, it's a better parent candidate
-            if shared_commits > max_shared_commits {
-                max_shared_commits = shared_commits;
-                best_parent = branch_name.clone();
+        let Some(stack_branch) = stack.branches.get(&branch_name) else {
+            return Err(TrainError::StackError {
+                message: format!("Branch '{}' is not part of the current stack", branch_name),
             }
-        }
+            .into());
+        };
+        ensure_not_frozen(&stack, &branch_name)?;
 
-        // If we found a stack branch that shares commits, use it
-        if max_shared_commits > 0 {
-            print_info(&format!(
-                "Detected '{}' as parent (shares {} commits)",
-                best_parent, max_shared_commits
-            ));
-            Ok(best_parent)
-        } else {
-            // No shared commits with any stack branch, use base branch
+        self.ensure_clean_working_directory()?;
+
+        // Only the branch's own commits: everything since it forked from its parent
+        let parent = stack_branch
+            .parent
+            .clone()
+            .unwrap_or_else(|| stack.base_branch.clone());
+        let fork_point = self.git_repo.run(&[
+            "merge-base",
+            &self.git_repo.resolve_branch(&parent),
+            &branch_name,
+        ])?;
+        let commit_count = self.git_repo.run(&[
+            "rev-list",
+            "--count",
+            &format!("{}..{}", fork_point, branch_name),
+        ])?;
+        if commit_count.trim() == "0" {
             print_info(&format!(
-                "No shared commits with stack branches, using base branch '{}'",
-                stack.base_branch
+                "'{}' has no commits of its own on top of '{}'",
+                branch_name, parent
             ));
-            Ok(stack.base_branch.clone())
+            return Ok(());
         }
-    }
 
-    pub async fn add_branch_to_stack(&mut self, parent: Option<&str>) -> Result<()> {
-        print_train_header("Adding Branch to Stack");
+        let previous_tip = self.git_repo.get_commit_hash_for_branch(&branch_name)?;
+        print_info(&format!(
+            "Editing {} commit(s) of '{}' on top of '{}'",
+            commit_count.trim(),
+            branch_name,
+            parent
+        ));
 
-        let mut stack = self.get_or_load_current_stack()?;
-        let current_branch = self.get_current_branch()?;
+        self.git_repo.run(&["checkout", &branch_name])?;
+        if self
+            .git_repo
+            .run_interactive(&["rebase", "-i", &fork_point])
+            .is_err()
+        {
+            // Work through every stop that is caused by conflicts
+            while !matches!(self.conflict_resolver.get_git_state()?, GitState::Clean) {
+                if self.conflict_resolver.detect_conflicts()?.is_none() {
+                    print_warning("Rebase paused without conflicts (edit or break step).");
+                    print_info(
+                        "Finish with 'git rebase --continue', then run 'git-train sync' to restack descendants",
+                    );
+                    return Ok(());
+                }
+                let operation = format!("interactive rebase of {}", branch_name);
+                if let Err(e) = self.resolve_operation_conflicts(&operation).await {
+                    print_info("Resolve the rebase manually or run 'git rebase --abort'");
+                    return Err(e);
+                }
+            }
+        }
 
-        // Check if branch is already in the stack
-        if stack.branches.contains_key(&current_branch) {
-            print_warning(&format!(
-                "Branch '{}' is already part of the stack",
-                current_branch
-            ));
+        let new_tip = self.git_repo.get_commit_hash_for_branch(&branch_name)?;
+        if new_tip == previous_tip {
+            print_info("No changes made");
+            self.git_repo.run(&["checkout", &original_branch])?;
             return Ok(());
         }
 
-        // Ensure we're on a clean working directory
-        self.ensure_clean_working_directory()?;
+        // Children are replayed from the tip the branch had before the edit
+        let mut original = stack.clone();
+        if let Some(b) = original.branches.get_mut(&branch_name) {
+            b.commit_hash = previous_tip;
+        }
+        let mut updated_stack = stack.clone();
+        if let Some(b) = updated_stack.branches.get_mut(&branch_name) {
+            b.commit_hash = new_tip;
+            b.updated_at = Utc::now();
+        }
+        updated_stack.updated_at = Utc::now();
+        print_success(&format!("Rewrote '{}'", branch_name));
 
-        let current_commit = self.get_current_commit_hash()?;
+        print_info("Restacking descendant branches...");
+        self.rebase_downstream_branches_from(&mut updated_stack, &original, &branch_name)
+            .await?;
 
-        // Determine the parent branch
-        let parent_branch = if let Some(parent) = parent {
-            if !stack.branches.contains_key(parent) && parent != stack.base_branch {
-                return Err(TrainError::StackError {
-                    message: format!("Parent branch '{}' is not part of the stack", parent),
-                }
-                .into());
-            }
-            parent.to_string()
-        } else {
-            // Smart parent detection based on git history
-            self.detect_smart_parent(&current_branch, &stack).await?
-        };
+        self.git_repo.run(&["checkout", &original_branch])?;
 
-        // Add the branch to the stack
-        let branch = StackBranch {
-            name: current_branch.clone(),
-            parent: Some(parent_branch.clone()),
-            children: vec![],
-            commit_hash: current_commit,
-            mr_iid: None,
-            mr_title: None,
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
+        self.stack_state.save_stack(&updated_stack)?;
+        self.current_stack = Some(updated_stack);
+
+        print_success("Branch edited and descendants restacked");
+        Ok(())
+    }
+
+    /// Annotate each line of `file` with the stack level that last changed it.
+    ///
+    /// Blames the file at the tip of `branch` (default: the current branch), so only
+    /// the levels from the bottom of the stack up to that branch can own lines. Returns
+    /// the owning branch of every line, `None` for lines that come from the base.
+    pub async fn blame_file(
+        &mut self,
+        file: &str,
+        branch: Option<&str>,
+    ) -> Result<Vec<(Option<String>, String)>> {
+        print_train_header(&format!("Stack Blame: {}", file));
+
+        let stack = self.get_or_load_current_stack()?;
+        let branch = match branch {
+            Some(branch) => branch.to_string(),
+            None => self.get_current_branch()?,
         };
+        if !stack.branches.contains_key(&branch) {
+            return Err(TrainError::StackError {
+                message: format!(
+                    "Branch '{}' is not part of the stack; pass --branch with a stack branch",
+                    branch
+                ),
+            }
+            .into());
+        }
+        let mut levels = self.get_ancestor_branches(&stack, &branch);
+        levels.reverse();
+        levels.push(branch.clone());
 
-        stack.branches.insert(current_branch.clone(), branch);
-        stack.updated_at = Utc::now();
+        let lines = self.blame_levels(&stack, &levels, &branch, file, None)?;
 
-        // Save the updated stack
-        self.stack_state.save_stack(&stack)?;
-        self.current_stack = Some(stack);
+        let labels: Vec<String> = levels
+            .iter()
+            .enumerate()
+            .map(|(i, level)| format!("{}. {}", i + 1, level))
+            .collect();
+        let width = labels
+            .iter()
+            .map(|label| label.chars().count())
+            .chain(std::iter::once(stack.base_branch.chars().count()))
+            .max()
+            .unwrap_or(0);
+        let mut line_counts = vec![0; levels.len()];
+        for (number, (level, content)) in lines.iter().enumerate() {
+            let label = match level {
+                Some(level) => {
+                    line_counts[*level] += 1;
+                    style(format!("{:<width$}", labels[*level])).cyan()
+                }
+                None => style(format!("{:<width$}", stack.base_branch)).dim(),
+            };
+            println!("{} {:>4} │ {}", label, number + 1, content);
+        }
 
-        print_success(&format!(
-            "Added branch '{}' to stack with parent '{}'",
-            current_branch, parent_branch
-        ));
+        println!();
+        for (label, count) in labels.iter().zip(&line_counts) {
+            print_info(&format!("{}: {} line(s)", label, count));
+        }
 
-        Ok(())
+        Ok(lines
+            .into_iter()
+            .map(|(level, content)| (level.map(|level| levels[level].clone()), content))
+            .collect())
     }
 
-    pub async fn list_stacks(&self) -> Result<()> {
-        print_train_header("Available Stacks");
+    /// Find the stack level that introduced a regression.
+    ///
+    /// Bisects over the chain of branches from the bottom of the stack up to `top`
+    /// (default: the current branch), checking out each branch tip and running
+    /// `test_command` through the shell: exit 0 means good, anything else bad.
+    /// Returns the first bad branch, or `None` if the top of the chain passes.
+    pub async fn bisect_stack(
+        &mut self,
+        test_command: &str,
+        top: Option<&str>,
+    ) -> Result<Option<String>> {
+        print_train_header("Bisecting Stack");
 
-        let stacks = self.stack_state.list()?;
+        let stack = self.get_or_load_current_stack()?;
+        self.ensure_clean_working_directory()?;
+        let original_branch = self.get_current_branch()?;
 
-        if stacks.is_empty() {
-            print_info("No stacks found");
-            return Ok(());
+        let top = top.unwrap_or(&original_branch).to_string();
+        if !stack.branches.contains_key(&top) {
+            return Err(TrainError::StackError {
+                message: format!(
+                    "Branch '{}' is not part of the stack; pass --top with a stack branch",
+                    top
+                ),
+            }
+            .into());
         }
+        let mut levels = self.get_ancestor_branches(&stack, &top);
+        levels.reverse();
+        levels.push(top);
 
-        let current_stack_id = self.stack_state.get_current_stack_id().unwrap_or_default();
+        print_info(&format!(
+            "Bisecting {} levels: {} → {}",
+            levels.len(),
+            stack.base_branch,
+            levels.join(" → ")
+        ));
 
-        for stack in stacks {
-            let is_current = if current_stack_id == stack.id {
-                " (current)"
-            } else {
-                ""
-            };
-            let project_info = if let Some(project) = &stack.gitlab_project {
-                format!(" | Project: {}/{}", project.namespace.path, project.path)
+        let mut results: Vec<(String, bool)> = Vec::new();
+        let outcome = self
+            .bisect_levels(&stack.base_branch, &levels, test_command, &mut results)
+            .await;
+
+        // Always go back to where the user started
+        if let Err(e) = self.git_repo.run(&["checkout", &original_branch]) {
+            print_warning(&format!("Could not return to '{}': {}", original_branch, e));
+        }
+
+        let mut table = ui::Table::new(&["Branch", "Result"]);
+        for (branch, good) in &results {
+            let result = if *good {
+                style("✔ good").green().to_string()
             } else {
-                String::new()
+                style("✘ bad").red().to_string()
             };
+            table.add_row(vec![branch.clone(), result]);
+        }
+        table.print();
 
-            ui::print_info(&format!(
-                "▶ {} ({}){}",
-                stack.name,
-                &stack.id[..8],
-                is_current
-            ));
-            ui::print_info(&format!(
-                "   └─ Base: {} | Branches: {} | Updated: {}{}",
-                stack.base_branch,
-                stack.branches.len(),
-                stack.updated_at.format("%Y-%m-%d %H:%M"),
-                project_info
-            ));
+        let culprit = outcome?;
+        match &culprit {
+            Some(branch) if branch == &stack.base_branch => {
+                print_warning(&format!(
+                    "The base branch '{}' already fails; the regression predates the stack",
+                    branch
+                ));
+            }
+            Some(branch) => {
+                let level = levels.iter().position(|l| l == branch).unwrap_or(0) + 1;
+                print_error(&format!(
+                    "Regression introduced by level {} '{}'",
+                    level, branch
+                ));
+                if let Some(iid) = stack.branches.get(branch).and_then(|b| b.mr_iid) {
+                    match &stack.gitlab_project {
+                        Some(project) => print_info(&format!(
+                            "Merge request: !{} ({}/-/merge_requests/{})",
+                            iid, project.web_url, iid
+                        )),
+                        None => print_info(&format!("Merge request: !{}", iid)),
+                    }
+                }
+            }
+            None => print_success("The top of the stack passes; no regression found"),
         }
 
-        Ok(())
+        Ok(culprit)
     }
 
-    pub async fn switch_stack(&mut self, stack_identifier: &str) -> Result<()> {
-        print_train_header(&format!("Switching to Stack: {}", stack_identifier));
-
-        let stack = self.stack_state.find_by_identifier(stack_identifier)?;
-
-        // Update the current stack pointer
-        self.stack_state.set_current(&stack)?;
-
-        self.current_stack = Some(stack.clone());
-
-        print_success(&format!(
-            "Switched to stack '{}' ({})",
-            stack.name,
-            &stack.id[..8]
-        ));
+    /// Binary search over `levels` (bottom first), recording each tested branch.
+    async fn bisect_levels(
+        &self,
+        base: &str,
+        levels: &[String],
+        test_command: &str,
+        results: &mut Vec<(String, bool)>,
+    ) -> Result<Option<String>> {
+        let top = &levels[levels.len() - 1];
+        if self.run_bisect_test(top, test_command)? {
+            results.push((top.clone(), true));
+            return Ok(None);
+        }
+        results.push((top.clone(), false));
 
-        // Show status of the new stack
-        self.show_status().await?;
+        if !self.run_bisect_test(base, test_command)? {
+            results.push((base.to_string(), false));
+            return Ok(Some(base.to_string()));
+        }
+        results.push((base.to_string(), true));
+
+        // Invariant: the level at `good` passes (-1 is the base), the one at `bad` fails
+        let (mut good, mut bad) = (-1_isize, levels.len() as isize - 1);
+        while bad - good > 1 {
+            let mid = (good + bad) / 2;
+            let branch = &levels[mid as usize];
+            let passed = self.run_bisect_test(branch, test_command)?;
+            results.push((branch.clone(), passed));
+            if passed {
+                good = mid;
+            } else {
+                bad = mid;
+            }
+        }
 
-        Ok(())
+        Ok(Some(levels[bad as usize].clone()))
     }
 
-    pub async fn delete_stack(&mut self, stack_identifier: &str, force: bool) -> Result<()> {
-        print_train_header(&format!("Deleting Stack: {}", stack_identifier));
+    /// Check out `branch` and run the test command; true when it exits successfully
+    fn run_bisect_test(&self, branch: &str, test_command: &str) -> Result<bool> {
+        self.git_repo.run(&["checkout", "--quiet", branch])?;
+        print_info(&format!("Testing '{}': {}", branch, test_command));
+        let status = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(test_command)
+            .current_dir(self.git_repo.root())
+            .status()?;
+        Ok(status.success())
+    }
 
-        let stack = self.stack_state.find_by_identifier(stack_identifier)?;
+    /// Run `test_command` (default: `verify.command`) on every stack branch, bottom
+    /// first, in a temporary detached worktree so the working directory is untouched.
+    /// Returns each branch with whether it passed; fails when any level fails.
+    pub async fn verify_stack(
+        &mut self,
+        test_command: Option<&str>,
+    ) -> Result<Vec<(String, bool)>> {
+        print_train_header("Verifying Stack");
 
-        // Check if this is the current stack
-        let is_current_stack = if let Ok(current_stack_id) = self.stack_state.get_current_stack_id()
-        {
-            current_stack_id.trim() == stack.id
-        } else {
-            false
+        let test_command = match test_command.or(self.config.verify.command.as_deref()) {
+            Some(command) => command.to_string(),
+            None => {
+                return Err(TrainError::InvalidState {
+                    message: "No test command: pass --test or run 'git-train config set verify.command <command>'".to_string(),
+                }
+                .into())
+            }
         };
+        let stack = self.get_or_load_current_stack()?;
+        let hierarchy = self.build_branch_hierarchy(&stack);
+        let levels: Vec<(String, usize)> = self.branches_in_tree_order(&hierarchy, &stack);
 
-        // Show what will be deleted
-        print_warning(&format!(
-            "This will permanently delete stack '{}' ({})",
-            stack.name,
-            &stack.id[..8]
-        ));
+        let worktree_dir = tempfile::tempdir()?;
+        let worktree = worktree_dir.path().to_string_lossy().to_string();
+        self.git_repo
+            .run(&["worktree", "add", "--detach", &worktree, "HEAD"])?;
         print_info(&format!(
-            "Stack contains {} branches:",
-            stack.branches.len()
+            "Testing {} levels in a temporary worktree: {}",
+            levels.len(),
+            test_command
         ));
-        for branch_name in stack.branches.keys() {
-            ui::print_info(&format!("  - {}", branch_name));
-        }
 
-        if let Some(project) = &stack.gitlab_project {
-            print_info(&format!(
-                "Associated with GitLab project: {}/{}",
-                project.namespace.path, project.path
-            ));
+        let mut results: Vec<(String, bool)> = Vec::new();
+        let outcome = self.verify_levels(&worktree, &levels, &test_command, &mut results);
+
+        if let Err(e) = self
+            .git_repo
+            .run(&["worktree", "remove", "--force", &worktree])
+        {
+            print_warning(&format!("Could not remove the temporary worktree: {}", e));
         }
+        outcome?;
 
-        if is_current_stack {
-            print_warning("This is the current active stack");
+        let mut table = ui::Table::new(&["Level", "Branch", "Result"]);
+        for ((branch, passed), (_, depth)) in results.iter().zip(&levels) {
+            let result = if *passed {
+                style("✔ pass").green().to_string()
+            } else {
+                style("✘ fail").red().to_string()
+            };
+            table.add_row(vec![(depth + 1).to_string(), branch.clone(), result]);
         }
+        table.print();
 
-        // Confirm deletion unless forced
-        if !force {
-            print_warning(
-                "Are you sure you want to delete this stack? This action cannot be undone.",
-            );
-            let confirmed = get_user_input("Type 'yes' to confirm deletion", None)?;
-            if confirmed.to_lowercase() != "yes" {
-                print_info("Stack deletion cancelled");
-                return Ok(());
+        let failed = results.iter().filter(|(_, passed)| !passed).count();
+        if failed > 0 {
+            return Err(TrainError::StackError {
+                message: format!("{} of {} stack levels failed", failed, results.len()),
             }
+            .into());
         }
+        print_success("Every stack level passes");
+        Ok(results)
+    }
 
-        // Delete the stack file
-        self.stack_state.delete(&stack)?;
-        print_success(&format!("Deleted stack config for: {}", stack.name));
-
-        // If this was the current stack, clear the current stack reference
-        if is_current_stack {
-            self.current_stack = None;
-            print_info("Cleared current stack reference");
+    /// Check out each of `levels` in `worktree` and run the test command there
+    fn verify_levels(
+        &self,
+        worktree: &str,
+        levels: &[(String, usize)],
+        test_command: &str,
+        results: &mut Vec<(String, bool)>,
+    ) -> Result<()> {
+        for (branch, _) in levels {
+            let target = self.git_repo.resolve_branch(branch);
+            self.git_repo
+                .run(&["-C", worktree, "checkout", "--quiet", "--detach", &target])?;
+            print_info(&format!("Testing '{}'", branch));
+            let status = std::process::Command::new("sh")
+                .arg("-c")
+                .arg(test_command)
+                .current_dir(worktree)
+                .status()?;
+            results.push((branch.clone(), status.success()));
         }
-
-        print_success(&format!("Stack '{}' has been deleted", stack.name));
-        print_info("Note: Git branches were not deleted. You may want to clean them up manually if needed.");
-
         Ok(())
     }
 
-    pub async fn show_status(&mut self) -> Result<()> {
-        print_train_header("Stack Status");
+    /// Cherry-pick an external commit onto a stack level and restack its descendants
+    pub async fn pick_commit(&mut self, commit: &str, onto: Option<&str>) -> Result<()> {
+        print_train_header("Picking Commit into Stack");
 
         let stack = self.get_or_load_current_stack()?;
+        let original_branch = self.get_current_branch()?;
+        let target_branch = onto.unwrap_or(&original_branch).to_string();
 
-        ui::print_info(&format!("Stack: {} ({})", stack.name, &stack.id[..8]));
-        ui::print_info(&format!("Base branch: {}", stack.base_branch));
-
-        if let Some(project) = &stack.gitlab_project {
-            ui::print_info(&format!(
-                "GitLab project: {}/{} (ID: {})",
-                project.namespace.path, project.path, project.id
-            ));
-            ui::print_info(&format!("Project URL: {}", project.web_url));
+        if !stack.branches.contains_key(&target_branch) {
+            return Err(TrainError::StackError {
+                message: format!(
+                    "Branch '{}' is not part of the current stack",
+                    target_branch
+                ),
+            }
+            .into());
         }
 
-        ui::print_info(&format!(
-            "Created: {}",
-            stack.created_at.format("%Y-%m-%d %H:%M:%S UTC")
-        ));
-        ui::print_info(&format!(
-            "Updated: {}",
-            stack.updated_at.format("%Y-%m-%d %H:%M:%S UTC")
+        self.ensure_clean_working_directory()?;
+
+        let commit_hash = self
+            .git_repo
+            .run(&["rev-parse", "--verify", &format!("{}^{{commit}}", commit)])
+            .map_err(|_| TrainError::GitError {
+                message: format!("'{}' does not name a commit", commit),
+            })?;
+        let summary = self
+            .git_repo
+            .run(&["log", "-1", "--pretty=%s", &commit_hash])?;
+
+        print_info(&format!(
+            "Cherry-picking {} ({}) onto '{}'",
+            &commit_hash[..8],
+            summary,
+            target_branch
         ));
-        ui::print_info("");
 
-        // Build branch hierarchy and collect MR status
-        let hierarchy = self.build_branch_hierarchy(&stack);
-        let branch_mr_status = self.collect_mr_status_info(&stack).await;
-        self.print_branch_hierarchy_with_status(&hierarchy, &stack, &branch_mr_status, 0);
+        self.git_repo.run(&["checkout", &target_branch])?;
+        if self.git_repo.run(&["cherry-pick", &commit_hash]).is_err() {
+            let operation = format!(
+                "cherry-pick of {} onto {}",
+                &commit_hash[..8],
+                target_branch
+            );
+            if let Err(e) = self.resolve_operation_conflicts(&operation).await {
+                print_info("Resolve the cherry-pick manually or run 'git cherry-pick --abort'");
+                return Err(e);
+            }
+        }
 
-        // Show working directory status
-        let status_output = self.git_repo.run(&["status", "--porcelain"])?;
-        if !status_output.is_empty() {
-            ui::print_info("\nWorking directory status:");
-            ui::print_info(&status_output);
+        let mut updated_stack = stack.clone();
+        if let Some(branch) = updated_stack.branches.get_mut(&target_branch) {
+            branch.commit_hash = self.git_repo.get_commit_hash_for_branch(&target_branch)?;
+            branch.updated_at = Utc::now();
         }
+        updated_stack.updated_at = Utc::now();
+        print_success(&format!(
+            "Picked {} onto '{}'",
+            &commit_hash[..8],
+            target_branch
+        ));
+
+        print_info("Restacking descendant branches...");
+        self.rebase_downstream_branches_from(&mut updated_stack, &stack, &target_branch)
+            .await?;
 
+        self.git_repo.run(&["checkout", &original_branch])?;
+
+        self.stack_state.save_stack(&updated_stack)?;
+        self.current_stack = Some(updated_stack);
+
+        print_success("Commit picked and descendants restacked");
         Ok(())
     }
 
-    pub async fn navigate_stack_interactively(&mut self) -> Result<()> {
-        loop {
-            // Load current stack state
-            let stack = match self.get_or_load_current_stack() {
-                Ok(stack) => {
-                    self.current_stack = Some(stack.clone());
-                    stack
-                }
-                Err(_) => {
-                    print_warning(
-                        "No active stack found. Please create or switch to a stack first.",
-                    );
-                    return Ok(());
-                }
-            };
+    /// Intelligently detect the best parent branch by analyzing git history
+    async fn detect_smart_parent(&self, current_branch: &str, stack: &Stack) -> Result<BranchName> {
+        // Get the commits in the current branch that are not in the base branch
+        let base_ref = self.git_repo.resolve_branch(&stack.base_branch);
+        let commits_output = self.git_repo.run(&[
+            "rev-list",
+            &format!("{}..{}", base_ref, current_branch),
+            "--reverse",
+        ])?;
 
-            print_train_header(&format!("Navigate Stack: {}", stack.name));
+        let commits: Vec<&str> = commits_output.trim().lines().collect();
 
-            // Get current git branch
-            let current_git_branch = self.get_current_branch().ok();
+        if commits.is_empty() {
+            // No commits beyond base branch, parent should be base branch
+            return Ok(stack.base_branch.clone());
+        }
 
-            // Collect all branches in the stack
-            let mut branches: Vec<String> = stack.branches.keys().cloned().collect();
-            branches.sort();
+        // Check each stack branch to see which one contains the most commits from our branch
+        let mut best_parent = stack.base_branch.clone();
+        let mut max_shared_commits = 0;
 
-            // Collect MR status information (including merge status)
-            let branch_mr_status = self.collect_mr_status_info(&stack).await;
+        for branch_name in stack.branches.keys() {
+            // Get commits in this stack branch
+            let branch_commits_output = self
+                .git_repo
+                .run(&["rev-list", &format!("{}..{}", base_ref, branch_name)])?;
 
-            // Create navigation options
-            let options = ui::create_navigation_options(
-                &branches,
-                current_git_branch.as_deref(),
-                &branch_mr_status,
-            );
+            let branch_commits: std::collections::HashSet<&str> =
+                branch_commits_output.trim().lines().collect();
 
-            // Show interactive menu
-            match ui::interactive_stack_navigation(&options, "Select an action:") {
-                Ok(action) => {
-                    match action {
-                        ui::NavigationAction::SwitchToBranch(branch_name) => {
-                            if let Err(e) = self.switch_to_branch(&branch_name).await {
-                                print_error(&format!(
-                                    "Failed to switch to branch {}: {}",
-                                    branch_name, e
-                                ));
-                            }
-                        }
-                        ui::NavigationAction::ShowBranchInfo(branch_name) => {
-                            self.show_branch_info(&branch_name, &stack).await;
-                        }
-                        ui::NavigationAction::CreateMR(branch_name) => {
-                            if let Err(e) = self.create_mr_for_branch(&branch_name, &stack).await {
-                                print_error(&format!(
-                                    "Failed to create MR for {}: {}",
-                                    branch_name, e
-                                ));
-                            }
-                        }
-                        ui::NavigationAction::ViewMR(branch_name, mr_iid) => {
-                            self.view_mr_info(&branch_name, mr_iid, &stack).await;
-                        }
-                        ui::NavigationAction::RefreshStatus => {
-                            // Just continue the loop to refresh
-                            continue;
-                        }
-                        ui::NavigationAction::Exit => {
-                            print_info("Exiting navigation");
-                            break;
-                        }
-                    }
-                }
-                Err(_) => {
-                    // User cancelled (Ctrl+C or ESC)
-                    print_info("Navigation cancelled");
-                    break;
-                }
-            }
+            // Count how many of our commits are in this branch
+            let shared_commits = commits
+                .iter()
+                .filter(|commit| branch_commits.contains(*commit))
+                .count();
 
-            // Add a small pause for better UX
-            println!();
+            // If this branch contains more of our commits, it's a better parent candidate
+            if shared_commits > max_shared_commits {
+                max_shared_commits = shared_commits;
+                best_parent = branch_name.clone();
+            }
         }
 
-        Ok(())
+        // If we found a stack branch that shares commits, use it
+        if max_shared_commits > 0 {
+            print_info(&format!(
+                "Detected '{}' as parent (shares {} commits)",
+                best_parent, max_shared_commits
+            ));
+            Ok(best_parent)
+        } else {
+            // No shared commits with any stack branch, use base branch
+            print_info(&format!(
+                "No shared commits with stack branches, using base branch '{}'",
+                stack.base_branch
+            ));
+            Ok(stack.base_branch.clone())
+        }
     }
 
-    async fn switch_to_branch(&self, branch_name: &str) -> Result<()> {
-        // Ensure working directory is clean
-        if self.ensure_clean_working_directory().is_err() {
-            print_warning("Working directory is not clean. Stashing changes...");
-            self.git_repo
-                .run(&["stash", "push", "-m", "git-train navigation stash"])?;
+    pub async fn add_branch_to_stack(&mut self, parent: Option<&str>) -> Result<()> {
+        print_train_header("Adding Branch to Stack");
+
+        let mut stack = self.get_or_load_current_stack()?;
+        let current_branch = self.get_current_branch()?;
+
+        // Check if branch is already in the stack
+        if stack.branches.contains_key(&current_branch) {
+            print_warning(&format!(
+                "Branch '{}' is already part of the stack",
+                current_branch
+            ));
+            return Ok(());
         }
 
-        // Switch to the branch
-        self.git_repo.run(&["checkout", branch_name])?;
-        print_success(&format!("Switched to branch: {}", branch_name));
+        // Ensure we're on a clean working directory
+        self.ensure_clean_working_directory()?;
+
+        let current_branch = self.enforce_branch_name(&stack, &current_branch)?;
+
+        // Determine the parent branch
+        let parent_branch = if let Some(parent) = parent {
+            if !stack.branches.contains_key(parent) && parent != stack.base_branch {
+                return Err(TrainError::StackError {
+                    message: format!("Parent branch '{}' is not part of the stack", parent),
+                }
+                .into());
+            }
+            BranchName::from(parent)
+        } else {
+            // Smart parent detection based on git history
+            self.detect_smart_parent(&current_branch, &stack).await?
+        };
+
+        // Being behind the base is normal, but a stack parent must be an ancestor
+        if stack.branches.contains_key(&parent_branch) {
+            self.ensure_parent_is_ancestor(&current_branch, &parent_branch)?;
+        }
+        let current_commit = self.get_current_commit_hash()?;
+        // Levels above a private one can't be published before it
+        let state = match stack.branches.get(&parent_branch) {
+            Some(parent) if parent.state == BranchState::LocalOnly => BranchState::LocalOnly,
+            _ => BranchState::Unpushed,
+        };
+
+        // Add the branch to the stack
+        let branch = StackBranch {
+            name: BranchName::from(&current_branch),
+            parent: Some(parent_branch.clone()),
+            children: vec![],
+            commit_hash: current_commit,
+            mr_iid: None,
+            mr_title: None,
+            mr_template: None,
+            review_notes: Vec::new(),
+            target_history: Vec::new(),
+            description_hash: None,
+            remote_sha: None,
+            state,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        stack.branches.insert(branch.name.clone(), branch);
+        stack.updated_at = Utc::now();
+
+        // Save the updated stack
+        self.stack_state.save_stack(&stack)?;
+        self.current_stack = Some(stack);
+
+        print_success(&format!(
+            "Added branch '{}' to stack with parent '{}'",
+            current_branch, parent_branch
+        ));
 
         Ok(())
     }
 
-    async fn show_branch_info(&self, branch_name: &str, stack: &Stack) {
-        print_train_header(&format!("Branch Info: {}", branch_name));
-
-        if let Some(branch) = stack.branches.get(branch_name) {
-            ui::print_info(&format!("Branch: {}", branch.name));
-            ui::print_info(&format!(
-                "Parent: {}",
-                branch.parent.as_deref().unwrap_or(&stack.base_branch)
-            ));
-            ui::print_info(&format!("Commit: {}", &branch.commit_hash[..8]));
-            ui::print_info(&format!(
-                "Created: {}",
-                branch.created_at.format("%Y-%m-%d %H:%M:%S UTC")
-            ));
-            ui::print_info(&format!(
-                "Updated: {}",
-                branch.updated_at.format("%Y-%m-%d %H:%M:%S UTC")
-            ));
+    /// Make sure `parent` is an ancestor of the checked-out `branch`.
+    ///
+    /// When it isn't (the parent gained commits, or `branch` was cut from an older
+    /// version of it), offer to move the commits only `branch` has onto the parent's
+    /// tip; commits the parent already contains are dropped by the rebase. Declining, or
+    /// a rebase that conflicts, leaves the branch untouched and refuses with guidance.
+    fn ensure_parent_is_ancestor(&self, branch: &str, parent: &str) -> Result<()> {
+        if self.git_repo.is_ancestor(parent, branch) {
+            return Ok(());
+        }
 
-            if let Some(mr_iid) = branch.mr_iid {
-                ui::print_info(&format!("Merge Request: !{}", mr_iid));
-                if let Some(project) = &stack.gitlab_project {
-                    ui::print_info(&format!(
-                        "MR URL: {}/merge_requests/{}",
-                        project.web_url, mr_iid
-                    ));
-                }
+        let guidance = format!(
+            "'{}' is not based on the tip of '{}'; rebase it with 'git rebase {}' or pick another parent with --parent",
+            branch, parent, parent
+        );
+        let cherry = self.git_repo.run(&["cherry", parent, branch])?;
+        let own = cherry.lines().filter(|line| line.starts_with('+')).count();
+        let shared = cherry.lines().filter(|line| line.starts_with('-')).count();
+        print_warning(&format!(
+            "'{}' is not stacked on the current tip of '{}': {} of its commit(s) are not in '{}'{}",
+            branch,
+            parent,
+            own,
+            parent,
+            if shared > 0 {
+                format!(", {} already are", shared)
             } else {
-                ui::print_info("Merge Request: Not created");
+                String::new()
             }
+        ));
 
-            // Show children if any
-            let hierarchy = self.build_branch_hierarchy(stack);
-            if let Some(children) = hierarchy.get(branch_name) {
-                if !children.is_empty() {
-                    ui::print_info(&format!("Children: {}", children.join(", ")));
-                }
-            }
+        let prompt = format!(
+            "Move the {} commit(s) onto '{}' so '{}' becomes a new level above it?",
+            own, parent, branch
+        );
+        if !confirm_action(&prompt).unwrap_or(false) {
+            return Err(TrainError::StackError { message: guidance }.into());
+        }
 
-            // Show commit info
-            if let Ok(commit_info) =
-                self.git_repo
-                    .run(&["show", "--oneline", "-s", &branch.commit_hash])
-            {
-                ui::print_info(&format!("Commit info: {}", commit_info));
+        if self.git_repo.run(&["rebase", parent]).is_err() {
+            let _ = self.git_repo.run(&["rebase", "--abort"]);
+            return Err(TrainError::StackError {
+                message: format!(
+                    "Moving the commits hit conflicts and was undone. {}",
+                    guidance
+                ),
             }
-        } else {
-            print_error(&format!("Branch '{}' not found in stack", branch_name));
+            .into());
+        }
+        if !self.git_repo.is_ancestor(parent, branch) {
+            return Err(TrainError::StackError { message: guidance }.into());
         }
 
-        ui::print_info("\nPress Enter to continue...");
-        let _ = std::io::stdin().read_line(&mut String::new());
+        print_success(&format!("Moved {} commit(s) onto '{}'", own, parent));
+        Ok(())
     }
 
-    async fn create_mr_for_branch(&mut self, branch_name: &str, stack: &Stack) -> Result<()> {
-        if let Some(branch) = stack.branches.get(branch_name) {
-            let mut stack_mut = stack.clone();
-            self.create_or_update_mr_with_smart_targeting_and_store(
-                branch_name,
-                branch,
-                &mut stack_mut,
-            )
-            .await?;
+    /// Create a branch named from the configured template and add it to the stack.
+    ///
+    /// The branch starts at `parent`, which defaults to the current branch when it is
+    /// part of the stack and to the stack's base branch otherwise.
+    pub async fn create_branch(&mut self, description: &str, parent: Option<&str>) -> Result<()> {
+        let stack = self.get_or_load_current_stack()?;
+        self.ensure_clean_working_directory()?;
 
-            // Save the updated stack
-            self.stack_state.save_stack(&stack_mut)?;
-            self.current_stack = Some(stack_mut);
+        let slug = slugify(description, &self.config.naming);
+        if slug.is_empty() {
+            return Err(TrainError::StackError {
+                message: "Branch description must contain letters or digits".to_string(),
+            }
+            .into());
+        }
 
-            print_success(&format!(
-                "MR creation initiated for branch: {}",
-                branch_name
-            ));
-        } else {
-            print_error(&format!("Branch '{}' not found in stack", branch_name));
+        let template = self
+            .config
+            .branch
+            .name_template
+            .as_deref()
+            .unwrap_or(DEFAULT_BRANCH_TEMPLATE);
+        let name = BranchName::new(render_branch_name(
+            template,
+            &self.branch_name_user(),
+            &stack.name,
+            &slug,
+        ))?;
+        if self.git_repo.get_commit_hash_for_branch(&name).is_ok() {
+            return Err(TrainError::StackError {
+                message: format!("Branch '{}' already exists", name),
+            }
+            .into());
         }
 
-        Ok(())
+        let current_branch = self.get_current_branch()?;
+        let parent = match parent {
+            Some(parent) => parent.to_string(),
+            None if stack.branches.contains_key(&current_branch) => current_branch,
+            None => stack.base_branch.to_string(),
+        };
+
+        self.git_repo.run(&[
+            "checkout",
+            "--no-track",
+            "-b",
+            &name,
+            &self.git_repo.resolve_branch(&parent),
+        ])?;
+        print_success(&format!("Created branch '{}' from '{}'", name, parent));
+
+        self.add_branch_to_stack(Some(&parent)).await
     }
 
-    async fn view_mr_info(&self, branch_name: &str, mr_iid: u64, stack: &Stack) {
-        print_train_header(&format!("MR Info: !{} ({})", mr_iid, branch_name));
+    /// Add a level on top of the stack that bumps the version in every `bump.files`
+    /// manifest by `level`, commit it with `bump.message_template` and, unless
+    /// `no_push`, push the stack so the bump gets its MR below the stack table.
+    pub async fn bump_version(&mut self, level: BumpLevel, no_push: bool) -> Result<()> {
+        self.ensure_clean_working_directory()?;
+        let top = self.checkout_top().await?;
+        let root = self.git_repo.root().to_path_buf();
 
-        if let Some(gitlab_client) = &self.gitlab_client {
-            match gitlab_client.get_merge_request(mr_iid).await {
-                Ok(mr) => {
-                    ui::print_info(&format!("Title: {}", mr.title));
-                    ui::print_info(&format!("State: {}", mr.state));
-                    ui::print_info(&format!("Source: {}", mr.source_branch));
-                    ui::print_info(&format!("Target: {}", mr.target_branch));
-                    ui::print_info(&format!("ID: {}", mr.id));
-                    ui::print_info(&format!("IID: {}", mr.iid));
-
-                    if let Some(project) = &stack.gitlab_project {
-                        ui::print_info(&format!(
-                            "URL: {}/merge_requests/{}",
-                            project.web_url, mr.iid
-                        ));
-                    }
-
-                    if let Some(description) = &mr.description {
-                        if !description.is_empty() {
-                            ui::print_info("\nDescription:");
-                            ui::print_info(description);
-                        }
-                    }
-                }
-                Err(e) => {
-                    print_error(&format!("Failed to fetch MR info: {}", e));
+        let mut manifests = Vec::new();
+        for file in &self.config.bump.files {
+            let Ok(contents) = fs::read_to_string(root.join(file)) else {
+                continue;
+            };
+            match bump::read_version(file, &contents) {
+                Some(version) => {
+                    let parsed =
+                        Version::parse(version).ok_or_else(|| TrainError::InvalidState {
+                            message: format!(
+                                "{} has version '{}', not major.minor.patch",
+                                file, version
+                            ),
+                        })?;
+                    manifests.push((file.clone(), contents.clone(), parsed));
                 }
+                None => print_warning(&format!("No version field found in {}; skipping it", file)),
             }
-        } else {
-            print_error("GitLab client not available");
+        }
+        let Some(&(_, _, current)) = manifests.first() else {
+            return Err(TrainError::InvalidState {
+                message: format!(
+                    "None of the bump files ({}) has a version to bump",
+                    self.config.bump.files.join(", ")
+                ),
+            }
+            .into());
+        };
+        if let Some((file, _, other)) = manifests.iter().find(|(_, _, v)| *v != current) {
+            return Err(TrainError::InvalidState {
+                message: format!(
+                    "{} is at {} but {} is at {}; align them before bumping",
+                    manifests[0].0, current, file, other
+                ),
+            }
+            .into());
         }
 
-        ui::print_info("\nPress Enter to continue...");
-        let _ = std::io::stdin().read_line(&mut String::new());
+        let next = current.bump(level);
+        self.create_branch(&format!("release {}", next), Some(&top))
+            .await?;
+        for (file, contents, _) in &manifests {
+            if let Some(updated) = bump::write_version(file, contents, &next) {
+                fs::write(root.join(file), updated)?;
+            }
+        }
+        let message = self
+            .config
+            .bump
+            .message_template
+            .replace("{version}", &next.to_string())
+            .replace("{previous}", &current.to_string())
+            .replace("{level}", level.as_str());
+        self.commit_changes(Some(&message), false).await?;
+        print_success(&format!(
+            "Bumped {} from {} to {}",
+            manifests
+                .iter()
+                .map(|(file, _, _)| file.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+            current,
+            next
+        ));
+
+        if no_push {
+            print_info("Run 'git-train push' to open the release MR");
+            return Ok(());
+        }
+        self.push_stack_with_options(&PushOptions::default()).await
     }
 
-    pub async fn push_stack(&mut self) -> Result<()> {
-        print_train_header("Pushing Stack");
+    /// The `{user}` part of generated branch names: the local part of the git email
+    fn branch_name_user(&self) -> String {
+        let user = self
+            .git_repo
+            .user_email()
+            .and_then(|email| {
+                email
+                    .split('@')
+                    .next()
+                    .map(|local| slugify(local, &self.config.naming))
+            })
+            .or_else(|| {
+                std::env::var("USER")
+                    .ok()
+                    .map(|user| slugify(&user, &self.config.naming))
+            })
+            .unwrap_or_default();
+        if user.is_empty() {
+            "user".to_string()
+        } else {
+            user
+        }
+    }
 
-        let mut stack = self.get_or_load_current_stack()?;
-        let mut push_failures = Vec::new();
-        let mut successful_pushes = Vec::new();
+    /// Check `branch` against the configured naming scheme before it joins the stack.
+    ///
+    /// Returns the name the branch ends up with, which differs from `branch` only when
+    /// enforcement is `Fix` and the branch was renamed.
+    fn enforce_branch_name(&self, stack: &Stack, branch: &str) -> Result<String> {
+        let Some(template) = self.config.branch.name_template.as_deref() else {
+            return Ok(branch.to_string());
+        };
+        let user = self.branch_name_user();
+        if branch_name_matches(template, &user, &stack.name, branch, &self.config.naming) {
+            return Ok(branch.to_string());
+        }
 
-        let branch_mr_status = self.collect_mr_status_info(&stack).await;
+        let last_segment = branch.rsplit('/').next().unwrap_or(branch);
+        let suggestion = render_branch_name(
+            template,
+            &user,
+            &stack.name,
+            &slugify(last_segment, &self.config.naming),
+        );
 
-        // Push all branches in the stack
-        for branch_name in stack.branches.keys() {
-            if let Some(status) = branch_mr_status.get(branch_name) {
-                if status.state == "merged" {
-                    print_info(&format!(
-                        "Skipping push for branch '{}' as its MR !{} is already merged.",
-                        branch_name, status.iid
-                    ));
-                    continue;
-                }
+        match self.config.branch.enforcement {
+            NameEnforcement::Warn => {
+                print_warning(&format!(
+                    "Branch '{}' doesn't follow the naming scheme '{}' (e.g. '{}')",
+                    branch, template, suggestion
+                ));
+                Ok(branch.to_string())
             }
-            print_info(&format!("Pushing branch: {}", branch_name));
-
-            // First try a normal push
-            match self.git_repo.run(&[
-                "push",
-                "origin",
-                &format!("{}:{}", branch_name, branch_name),
-            ]) {
-                Ok(_) => {
-                    print_success(&format!("Pushed {}", branch_name));
-                    successful_pushes.push(branch_name.clone());
-                }
-                Err(e) => {
-                    // Check if this is a non-fast-forward error (common after rebase)
-                    let error_msg = format!("{}", e);
-                    if error_msg.contains("non-fast-forward") || error_msg.contains("rejected") {
-                        print_warning(&format!(
-                            "Branch {} was rejected (non-fast-forward)",
-                            branch_name
-                        ));
-                        print_info(
-                            "This is common after rebasing. Checking if force-push is safe...",
-                        );
-
-                        // Check if we should force push safely
-                        if self.should_force_push_branch(branch_name, &stack).await? {
-                            match self.git_repo.run(&[
-                                "push",
-                                "--force-with-lease",
-                                "origin",
-                                &format!("{}:{}", branch_name, branch_name),
-                            ]) {
-                                Ok(_) => {
-                                    print_success(&format!("Force-pushed {} safely", branch_name));
-                                    successful_pushes.push(branch_name.clone());
-                                }
-                                Err(force_err) => {
-                                    print_error(&format!(
-                                        "Force-push failed for {}: {}",
-                                        branch_name, force_err
-                                    ));
-                                    print_warning("This might mean someone else pushed changes. Manual intervention required.");
-                                    push_failures
-                                        .push((branch_name.clone(), format!("{}", force_err)));
-                                }
-                            }
-                        } else {
-                            print_warning(&format!(
-                                "Skipping force-push for {} (safety check failed)",
-                                branch_name
-                            ));
-                            push_failures.push((
-                                branch_name.clone(),
-                                "Force-push deemed unsafe".to_string(),
-                            ));
-                        }
-                    } else {
-                        print_error(&format!("Failed to push {}: {}", branch_name, e));
-                        push_failures.push((branch_name.clone(), format!("{}", e)));
+            NameEnforcement::Reject => Err(TrainError::StackError {
+                message: format!(
+                    "Branch '{}' doesn't follow the naming scheme '{}'. Rename it with 'git branch -m {}' or create branches with 'git-train branch create'",
+                    branch, template, suggestion
+                ),
+            }
+            .into()),
+            NameEnforcement::Fix => {
+                if self.git_repo.get_commit_hash_for_branch(&suggestion).is_ok() {
+                    return Err(TrainError::StackError {
+                        message: format!(
+                            "Cannot rename '{}' to '{}': that branch already exists",
+                            branch, suggestion
+                        ),
                     }
+                    .into());
                 }
+                self.git_repo.run(&["branch", "-m", branch, &suggestion])?;
+                print_info(&format!(
+                    "Renamed branch '{}' to '{}' to follow the naming scheme",
+                    branch, suggestion
+                ));
+                Ok(suggestion)
             }
         }
+    }
 
-        // Report results
-        if !successful_pushes.is_empty() {
-            print_success(&format!(
-                "Successfully pushed {} branches: {}",
-                successful_pushes.len(),
-                successful_pushes.join(", ")
-            ));
+    /// List stored stacks, narrowed down as `options` asks
+    pub async fn list_stacks(&self, options: &ListOptions) -> Result<()> {
+        print_train_header("Available Stacks");
+
+        let user_email = if options.mine {
+            Some(
+                self.git_repo
+                    .user_email()
+                    .ok_or_else(|| TrainError::InvalidState {
+                        message: "--mine needs git user.email to be set".to_string(),
+                    })?,
+            )
+        } else {
+            None
+        };
+        let owner = user_email.as_deref().or(options.author.as_deref());
+
+        let mut stacks = self.stack_state.list()?;
+        if let Some(owner) = owner {
+            stacks.retain(|stack| {
+                stack
+                    .owner
+                    .as_deref()
+                    .is_some_and(|created_by| created_by.eq_ignore_ascii_case(owner))
+            });
+        }
+        let archived = stacks.iter().filter(|stack| stack.archived).count();
+        if !options.archived {
+            stacks.retain(|stack| !stack.archived);
         }
+        let hidden_note = || {
+            if archived > 0 && !options.archived {
+                print_info(&format!(
+                    "{} fully merged stacks are archived; show them with 'git-train list --archived'",
+                    archived
+                ));
+            }
+        };
 
-        if !push_failures.is_empty() {
-            print_warning(&format!("Failed to push {} branches:", push_failures.len()));
-            for (branch, error) in &push_failures {
-                ui::print_error(&format!("  ✘ {}: {}", branch, error));
+        if stacks.is_empty() {
+            match owner {
+                Some(owner) => print_info(&format!("No stacks created by {}", owner)),
+                None => print_info("No stacks found"),
             }
-            print_info("You can:");
-            print_info("• Run 'git-train sync' to ensure branches are up to date");
-            print_info("• Force-push manually with 'git push --force-with-lease' if you're sure");
-            print_info("• Check for conflicts with remote changes");
+            hidden_note();
+            return Ok(());
         }
 
-        // Create or update merge requests with intelligent target branch selection
-        self.process_all_branches_for_mrs(&mut stack, "Updated merge request for")
-            .await;
-
-        self.update_all_mr_descriptions(&mut stack).await;
-
-        // Save the updated stack with MR IIDs
-        self.stack_state.save_stack(&stack)?;
-        self.current_stack = Some(stack);
+        let current_stack_id = self.stack_state.get_current_stack_id().unwrap_or_default();
 
-        if push_failures.is_empty() {
-            print_success("Stack pushed to remote successfully");
-        } else {
-            print_warning("Stack partially pushed to remote (some branches failed)");
+        let mut table = ui::Table::new(&[
+            "Stack", "ID", "Base", "Branches", "Merged", "Updated", "Owner", "Project",
+        ]);
+        for stack in stacks {
+            let name_cell = if current_stack_id.trim() == stack.id {
+                format!(
+                    "▶ {} {}",
+                    style(&stack.name).bold().cyan(),
+                    style("(current)").dim()
+                )
+            } else if stack.archived {
+                format!("  {} {}", stack.name, style("(archived)").dim())
+            } else {
+                format!("  {}", stack.name)
+            };
+            let (merged, total) = stack.merge_progress();
+            let project_cell = stack
+                .gitlab_project
+                .as_ref()
+                .map(|project| format!("{}/{}", project.namespace.path, project.path))
+                .unwrap_or_else(|| style("-").dim().to_string());
+            let owner_cell = stack
+                .owner
+                .clone()
+                .unwrap_or_else(|| style("-").dim().to_string());
+
+            table.add_row(vec![
+                name_cell,
+                stack.id.short().to_string(),
+                stack.base_branch.to_string(),
+                stack.branches.len().to_string(),
+                format!("{}/{}", merged, total),
+                stack.updated_at.format("%Y-%m-%d %H:%M").to_string(),
+                owner_cell,
+                project_cell,
+            ]);
         }
+        table.print();
+        hidden_note();
 
         Ok(())
     }
 
-    /// Determine if it's safe to force-push a branch
-    async fn should_force_push_branch(&self, branch_name: &str, stack: &Stack) -> Result<bool> {
-        // Safety checks for force-push
+    /// Make another stack active, optionally checking out its branch.
+    ///
+    /// `checkout` of `None` falls back to `git.checkout_on_switch` from the config.
+    pub async fn switch_stack(
+        &mut self,
+        stack_identifier: &str,
+        checkout: Option<bool>,
+    ) -> Result<()> {
+        print_train_header(&format!("Switching to Stack: {}", stack_identifier));
 
-        // 1. Check if the branch exists remotely
-        let remote_exists = self
-            .git_repo
-            .run(&["ls-remote", "--heads", "origin", branch_name])
-            .map(|output| !output.trim().is_empty())
-            .unwrap_or(false);
+        let mut stack = self.stack_state.find_by_identifier(stack_identifier)?;
 
-        if !remote_exists {
-            // New branch, safe to push
-            print_info(&format!(
-                "Branch {} doesn't exist remotely, safe to push",
-                branch_name
-            ));
-            return Ok(true);
+        if checkout.unwrap_or(self.config.git.checkout_on_switch) {
+            self.checkout_stack_branch(&stack)?;
         }
 
-        // 2. Check if this branch is part of our stack and we control it
-        if !stack.branches.contains_key(branch_name) {
-            print_warning(&format!(
-                "Branch {} is not part of our stack, unsafe to force-push",
-                branch_name
-            ));
-            return Ok(false);
+        // Update the current stack pointer
+        if self.track_head(&mut stack) {
+            self.stack_state.save_stack(&stack)?;
+        } else {
+            self.stack_state.set_current(&stack)?;
         }
 
-        // 3. Check configuration for automatic force-push behavior
-        if self.config.conflict_resolution.auto_force_push_after_rebase {
-            print_info(&format!(
-                "Auto force-push enabled, proceeding with {} (--force-with-lease)",
-                branch_name
-            ));
-        } else if self.config.conflict_resolution.prompt_before_force_push {
-            print_warning(&format!(
-                "Branch {} requires force-push after rebase",
-                branch_name
-            ));
-            print_info("This will overwrite the remote branch with your rebased version.");
+        self.current_stack = Some(stack.clone());
 
-            let proceed = confirm_action(&format!("Force-push {} safely?", branch_name))?;
-            if !proceed {
-                print_info("Skipping force-push. You can push manually later if needed.");
-                return Ok(false);
-            }
-        } else {
-            // Neither auto nor prompt enabled, skip force-push
-            print_info(&format!(
-                "Force-push not configured for automatic mode, skipping {}",
-                branch_name
-            ));
-            return Ok(false);
-        }
+        print_success(&format!(
+            "Switched to stack '{}' ({})",
+            stack.name,
+            stack.id.short()
+        ));
 
-        // 4. Additional safety: ensure we're not too far ahead (sanity check)
-        match self.git_repo.run(&[
-            "rev-list",
-            "--count",
-            &format!("origin/{}..{}", branch_name, branch_name),
-        ]) {
-            Ok(output) => {
-                if let Ok(ahead_count) = output.trim().parse::<u32>() {
-                    if ahead_count > 20 {
-                        print_warning(&format!(
-                            "Branch {} is {} commits ahead of remote, this seems unusual",
-                            branch_name, ahead_count
-                        ));
-                        print_warning("This might indicate a problem. Manual review recommended.");
-                        return Ok(false);
-                    }
-                }
-            }
-            Err(_) => {
-                // Can't determine, err on the side of caution
-                print_info("Could not determine commit difference, proceeding with caution");
-            }
-        }
+        // Show status of the new stack
+        self.show_status().await?;
 
-        Ok(true)
+        Ok(())
     }
 
-    /// Check for and attempt to recover from invalid git states
-    pub async fn check_and_recover_git_state(&self) -> Result<()> {
-        let git_state = self.conflict_resolver.get_git_state()?;
+    /// Check out the stack's recorded current branch, or its tip if that is gone,
+    /// carrying local changes across with a stash when auto-stash is enabled
+    fn checkout_stack_branch(&self, stack: &Stack) -> Result<()> {
+        let branch_exists =
+            |name: &str| self.git_repo.run(&["rev-parse", "--verify", name]).is_ok();
+
+        let target = stack
+            .current_branch
+            .as_deref()
+            .map(str::to_string)
+            .filter(|b| stack.branches.contains_key(b) && branch_exists(b))
+            .or_else(|| {
+                let hierarchy = self.build_branch_hierarchy(stack);
+                self.branches_in_tree_order(&hierarchy, stack)
+                    .into_iter()
+                    .map(|(name, _)| name)
+                    .rfind(|name| branch_exists(name))
+            })
+            .unwrap_or_else(|| stack.base_branch.to_string());
+
+        if self.get_current_branch().ok().as_deref() == Some(target.as_str()) {
+            print_info(&format!("Already on {}", target));
+            return Ok(());
+        }
 
-        match git_state {
-            GitState::Clean => Ok(()),
-            GitState::Rebasing | GitState::Merging | GitState::CherryPicking => {
+        let stashed = if self.has_uncommitted_changes()? {
+            if !self.config.git.auto_stash {
+                self.ensure_clean_working_directory()?;
+            }
+            self.git_repo.run(&[
+                "stash",
+                "push",
+                "--include-untracked",
+                "-m",
+                &format!("git-train switch to {}", stack.name),
+            ])?;
+            print_info("Stashed local changes");
+            true
+        } else {
+            false
+        };
+
+        if let Err(e) = self.git_repo.run(&["checkout", &target]) {
+            if stashed {
+                let _ = self.git_repo.run(&["stash", "pop"]);
+            }
+            return Err(e);
+        }
+        print_success(&format!("Checked out {}", target));
+
+        if stashed {
+            match self.git_repo.run(&["stash", "pop"]) {
+                Ok(_) => print_info("Restored stashed changes"),
+                Err(_) => print_warning(
+                    "Could not restore stashed changes cleanly; they are kept in 'git stash list'",
+                ),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Delete a stack's metadata and, with `--branches`/`--close-mrs`, its branches and
+    /// open MRs as well. Each branch and MR is confirmed separately unless forced;
+    /// branches that another stack also tracks are always kept.
+    pub async fn delete_stack(
+        &mut self,
+        stack_identifier: &str,
+        options: &DeleteOptions,
+    ) -> Result<()> {
+        print_train_header(&format!("Deleting Stack: {}", stack_identifier));
+
+        let stack = self.stack_state.find_by_identifier(stack_identifier)?;
+
+        // Check if this is the current stack
+        let is_current_stack = if let Ok(current_stack_id) = self.stack_state.get_current_stack_id()
+        {
+            current_stack_id.trim() == stack.id
+        } else {
+            false
+        };
+
+        // Show what will be deleted
+        print_warning(&format!(
+            "This will permanently delete stack '{}' ({})",
+            stack.name,
+            stack.id.short()
+        ));
+        print_info(&format!(
+            "Stack contains {} branches:",
+            stack.branches.len()
+        ));
+        for branch_name in stack.branches.keys() {
+            ui::print_info(&format!("  - {}", branch_name));
+        }
+
+        if let Some(project) = &stack.gitlab_project {
+            print_info(&format!(
+                "Associated with GitLab project: {}/{}",
+                project.namespace.path, project.path
+            ));
+        }
+
+        if is_current_stack {
+            print_warning("This is the current active stack");
+        }
+
+        // Work out which branches and MRs go with the stack
+        let mut branches: Vec<String> = Vec::new();
+        if options.branches {
+            let shared: Vec<BranchName> = self
+                .stack_state
+                .list()?
+                .into_iter()
+                .filter(|other| other.id != stack.id)
+                .flat_map(|other| other.branches.into_keys())
+                .collect();
+            let mut names: Vec<&BranchName> = stack.branches.keys().collect();
+            names.sort();
+            for name in names {
+                if shared.contains(name) {
+                    print_info(&format!("Keeping '{}': another stack tracks it", name));
+                } else {
+                    branches.push(name.to_string());
+                }
+            }
+        }
+
+        let mut open_mrs: Vec<(String, MrIid)> = Vec::new();
+        if options.close_mrs {
+            match self.gitlab().await {
+                Some(gitlab_client) => {
+                    for (name, branch) in &stack.branches {
+                        let Some(iid) = branch.mr_iid else {
+                            continue;
+                        };
+                        match gitlab_client.get_merge_request(iid).await {
+                            Ok(mr) if mr.state == "opened" => {
+                                open_mrs.push((name.to_string(), iid))
+                            }
+                            Ok(_) => {}
+                            Err(e) if self.disable_gitlab_on_auth_error(&e) => break,
+                            Err(e) => print_warning(&format!("Could not fetch MR !{}: {}", iid, e)),
+                        }
+                    }
+                    open_mrs.sort();
+                }
+                None => print_warning("GitLab is not configured; no MRs will be closed"),
+            }
+        }
+
+        if options.dry_run {
+            print_info("Dry run, nothing will be changed. Would:");
+            for (name, iid) in &open_mrs {
+                print_info(&format!("  - close !{} ({})", iid, name));
+            }
+            for name in &branches {
+                let remote = if self.git_repo.has_remote_branch(name) {
+                    format!(" and origin/{}", name)
+                } else {
+                    String::new()
+                };
+                print_info(&format!("  - delete branch {}{}", name, remote));
+            }
+            print_info(&format!(
+                "  - delete the metadata of stack '{}'",
+                stack.name
+            ));
+            return Ok(());
+        }
+
+        // Confirm deletion unless forced
+        if !options.force {
+            print_warning(
+                "Are you sure you want to delete this stack? This action cannot be undone.",
+            );
+            let confirmed = get_user_input("Type 'yes' to confirm deletion", None)?;
+            if confirmed.to_lowercase() != "yes" {
+                print_info("Stack deletion cancelled");
+                return Ok(());
+            }
+        }
+
+        if let Some(gitlab_client) = self.gitlab().await {
+            for (name, iid) in &open_mrs {
+                if !options.force && !confirm_action(&format!("Close !{} ({})?", iid, name))? {
+                    continue;
+                }
+                let note = format!("Closed by git-train: stack '{}' was deleted.", stack.name);
+                if let Err(e) = gitlab_client.create_merge_request_note(*iid, &note).await {
+                    print_warning(&format!("Could not comment on !{}: {}", iid, e));
+                }
+                match gitlab_client.close_merge_request(*iid).await {
+                    Ok(_) => print_success(&format!("Closed !{} ({})", iid, name)),
+                    Err(e) if self.disable_gitlab_on_auth_error(&e) => break,
+                    Err(e) => print_warning(&format!("Could not close !{}: {}", iid, e)),
+                }
+            }
+        }
+
+        if !branches.is_empty() {
+            // A checked-out branch can't be deleted; step onto the base first
+            let current_branch = self.git_repo.get_current_branch().ok();
+            if current_branch.is_some_and(|current| branches.contains(&current)) {
+                self.git_repo.ensure_local_branch(&stack.base_branch)?;
+                self.git_repo.run(&["checkout", &stack.base_branch])?;
+                print_info(&format!("Switched to '{}'", stack.base_branch));
+            }
+        }
+        for name in &branches {
+            if !options.force && !confirm_action(&format!("Delete branch '{}'?", name))? {
+                continue;
+            }
+            if self.git_repo.has_local_branch(name) {
+                match self.git_repo.run(&["branch", "-D", name]) {
+                    Ok(_) => print_success(&format!("Deleted branch {}", name)),
+                    Err(e) => print_warning(&format!("Could not delete {}: {}", name, e)),
+                }
+            }
+            if self.git_repo.has_remote_branch(name) {
+                match self.git_repo.run(&["push", "origin", "--delete", name]) {
+                    Ok(_) => print_success(&format!("Deleted origin/{}", name)),
+                    Err(e) => print_warning(&format!("Could not delete origin/{}: {}", name, e)),
+                }
+            }
+        }
+
+        // Delete the stack file
+        self.stack_state.delete(&stack)?;
+        print_success(&format!("Deleted stack config for: {}", stack.name));
+
+        // If this was the current stack, clear the current stack reference
+        if is_current_stack {
+            self.current_stack = None;
+            print_info("Cleared current stack reference");
+        }
+
+        print_success(&format!("Stack '{}' has been deleted", stack.name));
+        if !options.branches {
+            print_info("Note: Git branches were not deleted. Pass --branches to remove them too.");
+        }
+
+        Ok(())
+    }
+
+    /// Write a Markdown summary of the stack (titles, descriptions, MRs and largest
+    /// changes per level) to `output` or stdout, and return it
+    pub async fn summarize_stack(&mut self, output: Option<&str>) -> Result<String> {
+        let stack = self.get_or_load_current_stack()?;
+
+        let mut mrs = HashMap::new();
+        if let Some(gitlab) = self.gitlab().await {
+            let iids: Vec<MrIid> = stack.branches.values().filter_map(|b| b.mr_iid).collect();
+            let results =
+                future::join_all(iids.iter().map(|&iid| gitlab.get_merge_request(iid))).await;
+            for result in results {
+                match result {
+                    Ok(mr) => {
+                        mrs.insert(mr.iid, mr);
+                    }
+                    Err(e) if self.disable_gitlab_on_auth_error(&e) => break,
+                    Err(e) => print_warning(&format!("Could not fetch MR: {}", e)),
+                }
+            }
+        }
+
+        let mut diff_stats = HashMap::new();
+        for branch in stack.branches.values() {
+            let parent = self
+                .git_repo
+                .resolve_branch(branch.parent.as_deref().unwrap_or(&stack.base_branch));
+            if let Ok(files) = self.git_repo.diff_stat(&parent, &branch.name) {
+                diff_stats.insert(branch.name.to_string(), files);
+            }
+        }
+
+        let summary = markdown::build_stack_summary(&stack, &mrs, &diff_stats);
+        match output {
+            Some(path) => {
+                fs::write(path, &summary)?;
+                print_success(&format!("Wrote stack summary to {}", path));
+            }
+            None => println!("{}", summary),
+        }
+        Ok(summary)
+    }
+
+    /// Gather non-sensitive diagnostics into a single redacted bundle for bug reports
+    pub async fn debug_dump(&mut self, output: Option<&str>) -> Result<()> {
+        let mut bundle = String::new();
+        let mut section = |title: &str, body: &str| {
+            bundle.push_str(&format!(
+                "## {}\n\n```\n{}\n```\n\n",
+                title,
+                body.trim_end()
+            ));
+        };
+
+        section(
+            "Environment",
+            &format!(
+                "git-train: {}\nos: {} ({})\n{}\nGITLAB_URL: {}\nGITLAB_TOKEN is {}\nGITLAB_PROJECT_ID: {}",
+                env!("CARGO_PKG_VERSION"),
+                std::env::consts::OS,
+                std::env::consts::ARCH,
+                self.git_repo
+                    .run(&["--version"])
+                    .unwrap_or_else(|e| format!("git --version failed: {}", e)),
+                std::env::var("GITLAB_URL").unwrap_or_else(|_| "(default)".to_string()),
+                if std::env::var("GITLAB_TOKEN").is_ok() {
+                    "set"
+                } else {
+                    "not set"
+                },
+                std::env::var("GITLAB_PROJECT_ID").unwrap_or_else(|_| "not set".to_string()),
+            ),
+        );
+
+        section(
+            "Configuration",
+            &toml::to_string_pretty(&self.config)
+                .unwrap_or_else(|e| format!("Failed to serialize config: {}", e)),
+        );
+
+        let gitlab_available = self.gitlab().await.is_some();
+        let git_state = self
+            .conflict_resolver
+            .get_git_state()
+            .map(|state| format!("{:?}", state))
+            .unwrap_or_else(|e| format!("unknown ({})", e));
+        section(
+            "Git state",
+            &format!(
+                "state: {}\ncurrent branch: {}\nGitLab client: {}\n\n$ git status --porcelain\n{}\n\n$ git remote -v\n{}",
+                git_state,
+                self.get_current_branch()
+                    .unwrap_or_else(|e| format!("unknown ({})", e)),
+                if gitlab_available {
+                    "initialized"
+                } else {
+                    "not available"
+                },
+                self.git_repo.run(&["status", "--porcelain"]).unwrap_or_default(),
+                self.git_repo.run(&["remote", "-v"]).unwrap_or_default(),
+            ),
+        );
+
+        let current_stack_id = self.stack_state.get_current_stack_id().unwrap_or_default();
+        for stack in self.stack_state.list()? {
+            let marker = if current_stack_id.trim() == stack.id {
+                " (current)"
+            } else {
+                ""
+            };
+            section(
+                &format!("Stack '{}'{}", stack.name, marker),
+                &serde_json::to_string_pretty(&stack)?,
+            );
+        }
+
+        section(
+            "Recent reflog",
+            &self
+                .git_repo
+                .run(&["reflog", "-n", "20", "--date=iso"])
+                .unwrap_or_default(),
+        );
+
+        let audit = fs::read_to_string(self.git_repo.audit_log_path()).unwrap_or_default();
+        let lines: Vec<&str> = audit.lines().collect();
+        section(
+            "Recent git commands (audit log)",
+            &lines[lines.len().saturating_sub(40)..].join("\n"),
+        );
+
+        let bundle = format!(
+            "# git-train debug dump ({})\n\n{}",
+            Utc::now().format("%Y-%m-%d %H:%M:%S UTC"),
+            crate::utils::redact_secrets(&bundle)
+        );
+
+        match output {
+            Some(path) => {
+                fs::write(path, &bundle)?;
+                print_success(&format!("Wrote redacted debug bundle to {}", path));
+                print_info("Please review it before attaching it to an issue");
+            }
+            None => println!("{}", bundle),
+        }
+
+        Ok(())
+    }
+
+    /// Mark a stack branch as owned by a teammate, or hand it back to git-train.
+    ///
+    /// Frozen branches are never rebased or pushed; `sync` fast-forwards them to their
+    /// remote tip and restacks their children onto it.
+    pub async fn set_branch_frozen(&mut self, branch: &str, frozen: bool) -> Result<()> {
+        if !self.set_branch_held(branch, BranchState::Frozen, frozen)? {
+            return Ok(());
+        }
+
+        if frozen {
+            print_success(&format!(
+                "Froze '{}': git-train won't rebase or push it",
+                branch
+            ));
+            print_info("'git-train sync' follows its remote tip and restacks its children onto it");
+        } else {
+            print_success(&format!(
+                "Unfroze '{}': sync and push manage it again",
+                branch
+            ));
+        }
+        Ok(())
+    }
+
+    /// Pause a published branch, or resume it.
+    ///
+    /// `push` leaves a paused branch's remote branch and MR as they are, e.g. while
+    /// reviewers are mid-review; `sync` still restacks it locally.
+    pub async fn set_branch_paused(&mut self, branch: &str, paused: bool) -> Result<()> {
+        if !self.set_branch_held(branch, BranchState::Paused, paused)? {
+            return Ok(());
+        }
+
+        if paused {
+            print_success(&format!(
+                "Paused '{}': push leaves its remote branch and MR alone",
+                branch
+            ));
+            print_info(&format!(
+                "Run 'git-train branch resume {}' to push it again",
+                branch
+            ));
+        } else {
+            print_success(&format!(
+                "Resumed '{}': the next push updates it and its MR",
+                branch
+            ));
+        }
+        Ok(())
+    }
+
+    /// Move `branch` into `held` (frozen or paused), or release it to the state its MR
+    /// puts it in. Returns whether anything changed.
+    fn set_branch_held(&mut self, branch: &str, held: BranchState, hold: bool) -> Result<bool> {
+        let mut stack = self.get_or_load_current_stack()?;
+        let Some(stack_branch) = stack.branches.get_mut(branch) else {
+            return Err(TrainError::StackError {
+                message: format!("Branch '{}' is not in stack '{}'", branch, stack.name),
+            }
+            .into());
+        };
+        if (stack_branch.state == held) == hold {
+            print_info(&format!(
+                "'{}' is already {}{}",
+                branch,
+                if hold { "" } else { "not " },
+                held.as_str()
+            ));
+            return Ok(false);
+        }
+
+        let next = if hold {
+            held
+        } else {
+            stack_branch.settled_state()
+        };
+        stack_branch.transition(next)?;
+        stack.updated_at = Utc::now();
+        self.stack_state.save_stack(&stack)?;
+        self.current_stack = Some(stack);
+        Ok(true)
+    }
+
+    /// Make `branch` and every level above it private: `push` skips them, they get no
+    /// MR and stack tables leave them out until `git-train publish`.
+    pub async fn set_branch_private(&mut self, branch: &str) -> Result<()> {
+        let mut stack = self.get_or_load_current_stack()?;
+        if !stack.branches.contains_key(branch) {
+            return Err(TrainError::StackError {
+                message: format!("Branch '{}' is not in stack '{}'", branch, stack.name),
+            }
+            .into());
+        }
+
+        let mut levels = vec![branch.to_string()];
+        let mut pending = child_branches(&stack, branch);
+        while let Some(level) = pending.pop() {
+            pending.extend(child_branches(&stack, &level));
+            levels.push(level);
+        }
+        // Merged levels are done; whatever is stacked above them can still go private
+        levels.retain(|level| stack.branches[level.as_str()].state != BranchState::MrMerged);
+        if let Some(b) = levels
+            .iter()
+            .map(|level| &stack.branches[level.as_str()])
+            .find(|b| !b.state.can_become(BranchState::LocalOnly))
+        {
+            return Err(TrainError::StackError {
+                message: match b.mr_iid {
+                    Some(iid) => format!(
+                        "'{}' is already published as !{}; only levels without an MR can be private",
+                        b.name, iid
+                    ),
+                    None => format!(
+                        "'{}' is {}; only levels without an MR can be private",
+                        b.name,
+                        b.state.as_str()
+                    ),
+                },
+            }
+            .into());
+        }
+        levels.retain(|level| stack.branches[level.as_str()].state != BranchState::LocalOnly);
+        if levels.is_empty() {
+            print_info(&format!("'{}' is already private", branch));
+            return Ok(());
+        }
+
+        for level in &levels {
+            if let Some(b) = stack.branches.get_mut(level.as_str()) {
+                b.transition(BranchState::LocalOnly)?;
+            }
+        }
+        stack.updated_at = Utc::now();
+        self.stack_state.save_stack(&stack)?;
+        self.current_stack = Some(stack);
+
+        print_success(&format!("Made private: {}", levels.join(", ")));
+        print_info(&format!(
+            "'git-train push' leaves them local; run 'git-train publish {}' when it's ready",
+            branch
+        ));
+        Ok(())
+    }
+
+    /// Publish `branch` together with the private levels below it, then push the stack
+    /// so they get their MRs. Levels above `branch` stay private.
+    pub async fn publish_branch(&mut self, branch: &str) -> Result<()> {
+        let mut stack = self.get_or_load_current_stack()?;
+        if !stack.branches.contains_key(branch) {
+            return Err(TrainError::StackError {
+                message: format!("Branch '{}' is not in stack '{}'", branch, stack.name),
+            }
+            .into());
+        }
+
+        let mut levels = Vec::new();
+        let mut level = Some(branch.to_string());
+        while let Some(name) = level {
+            let Some(b) = stack.branches.get(name.as_str()) else {
+                break;
+            };
+            level = b.parent.as_ref().map(|parent| parent.to_string());
+            if b.state == BranchState::LocalOnly {
+                levels.push(name);
+            }
+        }
+        if levels.is_empty() {
+            print_info(&format!("'{}' is already published", branch));
+            return Ok(());
+        }
+
+        levels.reverse();
+        for level in &levels {
+            if let Some(b) = stack.branches.get_mut(level.as_str()) {
+                b.transition(BranchState::Unpushed)?;
+            }
+        }
+        stack.updated_at = Utc::now();
+        self.stack_state.save_stack(&stack)?;
+        self.current_stack = Some(stack);
+        print_success(&format!("Published: {}", levels.join(", ")));
+
+        self.push_stack_with_options(&PushOptions::default()).await
+    }
+
+    /// Show or change how `sync` brings the current stack's branches up to date
+    pub async fn set_update_strategy(&mut self, strategy: Option<UpdateStrategy>) -> Result<()> {
+        let mut stack = self.get_or_load_current_stack()?;
+
+        let Some(strategy) = strategy else {
+            print_info(&format!(
+                "Stack '{}' uses the {} update strategy",
+                stack.name,
+                stack.update_strategy.as_str()
+            ));
+            return Ok(());
+        };
+
+        if stack.update_strategy == strategy {
+            print_info(&format!(
+                "Stack '{}' already uses the {} update strategy",
+                stack.name,
+                strategy.as_str()
+            ));
+            return Ok(());
+        }
+
+        stack.update_strategy = strategy;
+        stack.updated_at = Utc::now();
+        self.stack_state.save_stack(&stack)?;
+        self.current_stack = Some(stack.clone());
+
+        print_success(&format!(
+            "Stack '{}' now uses the {} update strategy",
+            stack.name,
+            strategy.as_str()
+        ));
+        match strategy {
+            UpdateStrategy::Merge => print_info(
+                "'git-train sync' will merge each parent into its children; pushes never need --force",
+            ),
+            UpdateStrategy::Rebase => print_info(
+                "'git-train sync' will rebase children onto their parents; pushes may need --force-with-lease",
+            ),
+        }
+        Ok(())
+    }
+
+    /// Move the current stack onto `new_base`.
+    ///
+    /// The root branches are replayed from the old base onto the new one (only their own
+    /// commits), their descendants are restacked, and the root MRs are retargeted.
+    pub async fn move_stack_base(&mut self, new_base: &str) -> Result<()> {
+        print_train_header(&format!("Moving Stack onto {}", new_base));
+
+        self.ensure_clean_working_directory()?;
+        let stack = self.get_or_load_current_stack()?;
+        let old_base = stack.base_branch.clone();
+        if old_base == new_base {
+            print_info(&format!("Stack is already based on '{}'", new_base));
+            return Ok(());
+        }
+
+        let _ = self.git_repo.run(&["fetch", "origin", new_base]);
+        if !self.git_repo.has_local_branch(new_base) && !self.git_repo.has_remote_branch(new_base) {
+            return Err(TrainError::InvalidState {
+                message: format!("Branch '{}' does not exist locally or on origin", new_base),
+            }
+            .into());
+        }
+        let old_base_ref = self.git_repo.resolve_branch(&old_base);
+        let original_branch = self.get_current_branch()?;
+
+        let mut updated_stack = stack.clone();
+        updated_stack.base_branch = BranchName::from(new_base);
+        let mut roots = Vec::new();
+        for branch in updated_stack.branches.values_mut() {
+            if branch.parent.as_deref() == Some(old_base.as_str()) {
+                branch.parent = Some(BranchName::from(new_base));
+                roots.push(branch.name.clone());
+            }
+        }
+
+        let hierarchy = self.build_branch_hierarchy(&stack);
+        for (branch_name, _) in self.branches_in_tree_order(&hierarchy, &stack) {
+            let Some(parent) = stack.branches[&branch_name]
+                .parent
+                .as_deref()
+                .map(str::to_string)
+            else {
+                continue;
+            };
+            // Roots keep only the commits they added on top of the old base
+            let (onto, stored_parent_hash) = if parent == old_base {
+                let fork_point = self
+                    .git_repo
+                    .run(&["merge-base", &branch_name, &old_base_ref])?;
+                (new_base.to_string(), fork_point)
+            } else {
+                (parent.clone(), stack.branches[&parent].commit_hash.clone())
+            };
+
+            print_info(&format!("Updating '{}' from '{}'", branch_name, onto));
+            self.git_repo.run(&["checkout", &branch_name])?;
+            self.restack_branch(
+                &updated_stack,
+                &branch_name,
+                &onto,
+                Some(&stored_parent_hash),
+            )
+            .await?;
+            if let Some(branch) = updated_stack.branches.get_mut(&branch_name) {
+                branch.commit_hash = self.get_current_commit_hash()?;
+                branch.updated_at = Utc::now();
+            }
+        }
+        self.git_repo.run(&["checkout", &original_branch])?;
+
+        if let Some(gitlab_client) = self.gitlab().await {
+            if let Ok(project) = gitlab_client.detect_and_cache_project().await {
+                updated_stack.gitlab_project = Some(project);
+            }
+            self.snapshot_mr_metadata(&updated_stack, "set-base").await;
+            roots.sort();
+            for root in &roots {
+                let Some(branch) = updated_stack.branches.get_mut(root) else {
+                    continue;
+                };
+                let Some(iid) = branch.mr_iid else {
+                    continue;
+                };
+                match gitlab_client
+                    .update_merge_request_with_target(iid, None, None, Some(new_base.to_string()))
+                    .await
+                {
+                    Ok(_) => {
+                        branch.record_target_change(
+                            Some(&old_base),
+                            new_base,
+                            "stack moved onto a new base",
+                        );
+                        print_success(&format!("Retargeted !{} ({}) to '{}'", iid, root, new_base));
+                    }
+                    Err(e) => print_warning(&format!("Could not retarget !{}: {}", iid, e)),
+                }
+            }
+        }
+
+        updated_stack.updated_at = Utc::now();
+        self.stack_state.save_stack(&updated_stack)?;
+        self.current_stack = Some(updated_stack);
+
+        print_success(&format!(
+            "Moved stack from '{}' onto '{}'",
+            old_base, new_base
+        ));
+        print_info("Run 'git-train push' to update the remote branches");
+        Ok(())
+    }
+
+    /// The project's default branch when it no longer matches the stack's base, for
+    /// stacks created on the then-default branch (e.g. after a master → main rename).
+    pub async fn default_branch_change(&self, stack: &Stack) -> Option<String> {
+        let cached = stack.gitlab_project.as_ref()?;
+        if cached.default_branch != stack.base_branch {
+            return None;
+        }
+        let project = self.gitlab().await?.detect_and_cache_project().await.ok()?;
+        (project.default_branch != stack.base_branch).then_some(project.default_branch)
+    }
+
+    /// Select (or list) the named MR template for the stack or one of its branches
+    pub async fn set_mr_template(
+        &mut self,
+        name: Option<&str>,
+        branch: Option<&str>,
+        clear: bool,
+    ) -> Result<()> {
+        print_train_header("Merge Request Templates");
+
+        let mut stack = self.get_or_load_current_stack()?;
+        let templates = self.available_mr_templates()?;
+
+        if name.is_none() && !clear {
+            if templates.is_empty() {
+                print_info("No templates found in .gitlab/merge_request_templates/");
+            } else {
+                for template in &templates {
+                    ui::print_info(&format!("  - {}", template));
+                }
+            }
+            ui::print_config_item(
+                "Stack template",
+                stack.mr_template.as_deref().unwrap_or("(none)"),
+            );
+            let mut branch_names: Vec<&BranchName> = stack.branches.keys().collect();
+            branch_names.sort();
+            for branch_name in branch_names {
+                if let Some(template) = &stack.branches[branch_name].mr_template {
+                    ui::print_config_item(branch_name, template);
+                }
+            }
+            return Ok(());
+        }
+
+        let selection = match name {
+            Some(name) => {
+                let template = templates
+                    .iter()
+                    .find(|t| t.eq_ignore_ascii_case(name))
+                    .cloned()
+                    .ok_or_else(|| TrainError::StackError {
+                        message: format!(
+                            "Template '{}' not found. Available: {}",
+                            name,
+                            if templates.is_empty() {
+                                "(none)".to_string()
+                            } else {
+                                templates.join(", ")
+                            }
+                        ),
+                    })?;
+                Some(template)
+            }
+            None => None,
+        };
+
+        match branch {
+            Some(branch_name) => {
+                let stack_branch =
+                    stack
+                        .branches
+                        .get_mut(branch_name)
+                        .ok_or_else(|| TrainError::StackError {
+                            message: format!("Branch '{}' not found in stack", branch_name),
+                        })?;
+                stack_branch.mr_template = selection.clone();
+                stack_branch.updated_at = Utc::now();
+            }
+            None => stack.mr_template = selection.clone(),
+        }
+        stack.updated_at = Utc::now();
+
+        self.stack_state.save_stack(&stack)?;
+        self.current_stack = Some(stack);
+
+        let scope = branch
+            .map(|b| format!("branch '{}'", b))
+            .unwrap_or_else(|| "the stack".to_string());
+        match selection {
+            Some(template) => print_success(&format!(
+                "New MRs for {} will use template '{}'",
+                scope, template
+            )),
+            None => print_success(&format!("Cleared MR template for {}", scope)),
+        }
+        Ok(())
+    }
+
+    /// Show or edit the local review notes of a stack branch (defaults to the current branch)
+    pub async fn review_notes(
+        &mut self,
+        action: ReviewNoteAction,
+        branch: Option<&str>,
+    ) -> Result<()> {
+        let mut stack = self.get_or_load_current_stack()?;
+        let branch_name = match branch {
+            Some(branch) => branch.to_string(),
+            None => self.get_current_branch()?,
+        };
+        let stack_branch =
+            stack
+                .branches
+                .get_mut(&branch_name)
+                .ok_or_else(|| TrainError::StackError {
+                    message: format!("Branch '{}' not found in stack", branch_name),
+                })?;
+
+        let item_count = stack_branch.review_notes.len();
+        let check_index = |number: usize| -> Result<usize> {
+            if number == 0 || number > item_count {
+                return Err(TrainError::StackError {
+                    message: format!(
+                        "No review note #{} on '{}' ({} notes)",
+                        number, branch_name, item_count
+                    ),
+                }
+                .into());
+            }
+            Ok(number - 1)
+        };
+
+        match action {
+            ReviewNoteAction::Show => {
+                print_train_header(&format!("Review Notes: {}", branch_name));
+                print_review_notes(stack_branch);
+                return Ok(());
+            }
+            ReviewNoteAction::Add { text, todo } => {
+                stack_branch.review_notes.push(ReviewNote {
+                    text,
+                    todo,
+                    done: false,
+                    created_at: Utc::now(),
+                });
+                print_success(&format!(
+                    "Added {} #{} to {}",
+                    if todo { "TODO" } else { "note" },
+                    stack_branch.review_notes.len(),
+                    branch_name
+                ));
+            }
+            ReviewNoteAction::Check(number) | ReviewNoteAction::Uncheck(number) => {
+                let done = matches!(action, ReviewNoteAction::Check(_));
+                let note = &mut stack_branch.review_notes[check_index(number)?];
+                note.todo = true;
+                note.done = done;
+                print_success(&format!(
+                    "Marked #{} as {}",
+                    number,
+                    if done { "done" } else { "not done" }
+                ));
+            }
+            ReviewNoteAction::Remove(number) => {
+                let removed = stack_branch.review_notes.remove(check_index(number)?);
+                print_success(&format!("Removed: {}", removed.text));
+            }
+            ReviewNoteAction::Clear => {
+                stack_branch.review_notes.clear();
+                print_success(&format!("Cleared review notes for {}", branch_name));
+            }
+        }
+
+        let branch_snapshot = stack_branch.clone();
+        self.stack_state.save_stack(&stack)?;
+        self.current_stack = Some(stack);
+
+        if self.config.mr.sync_review_notes {
+            if let Err(e) = self.sync_review_notes_to_mr(&branch_snapshot).await {
+                print_warning(&format!("Could not sync review notes to MR: {}", e));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Mirror a branch's review notes into its MR description
+    async fn sync_review_notes_to_mr(&self, branch: &StackBranch) -> Result<()> {
+        let (Some(gitlab), Some(iid)) = (self.gitlab().await, branch.mr_iid) else {
+            return Ok(());
+        };
+
+        let mr = gitlab.get_merge_request(iid).await?;
+        let section = markdown::build_review_notes_section(branch);
+        let description = markdown::update_review_notes(&mr.description, section.as_deref());
+        if mr.description.as_deref() != Some(description.as_str()) {
+            gitlab
+                .update_merge_request(iid, None, Some(description))
+                .await?;
+            print_info(&format!("Synced review notes to MR !{}", iid));
+        }
+        Ok(())
+    }
+
+    pub async fn show_status(&mut self) -> Result<()> {
+        print_train_header("Stack Status");
+
+        let mut stack = self.get_or_load_current_stack()?;
+
+        if let Ok(Some(head)) = self.git_repo.detached_head() {
+            print_warning(&format!(
+                "HEAD is detached at {}; showing the stack from saved metadata",
+                head
+            ));
+        }
+        if let Ok(Some(session)) = self.conflict_resolver.load_session() {
+            print_warning(&format!(
+                "A conflict resolution is in progress ({}/{} files resolved); resume it with 'git-train continue'",
+                session.resolved_count(),
+                session.files.len()
+            ));
+        }
+
+        ui::print_info(&format!("Stack: {} ({})", stack.name, stack.id.short()));
+        ui::print_info(&format!("Repository: {}", self.git_repo.root().display()));
+        ui::print_info(&format!("Base branch: {}", stack.base_branch));
+        ui::print_info(&format!(
+            "Update strategy: {}",
+            stack.update_strategy.as_str()
+        ));
+
+        if let Some(project) = &stack.gitlab_project {
+            ui::print_info(&format!(
+                "GitLab project: {}/{} (ID: {})",
+                project.namespace.path, project.path, project.id
+            ));
+            ui::print_info(&format!("Project URL: {}", project.web_url));
+        }
+
+        ui::print_info(&format!(
+            "Created: {}",
+            stack.created_at.format("%Y-%m-%d %H:%M:%S UTC")
+        ));
+        ui::print_info(&format!(
+            "Updated: {}",
+            stack.updated_at.format("%Y-%m-%d %H:%M:%S UTC")
+        ));
+        ui::print_info("");
+
+        // Build branch hierarchy and collect MR status
+        let hierarchy = self.build_branch_hierarchy(&stack);
+        let branch_mr_status = self.collect_mr_status_info(&stack).await;
+        if self.record_mr_states(&mut stack, &branch_mr_status) {
+            self.stack_state.save_stack(&stack)?;
+            self.current_stack = Some(stack.clone());
+        }
+        self.print_branch_table(&hierarchy, &stack, &branch_mr_status);
+        let (merged, total) = stack.merge_progress();
+        ui::print_info(&format!("Merged: {}/{} levels", merged, total));
+
+        self.warn_about_oversized_levels(&stack);
+
+        // Review apps let reviewers click through to each level's deployment
+        let review_apps = self.collect_review_apps(&hierarchy, &stack).await;
+        if !review_apps.is_empty() {
+            ui::print_info("");
+            ui::print_info("Review apps:");
+            for (branch, environment, url) in &review_apps {
+                ui::print_info(&format!("  {} ({}): {}", branch, environment, url));
+            }
+        }
+
+        // A level can be clean locally and still conflict with its target on GitLab
+        let mut conflicted: Vec<(&String, &MrStatusInfo)> = branch_mr_status
+            .iter()
+            .filter(|(_, status)| status.conflicts && status.state == "opened")
+            .collect();
+        if !conflicted.is_empty() {
+            conflicted.sort_by_key(|(branch, _)| *branch);
+            ui::print_info("");
+            for (branch, status) in &conflicted {
+                print_warning(&format!(
+                    "MR !{} for '{}' conflicts with its target '{}' on GitLab",
+                    status.iid,
+                    branch,
+                    status.target_branch.as_deref().unwrap_or("?")
+                ));
+            }
+            print_info(
+                "Run 'git-train sync' and 'git-train push' to rebase them onto their targets",
+            );
+        }
+
+        // Surface MRs retargeted on GitLab since git-train last set them
+        let retargeted = unexpected_retargets(&stack, &branch_mr_status);
+        if !retargeted.is_empty() {
+            ui::print_info("");
+            for retarget in &retargeted {
+                print_warning(&format!(
+                    "MR !{} for '{}' targets '{}' on GitLab, but git-train last set '{}'",
+                    retarget.mr_iid, retarget.branch, retarget.actual, retarget.expected
+                ));
+            }
+            print_info("Run 'git-train push' to accept or correct the manual retargets");
+        }
+
+        // Surface branches that were rewritten outside git-train
+        let diverged = self.detect_diverged_branches(&stack);
+        if !diverged.is_empty() {
+            ui::print_info("");
+            for (branch, recorded, actual) in &diverged {
+                print_warning(&format!(
+                    "Branch '{}' moved outside git-train (recorded {}, actual {})",
+                    branch,
+                    &recorded[..8.min(recorded.len())],
+                    &actual[..8]
+                ));
+            }
+            print_info("Run 'git-train sync' to restack children onto the new commits");
+        }
+
+        // Stack tables in MR descriptions that no longer match the stack
+        let stale = self.stale_mr_descriptions().await?;
+        if !stale.is_empty() {
+            ui::print_info("");
+            for (branch, iid, reason) in &stale {
+                print_warning(&format!(
+                    "MR !{} for '{}' has an outdated stack table ({})",
+                    iid, branch, reason
+                ));
+            }
+            if confirm_action(&format!("Refresh {} stale MR descriptions?", stale.len()))
+                .unwrap_or(false)
+            {
+                let iids: Vec<MrIid> = stale.iter().map(|(_, iid, _)| *iid).collect();
+                let mut stack = stack.clone();
+                self.update_mr_descriptions(&mut stack, Some(&iids)).await;
+                self.stack_state.save_stack(&stack)?;
+                self.current_stack = Some(stack);
+            } else {
+                print_info("Run 'git-train mr update-all' to refresh them");
+            }
+        }
+
+        // Show working directory status
+        let status_output = self.git_repo.run(&["status", "--porcelain"])?;
+        if !status_output.is_empty() {
+            ui::print_info("\nWorking directory status:");
+            ui::print_info(&status_output);
+        }
+
+        Ok(())
+    }
+
+    /// Redraw a compact stack status every `interval` until Ctrl-C, highlighting what
+    /// changed since the previous refresh. Never prompts.
+    pub async fn watch_status(&mut self, interval: Duration) -> Result<()> {
+        let mut previous = None;
+        loop {
+            let (snapshot, _) = self.watch_frame(previous.as_ref(), interval).await?;
+            previous = Some(snapshot);
+            tokio::select! {
+                _ = tokio::time::sleep(interval) => {}
+                _ = tokio::signal::ctrl_c() => return Ok(()),
+            }
+        }
+    }
+
+    /// Render one `status --watch` refresh. Returns the state it showed, to compare the
+    /// next refresh against, and the changes since `previous`.
+    pub async fn watch_frame(
+        &mut self,
+        previous: Option<&WatchSnapshot>,
+        interval: Duration,
+    ) -> Result<(WatchSnapshot, Vec<String>)> {
+        // Pick up changes made by other git-train commands since the last refresh
+        self.current_stack = None;
+        let mut stack = self.get_or_load_current_stack()?;
+        let _ = self
+            .git_repo
+            .run(&["fetch", "--quiet", "origin", &stack.base_branch]);
+
+        let mut branch_mr_status = self.collect_mr_status_info(&stack).await;
+        let mut retarget_changes = Vec::new();
+
+        // Writes wait for commands changing the stack in other terminals; while one
+        // holds the lock they are left for a later refresh
+        if let Ok(_lock) = self.lock_operation("status --watch", false) {
+            self.current_stack = None;
+            stack = self.get_or_load_current_stack()?;
+            let mut changed = self.record_mr_states(&mut stack, &branch_mr_status);
+
+            // Open MRs still targeting a merged level would be closed by GitLab once
+            // its branch is deleted; point them at where that level was merged
+            let mut retargets = Vec::new();
+            for (merged, status) in &branch_mr_status {
+                let Some(target) = status.target_branch.as_deref() else {
+                    continue;
+                };
+                if status.state != "merged" {
+                    continue;
+                }
+                let children: Vec<String> = child_branches(&stack, merged)
+                    .into_iter()
+                    .filter(|child| {
+                        branch_mr_status.get(child).is_some_and(|s| {
+                            s.state == "opened" && s.target_branch.as_deref() == Some(merged)
+                        })
+                    })
+                    .collect();
+                if !children.is_empty() {
+                    retargets.push((merged.clone(), children, target.to_string()));
+                }
+            }
+            for (merged, children, target) in retargets {
+                self.retarget_children_of_merged(&mut stack, &merged, &children, &target)
+                    .await;
+                for child in children {
+                    if stack.branches[child.as_str()].last_set_target() == Some(target.as_str()) {
+                        if let Some(status) = branch_mr_status.get_mut(&child) {
+                            status.target_branch = Some(target.clone());
+                        }
+                        retarget_changes.push(format!(
+                            "Retargeted MR for '{}' to '{}' after '{}' merged",
+                            child, target, merged
+                        ));
+                        changed = true;
+                    }
+                }
+            }
+            if changed {
+                self.stack_state.save_stack(&stack)?;
+                self.current_stack = Some(stack.clone());
+            }
+        }
+
+        let snapshot = WatchSnapshot {
+            base_head: self
+                .git_repo
+                .run(&["rev-parse", &format!("origin/{}", stack.base_branch)])
+                .ok()
+                .map(|hash| hash.trim().to_string()),
+            branch_heads: stack
+                .branches
+                .keys()
+                .filter_map(|name| {
+                    let hash = self.git_repo.get_commit_hash_for_branch(name).ok()?;
+                    Some((name.to_string(), hash))
+                })
+                .collect(),
+            mr_states: branch_mr_status
+                .iter()
+                .map(|(branch, status)| (branch.clone(), (status.iid, status.state.clone())))
+                .collect(),
+        };
+        let mut changes = previous
+            .map(|previous| self.watch_changes(&stack, previous, &snapshot))
+            .unwrap_or_default();
+        changes.extend(retarget_changes);
+
+        if console::Term::stdout().is_term() {
+            let _ = console::Term::stdout().clear_screen();
+        }
+        print_train_header(&format!("Stack: {}", stack.name));
+        print_info(&format!(
+            "Refreshed {} every {}s (Ctrl-C to stop)",
+            chrono::Local::now().format("%H:%M:%S"),
+            interval.as_secs()
+        ));
+        let hierarchy = self.build_branch_hierarchy(&stack);
+        self.print_branch_table(&hierarchy, &stack, &branch_mr_status);
+        let (merged, total) = stack.merge_progress();
+        print_info(&format!("Merged: {}/{} levels", merged, total));
+
+        if !changes.is_empty() {
+            print_info("");
+            print_info("Since the last refresh:");
+            for change in &changes {
+                ui::print_change(change);
+            }
+        }
+
+        Ok((snapshot, changes))
+    }
+
+    /// Describe the differences between two watch refreshes, base first, then each
+    /// level in stack order
+    fn watch_changes(
+        &self,
+        stack: &Stack,
+        previous: &WatchSnapshot,
+        current: &WatchSnapshot,
+    ) -> Vec<String> {
+        let mut changes = Vec::new();
+
+        if let (Some(old), Some(new)) = (&previous.base_head, &current.base_head) {
+            if old != new {
+                let count = self
+                    .git_repo
+                    .run(&["rev-list", "--count", &format!("{}..{}", old, new)])
+                    .ok()
+                    .and_then(|count| count.trim().parse::<usize>().ok())
+                    .unwrap_or(0);
+                changes.push(format!(
+                    "{} new commit{} on base '{}'",
+                    count,
+                    if count == 1 { "" } else { "s" },
+                    stack.base_branch
+                ));
+            }
+        }
+
+        for (branch, _) in self.levels_in_order(stack) {
+            match (
+                previous.branch_heads.get(&branch),
+                current.branch_heads.get(&branch),
+            ) {
+                (None, Some(_)) => changes.push(format!("'{}' joined the stack", branch)),
+                (Some(old), Some(new)) if old != new => changes.push(format!(
+                    "'{}' moved from {} to {}",
+                    branch,
+                    &old[..8.min(old.len())],
+                    &new[..8.min(new.len())]
+                )),
+                _ => {}
+            }
+
+            match (
+                previous.mr_states.get(&branch),
+                current.mr_states.get(&branch),
+            ) {
+                (None, Some((iid, state))) => {
+                    changes.push(format!("MR !{} for '{}' is {}", iid, branch, state))
+                }
+                (Some((_, old)), Some((iid, new))) if old != new => {
+                    changes.push(format!("MR !{} for '{}': {} → {}", iid, branch, old, new))
+                }
+                _ => {}
+            }
+        }
+
+        changes
+    }
+
+    pub async fn navigate_stack_interactively(&mut self) -> Result<()> {
+        // MR statuses from the last refresh; the menu renders from these right away and
+        // a throttled refresh runs while it waits for input
+        let mut mr_status_cache: HashMap<String, MrStatusInfo> = HashMap::new();
+        let mut last_refresh: Option<Instant> = None;
+
+        loop {
+            // Load current stack state
+            let stack = match self.get_or_load_current_stack() {
+                Ok(stack) => {
+                    self.current_stack = Some(stack.clone());
+                    stack
+                }
+                Err(_) => {
+                    print_warning(
+                        "No active stack found. Please create or switch to a stack first.",
+                    );
+                    return Ok(());
+                }
+            };
+
+            print_train_header(&format!("Navigate Stack: {}", stack.name));
+
+            // Get current git branch
+            let current_git_branch = self.get_current_branch().ok();
+
+            // Collect all branches in the stack
+            let mut branches: Vec<String> = stack.branches.keys().map(|b| b.to_string()).collect();
+            branches.sort();
+
+            // Show the last known MR statuses, marking them stale when a refresh is due
+            let refresh_due = self.gitlab().await.is_some()
+                && last_refresh.is_none_or(|at| at.elapsed() >= MR_STATUS_REFRESH_INTERVAL);
+            let mut branch_mr_status =
+                Self::stored_mr_status(&stack, self.gitlab().await.is_some());
+            for (branch_name, status) in branch_mr_status.iter_mut() {
+                if let Some(cached) = mr_status_cache.get(branch_name) {
+                    *status = MrStatusInfo {
+                        stale: refresh_due,
+                        ..cached.clone()
+                    };
+                }
+            }
+
+            // Create navigation options
+            let options = ui::create_navigation_options(
+                &branches,
+                current_git_branch.as_deref(),
+                &branch_mr_status,
+            );
+
+            // Show interactive menu, refreshing MR statuses while it waits for input
+            let menu = tokio::task::spawn_blocking(move || {
+                ui::interactive_stack_navigation(&options, "Select an action:")
+            });
+            let selection = if refresh_due {
+                let (selection, fresh) = tokio::join!(menu, self.collect_mr_status_info(&stack));
+                mr_status_cache = fresh;
+                last_refresh = Some(Instant::now());
+                selection?
+            } else {
+                menu.await?
+            };
+
+            match selection {
+                Ok(action) => {
+                    match action {
+                        ui::NavigationAction::SwitchToBranch(branch_name) => {
+                            if let Err(e) = self.switch_to_branch(&branch_name).await {
+                                print_error(&format!(
+                                    "Failed to switch to branch {}: {}",
+                                    branch_name, e
+                                ));
+                            } else if let Err(e) = self.record_current_branch() {
+                                print_warning(&format!("Could not record current branch: {}", e));
+                            }
+                        }
+                        ui::NavigationAction::ShowBranchInfo(branch_name) => {
+                            self.show_branch_info(&branch_name, &stack).await;
+                        }
+                        ui::NavigationAction::CreateMR(branch_name) => {
+                            if let Err(e) = self.create_mr_for_branch(&branch_name, &stack).await {
+                                print_error(&format!(
+                                    "Failed to create MR for {}: {}",
+                                    branch_name, e
+                                ));
+                            }
+                        }
+                        ui::NavigationAction::ViewMR(branch_name, mr_iid) => {
+                            self.view_mr_info(&branch_name, mr_iid, &stack).await;
+                        }
+                        ui::NavigationAction::RefreshStatus => {
+                            // Refresh in the foreground so the next menu is current
+                            mr_status_cache = self.collect_mr_status_info(&stack).await;
+                            last_refresh = Some(Instant::now());
+                            continue;
+                        }
+                        ui::NavigationAction::Exit => {
+                            print_info("Exiting navigation");
+                            break;
+                        }
+                    }
+                }
+                Err(_) => {
+                    // User cancelled (Ctrl+C or ESC)
+                    print_info("Navigation cancelled");
+                    break;
+                }
+            }
+
+            // Add a small pause for better UX
+            println!();
+        }
+
+        Ok(())
+    }
+
+    /// Check out the deepest descendant of the current branch (of the whole stack when
+    /// HEAD is not on a stack branch). When the chain forks, the leaves are offered in
+    /// name order. Returns the branch that ends up checked out.
+    pub async fn checkout_top(&mut self) -> Result<String> {
+        let stack = self.get_or_load_current_stack()?;
+        let current = self.get_current_branch()?;
+        let hierarchy = self.build_branch_hierarchy(&stack);
+
+        let start = if stack.branches.contains_key(&current) {
+            current
+        } else {
+            stack.base_branch.to_string()
+        };
+        let mut leaves = Vec::new();
+        let mut visited = std::collections::HashSet::new();
+        let mut pending = vec![start];
+        while let Some(branch) = pending.pop() {
+            if !visited.insert(branch.clone()) {
+                continue;
+            }
+            match hierarchy.get(&branch) {
+                Some(children) if !children.is_empty() => pending.extend(children.clone()),
+                _ if stack.branches.contains_key(&branch) => leaves.push(branch),
+                _ => {}
+            }
+        }
+
+        let top = self.pick_stack_end(leaves, "Which top do you want to check out?")?;
+        self.checkout_stack_end(&top).await?;
+        Ok(top)
+    }
+
+    /// Check out the first level above the base in the current branch's chain (asking
+    /// among the stack's roots when HEAD is not on a stack branch). Returns the branch
+    /// that ends up checked out.
+    pub async fn checkout_bottom(&mut self) -> Result<String> {
+        let stack = self.get_or_load_current_stack()?;
+        let current = self.get_current_branch()?;
+
+        let is_root = |branch: &StackBranch| {
+            branch
+                .parent
+                .as_ref()
+                .is_none_or(|parent| !stack.branches.contains_key(parent))
+        };
+        let candidates = match stack.branches.get(current.as_str()) {
+            Some(mut branch) => {
+                let mut visited = std::collections::HashSet::new();
+                while !is_root(branch) && visited.insert(branch.name.clone()) {
+                    let parent = branch.parent.as_ref().expect("non-root has a parent");
+                    branch = &stack.branches[parent];
+                }
+                vec![branch.name.to_string()]
+            }
+            None => stack
+                .branches
+                .values()
+                .filter(|branch| is_root(branch))
+                .map(|branch| branch.name.to_string())
+                .collect(),
+        };
+
+        let bottom = self.pick_stack_end(candidates, "Which bottom do you want to check out?")?;
+        self.checkout_stack_end(&bottom).await?;
+        Ok(bottom)
+    }
+
+    fn pick_stack_end(&self, mut candidates: Vec<String>, prompt: &str) -> Result<String> {
+        candidates.sort();
+        match candidates.len() {
+            0 => Err(TrainError::StackError {
+                message: "Stack has no branches".to_string(),
+            }
+            .into()),
+            1 => Ok(candidates.remove(0)),
+            _ => {
+                let choice = ui::select_from_list(&candidates, prompt)?;
+                Ok(candidates.remove(choice))
+            }
+        }
+    }
+
+    async fn checkout_stack_end(&self, branch_name: &str) -> Result<()> {
+        if self.get_current_branch().ok().as_deref() == Some(branch_name) {
+            print_info(&format!("Already on {}", branch_name));
+            return Ok(());
+        }
+        self.switch_to_branch(branch_name).await
+    }
+
+    async fn switch_to_branch(&self, branch_name: &str) -> Result<()> {
+        // Ensure working directory is clean
+        if self.ensure_clean_working_directory().is_err() {
+            print_warning("Working directory is not clean. Stashing changes...");
+            self.git_repo
+                .run(&["stash", "push", "-m", "git-train navigation stash"])?;
+        }
+
+        // Switch to the branch
+        self.git_repo.run(&["checkout", branch_name])?;
+        print_success(&format!("Switched to branch: {}", branch_name));
+
+        Ok(())
+    }
+
+    async fn show_branch_info(&self, branch_name: &str, stack: &Stack) {
+        print_train_header(&format!("Branch Info: {}", branch_name));
+
+        if let Some(branch) = stack.branches.get(branch_name) {
+            ui::print_info(&format!("Branch: {}", branch.name));
+            ui::print_info(&format!(
+                "Parent: {}",
+                branch.parent.as_deref().unwrap_or(&stack.base_branch)
+            ));
+            if !branch.commit_hash.is_empty() {
+                ui::print_info(&format!(
+                    "Commit: {}",
+                    &branch.commit_hash[..8.min(branch.commit_hash.len())]
+                ));
+            }
+            ui::print_info(&format!(
+                "Created: {}",
+                branch.created_at.format("%Y-%m-%d %H:%M:%S UTC")
+            ));
+            ui::print_info(&format!(
+                "Updated: {}",
+                branch.updated_at.format("%Y-%m-%d %H:%M:%S UTC")
+            ));
+
+            if let Some(mr_iid) = branch.mr_iid {
+                ui::print_info(&format!("Merge Request: !{}", mr_iid));
+                if let Some(project) = &stack.gitlab_project {
+                    ui::print_info(&format!(
+                        "MR URL: {}/merge_requests/{}",
+                        project.web_url, mr_iid
+                    ));
+                }
+            } else {
+                ui::print_info("Merge Request: Not created");
+            }
+
+            // Show children if any
+            let hierarchy = self.build_branch_hierarchy(stack);
+            if let Some(children) = hierarchy.get(branch_name) {
+                if !children.is_empty() {
+                    ui::print_info(&format!("Children: {}", children.join(", ")));
+                }
+            }
+
+            if !branch.review_notes.is_empty() {
+                ui::print_info("Review notes:");
+                print_review_notes(branch);
+            }
+
+            if !branch.target_history.is_empty() {
+                ui::print_info("MR target history:");
+                for change in branch.target_history.iter().rev().take(5) {
+                    println!(
+                        "  {} {} → {} ({})",
+                        change.timestamp.format("%Y-%m-%d %H:%M"),
+                        change.from.as_deref().unwrap_or("(created)"),
+                        change.to,
+                        change.reason
+                    );
+                }
+            }
+
+            // Show commit info
+            if let Ok(commit_info) =
+                self.git_repo
+                    .run(&["show", "--oneline", "-s", &branch.commit_hash])
+            {
+                ui::print_info(&format!("Commit info: {}", commit_info));
+            }
+        } else {
+            print_error(&format!("Branch '{}' not found in stack", branch_name));
+        }
+
+        ui::print_info("\nPress Enter to continue...");
+        let _ = std::io::stdin().read_line(&mut String::new());
+    }
+
+    async fn create_mr_for_branch(&mut self, branch_name: &str, stack: &Stack) -> Result<()> {
+        if let Some(branch) = stack.branches.get(branch_name) {
+            let mut stack_mut = stack.clone();
+            self.create_or_update_mr_with_smart_targeting_and_store(
+                branch_name,
+                branch,
+                &mut stack_mut,
+                None,
+            )
+            .await?;
+
+            // Save the updated stack
+            self.stack_state.save_stack(&stack_mut)?;
+            self.current_stack = Some(stack_mut);
+
+            print_success(&format!(
+                "MR creation initiated for branch: {}",
+                branch_name
+            ));
+        } else {
+            print_error(&format!("Branch '{}' not found in stack", branch_name));
+        }
+
+        Ok(())
+    }
+
+    async fn view_mr_info(&self, branch_name: &str, mr_iid: MrIid, stack: &Stack) {
+        print_train_header(&format!("MR Info: !{} ({})", mr_iid, branch_name));
+
+        if let Some(gitlab_client) = self.gitlab().await {
+            match gitlab_client.get_merge_request(mr_iid).await {
+                Ok(mr) => {
+                    ui::print_info(&format!("Title: {}", mr.title));
+                    ui::print_info(&format!("State: {}", mr.state));
+                    ui::print_info(&format!("Source: {}", mr.source_branch));
+                    ui::print_info(&format!("Target: {}", mr.target_branch));
+                    ui::print_info(&format!("ID: {}", mr.id));
+                    ui::print_info(&format!("IID: {}", mr.iid));
+
+                    if let Some(project) = &stack.gitlab_project {
+                        ui::print_info(&format!(
+                            "URL: {}/merge_requests/{}",
+                            project.web_url, mr.iid
+                        ));
+                    }
+
+                    if let Some(description) = &mr.description {
+                        if !description.is_empty() {
+                            ui::print_info("\nDescription:");
+                            ui::print_info(description);
+                        }
+                    }
+                }
+                Err(e) => {
+                    print_error(&format!("Failed to fetch MR info: {}", e));
+                }
+            }
+        } else {
+            print_error("GitLab client not available");
+        }
+
+        ui::print_info("\nPress Enter to continue...");
+        let _ = std::io::stdin().read_line(&mut String::new());
+    }
+
+    pub async fn push_stack(&mut self) -> Result<()> {
+        self.push_stack_with_options(&PushOptions::default()).await
+    }
+
+    /// Fetch the base and run a sync when any branch is behind its parent, so a push
+    /// never opens MRs that GitLab immediately reports as conflicting
+    async fn sync_if_behind(&mut self, range: Option<&BranchRange>) -> Result<()> {
+        let stack = self.get_or_load_current_stack()?;
+        if let Err(e) = self.git_repo.run(&["fetch", "origin", &stack.base_branch]) {
+            print_warning(&format!("Could not fetch '{}': {}", stack.base_branch, e));
+        }
+
+        let mut behind: Vec<&str> = stack
+            .branches
+            .values()
+            .filter(|branch| {
+                let parent = branch.parent.as_deref().unwrap_or(&stack.base_branch);
+                self.ahead_behind(&branch.name, parent)
+                    .is_some_and(|(behind, _)| behind > 0)
+            })
+            .map(|branch| branch.name.as_str())
+            .collect();
+        if behind.is_empty() {
+            return Ok(());
+        }
+        behind.sort();
+        print_info(&format!(
+            "Behind their parent: {}; syncing before push",
+            behind.join(", ")
+        ));
+        self.sync_with_remote_with_options(&SyncOptions {
+            range: range.cloned(),
+            ..Default::default()
+        })
+        .await
+    }
+
+    pub async fn push_stack_with_options(&mut self, options: &PushOptions) -> Result<()> {
+        let stack_name = self.current_stack_name();
+        events::emit(&Event::OperationStarted {
+            operation: "push",
+            stack: &stack_name,
+        });
+        let result = self.push_stack_branches(options).await;
+        events::emit(&Event::OperationFinished {
+            operation: "push",
+            success: result.is_ok(),
+        });
+        result
+    }
+
+    async fn push_stack_branches(&mut self, options: &PushOptions) -> Result<()> {
+        if options.sync || self.config.push.auto_sync {
+            self.sync_if_behind(options.range.as_ref()).await?;
+        }
+
+        print_train_header("Pushing Stack");
+
+        let mut stack = self.get_or_load_current_stack()?;
+        let deleted = self.deleted_local_branches(&stack);
+        let mut push_failures = Vec::new();
+        let mut successful_pushes = Vec::new();
+
+        self.warn_about_oversized_levels(&stack);
+
+        let branch_mr_status = if options.no_mr {
+            HashMap::new()
+        } else {
+            self.collect_mr_status_info(&stack).await
+        };
+
+        let selection = self.resolve_branch_range(&stack, options.range.as_ref())?;
+        if let Some(selection) = &selection {
+            let mut names: Vec<&str> = selection.iter().map(String::as_str).collect();
+            names.sort();
+            print_info(&format!("Limiting push to: {}", names.join(", ")));
+        }
+
+        // Push all branches in the stack
+        for branch_name in stack.branches.keys() {
+            if selection
+                .as_ref()
+                .is_some_and(|selection| !selection.contains(branch_name.as_str()))
+            {
+                continue;
+            }
+            if let Some(status) = branch_mr_status.get(branch_name.as_str()) {
+                if status.state == "merged" {
+                    print_info(&format!(
+                        "Skipping push for branch '{}' as its MR !{} is already merged.",
+                        branch_name, status.iid
+                    ));
+                    continue;
+                }
+            }
+            match stack.branches[branch_name].state {
+                BranchState::MrMerged => {
+                    print_info(&format!(
+                        "Skipping push for branch '{}' as its MR is already merged.",
+                        branch_name
+                    ));
+                    continue;
+                }
+                BranchState::Frozen => {
+                    print_info(&format!(
+                        "Skipping push for branch '{}' as it is frozen.",
+                        branch_name
+                    ));
+                    continue;
+                }
+                BranchState::LocalOnly => {
+                    print_info(&format!(
+                        "Skipping push for branch '{}' as it is private (run 'git-train publish {}' when it's ready).",
+                        branch_name, branch_name
+                    ));
+                    continue;
+                }
+                BranchState::Paused => {
+                    print_info(&format!(
+                        "Skipping push for branch '{}' as it is paused (run 'git-train branch resume {}' to push it again).",
+                        branch_name, branch_name
+                    ));
+                    continue;
+                }
+                _ => {}
+            }
+            if deleted.iter().any(|b| b == branch_name) {
+                push_failures.push((
+                    branch_name.clone(),
+                    "Deleted locally (run 'git-train repair')".to_string(),
+                ));
+                continue;
+            }
+            print_info(&format!("Pushing branch: {}", branch_name));
+
+            // First try a normal push
+            match self.git_repo.run(&[
+                "push",
+                "origin",
+                &format!("{}:{}", branch_name, branch_name),
+            ]) {
+                Ok(_) => {
+                    print_success(&format!("Pushed {}", branch_name));
+                    events::emit(&Event::BranchPushed {
+                        branch: branch_name,
+                        forced: false,
+                    });
+                    successful_pushes.push(branch_name.clone());
+                }
+                Err(e) => {
+                    // Check if this is a non-fast-forward error (common after rebase)
+                    let error_msg = format!("{}", e);
+                    let rejected =
+                        error_msg.contains("non-fast-forward") || error_msg.contains("rejected");
+                    if rejected && stack.update_strategy == UpdateStrategy::Merge {
+                        // Merge-based stacks only ever add commits, so a rejection means
+                        // the remote has work we don't; never overwrite it
+                        print_warning(&format!(
+                            "Branch {} was rejected: the remote has commits that are not in the local branch",
+                            branch_name
+                        ));
+                        print_info("Merge-based stacks never force-push. Run 'git-train sync' to merge them in first.");
+                        push_failures.push((
+                            branch_name.clone(),
+                            "Remote has new commits (merge strategy never force-pushes)"
+                                .to_string(),
+                        ));
+                    } else if rejected {
+                        print_warning(&format!(
+                            "Branch {} was rejected (non-fast-forward)",
+                            branch_name
+                        ));
+                        print_info(
+                            "This is common after rebasing. Checking if force-push is safe...",
+                        );
+
+                        // Check if we should force push safely
+                        if self.should_force_push_branch(branch_name, &stack).await? {
+                            match self.force_push_with_lease(branch_name, &stack).await {
+                                Ok(_) => {
+                                    print_success(&format!("Force-pushed {} safely", branch_name));
+                                    events::emit(&Event::BranchPushed {
+                                        branch: branch_name,
+                                        forced: true,
+                                    });
+                                    successful_pushes.push(branch_name.clone());
+                                }
+                                Err(force_err) => {
+                                    print_error(&format!(
+                                        "Force-push failed for {}: {}",
+                                        branch_name, force_err
+                                    ));
+                                    print_warning("This might mean someone else pushed changes. Manual intervention required.");
+                                    push_failures
+                                        .push((branch_name.clone(), format!("{}", force_err)));
+                                }
+                            }
+                        } else {
+                            print_warning(&format!(
+                                "Skipping force-push for {} (safety check failed)",
+                                branch_name
+                            ));
+                            push_failures.push((
+                                branch_name.clone(),
+                                "Force-push deemed unsafe".to_string(),
+                            ));
+                        }
+                    } else {
+                        print_error(&format!("Failed to push {}: {}", branch_name, e));
+                        push_failures.push((branch_name.clone(), format!("{}", e)));
+                    }
+                }
+            }
+        }
+
+        self.record_mr_states(&mut stack, &branch_mr_status);
+        self.record_remote_shas(&mut stack, &successful_pushes);
+
+        if self.config.git.set_upstream {
+            for branch_name in &successful_pushes {
+                if let Err(e) = self.set_upstream(branch_name) {
+                    print_warning(&format!(
+                        "Could not set the upstream of '{}': {}",
+                        branch_name, e
+                    ));
+                }
+            }
+        }
+
+        // Report results
+        if !successful_pushes.is_empty() {
+            print_success(&format!(
+                "Successfully pushed {} branches: {}",
+                successful_pushes.len(),
+                successful_pushes.join(", ")
+            ));
+        }
+
+        // GitLab can't open or update an MR for a branch that never made it to the remote
+        let mut not_on_remote: Vec<String> = push_failures
+            .iter()
+            .filter(|(branch, _)| !self.git_repo.has_remote_branch(branch))
+            .map(|(branch, _)| branch.to_string())
+            .collect();
+        not_on_remote.sort();
+
+        if !push_failures.is_empty() {
+            print_warning(&format!("Failed to push {} branches:", push_failures.len()));
+            for (branch, error) in &push_failures {
+                ui::print_error(&format!("  ✘ {}: {}", branch, error));
+            }
+            if !options.no_mr && !not_on_remote.is_empty() {
+                print_warning(&format!(
+                    "Skipped merge requests for branches not on the remote: {}",
+                    not_on_remote.join(", ")
+                ));
+            }
+            print_info("You can:");
+            print_info("• Run 'git-train sync' to ensure branches are up to date");
+            print_info("• Force-push manually with 'git push --force-with-lease' if you're sure");
+            print_info("• Check for conflicts with remote changes");
+        }
+
+        if options.no_mr {
+            print_info("Skipped merge requests; run 'git-train mr update-all' to update them");
+        } else {
+            let mr_selection = if not_on_remote.is_empty() {
+                selection.clone()
+            } else {
+                Some(
+                    stack
+                        .branches
+                        .keys()
+                        .filter(|branch| {
+                            selection
+                                .as_ref()
+                                .is_none_or(|selection| selection.contains(branch.as_str()))
+                        })
+                        .filter(|branch| !not_on_remote.iter().any(|b| b == *branch))
+                        .map(|branch| branch.to_string())
+                        .collect(),
+                )
+            };
+
+            self.snapshot_mr_metadata(&stack, "push").await;
+
+            // Create or update merge requests with intelligent target branch selection
+            self.process_all_branches_for_mrs(
+                &mut stack,
+                "Updated merge request for",
+                options.template.as_deref(),
+                mr_selection.as_ref(),
+            )
+            .await;
+
+            let milestone = options.milestone.as_ref().or(self
+                .config
+                .mr
+                .default_milestone
+                .as_ref());
+            if let Some(milestone) = milestone {
+                self.assign_milestone(&stack, milestone).await;
+            }
+
+            self.update_all_mr_descriptions(&mut stack).await;
+        }
+
+        // Save the updated stack with MR IIDs
+        self.stack_state.save_stack(&stack)?;
+        self.current_stack = Some(stack);
+
+        if push_failures.is_empty() {
+            print_success("Stack pushed to remote successfully");
+        } else {
+            print_warning("Stack partially pushed to remote (some branches failed)");
+        }
+
+        Ok(())
+    }
+
+    /// Put every MR of the stack on the milestone titled `title`, skipping MRs that
+    /// are already on it
+    async fn assign_milestone(&self, stack: &Stack, title: &str) {
+        let Some(gitlab_client) = self.gitlab().await else {
+            return;
+        };
+        let milestone = match gitlab_client.find_milestone(title).await {
+            Ok(Some(milestone)) => milestone,
+            Ok(None) => {
+                print_warning(&format!(
+                    "No active milestone '{}' in the project or its groups",
+                    title
+                ));
+                return;
+            }
+            Err(e) => {
+                if !self.disable_gitlab_on_auth_error(&e) {
+                    print_warning(&format!("Could not look up milestone '{}': {}", title, e));
+                }
+                return;
+            }
+        };
+
+        let mut assigned = 0;
+        for branch in stack.branches.values() {
+            let Some(iid) = branch.mr_iid else {
+                continue;
+            };
+            match gitlab_client.get_merge_request(iid).await {
+                Ok(mr) if mr.state != "opened" => continue,
+                Ok(mr) if mr.milestone.as_ref().is_some_and(|m| m.id == milestone.id) => continue,
+                _ => {}
+            }
+            match gitlab_client
+                .set_merge_request_milestone(iid, milestone.id)
+                .await
+            {
+                Ok(_) => assigned += 1,
+                Err(e) if self.disable_gitlab_on_auth_error(&e) => break,
+                Err(e) => print_warning(&format!(
+                    "Could not set the milestone of MR !{}: {}",
+                    iid, e
+                )),
+            }
+        }
+        if assigned > 0 {
+            print_success(&format!(
+                "Assigned {} MRs to milestone '{}'",
+                assigned, milestone.title
+            ));
+        }
+    }
+
+    /// Create, retarget and describe the stack's merge requests without pushing.
+    ///
+    /// The counterpart of `push --no-mr`: branches that aren't on the remote yet are
+    /// reported, since GitLab can't open an MR for them.
+    pub async fn update_all_mrs(&mut self, template: Option<&str>) -> Result<()> {
+        print_train_header("Updating Merge Requests");
+
+        if self.gitlab().await.is_none() {
+            return Err(TrainError::GitLabError {
+                message: "GitLab is not configured; there are no merge requests to update"
+                    .to_string(),
+            }
+            .into());
+        }
+
+        let mut stack = self.get_or_load_current_stack()?;
+
+        let mut unpushed: Vec<&BranchName> = stack
+            .branches
+            .iter()
+            .filter(|(name, branch)| {
+                branch.state != BranchState::LocalOnly && !self.git_repo.has_remote_branch(name)
+            })
+            .map(|(name, _)| name)
+            .collect();
+        if !unpushed.is_empty() {
+            unpushed.sort();
+            print_warning(&format!(
+                "Not on the remote yet (run 'git-train push --no-mr' first): {}",
+                unpushed
+                    .iter()
+                    .map(|b| b.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+
+        self.snapshot_mr_metadata(&stack, "mr update-all").await;
+        self.process_all_branches_for_mrs(&mut stack, "Updated merge request for", template, None)
+            .await;
+        self.update_all_mr_descriptions(&mut stack).await;
+
+        self.stack_state.save_stack(&stack)?;
+        self.current_stack = Some(stack);
+
+        print_success("Merge requests updated");
+        Ok(())
+    }
+
+    /// Save the title, description and target of the stack's open MRs to the oplog
+    /// before `operation` changes them in bulk, so `git-train undo` can put them back
+    async fn snapshot_mr_metadata(&self, stack: &Stack, operation: &str) {
+        let Some(gitlab) = self.gitlab().await else {
+            return;
+        };
+        let branches: Vec<(&BranchName, MrIid)> = stack
+            .branches
+            .iter()
+            .filter_map(|(name, branch)| branch.mr_iid.map(|iid| (name, iid)))
+            .collect();
+        if branches.is_empty() {
+            return;
+        }
+        let results = future::join_all(
+            branches
+                .iter()
+                .map(|(_, iid)| gitlab.get_merge_request(*iid)),
+        )
+        .await;
+
+        let mut mrs = Vec::new();
+        for ((branch, iid), result) in branches.into_iter().zip(results) {
+            match result {
+                Ok(mr) if mr.state == "opened" => mrs.push(MrSnapshot {
+                    iid: mr.iid,
+                    branch: branch.clone(),
+                    title: mr.title,
+                    description: mr.description,
+                    target_branch: mr.target_branch,
+                }),
+                Ok(_) => {}
+                Err(e) if self.disable_gitlab_on_auth_error(&e) => return,
+                Err(e) => print_warning(&format!(
+                    "Could not save MR !{} before '{}' (undo won't restore it): {}",
+                    iid, operation, e
+                )),
+            }
+        }
+        if mrs.is_empty() {
+            return;
+        }
+        mrs.sort_by_key(|mr| mr.iid);
+
+        let entry = MrOperation {
+            timestamp: Utc::now(),
+            stack_id: stack.id.clone(),
+            operation: operation.to_string(),
+            mrs,
+        };
+        if let Err(e) = self.stack_state.append_oplog(&entry) {
+            print_warning(&format!("Could not record MR metadata for undo: {}", e));
+        }
+    }
+
+    /// Put the titles, descriptions and targets of the current stack's MRs back to what
+    /// they were before its last bulk MR update (push, `mr update-all`, retargets).
+    ///
+    /// Each undo steps one operation further back; MRs that were merged or closed in
+    /// the meantime are left alone.
+    pub async fn undo_mr_update(&mut self) -> Result<()> {
+        print_train_header("Undoing MR Update");
+
+        let mut stack = self.get_or_load_current_stack()?;
+        let Some(gitlab) = self.gitlab().await else {
+            return Err(TrainError::GitLabError {
+                message: "GitLab is not configured; there are no merge requests to restore"
+                    .to_string(),
+            }
+            .into());
+        };
+        let Some(operation) = self
+            .stack_state
+            .load_oplog()?
+            .into_iter()
+            .rev()
+            .find(|op| op.stack_id == stack.id)
+        else {
+            print_info("Nothing to undo: no MR updates were recorded for this stack");
+            return Ok(());
+        };
+        print_info(&format!(
+            "Restoring {} MRs to how they were before '{}' ({})",
+            operation.mrs.len(),
+            operation.operation,
+            operation.timestamp.format("%Y-%m-%d %H:%M:%S UTC")
+        ));
+
+        let mut failures = 0;
+        for snapshot in &operation.mrs {
+            let mr = match gitlab.get_merge_request(snapshot.iid).await {
+                Ok(mr) => mr,
+                Err(e) => {
+                    if self.disable_gitlab_on_auth_error(&e) {
+                        return Err(e);
+                    }
+                    print_warning(&format!("Could not fetch MR !{}: {}", snapshot.iid, e));
+                    failures += 1;
+                    continue;
+                }
+            };
+            if mr.state != "opened" {
+                print_info(&format!(
+                    "Skipping !{} ({}): it is {}",
+                    snapshot.iid, snapshot.branch, mr.state
+                ));
+                continue;
+            }
+
+            let title = (mr.title != snapshot.title).then(|| snapshot.title.clone());
+            let description = (mr.description != snapshot.description)
+                .then(|| snapshot.description.clone().unwrap_or_default());
+            let target = (mr.target_branch != snapshot.target_branch)
+                .then(|| snapshot.target_branch.clone());
+            if title.is_none() && description.is_none() && target.is_none() {
+                continue;
+            }
+
+            match gitlab
+                .update_merge_request_with_target(snapshot.iid, title, description, target.clone())
+                .await
+            {
+                Ok(_) => {
+                    if let Some(branch) = stack.branches.get_mut(&snapshot.branch) {
+                        if let Some(target) = &target {
+                            branch.record_target_change(Some(&mr.target_branch), target, "undo");
+                        }
+                        branch.description_hash =
+                            markdown::stack_table_section(&snapshot.description)
+                                .map(markdown::table_hash);
+                    }
+                    print_success(&format!("Restored !{} ({})", snapshot.iid, snapshot.branch));
+                }
+                Err(e) => {
+                    if self.disable_gitlab_on_auth_error(&e) {
+                        return Err(e);
+                    }
+                    print_warning(&format!("Could not restore !{}: {}", snapshot.iid, e));
+                    failures += 1;
+                }
+            }
+        }
+
+        self.stack_state.save_stack(&stack)?;
+        self.current_stack = Some(stack.clone());
+
+        if failures > 0 {
+            print_warning(&format!(
+                "{} MRs could not be restored; run 'git-train undo' again to retry",
+                failures
+            ));
+        } else {
+            self.stack_state.drop_last_oplog(&stack.id)?;
+            print_success(&format!("Undid '{}'", operation.operation));
+        }
+        Ok(())
+    }
+
+    /// Land the stack once its merge requests are approved.
+    ///
+    /// Without `squash_all` only the bottom MR is merged. With it, the whole (linear)
+    /// stack is collapsed onto the base as one commit or one commit per level, pushed
+    /// to the bottom branch and merged through the bottom MR; the upper MRs are closed
+    /// with a reference to it.
+    pub async fn land_stack(&mut self, options: &LandOptions) -> Result<()> {
+        print_train_header("Landing Stack");
+
+        let mut stack = self.get_or_load_current_stack()?;
+        let Some(gitlab_client) = self.gitlab().await else {
+            return Err(TrainError::GitLabError {
+                message: "GitLab is not configured; landing requires merge requests".to_string(),
+            }
+            .into());
+        };
+
+        let hierarchy = self.build_branch_hierarchy(&stack);
+        let order: Vec<String> = self
+            .branches_in_tree_order(&hierarchy, &stack)
+            .into_iter()
+            .map(|(branch, _)| branch)
+            .collect();
+        let Some(bottom) = order.first().cloned() else {
+            return Err(TrainError::StackError {
+                message: "Stack has no branches to land".to_string(),
+            }
+            .into());
+        };
+        let top = order[order.len() - 1].clone();
+
+        if options.squash_all {
+            // Only a single chain of branches can be collapsed into one history
+            if let Some((parent, children)) = hierarchy.iter().find(|(_, c)| c.len() > 1) {
+                return Err(TrainError::StackError {
+                    message: format!(
+                        "'{}' has {} child branches; land --squash-all needs a linear stack",
+                        parent,
+                        children.len()
+                    ),
+                }
+                .into());
+            }
+            if let Some(frozen) = order
+                .iter()
+                .find(|b| stack.branches[b.as_str()].state == BranchState::Frozen)
+            {
+                return Err(TrainError::StackError {
+                    message: format!("'{}' is frozen; land --squash-all would rewrite it", frozen),
+                }
+                .into());
+            }
+        }
+
+        // Every level being landed needs an open, approved MR
+        let levels = if options.squash_all {
+            &order[..]
+        } else {
+            &order[..1]
+        };
+        let mut landing: Vec<(String, MergeRequest)> = Vec::new();
+        let mut blockers = Vec::new();
+        for branch in levels {
+            let Some(iid) = stack.branches.get(branch).and_then(|b| b.mr_iid) else {
+                blockers.push(format!(
+                    "{}: no merge request (run 'git-train push')",
+                    branch
+                ));
+                continue;
+            };
+            match gitlab_client.get_merge_request(iid).await {
+                Ok(mr) if mr.state != "opened" => {
+                    blockers.push(format!("{}: MR !{} is {}", branch, iid, mr.state));
+                }
+                Ok(mr) if mr.conflicts_with_target() => blockers.push(format!(
+                    "{}: MR !{} conflicts with its target '{}'",
+                    branch, iid, mr.target_branch
+                )),
+                Ok(mr) => match gitlab_client.get_merge_request_approvals(iid).await {
+                    Ok(approvals) if approvals.approved => landing.push((branch.clone(), mr)),
+                    Ok(approvals) => blockers.push(format!(
+                        "{}: MR !{} needs {} more approval(s)",
+                        branch, iid, approvals.approvals_left
+                    )),
+                    Err(e) => blockers.push(format!(
+                        "{}: could not check approvals of !{}: {}",
+                        branch, iid, e
+                    )),
+                },
+                Err(e) => blockers.push(format!("{}: could not fetch MR !{}: {}", branch, iid, e)),
+            }
+        }
+
+        if !blockers.is_empty() {
+            print_warning("The stack is not ready to land:");
+            for blocker in &blockers {
+                ui::print_error(&format!("  ✘ {}", blocker));
+            }
+            return Err(TrainError::InvalidState {
+                message: format!("{} merge request(s) are not ready to land", blockers.len()),
+            }
+            .into());
+        }
+
+        let bottom_mr = landing[0].1.clone();
+
+        if !options.squash_all {
+            let params = MergeParams {
+                squash_message: options
+                    .squash
+                    .then(|| markdown::squash_commit_message(&bottom_mr)),
+                ..Default::default()
+            };
+            gitlab_client
+                .merge_merge_request(bottom_mr.iid, &params)
+                .await?;
+            print_success(&format!("Merged !{} ({})", bottom_mr.iid, bottom));
+
+            // Retarget the next levels before the merged branch can disappear
+            let children = child_branches(&stack, &bottom);
+            let retargeted = self
+                .retarget_children_of_merged(
+                    &mut stack,
+                    &bottom,
+                    &children,
+                    &bottom_mr.target_branch,
+                )
+                .await;
+            if options.delete_source_branch || self.config.mr.delete_source_branch {
+                self.delete_merged_source_branch(&bottom, retargeted);
+            }
+            if let Some(b) = stack.branches.get_mut(&bottom) {
+                b.transition(BranchState::MrMerged)?;
+            }
+            stack.updated_at = Utc::now();
+            self.stack_state.save_stack(&stack)?;
+            self.current_stack = Some(stack.clone());
+
+            print_info(&format!(
+                "Run 'git-train sync' to move the next level onto '{}'",
+                stack.base_branch
+            ));
+            return Ok(());
+        }
+
+        self.ensure_clean_working_directory()?;
+        self.git_repo
+            .run(&["fetch", "origin", &stack.base_branch])?;
+        let base_ref = format!("origin/{}", stack.base_branch);
+        if let Some(stale) = order
+            .iter()
+            .find(|branch| !self.git_repo.is_ancestor(&base_ref, branch))
+        {
+            return Err(TrainError::InvalidState {
+                message: format!(
+                    "'{}' is not up to date with {}; run 'git-train sync' first",
+                    stale, base_ref
+                ),
+            }
+            .into());
+        }
+
+        // Build the collapsed history next to the stack, without touching any branch yet
+        let base_commit = self.git_repo.get_commit_hash_for_branch(&base_ref)?;
+        let squashed = if options.per_level {
+            let mut parent = base_commit;
+            for (branch, mr) in &landing {
+                let message = format!("{} (!{})", mr.title, mr.iid);
+                parent = self.git_repo.commit_tree(branch, &parent, &message)?;
+            }
+            parent
+        } else {
+            let mut message = format!(
+                "[Stack: {}] Land {} merge requests\n\n",
+                stack.name,
+                landing.len()
+            );
+            for (_, mr) in &landing {
+                message.push_str(&format!("* {} (!{})\n", mr.title, mr.iid));
+            }
+            self.git_repo.commit_tree(&top, &base_commit, &message)?
+        };
+        print_success(&format!(
+            "Collapsed {} levels onto {} ({})",
+            landing.len(),
+            base_ref,
+            &squashed[..8]
+        ));
+
+        // Point the bottom branch at the collapsed history and publish it
+        if self.get_current_branch()? == bottom {
+            self.git_repo.run(&["reset", "--hard", &squashed])?;
+        } else {
+            self.git_repo.run(&["branch", "-f", &bottom, &squashed])?;
+        }
+        self.git_repo.run(&[
+            "push",
+            &lease_for(&bottom, &stack),
+            "origin",
+            &format!("{}:{}", bottom, bottom),
+        ])?;
+        print_success(&format!("Pushed collapsed stack to {}", bottom));
+
+        gitlab_client
+            .merge_merge_request(bottom_mr.iid, &MergeParams::default())
+            .await?;
+        print_success(&format!("Merged !{} ({})", bottom_mr.iid, bottom));
+
+        for (branch, mr) in &landing[1..] {
+            let note = format!(
+                "Landed as part of !{} ({}) with `git-train land --squash-all`.",
+                bottom_mr.iid, bottom_mr.web_url
+            );
+            if let Err(e) = gitlab_client.create_merge_request_note(mr.iid, &note).await {
+                print_warning(&format!("Could not comment on !{}: {}", mr.iid, e));
+            }
+            match gitlab_client.close_merge_request(mr.iid).await {
+                Ok(_) => print_success(&format!("Closed !{} ({})", mr.iid, branch)),
+                Err(e) => print_warning(&format!("Could not close !{}: {}", mr.iid, e)),
+            }
+        }
+
+        if let Some(branch) = stack.branches.get_mut(&bottom) {
+            branch.commit_hash = squashed;
+            branch.updated_at = Utc::now();
+        }
+        stack.updated_at = Utc::now();
+        self.stack_state.save_stack(&stack)?;
+        self.current_stack = Some(stack.clone());
+
+        print_success(&format!("Stack '{}' landed", stack.name));
+        print_info(&format!(
+            "Delete it with 'git-train delete {}' once you no longer need the local branches",
+            stack.name
+        ));
+        Ok(())
+    }
+
+    /// Merge the MR of a single stack branch through the API, refusing while the MR of
+    /// its parent is unmerged, then point the MRs of its children at its target
+    pub async fn merge_branch_mr(&mut self, branch: &str, options: &MrMergeOptions) -> Result<()> {
+        print_train_header(&format!("Merging MR for {}", branch));
+
+        let mut stack = self.get_or_load_current_stack()?;
+        let Some(gitlab_client) = self.gitlab().await else {
+            return Err(TrainError::GitLabError {
+                message: "GitLab is not configured; merging requires merge requests".to_string(),
+            }
+            .into());
+        };
+        let Some(stack_branch) = stack.branches.get(branch) else {
+            return Err(TrainError::StackError {
+                message: format!("Branch '{}' is not in stack '{}'", branch, stack.name),
+            }
+            .into());
+        };
+        let Some(iid) = stack_branch.mr_iid else {
+            return Err(TrainError::InvalidState {
+                message: format!("'{}' has no merge request (run 'git-train push')", branch),
+            }
+            .into());
+        };
+
+        // The parent level has to land first, or this MR would merge its commits too
+        if let Some(parent) = stack_branch
+            .parent
+            .as_ref()
+            .filter(|parent| **parent != stack.base_branch)
+        {
+            let parent_merged = match stack.branches.get(parent).and_then(|b| b.mr_iid) {
+                Some(parent_iid) => {
+                    gitlab_client.get_merge_request(parent_iid).await?.state == "merged"
+                }
+                None => false,
+            };
+            if !parent_merged {
+                return Err(TrainError::InvalidState {
+                    message: format!(
+                        "The MR of parent branch '{}' is not merged yet; merge it first",
+                        parent
+                    ),
+                }
+                .into());
+            }
+        }
+
+        let mr = gitlab_client.get_merge_request(iid).await?;
+        if mr.state != "opened" {
+            return Err(TrainError::InvalidState {
+                message: format!("MR !{} is {}", iid, mr.state),
+            }
+            .into());
+        }
+        if stack.branches.contains_key(&mr.target_branch) {
+            return Err(TrainError::InvalidState {
+                message: format!(
+                    "MR !{} still targets '{}'; run 'git-train push' to retarget it",
+                    iid, mr.target_branch
+                ),
+            }
+            .into());
+        }
+        if mr.conflicts_with_target() {
+            return Err(TrainError::InvalidState {
+                message: format!(
+                    "MR !{} conflicts with its target '{}'; run 'git-train sync' first",
+                    iid, mr.target_branch
+                ),
+            }
+            .into());
+        }
+
+        let method = options.method.unwrap_or(self.config.mr.merge_method);
+        if method == MergeMethod::Rebase {
+            print_info(&format!("Rebasing !{} onto '{}'...", iid, mr.target_branch));
+            gitlab_client.rebase_merge_request(iid).await?;
+        }
+        // The source branch is deleted by us once the children stopped targeting it;
+        // GitLab would delete it right away and close their MRs
+        let params = MergeParams {
+            squash_message: (method == MergeMethod::Squash).then(|| {
+                options
+                    .squash_message
+                    .clone()
+                    .unwrap_or_else(|| markdown::squash_commit_message(&mr))
+            }),
+            remove_source_branch: false,
+        };
+        gitlab_client.merge_merge_request(iid, &params).await?;
+        print_success(&format!(
+            "Merged !{} ({}) into '{}' ({})",
+            iid,
+            branch,
+            mr.target_branch,
+            method.as_str()
+        ));
+
+        // Children now have to target whatever the merged branch was merged into
+        self.snapshot_mr_metadata(&stack, "mr merge").await;
+        let children = child_branches(&stack, branch);
+        let retargeted = self
+            .retarget_children_of_merged(&mut stack, branch, &children, &mr.target_branch)
+            .await;
+        if options.delete_source_branch || self.config.mr.delete_source_branch {
+            self.delete_merged_source_branch(branch, retargeted);
+        }
+        if let Some(b) = stack.branches.get_mut(branch) {
+            b.transition(BranchState::MrMerged)?;
+        }
+        self.archive_if_complete(&mut stack);
+        stack.updated_at = Utc::now();
+        self.stack_state.save_stack(&stack)?;
+        self.current_stack = Some(stack);
+
+        print_info("Run 'git-train sync' to move the remaining levels onto the new base");
+        Ok(())
+    }
+
+    /// Point the MRs of `children` at `target`, the branch `merged` was merged into, so
+    /// GitLab doesn't close them once `merged` is deleted. Returns whether every child
+    /// MR was retargeted.
+    async fn retarget_children_of_merged(
+        &self,
+        stack: &mut Stack,
+        merged: &str,
+        children: &[String],
+        target: &str,
+    ) -> bool {
+        let Some(gitlab_client) = self.gitlab().await else {
+            return children.is_empty();
+        };
+        let mut all_retargeted = true;
+        for child in children {
+            let Some(child_iid) = stack.branches.get(child.as_str()).and_then(|b| b.mr_iid) else {
+                continue;
+            };
+            match gitlab_client
+                .update_merge_request_with_target(child_iid, None, None, Some(target.to_string()))
+                .await
+            {
+                Ok(_) => {
+                    print_success(&format!(
+                        "Retargeted !{} ({}) to '{}'",
+                        child_iid, child, target
+                    ));
+                    if let Some(b) = stack.branches.get_mut(child.as_str()) {
+                        b.record_target_change(Some(merged), target, "parent MR merged");
+                        b.updated_at = Utc::now();
+                    }
+                }
+                Err(e) => {
+                    print_warning(&format!("Could not retarget !{}: {}", child_iid, e));
+                    all_retargeted = false;
+                }
+            }
+        }
+        all_retargeted
+    }
+
+    /// Delete a merged branch on the remote, but only once no child MR targets it
+    fn delete_merged_source_branch(&self, branch: &str, children_retargeted: bool) {
+        if !children_retargeted {
+            print_warning(&format!(
+                "Kept '{}' on the remote: some child MRs still target it",
+                branch
+            ));
+            return;
+        }
+        match self.git_repo.run(&["push", "origin", "--delete", branch]) {
+            Ok(_) => print_success(&format!("Deleted '{}' on the remote", branch)),
+            Err(e) => print_warning(&format!(
+                "Could not delete '{}' on the remote: {}",
+                branch, e
+            )),
+        }
+    }
+
+    /// Determine if it's safe to force-push a branch
+    async fn should_force_push_branch(&self, branch_name: &str, stack: &Stack) -> Result<bool> {
+        // Safety checks for force-push
+
+        // 1. Check if the branch exists remotely
+        let remote_exists = self
+            .git_repo
+            .run(&["ls-remote", "--heads", "origin", branch_name])
+            .map(|output| !output.trim().is_empty())
+            .unwrap_or(false);
+
+        if !remote_exists {
+            // New branch, safe to push
+            print_info(&format!(
+                "Branch {} doesn't exist remotely, safe to push",
+                branch_name
+            ));
+            return Ok(true);
+        }
+
+        // 2. Check if this branch is part of our stack and we control it
+        if !stack.branches.contains_key(branch_name) {
+            print_warning(&format!(
+                "Branch {} is not part of our stack, unsafe to force-push",
+                branch_name
+            ));
+            return Ok(false);
+        }
+
+        // 3. Show what the force-push would discard from the remote, and always ask
+        //    before dropping commits somebody else authored
+        let foreign_commits_confirmed = match self.review_discarded_remote_commits(branch_name) {
+            Ok(0) => false,
+            Ok(_) => {
+                let proceed = confirm_action(&format!(
+                    "Force-push {} and discard commits authored by others?",
+                    branch_name
+                ))?;
+                if !proceed {
+                    print_info(
+                        "Skipping force-push. Fetch and integrate the remote commits first.",
+                    );
+                    return Ok(false);
+                }
+                true
+            }
+            Err(e) => {
+                print_warning(&format!(
+                    "Could not inspect remote commits for {}: {}",
+                    branch_name, e
+                ));
+                print_warning("Manual review recommended.");
+                return Ok(false);
+            }
+        };
+
+        // 4. Check configuration for automatic force-push behavior
+        if self.config.conflict_resolution.auto_force_push_after_rebase {
+            print_info(&format!(
+                "Auto force-push enabled, proceeding with {} (--force-with-lease)",
+                branch_name
+            ));
+        } else if self.config.conflict_resolution.prompt_before_force_push {
+            if foreign_commits_confirmed {
+                return Ok(true);
+            }
+            print_warning(&format!(
+                "Branch {} requires force-push after rebase",
+                branch_name
+            ));
+            print_info("This will overwrite the remote branch with your rebased version.");
+
+            let proceed = confirm_action(&format!("Force-push {} safely?", branch_name))?;
+            if !proceed {
+                print_info("Skipping force-push. You can push manually later if needed.");
+                return Ok(false);
+            }
+        } else {
+            // Neither auto nor prompt enabled, skip force-push
+            print_info(&format!(
+                "Force-push not configured for automatic mode, skipping {}",
+                branch_name
+            ));
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+
+    /// Track `origin/<branch_name>` from the local branch, leaving an upstream that is
+    /// already set that way untouched
+    fn set_upstream(&self, branch_name: &str) -> Result<()> {
+        let remote_key = format!("branch.{}.remote", branch_name);
+        let merge_key = format!("branch.{}.merge", branch_name);
+        let merge_ref = format!("refs/heads/{}", branch_name);
+        // `git config <key>` exits non-zero for unset keys
+        let current = |key: &str| self.git_repo.run(&["config", key]).unwrap_or_default();
+        if current(&remote_key) == "origin" && current(&merge_key) == merge_ref {
+            return Ok(());
+        }
+
+        self.git_repo.run(&["config", &remote_key, "origin"])?;
+        self.git_repo.run(&["config", &merge_key, &merge_ref])?;
+        print_info(&format!(
+            "Set upstream of '{}' to 'origin/{}'",
+            branch_name, branch_name
+        ));
+        Ok(())
+    }
+
+    /// Force-push `branch_name` with a lease. When the lease turns out stale because the
+    /// remote moved after it was last fetched, fetch it, repeat the safety check (which
+    /// shows what changed remotely) and try again.
+    async fn force_push_with_lease(&self, branch_name: &str, stack: &Stack) -> Result<()> {
+        let refspec = format!("{}:{}", branch_name, branch_name);
+        let remote_ref = format!("refs/remotes/origin/{}", branch_name);
+        let mut lease = lease_for(branch_name, stack);
+        let mut attempts = 1;
+        loop {
+            let error = match self.git_repo.run(&["push", &lease, "origin", &refspec]) {
+                Ok(_) => return Ok(()),
+                Err(e) => e,
+            };
+            if !error.to_string().contains("stale info") || attempts >= MAX_LEASE_ATTEMPTS {
+                return Err(error);
+            }
+            attempts += 1;
+
+            print_warning(&format!(
+                "The remote {} moved since it was last fetched; checking it again",
+                branch_name
+            ));
+            self.git_repo.run(&[
+                "fetch",
+                "origin",
+                &format!("+refs/heads/{}:{}", branch_name, remote_ref),
+            ])?;
+            if !self.should_force_push_branch(branch_name, stack).await? {
+                return Err(TrainError::GitError {
+                    message: "Force-push declined after the remote moved".to_string(),
+                }
+                .into());
+            }
+            // Lease on exactly the tip that was just reviewed
+            let reviewed = self.git_repo.get_commit_hash_for_branch(&remote_ref)?;
+            lease = format!("--force-with-lease={}:{}", branch_name, reviewed);
+        }
+    }
+
+    /// Record the commits just pushed as the remote state of their branches
+    fn record_remote_shas(&self, stack: &mut Stack, pushed: &[BranchName]) {
+        for branch_name in pushed {
+            let Ok(hash) = self.git_repo.get_commit_hash_for_branch(branch_name) else {
+                continue;
+            };
+            if let Some(branch) = stack.branches.get_mut(branch_name) {
+                branch.remote_sha = Some(hash);
+                if branch.state == BranchState::Unpushed {
+                    branch.state = BranchState::Pushed;
+                }
+            }
+        }
+    }
+
+    /// Print the remote-only commits a force-push of `branch_name` would discard.
+    ///
+    /// Returns how many of them were authored by someone other than the current user.
+    fn review_discarded_remote_commits(&self, branch_name: &str) -> Result<usize> {
+        // Refresh origin/<branch> so the summary (and the lease) match the remote
+        self.git_repo.run(&["fetch", "origin", branch_name])?;
+
+        let remote_ref = format!("origin/{}", branch_name);
+        let discarded = self.git_repo.commits_only_in(branch_name, &remote_ref)?;
+        if discarded.is_empty() {
+            print_info(&format!(
+                "No remote-only commits on {}, nothing will be discarded",
+                branch_name
+            ));
+            return Ok(0);
+        }
+
+        let user_email = self.git_repo.user_email();
+        let is_foreign = |email: &str| {
+            user_email
+                .as_deref()
+                .is_none_or(|own| !own.eq_ignore_ascii_case(email))
+        };
+
+        print_info(&format!(
+            "Force-pushing {} will discard {} remote commit(s) ({}..{}):",
+            branch_name,
+            discarded.len(),
+            branch_name,
+            remote_ref
+        ));
+        for commit in &discarded {
+            let line = format!(
+                "  {} {} ({} <{}>)",
+                &commit.hash[..commit.hash.len().min(8)],
+                commit.subject,
+                commit.author_name,
+                commit.author_email
+            );
+            if is_foreign(&commit.author_email) {
+                println!("{}", style(line).yellow());
+            } else {
+                println!("{}", style(line).dim());
+            }
+        }
+
+        let foreign = discarded
+            .iter()
+            .filter(|c| is_foreign(&c.author_email))
+            .count();
+        if foreign > 0 {
+            print_warning(&format!(
+                "{} of these commits were not authored by you",
+                foreign
+            ));
+        }
+        Ok(foreign)
+    }
+
+    /// Check for and attempt to recover from invalid git states
+    pub async fn check_and_recover_git_state(&self) -> Result<()> {
+        let git_state = self.conflict_resolver.get_git_state()?;
+
+        match git_state {
+            GitState::Clean => Ok(()),
+            GitState::Rebasing | GitState::Merging | GitState::CherryPicking => {
                 print_warning(&format!(
                     "Git is in state {:?}. Recovery options:",
                     git_state
                 ));
 
-                if let Some(conflicts) = self.conflict_resolver.detect_conflicts()? {
-                    print_info(&format!(
-                        "Found {} conflicted files that need resolution",
-                        conflicts.files.len()
+                if let Some(conflicts) = self.conflict_resolver.detect_conflicts()? {
+                    print_info(&format!(
+                        "Found {} conflicted files that need resolution",
+                        conflicts.files.len()
+                    ));
+                    self.conflict_resolver.print_conflict_summary(&conflicts);
+
+                    let options = vec![
+                        "Try to resolve conflicts automatically",
+                        "Resolve conflicts interactively",
+                        "Abort the current operation",
+                        "Continue with manual resolution later",
+                    ];
+
+                    let choice = ui::select_from_list(&options, "How would you like to proceed?")?;
+
+                    match choice {
+                        0 => {
+                            if self
+                                .conflict_resolver
+                                .auto_resolve_conflicts(&conflicts)
+                                .await?
+                            {
+                                let state = self.conflict_resolver.get_git_state()?;
+                                self.conflict_resolver
+                                    .verify_conflicts_resolved(&conflicts, state)
+                                    .await?;
+                                print_success(
+                                    "Automatically resolved conflicts and completed operation",
+                                );
+                                Ok(())
+                            } else {
+                                print_warning("Automatic resolution failed. Please resolve manually or abort.");
+                                Err(TrainError::InvalidState {
+                                    message: "Automatic conflict resolution failed".to_string(),
+                                }
+                                .into())
+                            }
+                        }
+                        1 => {
+                            self.conflict_resolver
+                                .resolve_conflicts_interactively(&conflicts)
+                                .await
+                        }
+                        2 => {
+                            self.conflict_resolver.abort_current_operation()?;
+                            print_success("Aborted current operation. Repository is now clean.");
+                            Ok(())
+                        }
+                        3 => {
+                            print_info("Resolution deferred. Re-run 'git-train sync' when ready.");
+                            Err(TrainError::InvalidState {
+                                message: "Manual conflict resolution deferred".to_string(),
+                            }
+                            .into())
+                        }
+                        _ => unreachable!(),
+                    }
+                } else {
+                    // No conflicts detected - this could be stale state files
+                    print_info("No conflicts detected. This might be stale git state files.");
+                    print_info("Attempting to clean up stale state and continue...");
+
+                    // The get_git_state() call should have already cleaned up stale files,
+                    // so let's check the state again after cleanup
+                    let new_git_state = self.conflict_resolver.get_git_state()?;
+
+                    if matches!(new_git_state, GitState::Clean) {
+                        print_success("Successfully cleaned up stale git state files. Repository is now clean.");
+                        Ok(())
+                    } else {
+                        // Still in problematic state, try to continue the operation
+                        print_info(
+                            "Repository still shows active operation. Attempting to continue...",
+                        );
+                        match git_state {
+                            GitState::Rebasing => {
+                                match self.git_repo.run(&["rebase", "--continue"]) {
+                                    Ok(_) => {
+                                        print_success("Successfully continued rebase");
+                                        Ok(())
+                                    }
+                                    Err(_) => {
+                                        print_warning(
+                                            "Could not continue rebase. Offering to abort...",
+                                        );
+                                        if confirm_action("Abort the rebase?")? {
+                                            self.git_repo.run(&["rebase", "--abort"])?;
+                                            print_success(
+                                                "Rebase aborted. Repository is now clean.",
+                                            );
+                                            Ok(())
+                                        } else {
+                                            Err(TrainError::InvalidState {
+                                                message: "Could not continue interrupted rebase"
+                                                    .to_string(),
+                                            }
+                                            .into())
+                                        }
+                                    }
+                                }
+                            }
+                            GitState::Merging => {
+                                match self.git_repo.run(&["commit", "--no-edit"]) {
+                                    Ok(_) => {
+                                        print_success("Successfully completed merge");
+                                        Ok(())
+                                    }
+                                    Err(_) => {
+                                        print_warning(
+                                            "Could not complete merge. Offering to abort...",
+                                        );
+                                        if confirm_action("Abort the merge?")? {
+                                            self.git_repo.run(&["merge", "--abort"])?;
+                                            print_success(
+                                                "Merge aborted. Repository is now clean.",
+                                            );
+                                            Ok(())
+                                        } else {
+                                            Err(TrainError::InvalidState {
+                                                message: "Could not complete interrupted merge"
+                                                    .to_string(),
+                                            }
+                                            .into())
+                                        }
+                                    }
+                                }
+                            }
+                            GitState::CherryPicking => {
+                                match self.git_repo.run(&["cherry-pick", "--continue"]) {
+                                    Ok(_) => {
+                                        print_success("Successfully continued cherry-pick");
+                                        Ok(())
+                                    }
+                                    Err(_) => {
+                                        print_warning(
+                                            "Could not continue cherry-pick. Offering to abort...",
+                                        );
+                                        if confirm_action("Abort the cherry-pick?")? {
+                                            self.git_repo.run(&["cherry-pick", "--abort"])?;
+                                            print_success(
+                                                "Cherry-pick aborted. Repository is now clean.",
+                                            );
+                                            Ok(())
+                                        } else {
+                                            Err(TrainError::InvalidState {
+                                                message:
+                                                    "Could not continue interrupted cherry-pick"
+                                                        .to_string(),
+                                            }
+                                            .into())
+                                        }
+                                    }
+                                }
+                            }
+                            _ => Ok(()),
+                        }
+                    }
+                }
+            }
+            GitState::Conflicted => {
+                print_warning("Repository has unresolved conflicts.");
+                if let Some(conflicts) = self.conflict_resolver.detect_conflicts()? {
+                    self.conflict_resolver
+                        .resolve_conflicts_interactively(&conflicts)
+                        .await
+                } else {
+                    Err(TrainError::InvalidState {
+                        message: "Repository appears to have conflicts but none were detected"
+                            .to_string(),
+                    }
+                    .into())
+                }
+            }
+        }
+    }
+
+    pub async fn sync_with_remote(&mut self) -> Result<()> {
+        self.sync_with_remote_with_options(&SyncOptions::default())
+            .await
+    }
+
+    pub async fn sync_with_remote_with_options(&mut self, options: &SyncOptions) -> Result<()> {
+        let started = Instant::now();
+        self.conflicts_seen.store(0, Ordering::Relaxed);
+
+        let stack_name = self.current_stack_name();
+        events::emit(&Event::OperationStarted {
+            operation: "sync",
+            stack: &stack_name,
+        });
+        let result = self.sync_stack_with_remote(options).await;
+        events::emit(&Event::OperationFinished {
+            operation: "sync",
+            success: result.is_ok(),
+        });
+
+        if self.config.history.enabled {
+            if let Err(e) = self.record_sync_snapshot(started, result.is_ok()) {
+                print_warning(&format!("Could not record sync history: {}", e));
+            }
+        }
+        result
+    }
+
+    /// Sync every stored stack in turn, isolating failures so one stack's conflict
+    /// does not stop the others
+    pub async fn sync_all_stacks(&mut self, options: &SyncOptions) -> Result<()> {
+        print_train_header("Syncing All Stacks");
+
+        let stacks = self.stack_state.list()?;
+        if stacks.is_empty() {
+            print_info("No stacks found");
+            return Ok(());
+        }
+
+        self.ensure_clean_working_directory()?;
+        let original_branch = self.get_current_branch()?;
+        let original_stack_id = self.stack_state.get_current_stack_id()?;
+
+        let mut results: Vec<(String, std::result::Result<(), String>)> = Vec::new();
+        for stack in stacks {
+            let name = stack.name.clone();
+            self.stack_state.set_current(&stack)?;
+            self.current_stack = Some(stack);
+
+            let result = self.sync_with_remote_with_options(options).await;
+            if let Err(e) = &result {
+                // Leave the repository clean for the next stack
+                match self.conflict_resolver.get_git_state()? {
+                    GitState::Rebasing | GitState::Conflicted => {
+                        let _ = self.git_repo.run(&["rebase", "--abort"]);
+                    }
+                    GitState::Merging => {
+                        let _ = self.git_repo.run(&["merge", "--abort"]);
+                    }
+                    GitState::CherryPicking => {
+                        let _ = self.git_repo.run(&["cherry-pick", "--abort"]);
+                    }
+                    GitState::Clean => {}
+                }
+                print_warning(&format!("Stack '{}' failed to sync: {}", name, e));
+            }
+            results.push((name, result.map_err(|e| e.to_string())));
+        }
+
+        // Restore where the user started
+        if let Err(e) = self.git_repo.run(&["checkout", &original_branch]) {
+            print_warning(&format!("Could not return to '{}': {}", original_branch, e));
+        }
+        self.current_stack = None;
+        if let Ok(stack) = self
+            .stack_state
+            .find_by_identifier(original_stack_id.trim())
+        {
+            self.stack_state.set_current(&stack)?;
+        }
+
+        println!();
+        let mut table = ui::Table::new(&["Stack", "Result", "Details"]);
+        for (name, result) in &results {
+            let (status, details) = match result {
+                Ok(()) => (style("✔ synced").green().to_string(), String::new()),
+                Err(e) => (
+                    style("✘ failed").red().to_string(),
+                    e.lines().next().unwrap_or_default().to_string(),
+                ),
+            };
+            table.add_row(vec![name.clone(), status, details]);
+        }
+        table.print();
+
+        let failed = results.iter().filter(|(_, r)| r.is_err()).count();
+        if failed == 0 {
+            print_success(&format!("Synced {} stacks", results.len()));
+            Ok(())
+        } else {
+            Err(TrainError::StackError {
+                message: format!("{} of {} stacks failed to sync", failed, results.len()),
+            }
+            .into())
+        }
+    }
+
+    fn record_sync_snapshot(&mut self, started: Instant, success: bool) -> Result<()> {
+        let stack = self.get_or_load_current_stack()?;
+        let hierarchy = self.build_branch_hierarchy(&stack);
+        let max_depth = self
+            .branches_in_tree_order(&hierarchy, &stack)
+            .iter()
+            .map(|(_, depth)| depth + 1)
+            .max()
+            .unwrap_or(0);
+
+        self.stack_state.append_history(&SyncSnapshot {
+            timestamp: Utc::now(),
+            stack_id: stack.id.clone(),
+            stack_name: stack.name.clone(),
+            branch_count: stack.branches.len(),
+            max_depth,
+            conflicts: self.conflicts_seen.load(Ordering::Relaxed),
+            duration_ms: started.elapsed().as_millis() as u64,
+            success,
+        })
+    }
+
+    /// Show recorded sync snapshots and how the stack's shape is trending
+    pub async fn show_history(&mut self, limit: usize, all_stacks: bool) -> Result<()> {
+        print_train_header("Sync History");
+
+        let mut history = self.stack_state.load_history()?;
+        if !all_stacks {
+            let stack = self.get_or_load_current_stack()?;
+            history.retain(|s| s.stack_id == stack.id);
+        }
+
+        if history.is_empty() {
+            print_info("No syncs recorded yet");
+            if !self.config.history.enabled {
+                print_info("Enable local sync history with: git-train config set-history on");
+            }
+            return Ok(());
+        }
+
+        let mut table = ui::Table::new(&[
+            "When",
+            "Stack",
+            "Branches",
+            "Depth",
+            "Conflicts",
+            "Duration",
+            "Result",
+        ]);
+        for snapshot in history.iter().rev().take(limit.max(1)) {
+            table.add_row(vec![
+                snapshot.timestamp.format("%Y-%m-%d %H:%M").to_string(),
+                snapshot.stack_name.clone(),
+                snapshot.branch_count.to_string(),
+                snapshot.max_depth.to_string(),
+                match snapshot.conflicts {
+                    0 => style("0").dim().to_string(),
+                    n => style(n.to_string()).yellow().to_string(),
+                },
+                format!("{:.1}s", snapshot.duration_ms as f64 / 1000.0),
+                if snapshot.success {
+                    style("ok").green().to_string()
+                } else {
+                    style("failed").red().to_string()
+                },
+            ]);
+        }
+        table.print();
+
+        // Compare the latest half of the window against the earlier half
+        let window = &history[history.len().saturating_sub(20)..];
+        if window.len() >= 4 {
+            let (earlier, recent) = window.split_at(window.len() / 2);
+            let average = |snapshots: &[SyncSnapshot], f: fn(&SyncSnapshot) -> f64| {
+                snapshots.iter().map(f).sum::<f64>() / snapshots.len() as f64
+            };
+            let trend = |label: &str, f: fn(&SyncSnapshot) -> f64| {
+                let (before, after) = (average(earlier, f), average(recent, f));
+                let arrow = if after > before * 1.1 {
+                    style("↑").yellow().to_string()
+                } else if after < before * 0.9 {
+                    style("↓").green().to_string()
+                } else {
+                    "→".to_string()
+                };
+                ui::print_config_item(label, &format!("{:.1} → {:.1} {}", before, after, arrow));
+            };
+
+            println!();
+            print_info(&format!(
+                "Trends over the last {} syncs (earlier → recent average):",
+                window.len()
+            ));
+            trend("Depth", |s| s.max_depth as f64);
+            trend("Branches", |s| s.branch_count as f64);
+            trend("Conflicts per sync", |s| s.conflicts as f64);
+            trend("Duration (s)", |s| s.duration_ms as f64 / 1000.0);
+        }
+
+        Ok(())
+    }
+
+    /// Levels sitting directly on the base that `sync` leaves there under the
+    /// `when_behind` policy: the base is at most `when_behind` commits ahead of them and
+    /// its new commits touch none of the files they or the levels above them change.
+    fn deferred_restacks(
+        &self,
+        stack: &Stack,
+        when_behind: usize,
+        pruned_tips: &HashMap<String, String>,
+    ) -> HashSet<String> {
+        let mut deferred = HashSet::new();
+        if when_behind == 0 {
+            return deferred;
+        }
+
+        let base = stack.base_branch.as_str();
+        let mut roots: Vec<&BranchName> = stack
+            .branches
+            .values()
+            .filter(|b| b.parent.as_ref() == Some(&stack.base_branch))
+            .filter(|b| b.state != BranchState::Frozen)
+            .map(|b| &b.name)
+            .filter(|name| !pruned_tips.contains_key(name.as_str()))
+            .collect();
+        roots.sort();
+
+        for root in roots {
+            let behind = match self.ahead_behind(root, base) {
+                Some((behind, _)) if behind > 0 => behind,
+                _ => continue,
+            };
+            if behind > when_behind {
+                continue;
+            }
+            let Ok(base_changes) = self.git_repo.diff_stat(root, base) else {
+                continue;
+            };
+
+            let mut levels = vec![root.to_string()];
+            let mut pending = child_branches(stack, root);
+            while let Some(level) = pending.pop() {
+                pending.extend(child_branches(stack, &level));
+                levels.push(level);
+            }
+            let mut stack_files = HashSet::new();
+            for level in &levels {
+                match self.git_repo.diff_stat(base, level) {
+                    Ok(changes) => stack_files.extend(changes.into_iter().map(|c| c.path)),
+                    // Can't tell what the stack touches: restack to be safe
+                    Err(_) => stack_files.extend(base_changes.iter().map(|c| c.path.clone())),
+                }
+            }
+
+            let overlapping: Vec<&str> = base_changes
+                .iter()
+                .map(|c| c.path.as_str())
+                .filter(|path| stack_files.contains(*path))
+                .collect();
+            if overlapping.is_empty() {
+                print_info(&format!(
+                    "Leaving '{}' on its base: '{}' is {} commit(s) ahead (restacking above {}) and touches none of the stack's files",
+                    root, base, behind, when_behind
+                ));
+                deferred.insert(root.to_string());
+            } else {
+                print_info(&format!(
+                    "Restacking '{}': '{}' changed files the stack changes too ({})",
+                    root,
+                    base,
+                    overlapping.join(", ")
+                ));
+            }
+        }
+        deferred
+    }
+
+    async fn sync_stack_with_remote(&mut self, options: &SyncOptions) -> Result<()> {
+        print_train_header("Syncing with Remote");
+
+        // First check and attempt to recover from any invalid git state
+        if let Err(e) = self.check_and_recover_git_state().await {
+            print_error(&format!("Cannot sync: {}", e));
+            print_info("Please resolve the git state issue first:");
+            print_info("• Conflicts will be handled during sync");
+            return Err(e);
+        }
+
+        let mut stack = self.get_or_load_current_stack()?;
+        self.ensure_local_branches(&stack)?;
+        let current_branch = self.get_current_branch()?;
+
+        // After a default branch migration the old base may stop receiving updates
+        if let Some(new_base) = self.default_branch_change(&stack).await {
+            print_warning(&format!(
+                "The project's default branch changed from '{}' to '{}'",
+                stack.base_branch, new_base
+            ));
+            if confirm_action(&format!(
+                "Move the stack onto '{}' and retarget its root MRs?",
+                new_base
+            ))
+            .unwrap_or(false)
+            {
+                self.move_stack_base(&new_base).await?;
+                stack = self.get_or_load_current_stack()?;
+            } else {
+                print_info(&format!(
+                    "Keeping '{}'. Move the stack later with 'git-train set-base {}'",
+                    stack.base_branch, new_base
+                ));
+            }
+        }
+
+        // Update the base branch, creating it first in clones that only have origin's
+        print_info(&format!("Updating base branch: {}", stack.base_branch));
+        self.git_repo
+            .run(&["fetch", "origin", &stack.base_branch])?;
+        if self.git_repo.ensure_local_branch(&stack.base_branch)? {
+            print_info(&format!(
+                "Created local '{}' from 'origin/{}'",
+                stack.base_branch, stack.base_branch
+            ));
+        }
+        self.git_repo.run(&["checkout", &stack.base_branch])?;
+        self.git_repo.run(&["pull", "origin", &stack.base_branch])?;
+
+        // Branches deleted on the remote, e.g. after a manual merge. Children of pruned
+        // branches are replayed from the pruned tip so its commits are left behind.
+        let mut pruned_tips: HashMap<String, String> = HashMap::new();
+        let deleted = self.find_remote_deleted_branches(&stack)?;
+        if !deleted.is_empty() {
+            for branch in &deleted {
+                // A stale remote-tracking ref makes --force-with-lease pushes to it fail
+                let _ = self.git_repo.run(&[
+                    "update-ref",
+                    "-d",
+                    &format!("refs/remotes/origin/{}", branch),
+                ]);
+            }
+            if options.prune {
+                for branch in &deleted {
+                    if let Some((tip, children)) = remove_branch_from_stack(&mut stack, branch) {
+                        for child in children {
+                            pruned_tips.insert(child, tip.clone());
+                        }
+                    }
+                    print_success(&format!(
+                        "Removed '{}' from the stack (deleted on the remote); the local branch is kept",
+                        branch
                     ));
-                    self.conflict_resolver.print_conflict_summary(&conflicts);
+                }
+                self.stack_state.save_stack(&stack)?;
+                self.current_stack = Some(stack.clone());
+            } else {
+                for branch in &deleted {
+                    print_warning(&format!("Branch '{}' was deleted on the remote", branch));
+                }
+                print_info(
+                    "Run 'git-train sync --prune' to remove them from the stack and reparent their children",
+                );
+            }
+        }
+
+        let selection = self.resolve_branch_range(&stack, options.range.as_ref())?;
+        if let Some(selection) = &selection {
+            let mut names: Vec<&str> = selection.iter().map(String::as_str).collect();
+            names.sort();
+            print_info(&format!("Limiting restack to: {}", names.join(", ")));
+        }
+
+        // Rebase all stack branches with better error handling
+        let mut updated_stack = stack.clone();
+        let hierarchy = self.build_branch_hierarchy(&stack);
+        let when_behind = options.when_behind.unwrap_or(self.config.sync.when_behind);
+        let deferred = self.deferred_restacks(&stack, when_behind, &pruned_tips);
+
+        let rebase_result = {
+            let mut rebased_branches = std::collections::HashSet::new();
+
+            // Start with branches that have the base branch as a parent
+            let mut branches_to_rebase: Vec<String> = stack
+                .branches
+                .keys()
+                .filter(|k| {
+                    stack.branches.get(*k).and_then(|b| b.parent.as_ref())
+                        == Some(&stack.base_branch)
+                })
+                .map(|b| b.to_string())
+                .collect();
+
+            // Sort for consistent order
+            branches_to_rebase.sort();
+
+            let mut all_rebased_ok = true;
+            let mut first_error: Option<anyhow::Error> = None;
+
+            while let Some(branch_name) = branches_to_rebase.pop() {
+                if rebased_branches.contains(&branch_name) {
+                    continue;
+                }
+
+                // Outside the selected range: leave the branch alone but keep walking,
+                // since the range may start further up
+                if selection
+                    .as_ref()
+                    .is_some_and(|selection| !selection.contains(&branch_name))
+                {
+                    rebased_branches.insert(branch_name.clone());
+                    if let Some(children) = hierarchy.get(&branch_name) {
+                        branches_to_rebase.extend(children.iter().cloned());
+                    }
+                    continue;
+                }
+
+                // A teammate owns frozen branches: follow their remote tip instead, and
+                // let the children restack onto it
+                if stack
+                    .branches
+                    .get(&branch_name)
+                    .is_some_and(|b| b.state == BranchState::Frozen)
+                {
+                    let tip = self.follow_frozen_branch(&branch_name)?;
+                    if let Some(branch) = updated_stack.branches.get_mut(&branch_name) {
+                        if branch.commit_hash != tip {
+                            branch.commit_hash = tip;
+                            branch.updated_at = Utc::now();
+                        }
+                    }
+                    rebased_branches.insert(branch_name.clone());
+                    if let Some(children) = hierarchy.get(&branch_name) {
+                        branches_to_rebase.extend(children.iter().cloned());
+                    }
+                    continue;
+                }
+
+                // Not worth a force-push yet; the children still restack onto it
+                if deferred.contains(&branch_name) {
+                    let tip = self.git_repo.get_commit_hash_for_branch(&branch_name)?;
+                    if let Some(branch) = updated_stack.branches.get_mut(&branch_name) {
+                        if branch.commit_hash != tip {
+                            branch.commit_hash = tip;
+                            branch.updated_at = Utc::now();
+                        }
+                    }
+                    rebased_branches.insert(branch_name.clone());
+                    if let Some(children) = hierarchy.get(&branch_name) {
+                        branches_to_rebase.extend(children.iter().cloned());
+                    }
+                    continue;
+                }
+
+                let parent_branch_name = stack
+                    .branches
+                    .get(&branch_name)
+                    .and_then(|b| b.parent.as_ref().map(|p| p.to_string()))
+                    .unwrap_or_else(|| stack.base_branch.to_string());
 
-                    let options = vec![
-                        "Try to resolve conflicts automatically",
-                        "Resolve conflicts interactively",
-                        "Abort the current operation",
-                        "Continue with manual resolution later",
-                    ];
+                self.git_repo.run(&["checkout", &branch_name])?;
 
-                    let choice = ui::select_from_list(&options, "How would you like to proceed?")?;
+                // Children of stack branches are replayed from the parent's recorded tip
+                let stored_parent_hash =
+                    pruned_tips
+                        .get(&branch_name)
+                        .map(String::as_str)
+                        .or_else(|| {
+                            stack
+                                .branches
+                                .get(&parent_branch_name)
+                                .map(|b| b.commit_hash.as_str())
+                        });
 
-                    match choice {
-                        0 => {
-                            if self
-                                .conflict_resolver
-                                .auto_resolve_conflicts(&conflicts)
-                                .await?
-                            {
-                                let state = self.conflict_resolver.get_git_state()?;
-                                self.conflict_resolver
-                                    .verify_conflicts_resolved(&conflicts, state)
-                                    .await?;
-                                print_success(
-                                    "Automatically resolved conflicts and completed operation",
-                                );
-                                Ok(())
-                            } else {
-                                print_warning("Automatic resolution failed. Please resolve manually or abort.");
-                                Err(TrainError::InvalidState {
-                                    message: "Automatic conflict resolution failed".to_string(),
-                                }
-                                .into())
-                            }
-                        }
-                        1 => {
-                            self.conflict_resolver
-                                .resolve_conflicts_interactively(&conflicts)
-                                .await
-                        }
-                        2 => {
-                            self.conflict_resolver.abort_current_operation()?;
-                            print_success("Aborted current operation. Repository is now clean.");
-                            Ok(())
+                match self
+                    .restack_branch(
+                        &stack,
+                        &branch_name,
+                        &parent_branch_name,
+                        stored_parent_hash,
+                    )
+                    .await
+                {
+                    Ok(_) => {
+                        // Update commit hash
+                        if let Some(branch) = updated_stack.branches.get_mut(&branch_name) {
+                            branch.commit_hash = self.get_current_commit_hash()?;
+                            branch.updated_at = Utc::now();
                         }
-                        3 => {
-                            print_info("Resolution deferred. Re-run 'git-train sync' when ready.");
-                            Err(TrainError::InvalidState {
-                                message: "Manual conflict resolution deferred".to_string(),
+                        events::emit(&Event::BranchRebased {
+                            branch: &branch_name,
+                            onto: &parent_branch_name,
+                        });
+                        rebased_branches.insert(branch_name.clone());
+
+                        // Add children of this branch to the queue
+                        if let Some(children) = hierarchy.get(&branch_name) {
+                            for child in children {
+                                branches_to_rebase.push(child.clone());
                             }
-                            .into())
                         }
-                        _ => unreachable!(),
                     }
-                } else {
-                    // No conflicts detected - this could be stale state files
-                    print_info("No conflicts detected. This might be stale git state files.");
-                    print_info("Attempting to clean up stale state and continue...");
-
-                    // The get_git_state() call should have already cleaned up stale files,
-                    // so let's check the state again after cleanup
-                    let new_git_state = self.conflict_resolver.get_git_state()?;
+                    Err(e) => {
+                        print_error(&format!("Failed to rebase branch '{}': {}", branch_name, e));
+                        all_rebased_ok = false;
 
-                    if matches!(new_git_state, GitState::Clean) {
-                        print_success("Successfully cleaned up stale git state files. Repository is now clean.");
-                        Ok(())
-                    } else {
-                        // Still in problematic state, try to continue the operation
-                        print_info(
-                            "Repository still shows active operation. Attempting to continue...",
-                        );
-                        match git_state {
-                            GitState::Rebasing => {
-                                match self.git_repo.run(&["rebase", "--continue"]) {
-                                    Ok(_) => {
-                                        print_success("Successfully continued rebase");
-                                        Ok(())
-                                    }
-                                    Err(_) => {
-                                        print_warning(
-                                            "Could not continue rebase. Offering to abort...",
-                                        );
-                                        if confirm_action("Abort the rebase?")? {
-                                            self.git_repo.run(&["rebase", "--abort"])?;
-                                            print_success(
-                                                "Rebase aborted. Repository is now clean.",
-                                            );
-                                            Ok(())
-                                        } else {
-                                            Err(TrainError::InvalidState {
-                                                message: "Could not continue interrupted rebase"
-                                                    .to_string(),
-                                            }
-                                            .into())
-                                        }
-                                    }
-                                }
-                            }
-                            GitState::Merging => {
-                                match self.git_repo.run(&["commit", "--no-edit"]) {
-                                    Ok(_) => {
-                                        print_success("Successfully completed merge");
-                                        Ok(())
-                                    }
-                                    Err(_) => {
-                                        print_warning(
-                                            "Could not complete merge. Offering to abort...",
-                                        );
-                                        if confirm_action("Abort the merge?")? {
-                                            self.git_repo.run(&["merge", "--abort"])?;
-                                            print_success(
-                                                "Merge aborted. Repository is now clean.",
-                                            );
-                                            Ok(())
-                                        } else {
-                                            Err(TrainError::InvalidState {
-                                                message: "Could not complete interrupted merge"
-                                                    .to_string(),
-                                            }
-                                            .into())
-                                        }
-                                    }
-                                }
-                            }
-                            GitState::CherryPicking => {
-                                match self.git_repo.run(&["cherry-pick", "--continue"]) {
-                                    Ok(_) => {
-                                        print_success("Successfully continued cherry-pick");
-                                        Ok(())
-                                    }
-                                    Err(_) => {
-                                        print_warning(
-                                            "Could not continue cherry-pick. Offering to abort...",
-                                        );
-                                        if confirm_action("Abort the cherry-pick?")? {
-                                            self.git_repo.run(&["cherry-pick", "--abort"])?;
-                                            print_success(
-                                                "Cherry-pick aborted. Repository is now clean.",
-                                            );
-                                            Ok(())
-                                        } else {
-                                            Err(TrainError::InvalidState {
-                                                message:
-                                                    "Could not continue interrupted cherry-pick"
-                                                        .to_string(),
-                                            }
-                                            .into())
-                                        }
-                                    }
-                                }
-                            }
-                            _ => Ok(()),
+                        // Store the first error, especially if it's a conflict resolution error
+                        if first_error.is_none() {
+                            first_error = Some(e);
                         }
+
+                        // Don't proceed with children if parent fails
+                    }
+                }
+            }
+
+            if all_rebased_ok {
+                Ok(())
+            } else {
+                // Return the specific error if available, otherwise use generic message
+                if let Some(original_error) = first_error {
+                    Err(original_error)
+                } else {
+                    Err(TrainError::GitError {
+                        message: "One or more branches failed to rebase".to_string(),
                     }
+                    .into())
+                }
+            }
+        };
+
+        if let Err(e) = rebase_result {
+            print_error(&format!("Some branches failed to rebase: {}", e));
+            print_info("You can:");
+            print_info("• Re-run 'git-train sync' to handle conflicts");
+            print_info("• Run 'git-train sync' again after resolving issues");
+
+            // Try to return to a safe state
+            if self.git_repo.run(&["checkout", &current_branch]).is_err() {
+                print_warning(&format!(
+                    "Could not return to original branch '{}'. You may need to checkout manually.",
+                    current_branch
+                ));
+            }
+
+            return Err(e);
+        }
+
+        // Update merge request targets if GitLab client is available
+        if self.gitlab().await.is_some() {
+            print_info("Updating merge request targets after sync...");
+            self.process_branches_with_mrs_for_updates(
+                &mut updated_stack,
+                "Updated MR targets for",
+            )
+            .await;
+
+            self.update_all_mr_descriptions(&mut updated_stack).await;
+        }
+
+        // Switch back to the original branch
+        self.git_repo.run(&["checkout", &current_branch])?;
+
+        // Save the updated stack
+        self.stack_state.save_stack(&updated_stack)?;
+        self.current_stack = Some(updated_stack);
+
+        print_success("Stack synchronized with remote and MR targets updated");
+
+        Ok(())
+    }
+
+    /// Fast-forward a frozen branch to `origin/<branch>` and return its tip. Local
+    /// commits the remote doesn't have are left alone with a warning.
+    fn follow_frozen_branch(&self, branch: &str) -> Result<String> {
+        if let Err(e) = self.git_repo.run(&["fetch", "origin", branch]) {
+            print_warning(&format!(
+                "Could not fetch frozen branch '{}': {}",
+                branch, e
+            ));
+        }
+        let remote = format!("origin/{}", branch);
+        if !self.git_repo.has_remote_branch(branch) {
+            print_info(&format!(
+                "'{}' is frozen and not on origin; keeping the local branch",
+                branch
+            ));
+        } else if self.git_repo.is_ancestor(branch, &remote) {
+            self.git_repo
+                .run(&["update-ref", &format!("refs/heads/{}", branch), &remote])?;
+            print_info(&format!("'{}' is frozen; following {}", branch, remote));
+        } else {
+            print_warning(&format!(
+                "Frozen branch '{}' has local commits that {} doesn't; keeping the local branch",
+                branch, remote
+            ));
+        }
+        self.git_repo.get_commit_hash_for_branch(branch)
+    }
+
+    /// Recreate stack branches that are missing locally, e.g. in a fresh clone that
+    /// was given the stack metadata. Each branch starts from `origin/<branch>` when the
+    /// remote has it, otherwise from its recorded commit if that is still available.
+    pub async fn restore_branches(&mut self, dry_run: bool) -> Result<()> {
+        print_train_header("Restoring Stack Branches");
+
+        let stack = self.get_or_load_current_stack()?;
+        let missing = self.missing_local_branches(&stack);
+        if missing.is_empty() {
+            print_success("Every stack branch exists locally");
+            return Ok(());
+        }
+
+        if !dry_run {
+            if let Err(e) = self.git_repo.run(&["fetch", "origin"]) {
+                print_warning(&format!("Could not fetch origin: {}", e));
+            }
+        }
+
+        let mut unrestorable = Vec::new();
+        for branch in &missing {
+            let Some(source) = self.restore_source(&stack, branch) else {
+                print_warning(&format!(
+                    "'{}' is not on origin and its recorded commit is not in this clone",
+                    branch
+                ));
+                unrestorable.push(branch.clone());
+                continue;
+            };
+            if dry_run {
+                print_info(&format!(
+                    "Would create '{}' from {}",
+                    branch,
+                    describe_restore_source(&source)
+                ));
+                continue;
+            }
+            self.restore_branch(branch, &source)?;
+        }
+
+        if !unrestorable.is_empty() {
+            return Err(TrainError::StackError {
+                message: format!(
+                    "Could not restore {}; fetch them from whoever pushed the stack",
+                    unrestorable.join(", ")
+                ),
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Where a missing `branch` can be recreated from: `origin/<branch>` when the remote
+    /// has it, otherwise its recorded commit if that is still in the clone
+    fn restore_source(&self, stack: &Stack, branch: &str) -> Option<String> {
+        if self.git_repo.has_remote_branch(branch) {
+            return Some(format!("origin/{}", branch));
+        }
+        stack
+            .branches
+            .get(branch)
+            .map(|b| b.commit_hash.as_str())
+            .filter(|hash| !hash.is_empty())
+            .filter(|hash| self.git_repo.has_commit(hash))
+            .map(str::to_string)
+    }
+
+    /// Recreate the local `branch` from a [`Self::restore_source`]
+    fn restore_branch(&self, branch: &str, source: &str) -> Result<()> {
+        if source.starts_with("origin/") {
+            self.git_repo.ensure_local_branch(branch)?;
+        } else {
+            self.git_repo.run(&["branch", branch, source])?;
+        }
+        print_success(&format!(
+            "Created '{}' from {}",
+            branch,
+            describe_restore_source(source)
+        ));
+        Ok(())
+    }
+
+    /// Deal with stack branches that were deleted locally with plain git: recreate each
+    /// one from origin or its recorded commit, or drop it from the stack so its children
+    /// build on its parent. Without `restore` or `remove`, asks for every branch.
+    pub async fn repair_stack(&mut self, restore: bool, remove: bool) -> Result<()> {
+        print_train_header("Repairing Stack");
+
+        let mut stack = self.get_or_load_current_stack()?;
+        let deleted = self.deleted_local_branches(&stack);
+        if deleted.is_empty() {
+            print_success("Every stack branch exists locally");
+            return Ok(());
+        }
+
+        let mut removed = Vec::new();
+        for branch in &deleted {
+            let source = self.restore_source(&stack, branch);
+            let parent = stack
+                .branches
+                .get(branch.as_str())
+                .and_then(|b| b.parent.clone())
+                .unwrap_or_else(|| stack.base_branch.clone());
+
+            let restore_branch = if restore {
+                true
+            } else if remove {
+                false
+            } else {
+                let mut options = Vec::new();
+                if let Some(source) = &source {
+                    options.push(format!(
+                        "Restore it from {}",
+                        describe_restore_source(source)
+                    ));
+                }
+                options.push(format!(
+                    "Remove it from the stack (its children move onto '{}')",
+                    parent
+                ));
+                options.push("Leave it for now".to_string());
+                let choice = ui::select_from_list(
+                    &options,
+                    &format!("Stack branch '{}' was deleted locally", branch),
+                )?;
+                match (choice, source.is_some()) {
+                    (0, true) => true,
+                    (0, false) | (1, true) => false,
+                    _ => continue,
                 }
+            };
+
+            if restore_branch {
+                match &source {
+                    Some(source) => self.restore_branch(branch, source)?,
+                    None => print_warning(&format!(
+                        "'{}' is not on origin and its recorded commit is not in this clone; run 'git-train repair --remove' to drop it",
+                        branch
+                    )),
+                }
+                continue;
             }
-            GitState::Conflicted => {
-                print_warning("Repository has unresolved conflicts.");
-                if let Some(conflicts) = self.conflict_resolver.detect_conflicts()? {
-                    self.conflict_resolver
-                        .resolve_conflicts_interactively(&conflicts)
-                        .await
-                } else {
-                    Err(TrainError::InvalidState {
-                        message: "Repository appears to have conflicts but none were detected"
-                            .to_string(),
-                    }
-                    .into())
+
+            if let Some((_, children)) = remove_branch_from_stack(&mut stack, branch) {
+                print_success(&format!("Removed '{}' from the stack", branch));
+                for child in children {
+                    print_info(&format!(
+                        "'{}' now builds on '{}' and still contains the commits of '{}'",
+                        child, parent, branch
+                    ));
                 }
+                removed.push(branch.clone());
+            }
+        }
+
+        if !removed.is_empty() {
+            if stack
+                .current_branch
+                .as_ref()
+                .is_some_and(|current| removed.iter().any(|b| b == current))
+            {
+                stack.current_branch = None;
             }
+            self.stack_state.save_stack(&stack)?;
+            self.current_stack = Some(stack);
         }
+        Ok(())
     }
 
-    pub async fn sync_with_remote(&mut self) -> Result<()> {
-        print_train_header("Syncing with Remote");
+    /// Stack branches (not the base) that are recorded in the metadata but no longer
+    /// exist locally, in tree order
+    pub fn deleted_local_branches(&self, stack: &Stack) -> Vec<String> {
+        self.missing_local_branches(stack)
+            .into_iter()
+            .filter(|branch| branch != &stack.base_branch)
+            .collect()
+    }
 
-        // First check and attempt to recover from any invalid git state
-        if let Err(e) = self.check_and_recover_git_state().await {
-            print_error(&format!("Cannot sync: {}", e));
-            print_info("Please resolve the git state issue first:");
-            print_info("• Conflicts will be handled during sync");
-            return Err(e);
+    /// The stack's base and stack branches that don't exist as local branches, in
+    /// tree order
+    fn missing_local_branches(&self, stack: &Stack) -> Vec<String> {
+        let hierarchy = self.build_branch_hierarchy(stack);
+        std::iter::once(stack.base_branch.to_string())
+            .chain(
+                self.branches_in_tree_order(&hierarchy, stack)
+                    .into_iter()
+                    .map(|(branch, _)| branch),
+            )
+            .filter(|branch| !self.git_repo.has_local_branch(branch))
+            .collect()
+    }
+
+    /// Fail with a pointer to `repair` when stack branches only exist in the metadata
+    fn ensure_local_branches(&self, stack: &Stack) -> Result<()> {
+        let missing = self.deleted_local_branches(stack);
+        if missing.is_empty() {
+            return Ok(());
         }
+        Err(TrainError::StackError {
+            message: format!(
+                "Stack branches missing locally: {}. Run 'git-train repair' to restore or remove them",
+                missing.join(", ")
+            ),
+        }
+        .into())
+    }
 
-        let stack = self.get_or_load_current_stack()?;
-        let current_branch = self.get_current_branch()?;
+    /// Stack branches that were pushed (they have an MR or an `origin/` ref) but no
+    /// longer exist on the remote, sorted by name
+    fn find_remote_deleted_branches(&self, stack: &Stack) -> Result<Vec<String>> {
+        let remote_heads = self.git_repo.run(&["ls-remote", "--heads", "origin"])?;
+        let remote_heads: std::collections::HashSet<&str> = remote_heads
+            .lines()
+            .filter_map(|line| line.split('\t').nth(1)?.strip_prefix("refs/heads/"))
+            .collect();
 
-        // Update the base branch
-        print_info(&format!("Updating base branch: {}", stack.base_branch));
-        self.git_repo.run(&["checkout", &stack.base_branch])?;
-        self.git_repo.run(&["pull", "origin", &stack.base_branch])?;
+        let mut deleted: Vec<String> = stack
+            .branches
+            .values()
+            .filter(|branch| !remote_heads.contains(branch.name.as_str()))
+            .filter(|branch| {
+                branch.mr_iid.is_some() || self.git_repo.has_remote_branch(&branch.name)
+            })
+            .map(|branch| branch.name.to_string())
+            .collect();
+        deleted.sort();
+        Ok(deleted)
+    }
 
-        // Rebase all stack branches with better error handling
-        let mut updated_stack = stack.clone();
-        let hierarchy = self.build_branch_hierarchy(&stack);
+    pub fn get_current_branch(&self) -> Result<String> {
+        self.git_repo.get_current_branch()
+    }
 
-        let rebase_result = {
-            let mut rebased_branches = std::collections::HashSet::new();
+    /// Name of the stack the next operation runs on, empty when there is none
+    fn current_stack_name(&mut self) -> String {
+        self.get_or_load_current_stack()
+            .map(|stack| stack.name)
+            .unwrap_or_default()
+    }
 
-            // Start with branches that have the base branch as a parent
-            let mut branches_to_rebase: Vec<String> = stack
-                .branches
-                .keys()
-                .filter(|k| {
-                    stack.branches.get(*k).and_then(|b| b.parent.as_ref())
-                        == Some(&stack.base_branch)
-                })
-                .cloned()
-                .collect();
+    /// Run `git commit` with `args`, adding `Stack-Id`/`Stack-Position` trailers when
+    /// `git.commit_trailers` is on. Amends replace the trailers instead of repeating them.
+    fn commit_with_trailers(&self, stack: &Stack, branch_name: &str, args: &[&str]) -> Result<()> {
+        let command = self.commit_command(stack, branch_name, args);
+        let command: Vec<&str> = command.iter().map(String::as_str).collect();
+        self.git_repo.run(&command)?;
+        Ok(())
+    }
 
-            // Sort for consistent order
-            branches_to_rebase.sort();
+    /// Arguments for `git commit` with `args`, plus the stack trailers when enabled
+    fn commit_command(&self, stack: &Stack, branch_name: &str, args: &[&str]) -> Vec<String> {
+        let mut command: Vec<String> = Vec::new();
+        if self.config.git.commit_trailers {
+            command.extend(["-c".to_string(), "trailer.ifexists=replace".to_string()]);
+        }
+        command.push("commit".to_string());
+        command.extend(args.iter().map(|arg| arg.to_string()));
+
+        if self.config.git.commit_trailers {
+            let position = self.get_branch_depth_in_stack(stack, branch_name) + 1;
+            command.extend([
+                "--trailer".to_string(),
+                format!("Stack-Id: {}", stack.id),
+                "--trailer".to_string(),
+                format!("Stack-Position: {}", position),
+            ]);
+        }
+        command
+    }
 
-            let mut all_rebased_ok = true;
-            let mut first_error: Option<anyhow::Error> = None;
+    /// Editor template for `commit` without `-m`: `message` followed by comment lines
+    /// (dropped by git) placing the branch in its stack
+    fn commit_message_template(&self, stack: &Stack, branch_name: &str, message: &str) -> String {
+        let position = self.get_branch_depth_in_stack(stack, branch_name) + 1;
+        let levels = stack
+            .branches
+            .keys()
+            .map(|branch| self.get_branch_depth_in_stack(stack, branch) + 1)
+            .max()
+            .unwrap_or(position);
+        let parent = stack
+            .branches
+            .get(branch_name)
+            .and_then(|branch| branch.parent.as_ref())
+            .unwrap_or(&stack.base_branch);
 
-            while let Some(branch_name) = branches_to_rebase.pop() {
-                if rebased_branches.contains(&branch_name) {
-                    continue;
+        format!(
+            "{}\n\n# Stack '{}', level {} of {}: {}\n# Parent: {}\n",
+            message.trim_end(),
+            stack.name,
+            position,
+            levels,
+            branch_name,
+            parent
+        )
+    }
+
+    fn get_current_commit_hash(&self) -> Result<String> {
+        self.git_repo.get_current_commit_hash()
+    }
+
+    pub fn has_uncommitted_changes(&self) -> Result<bool> {
+        self.git_repo.has_uncommitted_changes()
+    }
+
+    fn ensure_clean_working_directory(&self) -> Result<()> {
+        if self.has_uncommitted_changes()? {
+            return Err(TrainError::StackError {
+                message:
+                    "Working directory is not clean. Please commit or stash your changes first."
+                        .to_string(),
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Resume an interrupted manual conflict resolution from the first unresolved file,
+    /// first reporting where a command stopped by Ctrl+C left off
+    pub async fn continue_conflict_resolution(&mut self) -> Result<()> {
+        if let Some(interrupted) = self.git_repo.take_interrupted()? {
+            print_train_header("Resuming Interrupted Command");
+            print_warning(&format!(
+                "'{}' was interrupted at {} while running '{}'",
+                interrupted.command,
+                interrupted
+                    .interrupted_at
+                    .with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M:%S"),
+                interrupted.git_command
+            ));
+            if self.conflict_resolver.load_session()?.is_none() {
+                match self.conflict_resolver.get_git_state()? {
+                    GitState::Clean => print_info(&format!(
+                        "No git operation was left half-done; re-run '{}' to finish",
+                        interrupted.command
+                    )),
+                    state => print_info(&format!(
+                        "Git is still in a {:?} state: finish it (e.g. 'git rebase --continue') or undo it with '--abort', then re-run '{}'",
+                        state, interrupted.command
+                    )),
                 }
+                return Ok(());
+            }
+        }
 
-                let parent_branch_name = stack
-                    .branches
-                    .get(&branch_name)
-                    .and_then(|b| b.parent.clone())
-                    .unwrap_or_else(|| stack.base_branch.to_string());
+        print_train_header("Resuming Conflict Resolution");
+        self.conflict_resolver.resume_session().await?;
+        print_info(
+            "Re-run the git-train command that hit the conflicts (e.g. 'git-train sync') to finish updating the stack",
+        );
+        Ok(())
+    }
 
-                self.git_repo.run(&["checkout", &branch_name])?;
+    /// Operate on the named stack for the rest of this process instead of the active one.
+    ///
+    /// Backs the global `--stack` flag: the stack is used as if it were current, but
+    /// `current.json` is never read or rewritten, so scripts don't disturb the user's
+    /// interactive session.
+    pub fn pin_stack(&mut self, stack_identifier: &str) -> Result<()> {
+        let stack = self.stack_state.find_by_identifier(stack_identifier)?;
+        self.stack_state.pin(&stack);
+        self.current_stack = None;
+        Ok(())
+    }
 
-                match self.smart_rebase(&branch_name, &parent_branch_name).await {
-                    Ok(_) => {
-                        // Update commit hash
-                        if let Some(branch) = updated_stack.branches.get_mut(&branch_name) {
-                            branch.commit_hash = self.get_current_commit_hash()?;
-                            branch.updated_at = Utc::now();
-                        }
-                        rebased_branches.insert(branch_name.clone());
+    /// Local branches stacked on one another up to `top`, bottom first: each branch's tip
+    /// is in the history of the next and all of them have commits beyond `base`. When
+    /// several branches qualify as the level below, the one furthest from `base` wins.
+    /// Branches that already belong to a stack are left out.
+    pub fn detect_branch_chain(&self, top: &str, base: &str) -> Result<Vec<String>> {
+        let stacked: HashSet<String> = self
+            .stack_state
+            .list()?
+            .iter()
+            .flat_map(|stack| stack.branches.keys().map(|b| b.to_string()))
+            .collect();
+        if stacked.contains(top) {
+            return Err(TrainError::StackError {
+                message: format!("Branch '{}' already belongs to a stack", top),
+            }
+            .into());
+        }
 
-                        // Add children of this branch to the queue
-                        if let Some(children) = hierarchy.get(&branch_name) {
-                            for child in children {
-                                branches_to_rebase.push(child.clone());
-                            }
-                        }
+        let base_ref = self.git_repo.resolve_branch(base);
+        let commits_beyond_base = |branch: &str| {
+            self.git_repo
+                .run(&["rev-list", "--count", &format!("{}..{}", base_ref, branch)])
+                .ok()
+                .and_then(|count| count.trim().parse::<usize>().ok())
+                .unwrap_or(0)
+        };
+        let candidates: Vec<String> = self
+            .git_repo
+            .local_branches()
+            .into_iter()
+            .filter(|branch| branch != base && !stacked.contains(branch))
+            .collect();
+
+        let mut chain = vec![top.to_string()];
+        loop {
+            let current = chain.last().unwrap().clone();
+            let current_tip = self.git_repo.get_commit_hash_for_branch(&current)?;
+            let below = candidates
+                .iter()
+                .filter(|branch| !chain.contains(branch))
+                .filter(|branch| {
+                    self.git_repo
+                        .get_commit_hash_for_branch(branch)
+                        .is_ok_and(|tip| tip != current_tip)
+                })
+                .filter(|branch| self.git_repo.is_ancestor(branch, &current))
+                .map(|branch| (branch, commits_beyond_base(branch)))
+                .filter(|(_, commits)| *commits > 0)
+                .max_by_key(|(_, commits)| *commits);
+            match below {
+                Some((branch, _)) => chain.push(branch.clone()),
+                None => break,
+            }
+        }
+
+        chain.reverse();
+        Ok(chain)
+    }
+
+    /// Create stack `name` on `base` from existing branches, bottom first, each level
+    /// stacked on the one before it. Leaves the original branch checked out.
+    pub async fn adopt_branch_chain(
+        &mut self,
+        name: &str,
+        chain: &[String],
+        base: &str,
+    ) -> Result<()> {
+        let Some(bottom) = chain.first() else {
+            return Err(TrainError::StackError {
+                message: "No branches to adopt".to_string(),
+            }
+            .into());
+        };
+        self.ensure_clean_working_directory()?;
+        let original = self.get_current_branch()?;
+
+        let result = async {
+            self.git_repo.run(&["checkout", bottom])?;
+            self.create_stack_with_base(name, Some(base)).await?;
+            for pair in chain.windows(2) {
+                self.git_repo.run(&["checkout", &pair[1]])?;
+                self.add_branch_to_stack(Some(&pair[0])).await?;
+            }
+            anyhow::Ok(())
+        }
+        .await;
+
+        self.git_repo.run(&["checkout", &original])?;
+        result
+    }
+
+    /// Rebuild stack metadata from the stack table in MR `iid`'s description, e.g. after
+    /// `.git/train` was lost. Each MR listed in the table becomes a level whose parent is
+    /// the MR's target branch. Levels that never had an MR can't be recovered.
+    pub async fn recover_from_mr(&mut self, iid: MrIid, name: Option<&str>) -> Result<()> {
+        print_train_header(&format!("Recovering Stack from !{}", iid));
+
+        let gitlab_client = self.gitlab().await.ok_or_else(|| TrainError::GitLabError {
+            message: "GitLab client not available; recovering a stack reads its MRs".to_string(),
+        })?;
+        let mr = gitlab_client.get_merge_request(iid).await?;
+        let iids = markdown::stack_table_mr_iids(&mr.description);
+        if iids.is_empty() {
+            return Err(TrainError::StackError {
+                message: format!("!{} has no git-train stack table to recover from", iid),
+            }
+            .into());
+        }
+
+        let mut mrs = Vec::new();
+        for listed in iids {
+            if listed == mr.iid {
+                mrs.push(mr.clone());
+            } else {
+                mrs.push(gitlab_client.get_merge_request(listed).await?);
+            }
+        }
+        let gitlab_project = gitlab_client.detect_and_cache_project().await.ok();
+
+        let sources: HashSet<&str> = mrs.iter().map(|m| m.source_branch.as_str()).collect();
+        let Some(base_branch) = mrs
+            .iter()
+            .map(|m| m.target_branch.as_str())
+            .find(|target| !sources.contains(target))
+            .map(str::to_string)
+        else {
+            return Err(TrainError::StackError {
+                message: "The listed MRs target each other in a cycle; there is no base branch"
+                    .to_string(),
+            }
+            .into());
+        };
+
+        let stacks = self.stack_state.list()?;
+        for source in &sources {
+            if let Some(owner) = stacks.iter().find(|s| s.branches.contains_key(*source)) {
+                return Err(TrainError::StackError {
+                    message: format!(
+                        "'{}' already belongs to stack '{}'; delete it first to recover from !{}",
+                        source, owner.name, iid
+                    ),
+                }
+                .into());
+            }
+        }
+        let stack_name = name
+            .or_else(|| markdown::stack_name_from_title(&mr.title))
+            .unwrap_or(&mrs[0].source_branch);
+        let stack_name = BranchName::sanitized(stack_name, &self.config.naming).to_string();
+        if stacks.iter().any(|s| s.name == stack_name) {
+            return Err(TrainError::StackError {
+                message: format!(
+                    "A stack named '{}' already exists; pass --name to recover under another name",
+                    stack_name
+                ),
+            }
+            .into());
+        }
+
+        if let Err(e) = self.git_repo.run(&["fetch", "origin"]) {
+            print_warning(&format!("Could not fetch origin: {}", e));
+        }
+
+        let mut branches = HashMap::new();
+        let mut missing = Vec::new();
+        for mr in &mrs {
+            let name = BranchName::from(mr.source_branch.as_str());
+            let commit_hash = match self
+                .git_repo
+                .get_commit_hash_for_branch(&self.git_repo.resolve_branch(&name))
+            {
+                Ok(hash) => hash,
+                // GitLab usually deletes the branch of a merged MR; the MR still knows its tip
+                Err(_) => {
+                    if mr.state != "merged" {
+                        missing.push(name.to_string());
                     }
-                    Err(e) => {
-                        print_error(&format!("Failed to rebase branch '{}': {}", branch_name, e));
-                        all_rebased_ok = false;
+                    mr.sha.clone().unwrap_or_default()
+                }
+            };
+            let mut children: Vec<BranchName> = mrs
+                .iter()
+                .filter(|child| child.target_branch == mr.source_branch)
+                .map(|child| BranchName::from(child.source_branch.as_str()))
+                .collect();
+            children.sort();
+            branches.insert(
+                name.clone(),
+                StackBranch {
+                    name,
+                    parent: Some(BranchName::from(mr.target_branch.as_str())),
+                    children,
+                    commit_hash,
+                    mr_iid: Some(mr.iid),
+                    mr_title: Some(mr.title.clone()),
+                    mr_template: None,
+                    review_notes: Vec::new(),
+                    target_history: Vec::new(),
+                    description_hash: None,
+                    remote_sha: None,
+                    state: BranchState::of_mr(mr.state == "merged", &mr.title),
+                    created_at: Utc::now(),
+                    updated_at: Utc::now(),
+                },
+            );
+        }
+
+        let current_branch = self
+            .get_current_branch()
+            .ok()
+            .filter(|branch| branches.contains_key(branch.as_str()))
+            .map(BranchName::from);
+        let stack = Stack {
+            id: StackId::generate(),
+            name: stack_name.clone(),
+            base_branch: BranchName::from(&base_branch),
+            branches,
+            current_branch,
+            gitlab_project,
+            mr_template: None,
+            update_strategy: self.config.git.update_strategy,
+            owner: self.git_repo.user_email(),
+            archived: false,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        self.stack_state.save_stack(&stack)?;
+
+        for (branch, depth) in self.levels_in_order(&stack) {
+            if let Some(iid) = stack.branches.get(branch.as_str()).and_then(|b| b.mr_iid) {
+                print_info(&format!("{}{} (!{})", "  ".repeat(depth), branch, iid));
+            }
+        }
+        self.current_stack = Some(stack);
+        print_success(&format!(
+            "Recovered stack '{}' on '{}' from !{}",
+            stack_name, base_branch, iid
+        ));
+        if !missing.is_empty() {
+            print_warning(&format!(
+                "Not in this clone: {}. Run 'git-train restore' once they are pushed",
+                missing.join(", ")
+            ));
+        }
+        Ok(())
+    }
+
+    /// Take the repository-wide lock for `operation`; see [`OperationLock::acquire`]
+    pub fn lock_operation(&self, operation: &str, steal: bool) -> Result<OperationLock> {
+        OperationLock::acquire(&self.git_repo.train_dir(), operation, steal)
+    }
 
-                        // Store the first error, especially if it's a conflict resolution error
-                        if first_error.is_none() {
-                            first_error = Some(e);
-                        }
+    /// Drop the cached current stack so the next call reads it from disk again.
+    ///
+    /// Long-lived callers (`git-train serve`) use this between requests so they see
+    /// changes made by other git-train processes.
+    pub fn forget_current_stack(&mut self) {
+        self.current_stack = None;
+    }
 
-                        // Don't proceed with children if parent fails
-                    }
+    /// Gets the current stack, loading it if not already cached
+    pub fn get_or_load_current_stack(&mut self) -> Result<Stack> {
+        match &self.current_stack {
+            Some(stack) => Ok(stack.clone()),
+            None => {
+                let mut stack = self.stack_state.load_current()?;
+                let deleted = self.deleted_local_branches(&stack);
+                if !deleted.is_empty() {
+                    print_warning(&format!(
+                        "Stack branches deleted locally: {}. Run 'git-train repair' to restore or remove them",
+                        deleted.join(", ")
+                    ));
                 }
-            }
-
-            if all_rebased_ok {
-                Ok(())
-            } else {
-                // Return the specific error if available, otherwise use generic message
-                if let Some(original_error) = first_error {
-                    Err(original_error)
-                } else {
-                    Err(TrainError::GitError {
-                        message: "One or more branches failed to rebase".to_string(),
-                    }
-                    .into())
+                if self.track_head(&mut stack) {
+                    self.stack_state.save_stack(&stack)?;
                 }
+                self.current_stack = Some(stack.clone());
+                Ok(stack)
             }
+        }
+    }
+
+    /// Make the stack that owns the checked-out branch active when it isn't already,
+    /// according to `git.auto_switch_stack`
+    pub fn follow_head_to_owning_stack(&mut self) -> Result<()> {
+        let Ok(head) = self.get_current_branch() else {
+            return Ok(());
         };
+        let active_id = self.stack_state.get_current_stack_id()?;
+        let stacks = self.stack_state.list()?;
 
-        if let Err(e) = rebase_result {
-            print_error(&format!("Some branches failed to rebase: {}", e));
-            print_info("You can:");
-            print_info("• Re-run 'git-train sync' to handle conflicts");
-            print_info("• Run 'git-train sync' again after resolving issues");
+        if stacks
+            .iter()
+            .any(|s| s.id == active_id.trim() && s.branches.contains_key(&head))
+        {
+            return Ok(());
+        }
+        let Some(owner) = stacks.into_iter().find(|s| s.branches.contains_key(&head)) else {
+            return Ok(());
+        };
 
-            // Try to return to a safe state
-            if self.git_repo.run(&["checkout", &current_branch]).is_err() {
-                print_warning(&format!(
-                    "Could not return to original branch '{}'. You may need to checkout manually.",
-                    current_branch
-                ));
+        let should_switch = match self.config.git.auto_switch_stack {
+            crate::config::AutoSwitchMode::Always => true,
+            crate::config::AutoSwitchMode::Prompt => confirm_action(&format!(
+                "Branch '{}' belongs to stack '{}'. Switch to it?",
+                head, owner.name
+            ))
+            .unwrap_or(false),
+            crate::config::AutoSwitchMode::Never => false,
+        };
+
+        if should_switch {
+            self.stack_state.set_current(&owner)?;
+            print_info(&format!(
+                "Switched to stack '{}', which owns branch '{}'",
+                owner.name, head
+            ));
+            self.current_stack = Some(owner);
+        } else {
+            print_warning(&format!(
+                "Branch '{}' belongs to stack '{}', not the active stack. Run 'git-train switch {}' to change.",
+                head, owner.name, owner.name
+            ));
+        }
+        Ok(())
+    }
+
+    /// One-line summary for shell prompts, e.g. `my-stack 3/5 !12 ↑2 ✖conflict`, or `None`
+    /// without an active stack. Reads only local metadata and never talks to GitLab, so
+    /// it is cheap enough to run on every prompt.
+    pub fn prompt_summary(&self) -> Option<String> {
+        let stack = self.stack_state.load_current().ok()?;
+        let mut parts = vec![stack.name.clone()];
+
+        if let Ok(head) = self.git_repo.get_current_branch() {
+            let hierarchy = self.build_branch_hierarchy(&stack);
+            let order = self.branches_in_tree_order(&hierarchy, &stack);
+            if let Some(position) = order.iter().position(|(branch, _)| *branch == head) {
+                parts.push(format!("{}/{}", position + 1, order.len()));
+                if let Some(iid) = stack.branches.get(head.as_str()).and_then(|b| b.mr_iid) {
+                    parts.push(format!("!{}", iid));
+                }
+                if self.git_repo.has_remote_branch(&head) {
+                    let unpushed = self
+                        .git_repo
+                        .run(&["rev-list", "--count", &format!("origin/{}..{}", head, head)])
+                        .ok()
+                        .and_then(|count| count.trim().parse::<usize>().ok())
+                        .unwrap_or(0);
+                    if unpushed > 0 {
+                        parts.push(format!("↑{}", unpushed));
+                    }
+                }
             }
+        }
 
-            return Err(e);
+        // Marker files only: `git status` is too slow for a prompt in large repositories
+        let git_dir = self.git_repo.git_dir();
+        let operation =
+            if git_dir.join("rebase-merge").exists() || git_dir.join("rebase-apply").exists() {
+                Some("rebase")
+            } else if git_dir.join("MERGE_HEAD").exists() {
+                Some("merge")
+            } else if git_dir.join("CHERRY_PICK_HEAD").exists() {
+                Some("cherry-pick")
+            } else {
+                None
+            };
+        if let Some(operation) = operation {
+            let conflicted = self
+                .git_repo
+                .run(&["diff", "--name-only", "--diff-filter=U"])
+                .is_ok_and(|files| !files.trim().is_empty());
+            parts.push(format!(
+                "✖{}",
+                if conflicted { "conflict" } else { operation }
+            ));
         }
 
-        // Update merge request targets if GitLab client is available
-        if self.gitlab_client.is_some() {
-            print_info("Updating merge request targets after sync...");
-            self.process_branches_with_mrs_for_updates(
-                &mut updated_stack,
-                "Updated MR targets for",
-            )
-            .await;
+        Some(parts.join(" "))
+    }
 
-            self.update_all_mr_descriptions(&mut updated_stack).await;
+    /// Bring recorded branch tips and current branches in line with the repository after
+    /// a commit, checkout or rewrite made outside git-train (run by the installed hooks).
+    ///
+    /// A rewritten branch keeps its recorded commit while a child still builds on it, so
+    /// the next sync can replay only the child's own commits.
+    pub fn refresh_from_hook(&mut self, hook: &str) -> Result<()> {
+        if !matches!(self.conflict_resolver.get_git_state()?, GitState::Clean) {
+            // post-rewrite runs again once the rebase or merge is finished
+            return Ok(());
         }
+        info!("Refreshing stack metadata from the {} hook", hook);
 
-        // Switch back to the original branch
-        self.git_repo.run(&["checkout", &current_branch])?;
+        for mut stack in self.stack_state.list()? {
+            let mut changed = self.track_head(&mut stack);
 
-        // Save the updated stack
-        self.stack_state.save_stack(&updated_stack)?;
-        self.current_stack = Some(updated_stack);
+            let mut moved = Vec::new();
+            for (name, branch) in &stack.branches {
+                let Ok(tip) = self.git_repo.get_commit_hash_for_branch(name) else {
+                    continue;
+                };
+                if tip == branch.commit_hash {
+                    continue;
+                }
+                let rewritten = !self.git_repo.is_ancestor(&branch.commit_hash, &tip);
+                let child_builds_on_old = stack.branches.values().any(|child| {
+                    child.parent.as_deref() == Some(name.as_str())
+                        && self.git_repo.is_ancestor(&branch.commit_hash, &child.name)
+                });
+                if rewritten && child_builds_on_old {
+                    info!(
+                        "Keeping recorded commit of rewritten branch '{}' until the next sync",
+                        name
+                    );
+                    continue;
+                }
+                moved.push((name.clone(), tip));
+            }
 
-        print_success("Stack synchronized with remote and MR targets updated");
+            for (name, tip) in moved {
+                if let Some(branch) = stack.branches.get_mut(&name) {
+                    branch.commit_hash = tip;
+                    branch.updated_at = Utc::now();
+                    changed = true;
+                }
+            }
+
+            if changed {
+                stack.updated_at = Utc::now();
+                self.stack_state.write_stack(&stack)?;
+            }
+        }
 
+        self.current_stack = None;
         Ok(())
     }
 
-    pub fn get_current_branch(&self) -> Result<String> {
-        self.git_repo.get_current_branch()
+    /// Point `stack.current_branch` at HEAD when HEAD is one of the stack's branches.
+    ///
+    /// Returns whether the recorded branch changed.
+    fn track_head(&self, stack: &mut Stack) -> bool {
+        let Ok(head) = self.get_current_branch() else {
+            return false;
+        };
+        if !stack.branches.contains_key(&head) || stack.current_branch.as_deref() == Some(&head) {
+            return false;
+        }
+        stack.current_branch = Some(head.into());
+        true
     }
 
-    fn get_current_commit_hash(&self) -> Result<String> {
-        self.git_repo.get_current_commit_hash()
+    /// MRs whose target on GitLab differs from the one git-train last set
+    pub async fn find_unexpected_retargets(&self, stack: &Stack) -> Vec<UnexpectedRetarget> {
+        if self.gitlab().await.is_none() {
+            return Vec::new();
+        }
+        let branch_mr_status = self.collect_mr_status_info(stack).await;
+        unexpected_retargets(stack, &branch_mr_status)
     }
 
-    pub fn has_uncommitted_changes(&self) -> Result<bool> {
-        self.git_repo.has_uncommitted_changes()
-    }
+    /// Open MRs on GitLab for stack branches that have no MR recorded locally
+    pub async fn find_untracked_mrs(&self, stack: &Stack) -> Result<Vec<(String, MergeRequest)>> {
+        let Some(gitlab) = self.gitlab().await else {
+            return Ok(Vec::new());
+        };
 
-    fn ensure_clean_working_directory(&self) -> Result<()> {
-        if self.has_uncommitted_changes()? {
-            return Err(TrainError::StackError {
-                message:
-                    "Working directory is not clean. Please commit or stash your changes first."
-                        .to_string(),
+        let mut branch_names: Vec<&BranchName> = stack
+            .branches
+            .iter()
+            .filter(|(_, b)| b.mr_iid.is_none())
+            .map(|(name, _)| name)
+            .collect();
+        branch_names.sort();
+
+        let mut untracked = Vec::new();
+        for branch_name in branch_names {
+            if let Some(mr) = self
+                .find_open_mr_for_branch(branch_name, stack, gitlab)
+                .await?
+            {
+                untracked.push((branch_name.to_string(), mr));
             }
-            .into());
         }
-        Ok(())
+        Ok(untracked)
     }
 
-    /// Gets the current stack, loading it if not already cached
-    pub fn get_or_load_current_stack(&mut self) -> Result<Stack> {
-        match &self.current_stack {
-            Some(stack) => Ok(stack.clone()),
-            None => {
-                let stack = self.stack_state.load_current()?;
-                self.current_stack = Some(stack.clone());
-                Ok(stack)
-            }
+    /// Look up an open MR from `branch_name` pushed to our project: the fork we push to
+    /// when MRs are opened upstream, otherwise the stack's project (ignoring other forks)
+    async fn find_open_mr_for_branch(
+        &self,
+        branch_name: &str,
+        stack: &Stack,
+        gitlab: &(dyn GitLabApi + Send + Sync),
+    ) -> Result<Option<MergeRequest>> {
+        let project_id = match gitlab.fork_project().await? {
+            Some(fork) => Some(fork.id),
+            None => stack.gitlab_project.as_ref().map(|p| p.id),
+        };
+        let mrs = gitlab
+            .list_merge_requests_by_source_branch(branch_name, Some("opened"))
+            .await?;
+        Ok(mrs
+            .into_iter()
+            .find(|mr| match (mr.source_project_id, project_id) {
+                (Some(source), Some(project)) => source == project,
+                _ => true,
+            }))
+    }
+
+    /// Reconcile the active stack's recorded current branch with HEAD and persist it.
+    ///
+    /// Run after every command so checkouts made by git-train (or by hand) are reflected
+    /// by the next `status` or `switch --checkout`.
+    pub fn record_current_branch(&mut self) -> Result<()> {
+        // Re-read from disk: the command may have deleted or replaced the active stack
+        let Ok(mut stack) = self.stack_state.load_current() else {
+            return Ok(());
+        };
+        if self.track_head(&mut stack) {
+            self.stack_state.save_stack(&stack)?;
+        }
+        if self
+            .current_stack
+            .as_ref()
+            .is_some_and(|cached| cached.id == stack.id)
+        {
+            self.current_stack = Some(stack);
         }
+        Ok(())
     }
 
-    /// Collects MR status information for all branches in the stack
+    /// Collects MR status information for all branches in the stack, fetching up to
+    /// `MR_STATUS_CONCURRENCY` MRs from GitLab at a time
     async fn collect_mr_status_info(
         &self,
         stack: &Stack,
     ) -> std::collections::HashMap<String, MrStatusInfo> {
-        let mut branch_mr_status = std::collections::HashMap::new();
-
-        if let Some(gitlab_client) = &self.gitlab_client {
-            for (branch_name, branch) in &stack.branches {
-                if let Some(mr_iid) = branch.mr_iid {
-                    // Fetch current MR status from GitLab
-                    match gitlab_client.get_merge_request(mr_iid).await {
-                        Ok(mr) => {
-                            branch_mr_status.insert(
-                                branch_name.clone(),
-                                MrStatusInfo {
-                                    iid: mr_iid,
-                                    state: mr.state,
-                                },
-                            );
-                        }
-                        Err(_) => {
-                            // If we can't fetch MR status, show as unknown
-                            branch_mr_status.insert(
-                                branch_name.clone(),
-                                MrStatusInfo {
-                                    iid: mr_iid,
-                                    state: "unknown".to_string(),
-                                },
-                            );
-                        }
-                    }
-                }
-            }
-        } else {
+        let Some(gitlab_client) = self.gitlab().await else {
             // No GitLab client, just use the stored MR IIDs without status
-            for (branch_name, branch) in &stack.branches {
-                if let Some(mr_iid) = branch.mr_iid {
-                    branch_mr_status.insert(
-                        branch_name.clone(),
+            return Self::stored_mr_status(stack, false);
+        };
+
+        let fetches = stack.branches.iter().filter_map(|(branch_name, branch)| {
+            let mr_iid = branch.mr_iid?;
+            Some(async move {
+                let status = match gitlab_client.get_merge_request(mr_iid).await {
+                    Ok(mr) => MrStatusInfo {
+                        iid: mr_iid,
+                        conflicts: mr.conflicts_with_target(),
+                        state: mr.state,
+                        target_branch: Some(mr.target_branch),
+                        stale: false,
+                        title: Some(mr.title),
+                        head_sha: mr.sha,
+                    },
+                    // If we can't fetch MR status, show as unknown
+                    Err(e) => {
+                        self.disable_gitlab_on_auth_error(&e);
                         MrStatusInfo {
                             iid: mr_iid,
                             state: "unknown".to_string(),
-                        },
-                    );
-                }
+                            target_branch: None,
+                            stale: false,
+                            conflicts: false,
+                            title: None,
+                            head_sha: None,
+                        }
+                    }
+                };
+                (branch_name.to_string(), status)
+            })
+        });
+
+        stream::iter(fetches)
+            .buffer_unordered(MR_STATUS_CONCURRENCY)
+            .collect()
+            .await
+    }
+
+    /// Deployed environment URLs of the levels that have MRs, in tree order, as
+    /// (branch, environment, url). Levels whose environments can't be listed are skipped.
+    async fn collect_review_apps(
+        &self,
+        hierarchy: &HashMap<String, Vec<String>>,
+        stack: &Stack,
+    ) -> Vec<(String, String, String)> {
+        let Some(gitlab_client) = self.gitlab().await else {
+            return Vec::new();
+        };
+
+        let branches: Vec<String> = self
+            .branches_in_tree_order(hierarchy, stack)
+            .into_iter()
+            .map(|(branch, _)| branch)
+            .filter(|branch| stack.branches[branch.as_str()].mr_iid.is_some())
+            .collect();
+        let fetches = branches.into_iter().map(|branch| async move {
+            let environments: Vec<Environment> = gitlab_client
+                .list_branch_environments(&branch)
+                .await
+                .unwrap_or_else(|e| {
+                    self.disable_gitlab_on_auth_error(&e);
+                    Vec::new()
+                });
+            environments
+                .into_iter()
+                .filter_map(|env| Some((branch.clone(), env.name, env.external_url?)))
+                .collect::<Vec<_>>()
+        });
+
+        stream::iter(fetches)
+            .buffered(MR_STATUS_CONCURRENCY)
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+
+    /// Move levels along their lifecycle according to freshly fetched MR `statuses`,
+    /// archiving the stack once all are merged. Returns whether the stack changed.
+    fn record_mr_states(
+        &self,
+        stack: &mut Stack,
+        statuses: &HashMap<String, MrStatusInfo>,
+    ) -> bool {
+        let mut changed = false;
+        for (branch_name, status) in statuses {
+            if status.stale || !matches!(status.state.as_str(), "opened" | "merged") {
+                continue;
+            }
+            if let Some(branch) = stack.branches.get_mut(branch_name.as_str()) {
+                let before = branch.state;
+                branch.observe_mr(
+                    status.state == "merged",
+                    status.title.as_deref().unwrap_or_default(),
+                );
+                changed |= branch.state != before;
             }
         }
+        self.archive_if_complete(stack) || changed
+    }
+
+    /// Archive `stack` once every level is merged, if `mr.archive_merged_stacks` allows
+    fn archive_if_complete(&self, stack: &mut Stack) -> bool {
+        let (merged, total) = stack.merge_progress();
+        if stack.archived || total == 0 || merged < total || !self.config.mr.archive_merged_stacks {
+            return false;
+        }
+        stack.archived = true;
+        print_success(&format!(
+            "🎉 All {} levels of '{}' are merged. Stack complete!",
+            total, stack.name
+        ));
+        print_info("The stack is archived; 'git-train list --archived' still shows it");
+        true
+    }
 
-        branch_mr_status
+    /// MR status from the stored MR IIDs alone, without asking GitLab
+    fn stored_mr_status(stack: &Stack, stale: bool) -> HashMap<String, MrStatusInfo> {
+        stack
+            .branches
+            .iter()
+            .filter_map(|(branch_name, branch)| {
+                let status = MrStatusInfo {
+                    iid: branch.mr_iid?,
+                    state: "unknown".to_string(),
+                    target_branch: None,
+                    stale,
+                    conflicts: false,
+                    title: None,
+                    head_sha: None,
+                };
+                Some((branch_name.to_string(), status))
+            })
+            .collect()
     }
 
     /// Process all branches in the stack for MR creation/updates
-    async fn process_all_branches_for_mrs(&self, stack: &mut Stack, success_message_prefix: &str) {
-        if self.gitlab_client.is_some() {
-            let branches_to_process: Vec<(String, StackBranch)> =
-                stack.branches.clone().into_iter().collect();
+    async fn process_all_branches_for_mrs(
+        &self,
+        stack: &mut Stack,
+        success_message_prefix: &str,
+        template_override: Option<&str>,
+        only: Option<&HashSet<String>>,
+    ) {
+        if self.gitlab().await.is_some() {
+            let branches_to_process: Vec<(BranchName, StackBranch)> = stack
+                .branches
+                .clone()
+                .into_iter()
+                .filter(|(name, _)| only.is_none_or(|only| only.contains(name.as_str())))
+                .filter(|(_, branch)| !branch.state.is_held_back())
+                .collect();
             for (branch_name, branch) in branches_to_process {
                 match self
                     .create_or_update_mr_with_smart_targeting_and_store(
                         &branch_name,
                         &branch,
                         stack,
+                        template_override,
                     )
                     .await
                 {
                     Ok(_) => print_success(&format!("{} {}", success_message_prefix, branch_name)),
+                    Err(e) if self.disable_gitlab_on_auth_error(&e) => break,
                     Err(e) => {
                         print_warning(&format!("Failed to update MR for {}: {}", branch_name, e))
                     }
                 }
             }
-        } else {
+        } else if !self.gitlab_disabled.load(Ordering::Relaxed) {
             print_warning("GitLab client not available. Configure GitLab integration first.");
         }
     }
@@ -2049,22 +7646,24 @@ impl StackManager {
         stack: &mut Stack,
         success_message_prefix: &str,
     ) {
-        if self.gitlab_client.is_some() {
-            let branches_to_process: Vec<(String, StackBranch)> =
+        if self.gitlab().await.is_some() {
+            let branches_to_process: Vec<(BranchName, StackBranch)> =
                 stack.branches.clone().into_iter().collect();
             for (branch_name, branch) in branches_to_process {
-                if branch.mr_iid.is_some() {
+                if branch.mr_iid.is_some() && !branch.state.is_held_back() {
                     match self
                         .create_or_update_mr_with_smart_targeting_and_store(
                             &branch_name,
                             &branch,
                             stack,
+                            None,
                         )
                         .await
                     {
                         Ok(_) => {
                             print_success(&format!("{} {}", success_message_prefix, branch_name))
                         }
+                        Err(e) if self.disable_gitlab_on_auth_error(&e) => break,
                         Err(e) => print_warning(&format!(
                             "Failed to update MR for {}: {}",
                             branch_name, e
@@ -2072,131 +7671,474 @@ impl StackManager {
                     }
                 }
             }
-        } else {
-            print_warning("GitLab client not available. Configure GitLab integration first.");
+        } else if !self.gitlab_disabled.load(Ordering::Relaxed) {
+            print_warning("GitLab client not available. Configure GitLab integration first.");
+        }
+    }
+
+    /// Levels whose changes exceed `mr.max_changed_lines` or `mr.max_changed_files`,
+    /// with their per-file diff stats
+    pub fn oversized_levels(&self, stack: &Stack) -> Vec<(String, Vec<FileDiffStat>)> {
+        let max_lines = self.config.mr.max_changed_lines;
+        let max_files = self.config.mr.max_changed_files;
+        if max_lines == 0 && max_files == 0 {
+            return Vec::new();
+        }
+
+        let hierarchy = self.build_branch_hierarchy(stack);
+        let mut oversized = Vec::new();
+        for (branch_name, _) in self.branches_in_tree_order(&hierarchy, stack) {
+            let parent = stack.branches[&branch_name]
+                .parent
+                .as_deref()
+                .unwrap_or(&stack.base_branch);
+            let Ok(files) = self
+                .git_repo
+                .diff_stat(&self.git_repo.resolve_branch(parent), &branch_name)
+            else {
+                continue;
+            };
+            let lines: usize = files.iter().map(FileDiffStat::changed).sum();
+            if (max_lines > 0 && lines > max_lines) || (max_files > 0 && files.len() > max_files) {
+                oversized.push((branch_name, files));
+            }
+        }
+        oversized
+    }
+
+    /// Warn about levels that are too big to review comfortably and suggest where to split them
+    fn warn_about_oversized_levels(&self, stack: &Stack) {
+        let oversized = self.oversized_levels(stack);
+        if oversized.is_empty() {
+            return;
+        }
+
+        ui::print_info("");
+        for (branch_name, files) in &oversized {
+            let lines: usize = files.iter().map(FileDiffStat::changed).sum();
+            print_warning(&format!(
+                "'{}' changes {} lines in {} files (guideline: {} lines, {} files)",
+                branch_name,
+                lines,
+                files.len(),
+                self.config.mr.max_changed_lines,
+                self.config.mr.max_changed_files
+            ));
+            let clusters = suggest_split_points(files);
+            if !clusters.is_empty() {
+                print_info("  Suggested split points:");
+                for (dir, lines, count) in clusters.iter().take(5) {
+                    print_info(&format!("    • {} ({} lines, {} files)", dir, lines, count));
+                }
+            }
+        }
+        print_info(
+            "Smaller levels review faster: move each group onto its own level with 'git-train branch create'",
+        );
+    }
+
+    /// The stack's branches with their depth, parents before children
+    pub fn levels_in_order(&self, stack: &Stack) -> Vec<(String, usize)> {
+        let hierarchy = self.build_branch_hierarchy(stack);
+        self.branches_in_tree_order(&hierarchy, stack)
+    }
+
+    /// Branches of the stack in depth-first order with their depth below the base branch
+    fn branches_in_tree_order(
+        &self,
+        hierarchy: &HashMap<String, Vec<String>>,
+        stack: &Stack,
+    ) -> Vec<(String, usize)> {
+        fn visit(
+            hierarchy: &HashMap<String, Vec<String>>,
+            branch: &str,
+            depth: usize,
+            visited: &mut std::collections::HashSet<String>,
+            result: &mut Vec<(String, usize)>,
+        ) {
+            if !visited.insert(branch.to_string()) {
+                return;
+            }
+            result.push((branch.to_string(), depth));
+            if let Some(children) = hierarchy.get(branch) {
+                let mut children = children.clone();
+                children.sort();
+                for child in children {
+                    visit(hierarchy, &child, depth + 1, visited, result);
+                }
+            }
+        }
+
+        let mut result = Vec::new();
+        let mut visited = std::collections::HashSet::new();
+        let mut roots: Vec<String> = stack
+            .branches
+            .values()
+            .filter(|b| {
+                b.parent
+                    .as_ref()
+                    .is_none_or(|p| !stack.branches.contains_key(p))
+            })
+            .map(|b| b.name.to_string())
+            .collect();
+        roots.sort();
+        for root in roots {
+            visit(hierarchy, &root, 0, &mut visited, &mut result);
+        }
+
+        // Branches caught in a parent cycle are still shown
+        let mut remaining: Vec<String> = stack
+            .branches
+            .keys()
+            .filter(|name| !visited.contains(name.as_str()))
+            .map(|name| name.to_string())
+            .collect();
+        remaining.sort();
+        result.extend(remaining.into_iter().map(|name| (name, 0)));
+        result
+    }
+
+    /// Commits the branch is (behind, ahead) of its parent
+    fn ahead_behind(&self, branch: &str, parent: &str) -> Option<(usize, usize)> {
+        let parent = self.git_repo.resolve_branch(parent);
+        let output = self
+            .git_repo
+            .run(&[
+                "rev-list",
+                "--left-right",
+                "--count",
+                &format!("{}...{}", parent, branch),
+            ])
+            .ok()?;
+        let mut counts = output.split_whitespace().map(|c| c.parse::<usize>());
+        match (counts.next(), counts.next()) {
+            (Some(Ok(behind)), Some(Ok(ahead))) => Some((behind, ahead)),
+            _ => None,
+        }
+    }
+
+    /// Compare a level's local metadata and tip with its open MR on GitLab
+    pub fn mr_drift(
+        &self,
+        stack: &Stack,
+        branch_name: &str,
+        status: &MrStatusInfo,
+    ) -> Vec<MrDrift> {
+        let mut drift = Vec::new();
+        let Some(branch) = stack.branches.get(branch_name) else {
+            return drift;
+        };
+        if status.state != "opened" {
+            return drift;
+        }
+
+        let parent = branch.parent.as_deref().unwrap_or(&stack.base_branch);
+        if let Some(target) = status.target_branch.as_deref().filter(|t| *t != parent) {
+            drift.push(MrDrift::Target {
+                gitlab: target.to_string(),
+            });
+        }
+        if let (Some(local), Some(gitlab)) = (&branch.mr_title, &status.title) {
+            if local != gitlab {
+                drift.push(MrDrift::Title);
+            }
+        }
+        if let (Ok(local), Some(head)) = (
+            self.git_repo.get_commit_hash_for_branch(branch_name),
+            status.head_sha.as_deref(),
+        ) {
+            if local != head {
+                // A head missing locally was pushed from somewhere else
+                drift.push(
+                    if !self.git_repo.has_commit(head) || self.git_repo.is_ancestor(&local, head) {
+                        MrDrift::Behind
+                    } else if self.git_repo.is_ancestor(head, &local) {
+                        MrDrift::Unpushed
+                    } else {
+                        MrDrift::Diverged
+                    },
+                );
+            }
         }
+        drift
     }
 
-    fn print_branch_hierarchy_with_status(
+    fn print_branch_table(
         &self,
         hierarchy: &HashMap<String, Vec<String>>,
         stack: &Stack,
         branch_mr_status: &std::collections::HashMap<String, MrStatusInfo>,
-        indent: usize,
     ) {
-        let indent_str = "  ".repeat(indent);
-
-        for (branch_name, branch) in &stack.branches {
-            if branch.parent.is_none()
-                || (indent == 0 && branch.parent.as_ref() == Some(&stack.base_branch))
-            {
-                let status = if Some(branch_name) == stack.current_branch.as_ref() {
-                    " (current)"
-                } else {
-                    ""
-                };
-                let mr_info = format_mr_info_with_status(branch_name, branch_mr_status);
+        let mut table = ui::Table::new(&[
+            "Branch",
+            "MR",
+            "State",
+            "GitLab",
+            "Ahead/Behind",
+            "Review",
+            "Updated",
+        ]);
+        let mut drifted = false;
+
+        for (branch_name, depth) in self.branches_in_tree_order(hierarchy, stack) {
+            let Some(branch) = stack.branches.get(&branch_name) else {
+                continue;
+            };
 
-                ui::print_info(&format!(
-                    "{}▶ {}{}{}",
-                    indent_str, branch_name, status, mr_info
-                ));
-                ui::print_info(&format!("{}   └─ {}", indent_str, &branch.commit_hash[..8]));
+            let tree_prefix = if depth == 0 {
+                "▶ ".to_string()
+            } else {
+                format!("{}└─ ", "  ".repeat(depth - 1))
+            };
+            let name_cell = if Some(branch_name.as_str()) == stack.current_branch.as_deref() {
+                format!(
+                    "{}{} {}",
+                    tree_prefix,
+                    style(&branch_name).bold().cyan(),
+                    style("(current)").dim()
+                )
+            } else {
+                format!("{}{}", tree_prefix, branch_name)
+            };
+            let (mr_cell, state_cell) = match branch_mr_status.get(&branch_name) {
+                // Closing an MR isn't part of the lifecycle git-train tracks
+                Some(mr_status) if mr_status.state == "closed" => (
+                    format!("!{}", mr_status.iid),
+                    style("✘ CLOSED").red().to_string(),
+                ),
+                Some(mr_status) if mr_status.conflicts => (
+                    format!("!{}", mr_status.iid),
+                    format!(
+                        "{} {}",
+                        format_branch_state(branch.state),
+                        style("⚠ conflicts").red()
+                    ),
+                ),
+                Some(mr_status) => (
+                    format!("!{}", mr_status.iid),
+                    format_branch_state(branch.state),
+                ),
+                None => ("-".to_string(), format_branch_state(branch.state)),
+            };
 
-                if let Some(children) = hierarchy.get(branch_name) {
-                    for child in children {
-                        self.print_branch_details_with_status(
-                            child,
-                            stack,
-                            branch_mr_status,
-                            indent + 1,
-                        );
-                        self.print_children_recursive_with_status(
-                            hierarchy,
-                            stack,
-                            branch_mr_status,
-                            child,
-                            indent + 1,
-                        );
+            let drift_cell = match branch_mr_status.get(&branch_name) {
+                Some(status) if status.title.is_none() && status.head_sha.is_none() => {
+                    style("?").dim().to_string()
+                }
+                Some(status) => {
+                    let drift = self.mr_drift(stack, &branch_name, status);
+                    drifted |= !drift.is_empty();
+                    if drift.is_empty() {
+                        style("✔").green().to_string()
+                    } else {
+                        drift
+                            .iter()
+                            .map(MrDrift::marker)
+                            .collect::<Vec<_>>()
+                            .join(" ")
                     }
                 }
-            }
+                None => style("-").dim().to_string(),
+            };
+
+            let parent = branch.parent.as_deref().unwrap_or(&stack.base_branch);
+            let ahead_behind_cell = match self.ahead_behind(&branch_name, parent) {
+                Some((0, ahead)) => format!("↑{}", ahead),
+                Some((behind, ahead)) => {
+                    format!("↑{} {}", ahead, style(format!("↓{}", behind)).yellow())
+                }
+                None => style("?").dim().to_string(),
+            };
+
+            let review_cell = match branch.todo_progress() {
+                (_, 0) if branch.review_notes.is_empty() => style("-").dim().to_string(),
+                (_, 0) => format!("{} notes", branch.review_notes.len()),
+                (done, total) if done == total => {
+                    style(format!("✔ {}/{}", done, total)).green().to_string()
+                }
+                (done, total) => style(format!("{}/{}", done, total)).yellow().to_string(),
+            };
+
+            table.add_row(vec![
+                name_cell,
+                mr_cell,
+                state_cell,
+                drift_cell,
+                ahead_behind_cell,
+                review_cell,
+                branch.updated_at.format("%Y-%m-%d %H:%M").to_string(),
+            ]);
+        }
+
+        table.print();
+        if drifted {
+            ui::print_info(
+                "GitLab: ⇄ MR targets another branch than the parent, ✎ title edited on GitLab, \
+                 ↑ commits push would send, ↓ MR has commits not in the local branch, \
+                 ± histories diverged (push would force)",
+            );
         }
     }
 
-    fn print_branch_details_with_status(
-        &self,
-        branch_name: &str,
-        stack: &Stack,
-        branch_mr_status: &std::collections::HashMap<String, MrStatusInfo>,
-        indent: usize,
-    ) {
-        let indent_str = "  ".repeat(indent);
+    /// Base for a new stack, asking for one when none can be detected
+    fn determine_base_branch(&self, _current_branch: &str) -> Result<String> {
+        if let Some(branch) = self.detect_base_branch() {
+            return Ok(branch);
+        }
 
-        if let Some(branch) = stack.branches.get(branch_name) {
-            let status = if Some(branch_name) == stack.current_branch.as_deref() {
-                " (current)"
-            } else {
-                ""
-            };
-            let mr_info = format_mr_info_with_status(branch_name, branch_mr_status);
+        // Fallback to a warning and user input if needed
+        print_warning(&format!(
+            "Could not determine a default base branch (none of {} found)",
+            self.config
+                .git
+                .base_branch_candidates
+                .iter()
+                .map(|c| format!("'{}'", c))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+        let base = get_user_input("Please enter the base branch name:", None)?;
+        self.normalize_base_branch(&base)
+    }
 
-            ui::print_info(&format!(
-                "{}├─ {}{}{}",
-                indent_str, branch_name, status, mr_info
-            ));
-            ui::print_info(&format!("{}│  └─ {}", indent_str, &branch.commit_hash[..8]));
+    /// Base for a new stack: origin's HEAD branch when known, otherwise the first of
+    /// `git.base_branch_candidates` that exists locally or on origin. A glob candidate
+    /// picks its most recently committed match.
+    pub fn detect_base_branch(&self) -> Option<String> {
+        if let Some(branch) = self.git_repo.remote_head_branch() {
+            if self.git_repo.has_local_branch(&branch) || self.git_repo.has_remote_branch(&branch) {
+                return Some(branch);
+            }
         }
+
+        for candidate in &self.config.git.base_branch_candidates {
+            if candidate.contains(['*', '?', '[']) {
+                if let Some(branch) = self
+                    .git_repo
+                    .branches_matching(candidate)
+                    .into_iter()
+                    .next()
+                {
+                    return Some(branch);
+                }
+            } else if self.git_repo.has_local_branch(candidate)
+                || self.git_repo.has_remote_branch(candidate)
+            {
+                return Some(candidate.clone());
+            }
+        }
+        None
     }
 
-    fn print_children_recursive_with_status(
-        &self,
-        hierarchy: &HashMap<String, Vec<String>>,
-        stack: &Stack,
-        branch_mr_status: &std::collections::HashMap<String, MrStatusInfo>,
-        branch_name: &str,
-        indent: usize,
-    ) {
-        if let Some(children) = hierarchy.get(branch_name) {
-            for child in children {
-                self.print_branch_details_with_status(child, stack, branch_mr_status, indent);
-                self.print_children_recursive_with_status(
-                    hierarchy,
-                    stack,
-                    branch_mr_status,
-                    child,
-                    indent + 1,
-                );
+    /// Turn a base given as `origin/main` or `main` into the branch name stored on the
+    /// stack, checking that it exists locally or on origin
+    fn normalize_base_branch(&self, base: &str) -> Result<String> {
+        let name = base.strip_prefix("origin/").unwrap_or(base);
+        let name = if self.git_repo.has_local_branch(name) || self.git_repo.has_remote_branch(name)
+        {
+            name
+        } else if self.git_repo.has_local_branch(base) {
+            // A local branch that happens to be called "origin/..."
+            base
+        } else if self.git_repo.run(&["fetch", "origin", name]).is_ok()
+            && self.git_repo.has_remote_branch(name)
+        {
+            // Pushed by someone else since the last fetch
+            name
+        } else {
+            return Err(TrainError::StackError {
+                message: format!(
+                    "Base branch '{}' not found locally or on origin (try 'git fetch origin')",
+                    base
+                ),
+            }
+            .into());
+        };
+        Ok(name.to_string())
+    }
+
+    /// The stack table as it should currently appear in MR descriptions
+    fn render_stack_table(&self, stack: &Stack, mrs: &HashMap<MrIid, MergeRequest>) -> String {
+        let table_config = &self.config.stack_table;
+        let mut commit_counts = HashMap::new();
+        if table_config.columns.contains(&TableColumn::Commits) {
+            for branch in stack.branches.values() {
+                let parent = self
+                    .git_repo
+                    .resolve_branch(branch.parent.as_deref().unwrap_or(&stack.base_branch));
+                if let Ok(commits) = self.git_repo.commits_only_in(&parent, &branch.name) {
+                    commit_counts.insert(branch.name.to_string(), commits.len());
+                }
             }
         }
+        markdown::build_stack_table(stack, mrs, table_config, &commit_counts)
     }
 
-    fn determine_base_branch(&self, _current_branch: &str) -> Result<String> {
-        // Simple strategy: check for 'main' or 'master'
-        for branch in ["main", "master"] {
-            if self
-                .git_repo
-                .run(&["rev-parse", "--verify", branch])
-                .is_ok()
-            {
-                return Ok(branch.to_string());
+    /// Open MRs of the current stack whose description shows an outdated stack table
+    pub async fn stale_mr_descriptions(&mut self) -> Result<Vec<(String, MrIid, &'static str)>> {
+        let stack = self.get_or_load_current_stack()?;
+        Ok(self.find_stale_descriptions(&stack).await)
+    }
+
+    /// Open MRs whose description table differs from the one the stack would render
+    /// now, sorted by branch, with the reason
+    async fn find_stale_descriptions(&self, stack: &Stack) -> Vec<(String, MrIid, &'static str)> {
+        let Some(gitlab) = self.gitlab().await else {
+            return Vec::new();
+        };
+        let iids: Vec<MrIid> = stack.branches.values().filter_map(|b| b.mr_iid).collect();
+        let results = future::join_all(iids.iter().map(|&iid| gitlab.get_merge_request(iid))).await;
+        let mut mrs = HashMap::new();
+        for result in results {
+            match result {
+                Ok(mr) => {
+                    mrs.insert(mr.iid, mr);
+                }
+                Err(e) if self.disable_gitlab_on_auth_error(&e) => return Vec::new(),
+                Err(_) => {}
             }
         }
-        // Fallback to a warning and user input if needed
-        print_warning("Could not determine a default base branch ('main' or 'master' not found)");
-        get_user_input("Please enter the base branch name:", None)
+        let expected = markdown::table_hash(&self.render_stack_table(stack, &mrs));
+
+        let mut stale = Vec::new();
+        for branch in stack.branches.values() {
+            let Some(mr) = branch.mr_iid.and_then(|iid| mrs.get(&iid)) else {
+                continue;
+            };
+            if mr.state != "opened" {
+                continue;
+            }
+            let on_gitlab =
+                markdown::stack_table_section(&mr.description).map(markdown::table_hash);
+            if on_gitlab.as_deref() == Some(expected.as_str()) {
+                continue;
+            }
+            let reason = match (&on_gitlab, &branch.description_hash) {
+                (None, _) => "no stack table",
+                (Some(found), Some(written)) if found != written => "edited on GitLab",
+                _ => "stack changed since the last update",
+            };
+            stale.push((branch.name.to_string(), mr.iid, reason));
+        }
+        stale.sort();
+        stale
     }
 
     async fn update_all_mr_descriptions(&self, stack: &mut Stack) {
-        if self.gitlab_client.is_none() {
+        self.update_mr_descriptions(stack, None).await
+    }
+
+    /// Rewrite the stack table in the descriptions of the stack's MRs, or only of the
+    /// MRs in `only`, recording the table's hash on each updated branch
+    async fn update_mr_descriptions(&self, stack: &mut Stack, only: Option<&[MrIid]>) {
+        let Some(gitlab) = self.gitlab().await else {
             return;
-        }
-        let gitlab = self.gitlab_client.as_ref().unwrap();
+        };
 
         print_info("Updating all MR descriptions with stack view...");
 
         // 1. Collect all MR iids
-        let iids: Vec<u64> = stack.branches.values().filter_map(|b| b.mr_iid).collect();
+        let iids: Vec<MrIid> = stack.branches.values().filter_map(|b| b.mr_iid).collect();
         if iids.is_empty() {
             print_info("No merge requests to update.");
             return;
@@ -2206,7 +8148,13 @@ impl StackManager {
         let mr_futures = iids.iter().map(|&iid| gitlab.get_merge_request(iid));
         let results = future::join_all(mr_futures).await;
 
-        let mrs: HashMap<u64, MergeRequest> = results
+        if results.iter().any(|res| {
+            res.as_ref()
+                .is_err_and(|e| self.disable_gitlab_on_auth_error(e))
+        }) {
+            return;
+        }
+        let mrs: HashMap<MrIid, MergeRequest> = results
             .into_iter()
             .filter_map(|res| res.ok())
             .map(|mr| (mr.iid, mr))
@@ -2228,21 +8176,56 @@ impl StackManager {
         }
 
         // 3. Build the universal stack table
-        let stack_table = markdown::build_stack_table(stack, &mrs);
+        let stack_table = self.render_stack_table(stack, &mrs);
+        let table_hash = markdown::table_hash(&stack_table);
 
         // 4. Update all MRs concurrently
-        let update_futures = mrs.values().map(|mr| {
-            let new_description = markdown::update_description(&mr.description, &stack_table);
-            gitlab.update_merge_request(mr.iid, None, Some(new_description))
-        });
+        let update_futures = mrs
+            .values()
+            .filter(|mr| only.is_none_or(|only| only.contains(&mr.iid)))
+            .map(|mr| {
+                let mut new_description =
+                    markdown::update_description(&mr.description, &stack_table);
+                let branch = stack.branches.values().find(|b| b.mr_iid == Some(mr.iid));
+                if self.config.mr.sync_review_notes {
+                    let section = branch.and_then(markdown::build_review_notes_section);
+                    new_description =
+                        markdown::update_review_notes(&Some(new_description), section.as_deref());
+                }
+                // Switching to another mode drops references written earlier
+                let dependencies = branch
+                    .filter(|_| self.config.mr.dependency_links == DependencyLinks::Description)
+                    .and_then(|branch| markdown::build_dependency_section(stack, branch));
+                new_description =
+                    markdown::update_dependencies(&Some(new_description), dependencies.as_deref());
+                // Appended last, so it stays the footer
+                let landing = branch
+                    .filter(|_| self.config.mr.landing_instructions)
+                    .and_then(|branch| markdown::build_landing_section(stack, branch, &mrs));
+                new_description = markdown::update_landing_instructions(
+                    &Some(new_description),
+                    landing.as_deref(),
+                );
+                gitlab.update_merge_request(mr.iid, None, Some(new_description))
+            });
 
         let update_results = future::join_all(update_futures).await;
+        if self.config.mr.dependency_links == DependencyLinks::Api {
+            self.link_blocking_mrs(gitlab, stack, &mrs, only).await;
+        }
 
         let mut success_count = 0;
         for result in update_results {
             match result {
                 Ok(mr) => {
                     print_info(&format!("Updated description for MR !{}", mr.iid));
+                    if let Some(branch) = stack
+                        .branches
+                        .values_mut()
+                        .find(|b| b.mr_iid == Some(mr.iid))
+                    {
+                        branch.description_hash = Some(table_hash.clone());
+                    }
                     success_count += 1;
                 }
                 Err(e) => {
@@ -2259,7 +8242,57 @@ impl StackManager {
         }
     }
 
-    async fn propagate_changes(&self, stack: &mut Stack, changed_branch: &str) -> Result<()> {
+    /// Make each open MR blocked by its parent level's MR on GitLab, for
+    /// `mr.dependency_links = "api"`. Existing links are left alone.
+    async fn link_blocking_mrs(
+        &self,
+        gitlab: &(dyn GitLabApi + Send + Sync),
+        stack: &Stack,
+        mrs: &HashMap<MrIid, MergeRequest>,
+        only: Option<&[MrIid]>,
+    ) {
+        let mut pairs: Vec<(&MergeRequest, &MergeRequest)> = stack
+            .branches
+            .values()
+            .filter_map(|branch| {
+                let mr = mrs.get(&branch.mr_iid?)?;
+                let parent = stack.branches.get(branch.parent.as_ref()?)?;
+                let parent_mr = mrs.get(&parent.mr_iid?)?;
+                Some((mr, parent_mr))
+            })
+            .filter(|(mr, _)| mr.state == "opened")
+            .filter(|(mr, _)| only.is_none_or(|only| only.contains(&mr.iid)))
+            .collect();
+        pairs.sort_by_key(|(mr, _)| mr.iid);
+
+        for (mr, parent_mr) in pairs {
+            let result = match gitlab.list_blocking_merge_requests(mr.iid).await {
+                Ok(blocking) if blocking.contains(&parent_mr.iid) => continue,
+                Ok(_) => gitlab.add_blocking_merge_request(mr.iid, parent_mr).await,
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(()) => print_info(&format!(
+                    "Marked MR !{} as blocked by !{}",
+                    mr.iid, parent_mr.iid
+                )),
+                Err(e) => {
+                    if !self.disable_gitlab_on_auth_error(&e) {
+                        // Usually the instance lacks blocking MRs (GitLab Premium); one warning is enough
+                        print_warning(&format!("Could not link dependent MRs on GitLab: {}", e));
+                    }
+                    return;
+                }
+            }
+        }
+    }
+
+    async fn propagate_changes(
+        &self,
+        stack: &mut Stack,
+        changed_branch: &str,
+        previous_commit: Option<&str>,
+    ) -> Result<()> {
         let hierarchy = self.build_branch_hierarchy(stack);
         if let Some(children) = hierarchy.get(changed_branch) {
             for child_branch in children {
@@ -2267,34 +8300,132 @@ impl StackManager {
                     "Propagating changes to child branch: {}",
                     child_branch
                 ));
-                self.smart_rebase(child_branch, changed_branch).await?;
+                self.restack_branch(stack, child_branch, changed_branch, previous_commit)
+                    .await?;
             }
         }
         Ok(())
     }
 
+    /// Find stack branches whose actual tip no longer matches the recorded commit hash.
+    ///
+    /// Returns `(branch, recorded, actual)` tuples sorted by branch name.
+    fn detect_diverged_branches(&self, stack: &Stack) -> Vec<(String, String, String)> {
+        let mut diverged: Vec<(String, String, String)> = stack
+            .branches
+            .values()
+            .filter_map(|branch| {
+                let actual = self
+                    .git_repo
+                    .get_commit_hash_for_branch(&branch.name)
+                    .ok()?;
+                (actual != branch.commit_hash)
+                    .then(|| (branch.name.to_string(), branch.commit_hash.clone(), actual))
+            })
+            .collect();
+        diverged.sort();
+        diverged
+    }
+
     fn build_branch_hierarchy(&self, stack: &Stack) -> HashMap<String, Vec<String>> {
         let mut hierarchy: HashMap<String, Vec<String>> = HashMap::new();
 
         for (branch_name, branch) in &stack.branches {
             if let Some(parent) = &branch.parent {
                 hierarchy
-                    .entry(parent.clone())
+                    .entry(parent.to_string())
                     .or_default()
-                    .push(branch_name.clone());
+                    .push(branch_name.to_string());
             }
         }
 
         hierarchy
     }
 
+    /// Directory holding GitLab's named merge request templates
+    fn mr_templates_dir(&self) -> std::path::PathBuf {
+        self.git_repo
+            .root()
+            .join(".gitlab")
+            .join("merge_request_templates")
+    }
+
+    /// Names of the templates available in `.gitlab/merge_request_templates/`
+    fn available_mr_templates(&self) -> Result<Vec<String>> {
+        let dir = self.mr_templates_dir();
+        if !dir.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        let mut templates: Vec<String> = fs::read_dir(&dir)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|e| e == "md"))
+            .filter_map(|path| path.file_stem().map(|s| s.to_string_lossy().to_string()))
+            .collect();
+        templates.sort();
+        Ok(templates)
+    }
+
+    /// Pick the MR description template for a branch.
+    ///
+    /// Precedence: explicit override, branch selection, stack selection, configured
+    /// default, then the legacy single `.gitlab/merge_request_template.md`.
+    fn resolve_mr_template(
+        &self,
+        stack: &Stack,
+        branch_name: &str,
+        template_override: Option<&str>,
+    ) -> Result<Option<String>> {
+        let selected = template_override
+            .map(str::to_string)
+            .or_else(|| {
+                stack
+                    .branches
+                    .get(branch_name)
+                    .and_then(|b| b.mr_template.clone())
+            })
+            .or_else(|| stack.mr_template.clone())
+            .or_else(|| self.config.mr.default_template.clone());
+
+        if let Some(name) = selected {
+            let dir = self.mr_templates_dir();
+            let matching = self
+                .available_mr_templates()?
+                .into_iter()
+                .find(|t| t.eq_ignore_ascii_case(&name));
+            match matching {
+                Some(template) => {
+                    print_info(&format!("Using MR template '{}'", template));
+                    return Ok(fs::read_to_string(dir.join(format!("{}.md", template))).ok());
+                }
+                None => print_warning(&format!(
+                    "MR template '{}' not found in .gitlab/merge_request_templates/",
+                    name
+                )),
+            }
+        }
+
+        let template_path = self
+            .git_repo
+            .root()
+            .join(".gitlab")
+            .join("merge_request_template.md");
+        if template_path.exists() {
+            print_info("Found .gitlab/merge_request_template.md, using it.");
+            Ok(fs::read_to_string(template_path).ok())
+        } else {
+            Ok(None)
+        }
+    }
+
     /// Intelligently determine the optimal target branch for a given branch in the stack
     async fn determine_optimal_target_branch(
         &self,
         branch_name: &str,
         stack: &Stack,
         gitlab_client: &(dyn GitLabApi + Send + Sync),
-    ) -> Result<String> {
+    ) -> Result<(String, &'static str)> {
         let branch = stack
             .branches
             .get(branch_name)
@@ -2302,6 +8433,14 @@ impl StackManager {
                 message: format!("Branch '{}' not found in stack", branch_name),
             })?;
 
+        // Stack branches only exist in the fork, so upstream MRs can only target the base
+        if gitlab_client.fork_project().await?.is_some() {
+            return Ok((
+                stack.base_branch.to_string(),
+                "fork MRs can only target the upstream base branch",
+            ));
+        }
+
         // Use parent from stack as fallback
         let local_parent = branch.parent.as_ref().unwrap_or(&stack.base_branch);
 
@@ -2316,7 +8455,12 @@ impl StackManager {
                         "Keeping existing MR target '{}' for branch '{}'",
                         mr.target_branch, branch_name
                     ));
-                    return Ok(mr.target_branch);
+                    let reason = if branch.last_set_target() == Some(mr.target_branch.as_str()) {
+                        "existing target is still in the stack"
+                    } else {
+                        "kept target changed outside git-train"
+                    };
+                    return Ok((mr.target_branch, reason));
                 } else {
                     print_warning(&format!(
                         "MR target '{}' for branch '{}' is no longer in the stack. Detecting new target...",
@@ -2353,13 +8497,13 @@ impl StackManager {
                             "Detected '{}' as a better target than parent for '{}'",
                             sibling.name, branch_name
                         ));
-                        return Ok(sibling.name.clone());
+                        return Ok((sibling.name.to_string(), "branch is based on a sibling"));
                     }
                 }
             }
         }
 
-        Ok(local_parent.clone())
+        Ok((local_parent.to_string(), "parent branch in the stack"))
     }
 
     /// Create or update merge request with intelligent target branch selection and store MR IID
@@ -2368,48 +8512,84 @@ impl StackManager {
         branch_name: &str,
         branch: &StackBranch,
         stack: &mut Stack,
+        template_override: Option<&str>,
     ) -> Result<()> {
-        let gitlab_client = self
-            .gitlab_client
-            .as_ref()
-            .ok_or_else(|| TrainError::GitLabError {
-                message: "GitLab client not available".to_string(),
-            })?;
+        let gitlab_client = self.gitlab().await.ok_or_else(|| TrainError::GitLabError {
+            message: "GitLab client not available".to_string(),
+        })?;
 
-        let target_branch = self
-            .determine_optimal_target_branch(branch_name, stack, gitlab_client.as_ref())
+        let (target_branch, reason) = self
+            .determine_optimal_target_branch(branch_name, stack, gitlab_client)
             .await?;
 
-        if let Some(mr_iid) = branch.mr_iid {
+        // Attach an MR opened outside git-train instead of creating a duplicate
+        let existing_iid = match branch.mr_iid {
+            Some(iid) => Some(iid),
+            None => match self
+                .find_open_mr_for_branch(branch_name, stack, gitlab_client)
+                .await
+            {
+                Ok(Some(mr)) => {
+                    print_info(&format!(
+                        "Found existing MR !{} for branch '{}', attaching it to the stack",
+                        mr.iid, branch_name
+                    ));
+                    if let Some(b) = stack.branches.get_mut(branch_name) {
+                        b.mr_iid = Some(mr.iid);
+                        b.mr_title = Some(mr.title.clone());
+                        b.observe_mr(false, &mr.title);
+                    }
+                    Some(mr.iid)
+                }
+                Ok(None) => None,
+                Err(e) if is_gitlab_auth_error(&e) => return Err(e),
+                Err(e) => {
+                    print_warning(&format!(
+                        "Could not look up existing MRs for '{}': {}",
+                        branch_name, e
+                    ));
+                    None
+                }
+            },
+        };
+
+        if let Some(mr_iid) = existing_iid {
             // MR exists, fetch current state from GitLab to respect manual changes
             let current_mr = gitlab_client.get_merge_request(mr_iid).await?;
             let current_commit_message =
                 self.git_repo.get_commit_message_for_branch(branch_name)?;
             let expected_mr_title = format!("[Stack: {}] {}", stack.name, current_commit_message);
 
-            // Only update title if it's currently auto-generated (starts with [Stack: stack_name])
-            // This preserves manually set titles on GitLab
-            let title_update = if current_mr
+            let generated = current_mr
                 .title
-                .starts_with(&format!("[Stack: {}]", stack.name))
-                && current_mr.title != expected_mr_title
-            {
-                print_info(&format!(
-                    "Updating auto-generated MR !{} title to reflect commit message change",
-                    mr_iid
-                ));
-                Some(expected_mr_title.clone())
-            } else if !current_mr
-                .title
-                .starts_with(&format!("[Stack: {}]", stack.name))
-            {
-                print_info(&format!(
-                    "Preserving manually set title for MR !{}: '{}'",
-                    mr_iid, current_mr.title
-                ));
+                .starts_with(&format!("[Stack: {}]", stack.name));
+            let title_update = if current_mr.title == expected_mr_title {
                 None
             } else {
-                None
+                match (self.config.mr.title_sync, generated) {
+                    (TitleSync::FollowCommit, _) | (TitleSync::PrefixOnly, true) => {
+                        print_info(&format!(
+                            "Updating MR !{} title to follow the commit subject (mr.title_sync = {})",
+                            mr_iid,
+                            self.config.mr.title_sync.as_str()
+                        ));
+                        Some(expected_mr_title.clone())
+                    }
+                    (TitleSync::PrefixOnly, false) => {
+                        print_info(&format!(
+                            "Keeping manually set title for MR !{}: '{}' (mr.title_sync = prefix_only)",
+                            mr_iid, current_mr.title
+                        ));
+                        None
+                    }
+                    (TitleSync::Manual, _) => {
+                        print_info(&format!(
+                            "Keeping title of MR !{}: '{}' (mr.title_sync = manual)",
+                            mr_iid, current_mr.title
+                        ));
+                        None
+                    }
+                }
             };
 
             print_info(&format!(
@@ -2417,73 +8597,280 @@ impl StackManager {
                 mr_iid, branch_name, target_branch
             ));
             let updated_mr = gitlab_client
-                .update_merge_request_with_target(mr_iid, title_update, None, Some(target_branch))
+                .update_merge_request_with_target(
+                    mr_iid,
+                    title_update,
+                    None,
+                    Some(target_branch.clone()),
+                )
                 .await?;
 
             // Update stored title in stack to reflect current GitLab state
             if let Some(b) = stack.branches.get_mut(branch_name) {
                 b.mr_title = Some(updated_mr.title.clone());
+                b.observe_mr(updated_mr.state == "merged", &updated_mr.title);
                 b.updated_at = Utc::now();
+                if b.last_set_target() != Some(target_branch.as_str()) {
+                    let from = b
+                        .last_set_target()
+                        .map(str::to_string)
+                        .unwrap_or(current_mr.target_branch);
+                    b.record_target_change(Some(&from), &target_branch, reason);
+                }
             }
 
             print_success(&format!("Updated MR: {}", updated_mr.web_url));
+            events::emit(&Event::MrUpdated {
+                branch: branch_name,
+                iid: mr_iid,
+                created: false,
+            });
         } else {
             // MR does not exist, create it
             let commit_message = self.git_repo.get_commit_message_for_branch(branch_name)?;
             let mr_title = format!("[Stack: {}] {}", stack.name, commit_message);
 
             // Check for MR template
-            let template_description = {
-                let repo_root_output = self.git_repo.run(&["rev-parse", "--show-toplevel"])?;
-                let repo_root = std::path::PathBuf::from(repo_root_output.trim());
-                let template_path = repo_root.join(".gitlab").join("merge_request_template.md");
-                if template_path.exists() {
-                    print_info("Found .gitlab/merge_request_template.md, using it.");
-                    fs::read_to_string(template_path).ok()
-                } else {
-                    None
-                }
-            };
+            let template_description =
+                self.resolve_mr_template(stack, branch_name, template_override)?;
 
             print_info(&format!(
                 "Creating MR for branch '{}' targeting '{}'",
                 branch_name, target_branch
             ));
+            let reviewer_ids = self
+                .codeowner_reviewers(gitlab_client, branch_name, &target_branch)
+                .await;
             let request = CreateMergeRequestRequest {
                 source_branch: branch_name.to_string(),
-                target_branch,
+                target_branch: target_branch.clone(),
                 title: mr_title.clone(),
                 description: template_description,
+                reviewer_ids,
             };
             let new_mr = gitlab_client.create_merge_request(request).await?;
             print_success(&format!("Created MR: {}", new_mr.web_url));
+            events::emit(&Event::MrUpdated {
+                branch: branch_name,
+                iid: new_mr.iid,
+                created: true,
+            });
 
             // Store new MR info in stack
             if let Some(b) = stack.branches.get_mut(branch_name) {
                 b.mr_iid = Some(new_mr.iid);
+                b.observe_mr(false, &mr_title);
                 b.mr_title = Some(mr_title);
                 b.updated_at = Utc::now();
+                b.record_target_change(None, &target_branch, reason);
             }
         }
 
         Ok(())
     }
+
+    /// GitLab users among the CODEOWNERS of the files `branch` changes on top of
+    /// `target`, at most `mr.codeowner_reviewers` of them and never the MR's author.
+    /// Reviewers are a convenience, so lookup failures only warn.
+    async fn codeowner_reviewers(
+        &self,
+        gitlab_client: &dyn GitLabApi,
+        branch: &str,
+        target: &str,
+    ) -> Vec<u64> {
+        let limit = self.config.mr.codeowner_reviewers;
+        if limit == 0 {
+            return Vec::new();
+        }
+        // GitLab applies the target branch's CODEOWNERS
+        let target = self.git_repo.resolve_branch(target);
+        let Some(codeowners) = CODEOWNERS_PATHS.iter().find_map(|path| {
+            self.git_repo
+                .run(&["show", &format!("{}:{}", target, path)])
+                .ok()
+        }) else {
+            return Vec::new();
+        };
+        let changed: Vec<String> = match self.git_repo.diff_stat(&target, branch) {
+            Ok(files) => files.into_iter().map(|file| file.path).collect(),
+            Err(_) => return Vec::new(),
+        };
+        let owners = CodeOwners::parse(&codeowners).owners_for(&changed);
+        if owners.is_empty() {
+            return Vec::new();
+        }
+
+        let author = gitlab_client.current_user().await.ok().map(|user| user.id);
+        let mut reviewer_ids = Vec::new();
+        let mut reviewers = Vec::new();
+        for owner in owners {
+            if reviewer_ids.len() >= limit {
+                break;
+            }
+            // Groups and email addresses can't be asked for a review
+            let Some(username) = owner.strip_prefix('@').filter(|name| !name.contains('/')) else {
+                continue;
+            };
+            match gitlab_client.find_user(username).await {
+                Ok(Some(user)) if Some(user.id) != author => {
+                    reviewer_ids.push(user.id);
+                    reviewers.push(owner);
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    print_warning(&format!("Could not look up code owner {}: {}", owner, e));
+                    break;
+                }
+            }
+        }
+        if !reviewers.is_empty() {
+            print_info(&format!(
+                "Requesting review from {} (CODEOWNERS)",
+                reviewers.join(", ")
+            ));
+        }
+        reviewer_ids
+    }
+}
+
+/// Compare each MR's target on GitLab with the one recorded in its target history
+fn unexpected_retargets(
+    stack: &Stack,
+    branch_mr_status: &HashMap<String, MrStatusInfo>,
+) -> Vec<UnexpectedRetarget> {
+    let mut retargeted: Vec<UnexpectedRetarget> = branch_mr_status
+        .iter()
+        .filter_map(|(name, status)| {
+            let expected = stack.branches.get(name)?.last_set_target()?;
+            let actual = status.target_branch.as_deref()?;
+            (actual != expected).then(|| UnexpectedRetarget {
+                branch: name.clone(),
+                mr_iid: status.iid,
+                expected: expected.to_string(),
+                actual: actual.to_string(),
+            })
+        })
+        .collect();
+    retargeted.sort_by(|a, b| a.branch.cmp(&b.branch));
+    retargeted
+}
+
+/// Stack branches whose parent is `branch`, sorted by name
+fn child_branches(stack: &Stack, branch: &str) -> Vec<String> {
+    let mut children: Vec<String> = stack
+        .branches
+        .values()
+        .filter(|b| b.parent.as_deref() == Some(branch))
+        .map(|b| b.name.to_string())
+        .collect();
+    children.sort();
+    children
+}
+
+/// Drop `branch` from the stack and hand its children to its parent. Returns the
+/// removed branch's recorded tip and the reparented children.
+fn remove_branch_from_stack(stack: &mut Stack, branch: &str) -> Option<(String, Vec<String>)> {
+    let removed = stack.branches.remove(branch)?;
+    let mut children = Vec::new();
+    for child in stack.branches.values_mut() {
+        if child.parent.as_ref().is_some_and(|p| p == branch) {
+            child.parent = removed.parent.clone();
+            child.updated_at = Utc::now();
+            children.push(child.name.to_string());
+        }
+    }
+    if let Some(parent) = removed
+        .parent
+        .as_ref()
+        .and_then(|p| stack.branches.get_mut(p))
+    {
+        parent.children.retain(|c| c != branch);
+        parent
+            .children
+            .extend(children.iter().map(|c| BranchName::from(c.as_str())));
+    }
+    stack.updated_at = Utc::now();
+    Some((removed.commit_hash, children))
+}
+
+/// Refuse to rewrite a branch a teammate owns
+fn ensure_not_frozen(stack: &Stack, branch: &str) -> Result<()> {
+    if stack
+        .branches
+        .get(branch)
+        .is_some_and(|b| b.state == BranchState::Frozen)
+    {
+        return Err(TrainError::StackError {
+            message: format!(
+                "'{}' is frozen and won't be rewritten; run 'git-train branch unfreeze {}' first",
+                branch, branch
+            ),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// `--force-with-lease` on the commit git-train last pushed for `branch_name`, so a fetch
+/// that refreshed `origin/<branch>` in the meantime can't widen the lease. Branches with
+/// no recorded push fall back to the remote-tracking ref.
+fn lease_for(branch_name: &str, stack: &Stack) -> String {
+    match stack
+        .branches
+        .get(branch_name)
+        .and_then(|branch| branch.remote_sha.as_deref())
+    {
+        Some(sha) => format!("--force-with-lease={}:{}", branch_name, sha),
+        None => "--force-with-lease".to_string(),
+    }
 }
 
-/// Format MR info with status for enhanced display
-fn format_mr_info_with_status(
-    branch_name: &str,
-    branch_mr_status: &std::collections::HashMap<String, MrStatusInfo>,
-) -> String {
-    if let Some(mr_status) = branch_mr_status.get(branch_name) {
-        let (status_icon, status_text) = match mr_status.state.as_str() {
-            "merged" => ("✔", "MERGED".to_string()),
-            "closed" => ("✘", "CLOSED".to_string()),
-            "opened" => ("●", "OPEN".to_string()),
-            _ => ("?", mr_status.state.to_uppercase()),
-        };
-        format!(" [MR !{} {} {}]", mr_status.iid, status_icon, status_text)
+/// How a [`StackManager::restore_source`] is shown to the user
+fn describe_restore_source(source: &str) -> String {
+    if source.starts_with("origin/") {
+        source.to_string()
     } else {
-        String::new()
+        format!("recorded commit {}", &source[..8.min(source.len())])
+    }
+}
+
+fn emit_conflict_event(operation: &str, conflict_info: &ConflictInfo) {
+    events::emit(&Event::ConflictDetected {
+        operation,
+        files: conflict_info
+            .files
+            .iter()
+            .map(|f| f.path.as_str())
+            .collect(),
+    });
+}
+
+/// Print a branch's review notes as a numbered checklist
+fn print_review_notes(branch: &StackBranch) {
+    if branch.review_notes.is_empty() {
+        print_info("No review notes");
+        return;
+    }
+    for (i, note) in branch.review_notes.iter().enumerate() {
+        let marker = match (note.todo, note.done) {
+            (true, true) => style("[x]").green().to_string(),
+            (true, false) => style("[ ]").yellow().to_string(),
+            (false, _) => style(" • ").dim().to_string(),
+        };
+        println!("  {:>2}. {} {}", i + 1, marker, note.text);
+    }
+}
+
+/// Format a branch's lifecycle state as a colored badge for table output
+fn format_branch_state(state: BranchState) -> String {
+    match state {
+        BranchState::Unpushed => style("○ UNPUSHED").dim().to_string(),
+        BranchState::LocalOnly => style("🔒 LOCAL-ONLY").dim().to_string(),
+        BranchState::Pushed => style("↑ PUSHED").to_string(),
+        BranchState::MrOpen => style("● OPEN").cyan().to_string(),
+        BranchState::MrDraft => style("◌ DRAFT").yellow().to_string(),
+        BranchState::MrMerged => style("✔ MERGED").green().to_string(),
+        BranchState::Frozen => style("❄ FROZEN").blue().to_string(),
+        BranchState::Paused => style("⏸ PAUSED").yellow().to_string(),
     }
 }
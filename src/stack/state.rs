@@ -1,46 +1,77 @@
 use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tracing::info;
 
 use crate::errors::TrainError;
-use crate::stack::types::Stack;
+use crate::ids::StackId;
+use crate::stack::types::{MrOperation, Stack, SyncSnapshot};
+
+/// Number of sync snapshots kept in `history.jsonl`
+const MAX_HISTORY_ENTRIES: usize = 500;
+/// Number of MR metadata snapshots kept in `oplog.jsonl`
+const MAX_OPLOG_ENTRIES: usize = 100;
 
 pub struct StackState {
     train_dir: PathBuf,
+    /// This worktree's `current.json`, pointing at the active stack
+    current_file: PathBuf,
+    /// Stack id given with `--stack`; stands in for `current.json`, which is left untouched
+    pinned: Option<StackId>,
 }
 
 impl StackState {
-    pub fn new(train_dir: PathBuf) -> Result<Self> {
+    pub fn new(train_dir: PathBuf, current_file: PathBuf) -> Result<Self> {
         if !train_dir.exists() {
             fs::create_dir_all(&train_dir)?;
         }
-        Ok(Self { train_dir })
+        Ok(Self {
+            train_dir,
+            current_file,
+            pinned: None,
+        })
+    }
+
+    /// Treat `stack` as the current stack for this process without recording it in `current.json`
+    pub fn pin(&mut self, stack: &Stack) {
+        self.pinned = Some(stack.id.clone());
+    }
+
+    pub fn is_pinned(&self) -> bool {
+        self.pinned.is_some()
     }
 
     pub fn save_stack(&self, stack: &Stack) -> Result<()> {
+        self.write_stack(stack)?;
+
+        // Also save a "current" file for easy access
+        self.set_current(stack)?;
+        Ok(())
+    }
+
+    /// Persist a stack without making it the current one
+    pub fn write_stack(&self, stack: &Stack) -> Result<()> {
         let stack_file = self.train_dir.join(format!("{}.json", stack.id));
         let stack_json = serde_json::to_string_pretty(stack)?;
 
         fs::write(&stack_file, stack_json)?;
 
-        // Also save a "current" file for easy access
-        self.set_current(stack)?;
-
         info!("Saved stack state to: {:?}", stack_file);
         Ok(())
     }
 
     pub fn load_current(&self) -> Result<Stack> {
-        let current_file = self.train_dir.join("current.json");
-        if !current_file.exists() {
+        if !self.current_file.exists() && self.pinned.is_none() {
             return Err(TrainError::StackError {
                 message: "No current stack found. Use `git-train list` to see available stacks and `git-train switch` to activate one.".to_string(),
             }
             .into());
         }
 
-        let stack_id = fs::read_to_string(&current_file)?;
+        let stack_id = self.get_current_stack_id()?;
         let stack_file = self.train_dir.join(format!("{}.json", stack_id.trim()));
 
         if !stack_file.exists() {
@@ -55,9 +86,7 @@ impl StackState {
         }
 
         let stack_json = fs::read_to_string(&stack_file)?;
-        let stack: Stack = serde_json::from_str(&stack_json)?;
-
-        Ok(stack)
+        parse_stack(&stack_json)
     }
 
     pub fn find_by_identifier(&self, stack_identifier: &str) -> Result<Stack> {
@@ -83,7 +112,7 @@ impl StackState {
                 && path.file_stem().is_some_and(|s| s != "current")
             {
                 if let Ok(stack_json) = fs::read_to_string(&path) {
-                    if let Ok(stack) = serde_json::from_str::<Stack>(&stack_json) {
+                    if let Ok(stack) = parse_stack(&stack_json) {
                         stacks.push(stack);
                     }
                 }
@@ -95,8 +124,13 @@ impl StackState {
     }
 
     pub fn set_current(&self, stack: &Stack) -> Result<()> {
-        let current_file = self.train_dir.join("current.json");
-        fs::write(&current_file, &stack.id)?;
+        if self.pinned.is_some() {
+            return Ok(());
+        }
+        if let Some(dir) = self.current_file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.current_file, stack.id.as_str())?;
         Ok(())
     }
 
@@ -107,20 +141,146 @@ impl StackState {
         }
 
         // If this was the current stack, remove the current pointer
+        if self.is_pinned() {
+            return Ok(());
+        }
         if let Ok(current_id) = self.get_current_stack_id() {
-            if current_id == stack.id {
-                let current_file = self.train_dir.join("current.json");
-                if current_file.exists() {
-                    fs::remove_file(current_file)?;
-                }
+            if current_id == stack.id && self.current_file.exists() {
+                fs::remove_file(&self.current_file)?;
             }
         }
 
         Ok(())
     }
 
+    /// Append a sync snapshot to the local history, dropping the oldest entries
+    pub fn append_history(&self, snapshot: &SyncSnapshot) -> Result<()> {
+        let mut history = self.load_history()?;
+        history.push(snapshot.clone());
+        let skip = history.len().saturating_sub(MAX_HISTORY_ENTRIES);
+        write_jsonl(&self.train_dir.join("history.jsonl"), &history[skip..])
+    }
+
+    /// Load all recorded sync snapshots, oldest first (unreadable lines are skipped)
+    pub fn load_history(&self) -> Result<Vec<SyncSnapshot>> {
+        read_jsonl(&self.train_dir.join("history.jsonl"))
+    }
+
+    /// Record MR metadata before a bulk update, dropping the oldest entries
+    pub fn append_oplog(&self, operation: &MrOperation) -> Result<()> {
+        let mut oplog = self.load_oplog()?;
+        oplog.push(operation.clone());
+        let skip = oplog.len().saturating_sub(MAX_OPLOG_ENTRIES);
+        write_jsonl(&self.train_dir.join("oplog.jsonl"), &oplog[skip..])
+    }
+
+    /// Load all recorded MR operations, oldest first (unreadable lines are skipped)
+    pub fn load_oplog(&self) -> Result<Vec<MrOperation>> {
+        read_jsonl(&self.train_dir.join("oplog.jsonl"))
+    }
+
+    /// Forget the latest MR operation recorded for `stack_id`, once it has been undone
+    pub fn drop_last_oplog(&self, stack_id: &StackId) -> Result<()> {
+        let mut oplog = self.load_oplog()?;
+        if let Some(last) = oplog.iter().rposition(|op| &op.stack_id == stack_id) {
+            oplog.remove(last);
+            write_jsonl(&self.train_dir.join("oplog.jsonl"), &oplog)?;
+        }
+        Ok(())
+    }
+
     pub fn get_current_stack_id(&self) -> Result<String> {
-        let current_file = self.train_dir.join("current.json");
-        Ok(fs::read_to_string(current_file).unwrap_or_default())
+        if let Some(pinned) = &self.pinned {
+            return Ok(pinned.to_string());
+        }
+        Ok(fs::read_to_string(&self.current_file).unwrap_or_default())
+    }
+}
+
+/// Parse a stack file. Files written before branches had a lifecycle state carry
+/// `merged`, `frozen` and `local_only` flags instead, which the state is derived from.
+fn parse_stack(json: &str) -> Result<Stack> {
+    let mut value: Value = serde_json::from_str(json)?;
+    if let Some(branches) = value.get_mut("branches").and_then(Value::as_object_mut) {
+        for branch in branches.values_mut().filter_map(Value::as_object_mut) {
+            if branch.contains_key("state") {
+                continue;
+            }
+            let flag = |key: &str| branch.get(key).and_then(Value::as_bool) == Some(true);
+            let set = |key: &str| branch.get(key).is_some_and(|v| !v.is_null());
+            let state = if flag("merged") {
+                "mr-merged"
+            } else if flag("frozen") {
+                "frozen"
+            } else if flag("local_only") {
+                "local-only"
+            } else if set("mr_iid") {
+                "mr-open"
+            } else if set("remote_sha") {
+                "pushed"
+            } else {
+                "unpushed"
+            };
+            branch.insert("state".to_string(), state.into());
+        }
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+fn read_jsonl<T: DeserializeOwned>(file: &Path) -> Result<Vec<T>> {
+    if !file.exists() {
+        return Ok(Vec::new());
+    }
+    Ok(fs::read_to_string(file)?
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+fn write_jsonl<T: Serialize>(file: &Path, entries: &[T]) -> Result<()> {
+    let mut contents = String::new();
+    for entry in entries {
+        contents.push_str(&serde_json::to_string(entry)?);
+        contents.push('\n');
+    }
+    fs::write(file, contents)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stack::types::BranchState;
+
+    #[test]
+    fn test_stacks_saved_with_flags_get_a_state() {
+        let branch = |name: &str, extra: &str| {
+            format!(
+                r#""{name}": {{"name": "{name}", "parent": null, "children": [], "commit_hash": "abc", "mr_iid": null, "mr_title": null, "created_at": "2025-01-01T00:00:00Z", "updated_at": "2025-01-01T00:00:00Z"{extra}}}"#
+            )
+        };
+        let json = format!(
+            r#"{{"id": "s1", "name": "old", "base_branch": "main", "current_branch": null, "gitlab_project": null, "created_at": "2025-01-01T00:00:00Z", "updated_at": "2025-01-01T00:00:00Z", "branches": {{{}}}}}"#,
+            [
+                branch("merged", r#", "merged": true, "mr_iid": 1"#),
+                branch("frozen", r#", "frozen": true"#),
+                branch("private", r#", "local_only": true"#),
+                branch("open", r#", "mr_iid": 2, "merged": false"#),
+                branch("pushed", r#", "remote_sha": "abc""#),
+                branch("new", ""),
+                branch("current", r#", "state": "paused", "mr_iid": 3"#),
+            ]
+            .join(", ")
+        );
+
+        let stack = parse_stack(&json).unwrap();
+        let state = |name: &str| stack.branches[name].state;
+        assert_eq!(state("merged"), BranchState::MrMerged);
+        assert_eq!(state("frozen"), BranchState::Frozen);
+        assert_eq!(state("private"), BranchState::LocalOnly);
+        assert_eq!(state("open"), BranchState::MrOpen);
+        assert_eq!(state("pushed"), BranchState::Pushed);
+        assert_eq!(state("new"), BranchState::Unpushed);
+        assert_eq!(state("current"), BranchState::Paused);
     }
 }
@@ -2,28 +2,284 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use crate::config::UpdateStrategy;
+use crate::errors::TrainError;
 use crate::gitlab::api::GitLabProject;
+use crate::ids::{BranchName, MrIid, StackId};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StackBranch {
-    pub name: String,
-    pub parent: Option<String>,
-    pub children: Vec<String>,
+    pub name: BranchName,
+    pub parent: Option<BranchName>,
+    pub children: Vec<BranchName>,
     pub commit_hash: String,
-    pub mr_iid: Option<u64>,
+    pub mr_iid: Option<MrIid>,
     pub mr_title: Option<String>,
+    /// Named MR template for this branch, overriding the stack's selection
+    #[serde(default)]
+    pub mr_template: Option<String>,
+    /// Local review notes and TODO items for this level of the stack
+    #[serde(default)]
+    pub review_notes: Vec<ReviewNote>,
+    /// MR target changes made by git-train, oldest first
+    #[serde(default)]
+    pub target_history: Vec<TargetChange>,
+    /// Hash of the stack table git-train last wrote into this branch's MR description
+    #[serde(default)]
+    pub description_hash: Option<String>,
+    /// Commit git-train last pushed to `origin/<branch>`; force-pushes lease on it
+    #[serde(default)]
+    pub remote_sha: Option<String>,
+    /// Where the branch is in its push/MR lifecycle; change it with
+    /// [`StackBranch::transition`]
+    #[serde(default)]
+    pub state: BranchState,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+/// Where a stack branch is in its push/MR lifecycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BranchState {
+    /// Only exists locally; the next push publishes it
+    #[default]
+    Unpushed,
+    /// Private level: never pushed, gets no MR and is left out of stack tables until
+    /// `git-train publish`
+    LocalOnly,
+    /// On the remote without an MR
+    Pushed,
+    /// The MR is open and ready for review
+    MrOpen,
+    /// The MR is open as a draft (`Draft:` title)
+    MrDraft,
+    /// The MR is merged; nothing happens to the branch any more
+    MrMerged,
+    /// A teammate pushes to this branch: git-train never rebases or pushes it and
+    /// restacks its children onto its remote tip
+    Frozen,
+    /// Published, but push leaves its remote branch and MR alone until
+    /// `git-train branch resume`; sync still restacks it locally
+    Paused,
+}
+
+impl BranchState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BranchState::Unpushed => "unpushed",
+            BranchState::LocalOnly => "local-only",
+            BranchState::Pushed => "pushed",
+            BranchState::MrOpen => "mr-open",
+            BranchState::MrDraft => "mr-draft",
+            BranchState::MrMerged => "mr-merged",
+            BranchState::Frozen => "frozen",
+            BranchState::Paused => "paused",
+        }
+    }
+
+    /// Whether a branch may move from this state to `next`
+    pub fn can_become(self, next: BranchState) -> bool {
+        use BranchState::*;
+        if self == next {
+            return true;
+        }
+        match (self, next) {
+            (MrMerged, _) => false,
+            // Private levels are published before anything else can happen to them
+            (LocalOnly, next) => next == Unpushed,
+            (_, LocalOnly) => matches!(self, Unpushed | Pushed),
+            (_, MrMerged) => true,
+            // Nothing leaves the remote again
+            (_, Unpushed) => false,
+            // There is nothing on the remote to hold back yet
+            (Unpushed, Paused) => false,
+            // Frozen branches are already left alone, by their owner's choice
+            (Frozen, Paused) | (Paused, Frozen) => false,
+            _ => true,
+        }
+    }
+
+    /// Push and MR updates leave the branch alone
+    pub fn is_held_back(self) -> bool {
+        matches!(self, BranchState::LocalOnly | BranchState::Paused)
+    }
+
+    /// The state for an MR titled `title`, depending on whether GitLab reports it merged
+    pub fn of_mr(merged: bool, title: &str) -> Self {
+        if merged {
+            BranchState::MrMerged
+        } else if is_draft_title(title) {
+            BranchState::MrDraft
+        } else {
+            BranchState::MrOpen
+        }
+    }
+}
+
+/// GitLab treats MRs whose title starts with one of these as drafts
+fn is_draft_title(title: &str) -> bool {
+    let title = title.trim_start().to_lowercase();
+    ["draft:", "[draft]", "(draft)"]
+        .iter()
+        .any(|prefix| title.starts_with(prefix))
+}
+
+/// A local annotation on a stack branch, either a free-form note or a checklist item
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewNote {
+    pub text: String,
+    #[serde(default)]
+    pub todo: bool,
+    #[serde(default)]
+    pub done: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// One MR target change recorded by git-train
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TargetChange {
+    pub timestamp: DateTime<Utc>,
+    /// Target before the change; `None` when the MR was created
+    pub from: Option<String>,
+    pub to: String,
+    pub reason: String,
+}
+
+/// Number of target changes kept per branch
+const MAX_TARGET_HISTORY: usize = 20;
+
+impl StackBranch {
+    /// The MR target git-train last set for this branch, if any
+    pub fn last_set_target(&self) -> Option<&str> {
+        self.target_history.last().map(|c| c.to.as_str())
+    }
+
+    pub fn record_target_change(&mut self, from: Option<&str>, to: &str, reason: &str) {
+        self.target_history.push(TargetChange {
+            timestamp: Utc::now(),
+            from: from.map(str::to_string),
+            to: to.to_string(),
+            reason: reason.to_string(),
+        });
+        let excess = self.target_history.len().saturating_sub(MAX_TARGET_HISTORY);
+        self.target_history.drain(..excess);
+    }
+
+    /// Move the branch to `next`, refusing moves its lifecycle doesn't allow
+    pub fn transition(&mut self, next: BranchState) -> Result<(), TrainError> {
+        if !self.state.can_become(next) {
+            return Err(TrainError::InvalidState {
+                message: format!(
+                    "'{}' is {} and can't become {}",
+                    self.name,
+                    self.state.as_str(),
+                    next.as_str()
+                ),
+            });
+        }
+        if self.state != next {
+            self.state = next;
+            self.updated_at = Utc::now();
+        }
+        Ok(())
+    }
+
+    /// Follow what GitLab reports about the branch's MR. A merge ends the lifecycle from
+    /// any state; otherwise frozen and paused branches keep their state until released.
+    pub fn observe_mr(&mut self, merged: bool, title: &str) {
+        let next = BranchState::of_mr(merged, title);
+        if !merged && matches!(self.state, BranchState::Frozen | BranchState::Paused) {
+            return;
+        }
+        if self.state.can_become(next) {
+            self.state = next;
+        }
+    }
+
+    /// The state a frozen or paused branch returns to when released
+    pub fn settled_state(&self) -> BranchState {
+        match (&self.mr_iid, &self.mr_title) {
+            (Some(_), title) => BranchState::of_mr(false, title.as_deref().unwrap_or("")),
+            (None, _) => BranchState::Pushed,
+        }
+    }
+
+    /// (done, total) counts for the branch's TODO items
+    pub fn todo_progress(&self) -> (usize, usize) {
+        let todos = self.review_notes.iter().filter(|n| n.todo);
+        let total = todos.clone().count();
+        let done = todos.filter(|n| n.done).count();
+        (done, total)
+    }
+}
+
+/// Local record of a single `sync` run, kept for `git-train history`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncSnapshot {
+    pub timestamp: DateTime<Utc>,
+    pub stack_id: StackId,
+    pub stack_name: String,
+    pub branch_count: usize,
+    pub max_depth: usize,
+    pub conflicts: usize,
+    pub duration_ms: u64,
+    pub success: bool,
+}
+
+/// An MR's title, description and target as they were before git-train changed them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MrSnapshot {
+    pub iid: MrIid,
+    pub branch: BranchName,
+    pub title: String,
+    pub description: Option<String>,
+    pub target_branch: String,
+}
+
+/// MR metadata saved right before a bulk MR update, kept in `oplog.jsonl` for
+/// `git-train undo`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MrOperation {
+    pub timestamp: DateTime<Utc>,
+    pub stack_id: StackId,
+    /// Command that changed the MRs, e.g. `push`
+    pub operation: String,
+    pub mrs: Vec<MrSnapshot>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Stack {
-    pub id: String,
+    pub id: StackId,
     pub name: String,
-    pub base_branch: String,
-    pub branches: HashMap<String, StackBranch>,
-    pub current_branch: Option<String>,
+    pub base_branch: BranchName,
+    pub branches: HashMap<BranchName, StackBranch>,
+    pub current_branch: Option<BranchName>,
     pub gitlab_project: Option<GitLabProject>,
+    /// Named MR template used for every branch in the stack unless overridden
+    #[serde(default)]
+    pub mr_template: Option<String>,
+    /// Whether `sync` rebases children onto parents or merges parents into children
+    #[serde(default)]
+    pub update_strategy: UpdateStrategy,
+    /// git `user.email` of whoever created the stack
+    #[serde(default)]
+    pub owner: Option<String>,
+    /// Every level is merged; `list` leaves the stack out unless asked for archived stacks
+    #[serde(default)]
+    pub archived: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
+
+impl Stack {
+    /// Levels whose MR is merged, out of all levels
+    pub fn merge_progress(&self) -> (usize, usize) {
+        let merged = self
+            .branches
+            .values()
+            .filter(|b| b.state == BranchState::MrMerged)
+            .count();
+        (merged, self.branches.len())
+    }
+}
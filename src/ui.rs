@@ -1,6 +1,40 @@
 use anyhow::Result;
 use console::{style, Term};
 use inquire::{Confirm, Select, Text};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crate::ids::MrIid;
+
+/// How chatty git-train is, set once from the global `-v`/`--quiet` flags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Only warnings, errors and command results
+    Quiet,
+    Normal,
+    /// Log every git command with its timing
+    Verbose,
+    /// Everything, including tracing debug output
+    Debug,
+}
+
+static VERBOSITY: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
+
+pub fn set_verbosity(verbosity: Verbosity) {
+    VERBOSITY.store(verbosity as u8, Ordering::Relaxed);
+}
+
+pub fn verbosity() -> Verbosity {
+    match VERBOSITY.load(Ordering::Relaxed) {
+        0 => Verbosity::Quiet,
+        1 => Verbosity::Normal,
+        2 => Verbosity::Verbose,
+        _ => Verbosity::Debug,
+    }
+}
+
+fn is_quiet() -> bool {
+    verbosity() == Verbosity::Quiet
+}
 
 pub fn print_success(message: &str) {
     println!("{} {}", style("✔").bold().green(), message);
@@ -15,14 +49,25 @@ pub fn print_error(message: &str) {
 }
 
 pub fn print_info(message: &str) {
+    if is_quiet() {
+        return;
+    }
     println!("{} {}", style("ℹ").bold().blue(), message);
 }
 
+/// A change worth noticing, e.g. in `status --watch`
+pub fn print_change(message: &str) {
+    println!("{} {}", style("●").bold().yellow(), style(message).yellow());
+}
+
 pub fn print_config_item(key: &str, value: &str) {
     println!("  {}: {}", style(key).bold(), value);
 }
 
 pub fn print_train_header(title: &str) {
+    if is_quiet() {
+        return;
+    }
     let term = Term::stdout();
     let width = term.size().1 as usize;
     let border_width = width.min(80);
@@ -51,6 +96,97 @@ pub fn print_train_header(title: &str) {
     println!("{}", style(&border).bold().cyan());
 }
 
+/// A minimal column-aligned table renderer for terminal output.
+///
+/// Cells may contain styled text; widths are measured without ANSI codes and columns
+/// are truncated (widest first) when the table does not fit the terminal.
+pub struct Table {
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    const MIN_COLUMN_WIDTH: usize = 4;
+    const COLUMN_GAP: &'static str = "  ";
+
+    pub fn new(headers: &[&str]) -> Self {
+        Self {
+            headers: headers.iter().map(|h| h.to_string()).collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn add_row(&mut self, row: Vec<String>) {
+        self.rows.push(row);
+    }
+
+    /// Render the table so that no line exceeds `max_width` columns.
+    pub fn render(&self, max_width: usize) -> String {
+        let column_count = self.headers.len();
+        let mut widths: Vec<usize> = self
+            .headers
+            .iter()
+            .map(|h| console::measure_text_width(h))
+            .collect();
+        for row in &self.rows {
+            for (i, cell) in row.iter().take(column_count).enumerate() {
+                widths[i] = widths[i].max(console::measure_text_width(cell));
+            }
+        }
+
+        // Shrink the widest column until the table fits
+        let gaps = Self::COLUMN_GAP.len() * column_count.saturating_sub(1);
+        while widths.iter().sum::<usize>() + gaps > max_width {
+            let (widest, width) = widths
+                .iter()
+                .enumerate()
+                .max_by_key(|(_, w)| **w)
+                .map(|(i, w)| (i, *w))
+                .unwrap_or((0, 0));
+            if width <= Self::MIN_COLUMN_WIDTH {
+                break;
+            }
+            widths[widest] -= 1;
+        }
+
+        let render_line = |cells: &[String], header: bool| -> String {
+            let line = (0..column_count)
+                .map(|i| {
+                    let cell = cells.get(i).map(String::as_str).unwrap_or("");
+                    let truncated = if console::measure_text_width(cell) > widths[i] {
+                        console::truncate_str(cell, widths[i], "…")
+                    } else {
+                        cell.into()
+                    };
+                    let padded =
+                        console::pad_str(&truncated, widths[i], console::Alignment::Left, None)
+                            .to_string();
+                    if header {
+                        style(padded).bold().to_string()
+                    } else {
+                        padded
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(Self::COLUMN_GAP);
+            line.trim_end().to_string()
+        };
+
+        let mut output = render_line(&self.headers, true);
+        for row in &self.rows {
+            output.push('\n');
+            output.push_str(&render_line(row, false));
+        }
+        output
+    }
+
+    /// Print the table sized to the current terminal width.
+    pub fn print(&self) {
+        let width = Term::stdout().size().1 as usize;
+        println!("{}", self.render(width.max(20)));
+    }
+}
+
 pub fn confirm_action(message: &str) -> Result<bool> {
     let confirmation = Confirm::new(message).with_default(false).prompt()?;
 
@@ -94,7 +230,7 @@ pub enum NavigationAction {
     SwitchToBranch(String),
     ShowBranchInfo(String),
     CreateMR(String),
-    ViewMR(String, u64),
+    ViewMR(String, MrIid),
     RefreshStatus,
     Exit,
 }
@@ -107,8 +243,18 @@ impl std::fmt::Display for NavigationOption {
 
 #[derive(Debug, Clone)]
 pub struct MrStatusInfo {
-    pub iid: u64,
+    pub iid: MrIid,
     pub state: String,
+    /// Target branch on GitLab, when it could be fetched
+    pub target_branch: Option<String>,
+    /// Not (re)fetched yet; shown with a marker until a refresh completes
+    pub stale: bool,
+    /// GitLab reports conflicts with the MR's target branch
+    pub conflicts: bool,
+    /// MR title on GitLab, when it could be fetched
+    pub title: Option<String>,
+    /// Head commit of the MR on GitLab, when it could be fetched
+    pub head_sha: Option<String>,
 }
 
 pub fn create_navigation_options(
@@ -136,7 +282,16 @@ pub fn create_navigation_options(
                 "opened" => ("●", "OPEN"),
                 _ => ("?", mr_status.state.as_str()),
             };
-            format!(" [MR !{} {} {}]", mr_status.iid, status_symbol, status_text)
+            let conflicts = if mr_status.conflicts {
+                " ⚠ conflicts"
+            } else {
+                ""
+            };
+            let stale = if mr_status.stale { " (stale)" } else { "" };
+            format!(
+                " [MR !{} {} {}{}{}]",
+                mr_status.iid, status_symbol, status_text, conflicts, stale
+            )
         } else {
             String::new()
         };
@@ -281,3 +436,66 @@ pub fn interactive_stack_navigation(
     // Return the action from the selected option
     Ok(selection.action.clone())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_aligns_columns() {
+        let mut table = Table::new(&["Branch", "MR"]);
+        table.add_row(vec!["feature-1".to_string(), "!1".to_string()]);
+        table.add_row(vec!["f2".to_string(), "!22".to_string()]);
+
+        let rendered = table.render(80);
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1], "feature-1  !1");
+        assert_eq!(lines[2], "f2         !22");
+    }
+
+    #[test]
+    fn test_table_truncates_in_narrow_terminal() {
+        let mut table = Table::new(&["Branch", "State"]);
+        table.add_row(vec![
+            "a-very-long-branch-name-that-does-not-fit".to_string(),
+            "OPEN".to_string(),
+        ]);
+
+        let rendered = table.render(20);
+        for line in rendered.lines() {
+            assert!(console::measure_text_width(line) <= 20, "{}", line);
+        }
+        assert!(rendered.contains('…'));
+    }
+
+    #[test]
+    fn test_navigation_marks_stale_mr_status() {
+        let branches = vec!["feature-1".to_string(), "feature-2".to_string()];
+        let mut statuses = std::collections::HashMap::new();
+        for (branch, iid, stale) in [("feature-1", 1, false), ("feature-2", 2, true)] {
+            statuses.insert(
+                branch.to_string(),
+                MrStatusInfo {
+                    iid: MrIid::from(iid),
+                    state: "opened".to_string(),
+                    target_branch: None,
+                    stale,
+                    conflicts: false,
+                    title: None,
+                    head_sha: None,
+                },
+            );
+        }
+
+        let options = create_navigation_options(&branches, Some("feature-1"), &statuses);
+        let rows: Vec<String> = options
+            .iter()
+            .filter(|o| matches!(o.action, NavigationAction::SwitchToBranch(_)))
+            .map(|o| console::strip_ansi_codes(&o.display).to_string())
+            .collect();
+
+        assert!(rows[0].ends_with("[MR !1 ● OPEN]"), "{}", rows[0]);
+        assert!(rows[1].ends_with("[MR !2 ● OPEN (stale)]"), "{}", rows[1]);
+    }
+}
@@ -1,16 +1,30 @@
 // tests/integration_tests.rs
 
 use anyhow::Result;
+use gittrain::bump::BumpLevel;
 use gittrain::config::TrainConfig;
-use gittrain::git::GitRepository;
+use gittrain::errors::TrainError;
+use gittrain::git::{GitRepository, InterruptedCommand};
 use gittrain::gitlab::api::{
-    CreateMergeRequestRequest, GitLabApi, GitLabNamespace, GitLabProject, MergeRequest,
+    CreateMergeRequestRequest, Environment, GitLabApi, GitLabNamespace, GitLabProject, GitLabUser,
+    MergeParams, MergeRequest, MergeRequestApprovals, Milestone,
 };
-use gittrain::stack::StackManager;
+use gittrain::hooks::{self, HookInstall};
+use gittrain::ids::MrIid;
+use gittrain::serve;
+use gittrain::stack::types::BranchState;
+use gittrain::stack::{
+    BranchRange, DeleteOptions, LandOptions, ListOptions, MrDrift, MrMergeOptions, PushOptions,
+    ReviewNoteAction, StackManager, SyncOptions,
+};
+use gittrain::ui::MrStatusInfo;
 use std::collections::HashMap;
 use std::fs;
 
-use gittrain::config::AutoResolveStrategy;
+use gittrain::config::{
+    AutoResolveStrategy, AutoSwitchMode, CommitIdentity, DependencyLinks, MergeCommitHandling,
+    MergeMethod, NameEnforcement, TitleSync, UpdateStrategy,
+};
 use std::process::Command;
 use std::sync::{Arc, Mutex};
 use tempfile::TempDir;
@@ -106,8 +120,27 @@ impl TestRepo {
 #[derive(Clone)]
 struct MockGitLab {
     project: GitLabProject,
-    merge_requests: Arc<Mutex<HashMap<u64, MergeRequest>>>,
+    merge_requests: Arc<Mutex<HashMap<MrIid, MergeRequest>>>,
     next_mr_iid: Arc<Mutex<u64>>,
+    /// MRs listed here are reported as unapproved; all others are approved
+    unapproved: Arc<Mutex<Vec<MrIid>>>,
+    notes: Arc<Mutex<Vec<(MrIid, String)>>>,
+    /// Environments deployed per branch; branches listed are recorded in `environment_queries`
+    environments: Arc<Mutex<HashMap<String, Vec<Environment>>>>,
+    environment_queries: Arc<Mutex<Vec<String>>>,
+    milestones: Vec<Milestone>,
+    /// When set, every API call fails as if the token were revoked and is counted here
+    rejected_calls: Option<Arc<Mutex<usize>>>,
+    /// Squash commit messages passed when merging
+    squash_messages: Arc<Mutex<Vec<(MrIid, String)>>>,
+    /// Fork branches are pushed to while MRs are opened in `project`
+    fork: Option<GitLabProject>,
+    /// Blocking MRs per blocked MR
+    blocks: Arc<Mutex<HashMap<MrIid, Vec<MrIid>>>>,
+    /// Known users; the first one owns the token
+    users: Vec<GitLabUser>,
+    /// Reviewers requested when each MR was created
+    reviewers: Arc<Mutex<HashMap<MrIid, Vec<u64>>>>,
 }
 
 impl MockGitLab {
@@ -131,7 +164,41 @@ impl MockGitLab {
             project: project.clone(),
             merge_requests: Arc::new(Mutex::new(HashMap::new())),
             next_mr_iid: Arc::new(Mutex::new(1)),
+            unapproved: Arc::new(Mutex::new(Vec::new())),
+            notes: Arc::new(Mutex::new(Vec::new())),
+            environments: Arc::new(Mutex::new(HashMap::new())),
+            environment_queries: Arc::new(Mutex::new(Vec::new())),
+            milestones: vec![Milestone {
+                id: 11,
+                title: "v1.0".to_string(),
+            }],
+            rejected_calls: None,
+            squash_messages: Arc::new(Mutex::new(Vec::new())),
+            fork: None,
+            blocks: Arc::new(Mutex::new(HashMap::new())),
+            users: ["test-user", "alice", "bob", "carol"]
+                .iter()
+                .zip(1..)
+                .map(|(username, id)| GitLabUser {
+                    id,
+                    username: username.to_string(),
+                })
+                .collect(),
+            reviewers: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn check_token(&self) -> Result<()> {
+        let Some(rejected_calls) = &self.rejected_calls else {
+            return Ok(());
+        };
+        *rejected_calls.lock().unwrap() += 1;
+        Err(TrainError::GitLabAuthError {
+            status: 401,
+            endpoint: "/api/v4/projects/1/merge_requests".to_string(),
+            hint: "GITLAB_TOKEN is invalid, expired or revoked".to_string(),
         }
+        .into())
     }
 }
 
@@ -141,38 +208,54 @@ impl GitLabApi for MockGitLab {
         Ok(self.project.clone())
     }
 
+    async fn fork_project(&self) -> Result<Option<GitLabProject>> {
+        Ok(self.fork.clone())
+    }
+
     async fn create_merge_request(
         &self,
         request: CreateMergeRequestRequest,
     ) -> Result<MergeRequest> {
+        self.check_token()?;
         let mut iid = self.next_mr_iid.lock().unwrap();
         let new_iid = *iid;
         *iid += 1;
 
         let mr = MergeRequest {
             id: new_iid,
-            iid: new_iid,
+            iid: new_iid.into(),
             title: request.title,
             description: request.description,
             source_branch: request.source_branch,
             target_branch: request.target_branch,
+            source_project_id: Some(self.fork.as_ref().unwrap_or(&self.project).id),
             state: "opened".to_string(),
             web_url: format!("{}/merge_requests/{}", self.project.web_url, new_iid),
+            head_pipeline: None,
+            has_conflicts: false,
+            detailed_merge_status: None,
+            sha: None,
+            milestone: None,
         };
 
+        self.reviewers
+            .lock()
+            .unwrap()
+            .insert(mr.iid, request.reviewer_ids);
         self.merge_requests
             .lock()
             .unwrap()
-            .insert(new_iid, mr.clone());
+            .insert(mr.iid, mr.clone());
         Ok(mr)
     }
 
     async fn update_merge_request(
         &self,
-        iid: u64,
+        iid: MrIid,
         title: Option<String>,
         description: Option<String>,
     ) -> Result<MergeRequest> {
+        self.check_token()?;
         let mut mrs = self.merge_requests.lock().unwrap();
         let mr = mrs.get_mut(&iid).unwrap();
 
@@ -188,11 +271,12 @@ impl GitLabApi for MockGitLab {
 
     async fn update_merge_request_with_target(
         &self,
-        iid: u64,
+        iid: MrIid,
         title: Option<String>,
         description: Option<String>,
         target_branch: Option<String>,
     ) -> Result<MergeRequest> {
+        self.check_token()?;
         let mut mrs = self.merge_requests.lock().unwrap();
         let mr = mrs.get_mut(&iid).unwrap();
 
@@ -209,10 +293,138 @@ impl GitLabApi for MockGitLab {
         Ok(mr.clone())
     }
 
-    async fn get_merge_request(&self, iid: u64) -> Result<MergeRequest> {
+    async fn get_merge_request(&self, iid: MrIid) -> Result<MergeRequest> {
+        self.check_token()?;
         let mrs = self.merge_requests.lock().unwrap();
         Ok(mrs.get(&iid).cloned().unwrap())
     }
+
+    async fn list_merge_requests_by_source_branch(
+        &self,
+        source_branch: &str,
+        state: Option<&str>,
+    ) -> Result<Vec<MergeRequest>> {
+        self.check_token()?;
+        let mrs = self.merge_requests.lock().unwrap();
+        let mut matching: Vec<MergeRequest> = mrs
+            .values()
+            .filter(|mr| mr.source_branch == source_branch)
+            .filter(|mr| state.is_none_or(|state| mr.state == state))
+            .cloned()
+            .collect();
+        matching.sort_by_key(|mr| mr.iid);
+        Ok(matching)
+    }
+
+    async fn get_merge_request_approvals(&self, iid: MrIid) -> Result<MergeRequestApprovals> {
+        self.check_token()?;
+        let approved = !self.unapproved.lock().unwrap().contains(&iid);
+        Ok(MergeRequestApprovals {
+            approved,
+            approvals_left: if approved { 0 } else { 1 },
+        })
+    }
+
+    async fn list_branch_environments(&self, branch: &str) -> Result<Vec<Environment>> {
+        self.check_token()?;
+        self.environment_queries
+            .lock()
+            .unwrap()
+            .push(branch.to_string());
+        Ok(self
+            .environments
+            .lock()
+            .unwrap()
+            .get(branch)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn find_milestone(&self, title: &str) -> Result<Option<Milestone>> {
+        self.check_token()?;
+        Ok(self.milestones.iter().find(|m| m.title == title).cloned())
+    }
+
+    async fn set_merge_request_milestone(
+        &self,
+        iid: MrIid,
+        milestone_id: u64,
+    ) -> Result<MergeRequest> {
+        self.check_token()?;
+        let mut mrs = self.merge_requests.lock().unwrap();
+        let mr = mrs.get_mut(&iid).unwrap();
+        mr.milestone = self
+            .milestones
+            .iter()
+            .find(|m| m.id == milestone_id)
+            .cloned();
+        Ok(mr.clone())
+    }
+
+    async fn create_merge_request_note(&self, iid: MrIid, body: &str) -> Result<()> {
+        self.check_token()?;
+        self.notes.lock().unwrap().push((iid, body.to_string()));
+        Ok(())
+    }
+
+    async fn close_merge_request(&self, iid: MrIid) -> Result<MergeRequest> {
+        self.check_token()?;
+        let mut mrs = self.merge_requests.lock().unwrap();
+        let mr = mrs.get_mut(&iid).unwrap();
+        mr.state = "closed".to_string();
+        Ok(mr.clone())
+    }
+
+    async fn merge_merge_request(&self, iid: MrIid, params: &MergeParams) -> Result<MergeRequest> {
+        self.check_token()?;
+        if let Some(message) = &params.squash_message {
+            self.squash_messages
+                .lock()
+                .unwrap()
+                .push((iid, message.clone()));
+        }
+        let mut mrs = self.merge_requests.lock().unwrap();
+        let mr = mrs.get_mut(&iid).unwrap();
+        mr.state = "merged".to_string();
+        Ok(mr.clone())
+    }
+
+    async fn rebase_merge_request(&self, _iid: MrIid) -> Result<()> {
+        self.check_token()?;
+        Ok(())
+    }
+
+    async fn list_blocking_merge_requests(&self, iid: MrIid) -> Result<Vec<MrIid>> {
+        self.check_token()?;
+        Ok(self
+            .blocks
+            .lock()
+            .unwrap()
+            .get(&iid)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn add_blocking_merge_request(&self, iid: MrIid, blocking: &MergeRequest) -> Result<()> {
+        self.check_token()?;
+        self.blocks
+            .lock()
+            .unwrap()
+            .entry(iid)
+            .or_default()
+            .push(blocking.iid);
+        Ok(())
+    }
+
+    async fn current_user(&self) -> Result<GitLabUser> {
+        self.check_token()?;
+        Ok(self.users[0].clone())
+    }
+
+    async fn find_user(&self, username: &str) -> Result<Option<GitLabUser>> {
+        self.check_token()?;
+        Ok(self.users.iter().find(|u| u.username == username).cloned())
+    }
 }
 
 #[cfg(test)]
@@ -222,7 +434,7 @@ mod integration_tests {
     async fn setup() -> Result<(
         TestRepo,
         StackManager,
-        Arc<Mutex<HashMap<u64, MergeRequest>>>,
+        Arc<Mutex<HashMap<MrIid, MergeRequest>>>,
     )> {
         let test_repo = TestRepo::new()?;
         let mut config = TrainConfig::default();
@@ -280,8 +492,7 @@ mod integration_tests {
         let feature1_initial_hash = test_repo.commit("feat: add file1")?;
         stack_manager.create_stack("my-stack").await?;
 
-        // Create feature-2 from main to avoid inheriting file1.txt
-        test_repo.checkout("main")?;
+        // feature-2 touches a different file than feature-1
         test_repo.create_branch("feature-2")?;
         test_repo.create_file("file2.txt", "content2")?;
         test_repo.commit("feat: add file2")?;
@@ -332,148 +543,3608 @@ mod integration_tests {
     }
 
     #[tokio::test]
-    async fn test_edit_earlier_branch_from_latest() -> Result<()> {
+    async fn test_push_without_mrs_then_update_all() -> Result<()> {
         let (test_repo, mut stack_manager, mrs) = setup().await?;
 
-        // 1. Create a stack of 3 branches with different files
         test_repo.create_branch("feature-1")?;
-        test_repo.create_file("file1.txt", "original content from feature-1")?;
-        test_repo.commit("feat: add file1 in feature-1")?;
-        stack_manager.create_stack("my-stack").await?;
-
+        test_repo.commit("feat: first")?;
+        stack_manager.create_stack("no-mr").await?;
         test_repo.create_branch("feature-2")?;
-        test_repo.create_file("file2.txt", "content from feature-2")?;
-        test_repo.commit("feat: add file2 in feature-2")?;
+        test_repo.commit("feat: second")?;
         stack_manager.add_branch_to_stack(Some("feature-1")).await?;
 
-        test_repo.create_branch("feature-3")?;
-        test_repo.create_file("file3.txt", "content from feature-3")?;
-        test_repo.commit("feat: add file3 in feature-3")?;
-        stack_manager.add_branch_to_stack(Some("feature-2")).await?;
+        stack_manager
+            .push_stack_with_options(&PushOptions {
+                no_mr: true,
+                ..Default::default()
+            })
+            .await?;
+        assert!(mrs.lock().unwrap().is_empty());
+        assert!(test_repo.git_repo().has_remote_branch("feature-2"));
 
-        // 2. Push the stack to create MRs
-        stack_manager.push_stack().await?;
-        assert_eq!(mrs.lock().unwrap().len(), 3);
+        stack_manager.update_all_mrs(None).await?;
+        let mrs = mrs.lock().unwrap();
+        assert_eq!(mrs.len(), 2);
+        let mr2 = mrs
+            .values()
+            .find(|mr| mr.source_branch == "feature-2")
+            .unwrap();
+        assert_eq!(mr2.target_branch, "feature-1");
 
-        // 3. Stay on feature-3 (the latest branch) and edit a file from feature-1
-        // This simulates the common workflow where you're working on the latest branch
-        // but realize you need to fix something from an earlier branch
-        test_repo.create_file(
-            "file1.txt",
-            "updated content from feature-1 (edited from feature-3)",
-        )?;
-        // Don't commit yet - let amend_changes handle it
+        Ok(())
+    }
 
-        // 4. Use existing functionality to sync the stack - it should automatically detect
-        // that we've edited files from an earlier branch and handle it properly
+    #[tokio::test]
+    async fn test_sync_when_behind_skips_trivial_base_changes() -> Result<()> {
+        let (test_repo, mut stack_manager, _) = setup().await?;
+        let repo = test_repo.git_repo();
+        let advance_base = |file: &str, content: &str| -> Result<()> {
+            test_repo.checkout("main")?;
+            test_repo.create_file(file, content)?;
+            test_repo.commit(&format!("chore: update {}", file))?;
+            test_repo.run(&["push", "origin", "main"])?;
+            test_repo.checkout("feature-2")
+        };
+        let options = SyncOptions {
+            when_behind: Some(2),
+            ..Default::default()
+        };
+
+        test_repo.create_file("shared.txt", "1\n2\n3\n4\n5\n")?;
+        test_repo.commit("chore: shared file")?;
+        test_repo.run(&["push", "origin", "main"])?;
+        test_repo.create_branch("feature-1")?;
+        test_repo.create_file("shared.txt", "one\n2\n3\n4\n5\n")?;
+        test_repo.commit("feat: level 1")?;
+        stack_manager.create_stack("behind").await?;
+        test_repo.create_branch("feature-2")?;
+        test_repo.create_file("level-2.txt", "level 2")?;
+        test_repo.commit("feat: level 2")?;
+        stack_manager.add_branch_to_stack(Some("feature-1")).await?;
+
+        // One unrelated commit on the base isn't worth a force-push
+        advance_base("docs.md", "docs")?;
+        let tip = repo.get_commit_hash_for_branch("feature-2")?;
         stack_manager
-            .amend_changes(Some("fix: update file1 from earlier branch"))
+            .sync_with_remote_with_options(&options)
             .await?;
+        assert!(!repo.is_ancestor("main", "feature-1"));
+        assert_eq!(repo.get_commit_hash_for_branch("feature-2")?, tip);
 
-        // 5. Verify that:
-        // - feature-1 has the updated file1.txt content
-        // - feature-2 and feature-3 were rebased onto the updated feature-1
-        // - The stack hierarchy is maintained
+        // More than two commits behind restacks
+        advance_base("docs.md", "more docs")?;
+        advance_base("docs.md", "even more docs")?;
+        stack_manager
+            .sync_with_remote_with_options(&options)
+            .await?;
+        assert!(repo.is_ancestor("main", "feature-2"));
 
-        // Check feature-1 has the updated content
+        // So does a single commit touching a file the stack changes
+        advance_base("shared.txt", "1\n2\n3\n4\nfive\n")?;
+        stack_manager
+            .sync_with_remote_with_options(&options)
+            .await?;
+        assert!(repo.is_ancestor("main", "feature-2"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_push_and_sync_limited_to_a_range() -> Result<()> {
+        let (test_repo, mut stack_manager, mrs) = setup().await?;
+
+        for level in 1..=4 {
+            test_repo.create_branch(&format!("feature-{}", level))?;
+            test_repo.create_file(&format!("level-{}.txt", level), "content")?;
+            test_repo.commit(&format!("feat: level {}", level))?;
+            if level == 1 {
+                stack_manager.create_stack("ranges").await?;
+            } else {
+                let parent = format!("feature-{}", level - 1);
+                stack_manager.add_branch_to_stack(Some(&parent)).await?;
+            }
+        }
+
+        stack_manager
+            .push_stack_with_options(&PushOptions {
+                range: Some(BranchRange::UpTo("feature-2".to_string())),
+                ..Default::default()
+            })
+            .await?;
+        let repo = test_repo.git_repo();
+        assert!(repo.has_remote_branch("feature-2"));
+        assert!(!repo.has_remote_branch("feature-3"));
+        assert_eq!(mrs.lock().unwrap().len(), 2);
+
+        // Only levels 2 and 3 pick up the new commit on level 1
         test_repo.checkout("feature-1")?;
-        let file1_content = std::fs::read_to_string(test_repo.path().join("file1.txt"))?;
-        assert_eq!(
-            file1_content,
-            "updated content from feature-1 (edited from feature-3)"
-        );
+        test_repo.create_file("level-1-fix.txt", "fix")?;
+        test_repo.commit("fix: level 1")?;
+        test_repo.checkout("feature-4")?;
+        stack_manager
+            .sync_with_remote_with_options(&SyncOptions {
+                range: Some(BranchRange::only("feature-2..feature-3")),
+                ..Default::default()
+            })
+            .await?;
+        assert!(repo.is_ancestor("feature-1", "feature-2"));
+        assert!(repo.is_ancestor("feature-1", "feature-3"));
+        assert!(!repo.is_ancestor("feature-1", "feature-4"));
 
-        // Check that feature-2 was rebased (parent should be updated feature-1)
-        test_repo.checkout("feature-2")?;
-        let feature2_parent = test_repo.git_repo().run(&["rev-parse", "feature-2^"])?;
-        let feature1_hash = test_repo
-            .git_repo()
-            .get_commit_hash_for_branch("feature-1")?;
-        assert_eq!(feature2_parent.trim(), feature1_hash.trim());
+        let err = stack_manager
+            .push_stack_with_options(&PushOptions {
+                range: Some(BranchRange::only("feature-3..feature-1")),
+                ..Default::default()
+            })
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("is not below"));
 
-        // Check that feature-3 was rebased (parent should be updated feature-2)
-        test_repo.checkout("feature-3")?;
-        let feature3_parent = test_repo.git_repo().run(&["rev-parse", "feature-3^"])?;
-        let feature2_hash = test_repo
-            .git_repo()
-            .get_commit_hash_for_branch("feature-2")?;
-        assert_eq!(feature3_parent.trim(), feature2_hash.trim());
+        Ok(())
+    }
 
-        // Verify all files are present in feature-3
-        assert!(test_repo.path().join("file1.txt").exists());
-        assert!(test_repo.path().join("file2.txt").exists());
-        assert!(test_repo.path().join("file3.txt").exists());
+    #[tokio::test]
+    async fn test_repair_removes_or_restores_deleted_branches() -> Result<()> {
+        let (test_repo, mut stack_manager, mrs) = setup().await?;
 
-        // Check that file1.txt has the updated content in feature-3
-        let file1_content_in_feature3 =
-            std::fs::read_to_string(test_repo.path().join("file1.txt"))?;
+        for level in 1..=3 {
+            test_repo.create_branch(&format!("feature-{}", level))?;
+            test_repo.create_file(&format!("file{}.txt", level), "content")?;
+            test_repo.commit(&format!("feat: level {}", level))?;
+            if level == 1 {
+                stack_manager.create_stack("repair").await?;
+            } else {
+                let parent = format!("feature-{}", level - 1);
+                stack_manager.add_branch_to_stack(Some(&parent)).await?;
+            }
+        }
+        let repo = test_repo.git_repo();
+        let tip_1 = repo.get_commit_hash_for_branch("feature-1")?;
+        test_repo.run(&["branch", "-D", "feature-1", "feature-2"])?;
+
+        // Push leaves deleted branches out instead of failing on them
+        stack_manager.push_stack().await?;
+        assert!(repo.has_remote_branch("feature-3"));
+        assert!(!repo.has_remote_branch("feature-2"));
+        let stack = stack_manager.get_or_load_current_stack()?;
+        assert!(stack.branches["feature-2"].mr_iid.is_none());
         assert_eq!(
-            file1_content_in_feature3,
-            "updated content from feature-1 (edited from feature-3)"
+            stack_manager.deleted_local_branches(&stack),
+            vec!["feature-1", "feature-2"]
         );
 
-        // 6. Push the updated stack
+        stack_manager.repair_stack(true, false).await?;
+        assert_eq!(repo.get_commit_hash_for_branch("feature-1")?, tip_1);
+        // feature-2's recorded commit is still in the clone, so it comes back too
+        assert!(repo.has_local_branch("feature-2"));
+
+        test_repo.run(&["branch", "-D", "feature-2"])?;
+        stack_manager.repair_stack(false, true).await?;
+        let stack = stack_manager.get_or_load_current_stack()?;
+        assert!(!stack.branches.contains_key("feature-2"));
+        assert_eq!(
+            stack.branches["feature-3"].parent.as_deref(),
+            Some("feature-1")
+        );
+        assert!(stack_manager.deleted_local_branches(&stack).is_empty());
+
         stack_manager.push_stack().await?;
+        let mrs = mrs.lock().unwrap();
+        let feature_3 = mrs
+            .values()
+            .find(|mr| mr.source_branch == "feature-3")
+            .unwrap();
+        assert_eq!(feature_3.target_branch, "feature-1");
 
-        // Verify MRs are updated
-        let mrs_after = mrs.lock().unwrap();
-        assert_eq!(mrs_after.len(), 3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_restore_recreates_missing_branches() -> Result<()> {
+        let (test_repo, mut stack_manager, _mrs) = setup().await?;
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.commit("feat: first")?;
+        stack_manager.create_stack("restore").await?;
+        test_repo.create_branch("feature-2")?;
+        test_repo.commit("feat: second")?;
+        stack_manager.add_branch_to_stack(Some("feature-1")).await?;
+        stack_manager.push_stack().await?;
+        test_repo.create_branch("feature-3")?;
+        test_repo.commit("feat: local only")?;
+        stack_manager.add_branch_to_stack(Some("feature-2")).await?;
+
+        let repo = test_repo.git_repo();
+        let tips: Vec<String> = ["feature-1", "feature-2", "feature-3"]
+            .iter()
+            .map(|b| repo.get_commit_hash_for_branch(b))
+            .collect::<Result<_>>()?;
+        test_repo.checkout("main")?;
+        test_repo.run(&["branch", "-D", "feature-1", "feature-2", "feature-3"])?;
+
+        let err = stack_manager.sync_with_remote().await.unwrap_err();
+        assert!(err.to_string().contains("git-train repair"));
+
+        stack_manager.restore_branches(true).await?;
+        assert!(!repo.has_local_branch("feature-1"));
+
+        stack_manager.restore_branches(false).await?;
+        for (branch, tip) in ["feature-1", "feature-2", "feature-3"].iter().zip(&tips) {
+            assert_eq!(&repo.get_commit_hash_for_branch(branch)?, tip);
+        }
+        stack_manager.push_stack().await?;
 
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_conflict_detection() -> Result<()> {
-        // Custom setup for this test to control config
+    async fn test_push_continues_git_only_after_token_rejected() -> Result<()> {
         let test_repo = TestRepo::new()?;
         let mut config = TrainConfig::default();
-        config.conflict_resolution.auto_resolve_strategy = AutoResolveStrategy::Never;
         config.conflict_resolution.auto_force_push_after_rebase = true;
-        config.git.verify_signatures = false;
-        config.editor.default_editor = "true".to_string();
-        let mock_gitlab = MockGitLab::new();
-        let _mrs = mock_gitlab.merge_requests.clone();
-
-        let gitlab_client = Some(Box::new(mock_gitlab) as Box<dyn GitLabApi + Send + Sync>);
+        let mut gitlab = MockGitLab::new();
+        let rejected_calls = Arc::new(Mutex::new(0));
+        gitlab.rejected_calls = Some(rejected_calls.clone());
         let mut stack_manager = StackManager::new_with_config(
             config,
             Some(test_repo.git_repo().clone()),
-            gitlab_client,
+            Some(Box::new(gitlab)),
         )
         .await?;
 
-        // 1. Setup a stack
+        for level in 1..=3 {
+            test_repo.create_branch(&format!("feature-{}", level))?;
+            test_repo.commit(&format!("feat: level {}", level))?;
+            if level == 1 {
+                stack_manager.create_stack("revoked").await?;
+            } else {
+                let parent = format!("feature-{}", level - 1);
+                stack_manager.add_branch_to_stack(Some(&parent)).await?;
+            }
+        }
+
+        stack_manager.push_stack().await?;
+        assert!(test_repo.git_repo().has_remote_branch("feature-3"));
+        // The first rejection switches GitLab off instead of retrying for every branch
+        assert_eq!(*rejected_calls.lock().unwrap(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_status_detects_stale_mr_descriptions() -> Result<()> {
+        let (test_repo, mut stack_manager, mrs) = setup().await?;
+
         test_repo.create_branch("feature-1")?;
-        test_repo.create_file("file.txt", "line 1\nline 2\nline 3")?;
-        test_repo.commit("feat: add file")?;
-        stack_manager.create_stack("my-stack").await?;
+        test_repo.commit("feat: first")?;
+        stack_manager.create_stack("stale").await?;
+        test_repo.create_branch("feature-2")?;
+        test_repo.commit("feat: second")?;
+        stack_manager.add_branch_to_stack(Some("feature-1")).await?;
+        stack_manager.push_stack().await?;
+        assert!(stack_manager.stale_mr_descriptions().await?.is_empty());
 
-        // 2. Modify file on main to create a conflict on a specific line
+        // Someone rewrites the table on GitLab
+        for mr in mrs.lock().unwrap().values_mut() {
+            if mr.source_branch == "feature-1" {
+                let description = mr.description.clone().unwrap();
+                mr.description = Some(description.replace("feature-2", "renamed"));
+            }
+        }
+        let stale = stack_manager.stale_mr_descriptions().await?;
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].0, "feature-1");
+        assert_eq!(stale[0].2, "edited on GitLab");
+
+        // A new level makes every existing table outdated
+        test_repo.create_branch("feature-3")?;
+        test_repo.commit("feat: third")?;
+        stack_manager.add_branch_to_stack(Some("feature-2")).await?;
+        let stale = stack_manager.stale_mr_descriptions().await?;
+        assert_eq!(stale.len(), 2);
+        assert_eq!(stale[1].2, "stack changed since the last update");
+
+        stack_manager.push_stack().await?;
+        assert!(stack_manager.stale_mr_descriptions().await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_push_with_sync_restacks_behind_branches() -> Result<()> {
+        let (test_repo, mut stack_manager, _mrs) = setup().await?;
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.commit("feat: first")?;
+        stack_manager.create_stack("auto-sync").await?;
+        test_repo.create_branch("feature-2")?;
+        test_repo.commit("feat: second")?;
+        stack_manager.add_branch_to_stack(Some("feature-1")).await?;
+        stack_manager.push_stack().await?;
+
+        // The base moves on after the stack was pushed
         test_repo.checkout("main")?;
-        test_repo.create_file("file.txt", "line 1\nline 2 - main\nline 3")?;
-        test_repo.commit("refactor: update file on main")?;
+        test_repo.create_file("base.txt", "moved")?;
+        let base_commit = test_repo.commit("chore: base moves on")?;
         test_repo.run(&["push", "origin", "main"])?;
+        test_repo.checkout("feature-2")?;
 
-        // 3. Modify the same line on the feature branch
-        test_repo.checkout("feature-1")?;
-        test_repo.create_file("file.txt", "line 1\nline 2 - feature\nline 3")?;
-        test_repo.commit("feat: update file on feature")?;
+        stack_manager
+            .push_stack_with_options(&PushOptions {
+                sync: true,
+                ..Default::default()
+            })
+            .await?;
+        let git = test_repo.git_repo();
+        assert!(git.is_ancestor(&base_commit, "feature-1"));
+        assert!(git.is_ancestor("feature-1", "feature-2"));
+        assert!(git.is_ancestor("feature-2", "origin/feature-2"));
 
-        // 4. Try to sync by propagating changes from main, which should cause a conflict
-        let result = stack_manager.sync_with_remote().await;
+        Ok(())
+    }
 
-        // 5. Assert that a conflict was detected and the correct error is returned
-        assert!(result.is_err());
-        if let Some(err) = result.err() {
+    #[tokio::test]
+    async fn test_land_squashes_without_stack_table() -> Result<()> {
+        let test_repo = TestRepo::new()?;
+        let mut config = TrainConfig::default();
+        config.conflict_resolution.auto_force_push_after_rebase = true;
+        let gitlab = MockGitLab::new();
+        let squash_messages = gitlab.squash_messages.clone();
+        let mut stack_manager = StackManager::new_with_config(
+            config,
+            Some(test_repo.git_repo().clone()),
+            Some(Box::new(gitlab)),
+        )
+        .await?;
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.commit("feat: first")?;
+        stack_manager.create_stack("squash").await?;
+        test_repo.create_branch("feature-2")?;
+        test_repo.commit("feat: second")?;
+        stack_manager.add_branch_to_stack(Some("feature-1")).await?;
+        stack_manager.push_stack().await?;
+
+        stack_manager
+            .land_stack(&LandOptions {
+                squash: true,
+                ..Default::default()
+            })
+            .await?;
+        let squash_messages = squash_messages.lock().unwrap();
+        assert_eq!(squash_messages.len(), 1);
+        let message = &squash_messages[0].1;
+        assert!(message.starts_with("feat: first"));
+        assert!(!message.contains("git-train-stack"));
+        assert!(!message.contains("MR Train"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mr_merge_requires_merged_parent_and_retargets_children() -> Result<()> {
+        let test_repo = TestRepo::new()?;
+        let mut config = TrainConfig::default();
+        config.conflict_resolution.auto_force_push_after_rebase = true;
+        let gitlab = MockGitLab::new();
+        let mrs = gitlab.merge_requests.clone();
+        let squash_messages = gitlab.squash_messages.clone();
+        let mut stack_manager = StackManager::new_with_config(
+            config,
+            Some(test_repo.git_repo().clone()),
+            Some(Box::new(gitlab)),
+        )
+        .await?;
+
+        for level in 1..=3 {
+            test_repo.create_branch(&format!("feature-{}", level))?;
+            test_repo.commit(&format!("feat: level {}", level))?;
+            if level == 1 {
+                stack_manager.create_stack("merge").await?;
+            } else {
+                let parent = format!("feature-{}", level - 1);
+                stack_manager.add_branch_to_stack(Some(&parent)).await?;
+            }
+        }
+        stack_manager.push_stack().await?;
+        let stack = stack_manager.get_or_load_current_stack()?;
+        let iid = |branch: &str| stack.branches[branch].mr_iid.unwrap();
+
+        // The middle level can't go in before its parent
+        let error = stack_manager
+            .merge_branch_mr("feature-2", &MrMergeOptions::default())
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("'feature-1' is not merged yet"));
+        assert_eq!(mrs.lock().unwrap()[&iid("feature-2")].state, "opened");
+
+        let squash = MrMergeOptions {
+            method: Some(MergeMethod::Squash),
+            ..Default::default()
+        };
+        stack_manager.merge_branch_mr("feature-1", &squash).await?;
+        {
+            let mrs = mrs.lock().unwrap();
+            assert_eq!(mrs[&iid("feature-1")].state, "merged");
+            assert_eq!(mrs[&iid("feature-2")].target_branch, "main");
+            assert_eq!(mrs[&iid("feature-3")].target_branch, "feature-2");
+        }
+        assert!(squash_messages.lock().unwrap()[0]
+            .1
+            .starts_with("feat: level 1"));
+        let stack = stack_manager.get_or_load_current_stack()?;
+        let change = stack.branches["feature-2"].target_history.last().unwrap();
+        assert_eq!(change.reason, "parent MR merged");
+
+        stack_manager
+            .merge_branch_mr("feature-2", &MrMergeOptions::default())
+            .await?;
+        let mrs = mrs.lock().unwrap();
+        assert_eq!(mrs[&iid("feature-2")].state, "merged");
+        assert_eq!(mrs[&iid("feature-3")].target_branch, "main");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sync_keeps_or_linearizes_merge_commits() -> Result<()> {
+        let test_repo = TestRepo::new()?;
+        let git = test_repo.git_repo();
+        let mut config = TrainConfig::default();
+        config.conflict_resolution.auto_force_push_after_rebase = true;
+        config.git.merge_commits = MergeCommitHandling::Preserve;
+        let mut stack_manager = StackManager::new_offline(config.clone(), git.clone()).await?;
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.create_file("one.txt", "one")?;
+        test_repo.commit("feat: one")?;
+        test_repo.create_branch("side")?;
+        test_repo.create_file("side.txt", "side")?;
+        test_repo.commit("feat: side work")?;
+        test_repo.checkout("feature-1")?;
+        test_repo.run(&[
+            "merge",
+            "--no-ff",
+            "-m",
+            "Merge side into feature-1",
+            "side",
+        ])?;
+        stack_manager.create_stack("merges").await?;
+        test_repo.create_branch("feature-2")?;
+        test_repo.create_file("two.txt", "two")?;
+        test_repo.commit("feat: two")?;
+        stack_manager.add_branch_to_stack(Some("feature-1")).await?;
+
+        let advance_main = |name: &str| -> Result<()> {
+            test_repo.checkout("main")?;
+            test_repo.create_file(name, "base")?;
+            test_repo.commit(&format!("chore: {}", name))?;
+            test_repo.run(&["push", "origin", "main"])?;
+            test_repo.checkout("feature-2")
+        };
+        let merges = |range: &str| -> Result<usize> {
+            Ok(git.run(&["rev-list", "--merges", range])?.lines().count())
+        };
+
+        advance_main("base-1.txt")?;
+        stack_manager.sync_with_remote().await?;
+        assert!(git.is_ancestor("main", "feature-1"));
+        assert_eq!(merges("main..feature-1")?, 1);
+        assert!(git.is_ancestor("feature-1", "feature-2"));
+
+        config.git.merge_commits = MergeCommitHandling::Linearize;
+        let mut stack_manager = StackManager::new_offline(config, git.clone()).await?;
+        advance_main("base-2.txt")?;
+        stack_manager.sync_with_remote().await?;
+        assert!(git.is_ancestor("main", "feature-1"));
+        assert_eq!(merges("main..feature-2")?, 0);
+        assert!(git.run(&["cat-file", "-e", "feature-2:side.txt"]).is_ok());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_restacks_commit_as_the_configured_identity() -> Result<()> {
+        let test_repo = TestRepo::new()?;
+        let git = test_repo.git_repo();
+        let mut config = TrainConfig::default();
+        config.conflict_resolution.auto_force_push_after_rebase = true;
+        config.git.commit_identity = Some(CommitIdentity {
+            name: "Train Bot".to_string(),
+            email: "bot@example.com".to_string(),
+        });
+        let mut stack_manager = StackManager::new_offline(config, git.clone()).await?;
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.create_file("one.txt", "one")?;
+        test_repo.commit("feat: one")?;
+        stack_manager.create_stack("bot").await?;
+
+        test_repo.checkout("main")?;
+        test_repo.create_file("base.txt", "base")?;
+        test_repo.commit("chore: advance main")?;
+        test_repo.run(&["push", "origin", "main"])?;
+        test_repo.checkout("feature-1")?;
+
+        stack_manager.sync_with_remote().await?;
+
+        assert!(git.is_ancestor("main", "feature-1"));
+        let identity = git.run(&["log", "-1", "--format=%an <%ae>|%cn <%ce>", "feature-1"])?;
+        // The user's commit keeps its author; the bot only shows up as committer
+        assert_eq!(
+            identity.trim(),
+            "Test User <test@example.com>|Train Bot <bot@example.com>"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_prompt_summary_reads_local_state() -> Result<()> {
+        let (test_repo, mut stack_manager, _mrs) = setup().await?;
+        assert_eq!(stack_manager.prompt_summary(), None);
+
+        for level in 1..=3 {
+            test_repo.create_branch(&format!("feature-{}", level))?;
+            test_repo.create_file("shared.txt", &format!("level {}", level))?;
+            test_repo.commit(&format!("feat: level {}", level))?;
+            if level == 1 {
+                stack_manager.create_stack("prompt").await?;
+            } else {
+                let parent = format!("feature-{}", level - 1);
+                stack_manager.add_branch_to_stack(Some(&parent)).await?;
+            }
+        }
+        stack_manager.push_stack().await?;
+        let iid = stack_manager.get_or_load_current_stack()?.branches["feature-2"]
+            .mr_iid
+            .unwrap();
+
+        test_repo.checkout("feature-2")?;
+        test_repo.create_file("local.txt", "not pushed")?;
+        test_repo.commit("wip: local only")?;
+        assert_eq!(
+            stack_manager.prompt_summary().as_deref(),
+            Some(format!("prompt 2/3 !{} ↑1", iid).as_str())
+        );
+
+        // A conflicting rebase shows up without asking GitLab or running git status
+        test_repo.checkout("main")?;
+        test_repo.create_file("shared.txt", "main")?;
+        test_repo.commit("chore: main edits the same file")?;
+        test_repo.checkout("feature-3")?;
+        assert!(test_repo.run(&["rebase", "main"]).is_err());
+        let summary = stack_manager.prompt_summary().unwrap();
+        assert!(summary.ends_with("✖conflict"), "{}", summary);
+        test_repo.run(&["rebase", "--abort"])?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_summary_lists_every_level() -> Result<()> {
+        let (test_repo, mut stack_manager, _mrs) = setup().await?;
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.create_file("api.rs", "fn api() {}\n")?;
+        test_repo.commit("feat: add api")?;
+        stack_manager.create_stack("summary").await?;
+        test_repo.create_branch("feature-2")?;
+        test_repo.create_file("form.rs", "fn form() {}\n")?;
+        test_repo.commit("feat: add form")?;
+        stack_manager.add_branch_to_stack(Some("feature-1")).await?;
+        stack_manager.push_stack().await?;
+
+        let output = test_repo.path().join("SUMMARY.md");
+        let summary = stack_manager
+            .summarize_stack(Some(&output.to_string_lossy()))
+            .await?;
+        assert_eq!(fs::read_to_string(&output)?, summary);
+        assert!(summary.starts_with("# Stack: summary"));
+        let first = summary.find("## 1. feat: add api").unwrap();
+        let second = summary.find("## 2. feat: add form").unwrap();
+        assert!(first < second);
+        assert!(summary.contains("- `form.rs` (+1 −0)"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_push_assigns_milestone() -> Result<()> {
+        let (test_repo, mut stack_manager, mrs) = setup().await?;
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.commit("feat: first")?;
+        stack_manager.create_stack("milestone").await?;
+        test_repo.create_branch("feature-2")?;
+        test_repo.commit("feat: second")?;
+        stack_manager.add_branch_to_stack(Some("feature-1")).await?;
+
+        // An unknown milestone only warns; the MRs are still created
+        stack_manager
+            .push_stack_with_options(&PushOptions {
+                milestone: Some("v9.9".to_string()),
+                ..Default::default()
+            })
+            .await?;
+        assert_eq!(mrs.lock().unwrap().len(), 2);
+        assert!(mrs
+            .lock()
+            .unwrap()
+            .values()
+            .all(|mr| mr.milestone.is_none()));
+
+        stack_manager
+            .push_stack_with_options(&PushOptions {
+                milestone: Some("v1.0".to_string()),
+                ..Default::default()
+            })
+            .await?;
+        let mrs = mrs.lock().unwrap();
+        assert!(mrs
+            .values()
+            .all(|mr| mr.milestone.as_ref().map(|m| m.id) == Some(11)));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_delete_stack_with_branches_and_mrs() -> Result<()> {
+        let (test_repo, mut stack_manager, mrs) = setup().await?;
+
+        test_repo.create_branch("doomed-1")?;
+        test_repo.commit("feat: first")?;
+        stack_manager.create_stack("doomed").await?;
+        test_repo.create_branch("doomed-2")?;
+        test_repo.commit("feat: second")?;
+        stack_manager.add_branch_to_stack(Some("doomed-1")).await?;
+        stack_manager.push_stack().await?;
+
+        let mut options = DeleteOptions {
+            force: true,
+            branches: true,
+            close_mrs: true,
+            dry_run: true,
+        };
+        stack_manager.delete_stack("doomed", &options).await?;
+        assert!(test_repo.git_repo().has_local_branch("doomed-2"));
+        assert!(mrs.lock().unwrap().values().all(|mr| mr.state == "opened"));
+
+        options.dry_run = false;
+        stack_manager.delete_stack("doomed", &options).await?;
+        for branch in ["doomed-1", "doomed-2"] {
+            assert!(!test_repo.git_repo().has_local_branch(branch));
+            assert!(test_repo
+                .git_repo()
+                .run(&["ls-remote", "--heads", "origin", branch])?
+                .is_empty());
+        }
+        assert!(mrs.lock().unwrap().values().all(|mr| mr.state == "closed"));
+        assert_eq!(test_repo.git_repo().get_current_branch()?, "main");
+        assert!(stack_manager.get_or_load_current_stack().is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stack_follows_default_branch_change() -> Result<()> {
+        let test_repo = TestRepo::new()?;
+        let mut config = TrainConfig::default();
+        config.editor.default_editor = "true".to_string();
+
+        // The stack is created while the project still defaults to master
+        let mut old_gitlab = MockGitLab::new();
+        old_gitlab.project.default_branch = "master".to_string();
+        let mrs = old_gitlab.merge_requests.clone();
+        let next_mr_iid = old_gitlab.next_mr_iid.clone();
+        test_repo.run(&["branch", "master"])?;
+        test_repo.checkout("master")?;
+        test_repo.create_branch("legacy-1")?;
+        test_repo.commit("feat: first")?;
+        let mut stack_manager = StackManager::new_with_config(
+            config.clone(),
+            Some(test_repo.git_repo().clone()),
+            Some(Box::new(old_gitlab)),
+        )
+        .await?;
+        stack_manager
+            .create_stack_with_base("legacy", Some("master"))
+            .await?;
+        test_repo.create_branch("legacy-2")?;
+        test_repo.commit("feat: second")?;
+        stack_manager.add_branch_to_stack(Some("legacy-1")).await?;
+        stack_manager.push_stack().await?;
+
+        // main moves on and becomes the default
+        test_repo.checkout("main")?;
+        test_repo.create_file("main-only.txt", "new default")?;
+        test_repo.commit("chore: main moves on")?;
+        test_repo.checkout("legacy-2")?;
+        let mut new_gitlab = MockGitLab::new();
+        new_gitlab.merge_requests = mrs.clone();
+        new_gitlab.next_mr_iid = next_mr_iid;
+        let mut stack_manager = StackManager::new_with_config(
+            config,
+            Some(test_repo.git_repo().clone()),
+            Some(Box::new(new_gitlab)),
+        )
+        .await?;
+
+        let stack = stack_manager.get_or_load_current_stack()?;
+        assert_eq!(stack.base_branch, "master");
+        assert_eq!(
+            stack_manager.default_branch_change(&stack).await,
+            Some("main".to_string())
+        );
+
+        stack_manager.move_stack_base("main").await?;
+
+        let stack = stack_manager.get_or_load_current_stack()?;
+        assert_eq!(stack.base_branch, "main");
+        assert_eq!(stack.branches["legacy-1"].parent.as_deref(), Some("main"));
+        let repo = test_repo.git_repo();
+        assert!(repo.is_ancestor("main", "legacy-1"));
+        assert!(repo.is_ancestor("legacy-1", "legacy-2"));
+        assert_eq!(repo.get_current_branch()?, "legacy-2");
+        assert_eq!(stack_manager.default_branch_change(&stack).await, None);
+
+        let mrs = mrs.lock().unwrap();
+        let target_of = |branch: &str| {
+            mrs.values()
+                .find(|mr| mr.source_branch == branch)
+                .map(|mr| mr.target_branch.clone())
+        };
+        assert_eq!(target_of("legacy-1").as_deref(), Some("main"));
+        assert_eq!(target_of("legacy-2").as_deref(), Some("legacy-1"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_edit_earlier_branch_from_latest() -> Result<()> {
+        let (test_repo, mut stack_manager, mrs) = setup().await?;
+
+        // 1. Create a stack of 3 branches with different files
+        test_repo.create_branch("feature-1")?;
+        test_repo.create_file("file1.txt", "original content from feature-1")?;
+        test_repo.commit("feat: add file1 in feature-1")?;
+        stack_manager.create_stack("my-stack").await?;
+
+        test_repo.create_branch("feature-2")?;
+        test_repo.create_file("file2.txt", "content from feature-2")?;
+        test_repo.commit("feat: add file2 in feature-2")?;
+        stack_manager.add_branch_to_stack(Some("feature-1")).await?;
+
+        test_repo.create_branch("feature-3")?;
+        test_repo.create_file("file3.txt", "content from feature-3")?;
+        test_repo.commit("feat: add file3 in feature-3")?;
+        stack_manager.add_branch_to_stack(Some("feature-2")).await?;
+
+        // 2. Push the stack to create MRs
+        stack_manager.push_stack().await?;
+        assert_eq!(mrs.lock().unwrap().len(), 3);
+
+        // 3. Stay on feature-3 (the latest branch) and edit a file from feature-1
+        // This simulates the common workflow where you're working on the latest branch
+        // but realize you need to fix something from an earlier branch
+        test_repo.create_file(
+            "file1.txt",
+            "updated content from feature-1 (edited from feature-3)",
+        )?;
+        // Don't commit yet - let amend_changes handle it
+
+        // 4. Use existing functionality to sync the stack - it should automatically detect
+        // that we've edited files from an earlier branch and handle it properly
+        stack_manager
+            .amend_changes(Some("fix: update file1 from earlier branch"))
+            .await?;
+
+        // 5. Verify that:
+        // - feature-1 has the updated file1.txt content
+        // - feature-2 and feature-3 were rebased onto the updated feature-1
+        // - The stack hierarchy is maintained
+
+        // Check feature-1 has the updated content
+        test_repo.checkout("feature-1")?;
+        let file1_content = std::fs::read_to_string(test_repo.path().join("file1.txt"))?;
+        assert_eq!(
+            file1_content,
+            "updated content from feature-1 (edited from feature-3)"
+        );
+
+        // Check that feature-2 was rebased (parent should be updated feature-1)
+        test_repo.checkout("feature-2")?;
+        let feature2_parent = test_repo.git_repo().run(&["rev-parse", "feature-2^"])?;
+        let feature1_hash = test_repo
+            .git_repo()
+            .get_commit_hash_for_branch("feature-1")?;
+        assert_eq!(feature2_parent.trim(), feature1_hash.trim());
+
+        // Check that feature-3 was rebased (parent should be updated feature-2)
+        test_repo.checkout("feature-3")?;
+        let feature3_parent = test_repo.git_repo().run(&["rev-parse", "feature-3^"])?;
+        let feature2_hash = test_repo
+            .git_repo()
+            .get_commit_hash_for_branch("feature-2")?;
+        assert_eq!(feature3_parent.trim(), feature2_hash.trim());
+
+        // Verify all files are present in feature-3
+        assert!(test_repo.path().join("file1.txt").exists());
+        assert!(test_repo.path().join("file2.txt").exists());
+        assert!(test_repo.path().join("file3.txt").exists());
+
+        // Check that file1.txt has the updated content in feature-3
+        let file1_content_in_feature3 =
+            std::fs::read_to_string(test_repo.path().join("file1.txt"))?;
+        assert_eq!(
+            file1_content_in_feature3,
+            "updated content from feature-1 (edited from feature-3)"
+        );
+
+        // 6. Push the updated stack
+        stack_manager.push_stack().await?;
+
+        // Verify MRs are updated
+        let mrs_after = mrs.lock().unwrap();
+        assert_eq!(mrs_after.len(), 3);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_propagation_moves_only_the_earlier_branchs_hunks() -> Result<()> {
+        let (test_repo, mut stack_manager, _mrs) = setup().await?;
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.create_file("shared.txt", "a\nb\nc\nd\ne\nf\ng\n")?;
+        test_repo.commit("feat: add shared")?;
+        stack_manager.create_stack("hunks").await?;
+
+        test_repo.create_branch("feature-2")?;
+        test_repo.create_file("shared.txt", "a\nb\nc\nd\ne\nf\nG\n")?;
+        test_repo.commit("feat: tweak shared")?;
+        stack_manager.add_branch_to_stack(Some("feature-1")).await?;
+
+        // One edit on a line from feature-1, one on the line feature-2 changed
+        test_repo.create_file("shared.txt", "A1\nb\nc\nd\ne\nf\nG2\n")?;
+        stack_manager.amend_changes(None).await?;
+
+        let show = |rev: &str| test_repo.git_repo().run(&["show", rev]).unwrap();
+        assert_eq!(show("feature-1:shared.txt"), "A1\nb\nc\nd\ne\nf\ng");
+        assert_eq!(show("feature-2:shared.txt"), "A1\nb\nc\nd\ne\nf\nG2");
+        assert!(test_repo.git_repo().is_ancestor("feature-1", "feature-2"));
+
+        assert_eq!(
+            test_repo.git_repo().get_current_branch()?,
+            "feature-2".to_string()
+        );
+        assert!(!test_repo.git_repo().has_uncommitted_changes()?);
+        assert!(test_repo.git_repo().run(&["stash", "list"])?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_blame_attributes_lines_to_levels() -> Result<()> {
+        let (test_repo, mut stack_manager, _mrs) = setup().await?;
+
+        test_repo.create_file("shared.txt", "base\n")?;
+        test_repo.commit("chore: add shared")?;
+        test_repo.create_branch("feature-1")?;
+        test_repo.create_file("shared.txt", "base\none\n")?;
+        test_repo.commit("feat: one")?;
+        stack_manager.create_stack("blame").await?;
+        test_repo.create_branch("feature-2")?;
+        test_repo.create_file("shared.txt", "base\nONE\ntwo\n")?;
+        test_repo.commit("feat: two")?;
+        stack_manager.add_branch_to_stack(Some("feature-1")).await?;
+
+        let owners: Vec<Option<String>> = stack_manager
+            .blame_file("shared.txt", None)
+            .await?
+            .into_iter()
+            .map(|(owner, _)| owner)
+            .collect();
+        assert_eq!(
+            owners,
+            vec![None, Some("feature-2".into()), Some("feature-2".into())]
+        );
+
+        let lines = stack_manager
+            .blame_file("shared.txt", Some("feature-1"))
+            .await?;
+        assert_eq!(lines[1], (Some("feature-1".to_string()), "one".to_string()));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sync_restacks_child_after_parent_amended_outside() -> Result<()> {
+        let (test_repo, mut stack_manager, _mrs) = setup().await?;
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.create_file("file1.txt", "content1")?;
+        test_repo.commit("feat: add file1")?;
+        stack_manager.create_stack("my-stack").await?;
+
+        test_repo.create_branch("feature-2")?;
+        test_repo.create_file("file2.txt", "content2")?;
+        test_repo.commit("feat: add file2")?;
+        stack_manager.add_branch_to_stack(Some("feature-1")).await?;
+
+        // Amend the parent with raw git, leaving feature-2 on the old commit
+        test_repo.checkout("feature-1")?;
+        test_repo.create_file("file1.txt", "amended content1")?;
+        test_repo.run(&["commit", "--amend", "-m", "feat: add file1 (amended)"])?;
+        test_repo.checkout("feature-2")?;
+
+        stack_manager.sync_with_remote().await?;
+
+        let feature1_hash = test_repo
+            .git_repo()
+            .get_commit_hash_for_branch("feature-1")?;
+        let parent_of_feature2 = test_repo.git_repo().run(&["rev-parse", "feature-2^"])?;
+        assert_eq!(parent_of_feature2.trim(), feature1_hash.trim());
+
+        // Only the child's own commit is replayed, the stale parent commit is dropped
+        let count = test_repo
+            .git_repo()
+            .run(&["rev-list", "--count", "main..feature-2"])?;
+        assert_eq!(count.trim(), "2");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sync_prune_drops_branches_deleted_on_remote() -> Result<()> {
+        let (test_repo, mut stack_manager, _) = setup().await?;
+        let repo = test_repo.git_repo();
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.create_file("file1.txt", "content1")?;
+        test_repo.commit("feat: add file1")?;
+        stack_manager.create_stack("my-stack").await?;
+        test_repo.create_branch("feature-2")?;
+        test_repo.create_file("file2.txt", "content2")?;
+        test_repo.commit("feat: add file2")?;
+        stack_manager.add_branch_to_stack(Some("feature-1")).await?;
+        test_repo.create_branch("feature-3")?;
+        test_repo.create_file("file3.txt", "content3")?;
+        test_repo.commit("feat: add file3")?;
+        stack_manager.add_branch_to_stack(Some("feature-2")).await?;
+        stack_manager.push_stack().await?;
+
+        test_repo.run(&["push", "origin", "--delete", "feature-2"])?;
+
+        // Without --prune the branch is only reported
+        stack_manager.sync_with_remote().await?;
+        assert_eq!(stack_manager.get_or_load_current_stack()?.branches.len(), 3);
+
+        stack_manager
+            .sync_with_remote_with_options(&SyncOptions {
+                prune: true,
+                ..Default::default()
+            })
+            .await?;
+        let stack = stack_manager.get_or_load_current_stack()?;
+        assert!(!stack.branches.contains_key("feature-2"));
+        assert_eq!(
+            stack.branches["feature-3"].parent.as_deref(),
+            Some("feature-1")
+        );
+        assert!(stack.branches["feature-1"]
+            .children
+            .iter()
+            .any(|c| c == "feature-3"));
+
+        // The child is replayed onto its new parent without the pruned branch's commits
+        assert!(repo.is_ancestor("feature-1", "feature-3"));
+        let count = repo.run(&["rev-list", "--count", "feature-1..feature-3"])?;
+        assert_eq!(count.trim(), "1");
+        assert!(repo.has_local_branch("feature-2"));
+
+        stack_manager.push_stack().await?;
+        assert!(!repo.has_remote_branch("feature-2"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_merge_strategy_sync_preserves_history() -> Result<()> {
+        let (test_repo, mut stack_manager, mrs) = setup().await?;
+        let repo = test_repo.git_repo();
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.create_file("file1.txt", "content1")?;
+        test_repo.commit("feat: add file1")?;
+        stack_manager.create_stack("my-stack").await?;
+        stack_manager
+            .set_update_strategy(Some(UpdateStrategy::Merge))
+            .await?;
+
+        test_repo.create_branch("feature-2")?;
+        test_repo.create_file("file2.txt", "content2")?;
+        test_repo.commit("feat: add file2")?;
+        stack_manager.add_branch_to_stack(Some("feature-1")).await?;
+        stack_manager.push_stack().await?;
+
+        let tip1 = repo.get_commit_hash_for_branch("feature-1")?;
+        let tip2 = repo.get_commit_hash_for_branch("feature-2")?;
+
+        // Advance the base
+        test_repo.checkout("main")?;
+        test_repo.create_file("main.txt", "main")?;
+        test_repo.commit("chore: advance main")?;
+        test_repo.run(&["push", "origin", "main"])?;
+        test_repo.checkout("feature-2")?;
+
+        stack_manager.sync_with_remote().await?;
+
+        // Old tips are kept and the base is merged in, all the way up the stack
+        assert!(repo.is_ancestor(&tip1, "feature-1"));
+        assert!(repo.is_ancestor(&tip2, "feature-2"));
+        assert!(repo.is_ancestor("main", "feature-1"));
+        assert!(repo.is_ancestor("feature-1", "feature-2"));
+        let merges = repo.run(&["rev-list", "--merges", "main..feature-1"])?;
+        assert_eq!(merges.lines().count(), 1);
+
+        // Every push is a fast-forward of what's already on the remote
+        stack_manager.push_stack().await?;
+        assert_eq!(
+            repo.get_commit_hash_for_branch("origin/feature-2")?,
+            repo.get_commit_hash_for_branch("feature-2")?
+        );
+        assert!(repo.is_ancestor(&tip2, "origin/feature-2"));
+
+        let mrs = mrs.lock().unwrap();
+        assert!(mrs.values().all(|mr| mr
+            .description
+            .as_deref()
+            .is_some_and(|d| d.contains("never force-pushed"))));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_edit_branch_squashes_and_restacks() -> Result<()> {
+        let (test_repo, mut stack_manager, _mrs) = setup().await?;
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.create_file("file1.txt", "content1")?;
+        test_repo.commit("feat: add file1")?;
+        test_repo.create_file("file1b.txt", "content1b")?;
+        test_repo.commit("fixup: forgot a file")?;
+        stack_manager.create_stack("my-stack").await?;
+
+        test_repo.create_branch("feature-2")?;
+        test_repo.create_file("file2.txt", "content2")?;
+        test_repo.commit("feat: add file2")?;
+        stack_manager.add_branch_to_stack(Some("feature-1")).await?;
+
+        // Scripted "editor": fold the second commit into the first
+        std::env::set_var("GIT_SEQUENCE_EDITOR", "sed -i -e '2s/^pick/fixup/'");
+        let result = stack_manager.edit_branch(Some("feature-1")).await;
+        std::env::remove_var("GIT_SEQUENCE_EDITOR");
+        result?;
+
+        let repo = test_repo.git_repo();
+        assert_eq!(repo.run(&["rev-list", "--count", "main..feature-1"])?, "1");
+        assert_eq!(
+            repo.get_commit_message_for_branch("feature-1")?,
+            "feat: add file1"
+        );
+        let parent_of_feature2 = repo.run(&["rev-parse", "feature-2^"])?;
+        assert_eq!(
+            parent_of_feature2,
+            repo.get_commit_hash_for_branch("feature-1")?
+        );
+        assert_eq!(repo.run(&["rev-list", "--count", "main..feature-2"])?, "2");
+        assert_eq!(repo.get_current_branch()?, "feature-2");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pick_commit_mid_stack() -> Result<()> {
+        let (test_repo, mut stack_manager, _mrs) = setup().await?;
+
+        // A hotfix commit living outside the stack
+        test_repo.create_branch("hotfix")?;
+        test_repo.create_file("hotfix.txt", "fix")?;
+        let hotfix = test_repo.commit("fix: hotfix")?;
+        test_repo.checkout("main")?;
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.create_file("file1.txt", "content1")?;
+        test_repo.commit("feat: add file1")?;
+        stack_manager.create_stack("my-stack").await?;
+
+        test_repo.create_branch("feature-2")?;
+        test_repo.create_file("file2.txt", "content2")?;
+        test_repo.commit("feat: add file2")?;
+        stack_manager.add_branch_to_stack(Some("feature-1")).await?;
+
+        stack_manager
+            .pick_commit(&hotfix, Some("feature-1"))
+            .await?;
+
+        // Picked onto feature-1, feature-2 restacked on top, original branch restored
+        let subject = test_repo
+            .git_repo()
+            .get_commit_message_for_branch("feature-1")?;
+        assert_eq!(subject, "fix: hotfix");
+        let feature1_hash = test_repo
+            .git_repo()
+            .get_commit_hash_for_branch("feature-1")?;
+        let parent_of_feature2 = test_repo.git_repo().run(&["rev-parse", "feature-2^"])?;
+        assert_eq!(parent_of_feature2.trim(), feature1_hash.trim());
+        assert_eq!(test_repo.git_repo().get_current_branch()?, "feature-2");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_named_mr_template_selection() -> Result<()> {
+        let (test_repo, mut stack_manager, mrs) = setup().await?;
+
+        fs::create_dir_all(test_repo.path().join(".gitlab/merge_request_templates"))?;
+        test_repo.create_file(
+            ".gitlab/merge_request_templates/bugfix.md",
+            "## Bugfix checklist",
+        )?;
+        test_repo.create_file(
+            ".gitlab/merge_request_templates/feature.md",
+            "## Feature checklist",
+        )?;
+        test_repo.commit("chore: add mr templates")?;
+        test_repo.run(&["push", "origin", "main"])?;
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.create_file("file1.txt", "content1")?;
+        test_repo.commit("feat: add file1")?;
+        stack_manager.create_stack("my-stack").await?;
+
+        test_repo.create_branch("feature-2")?;
+        test_repo.create_file("file2.txt", "content2")?;
+        test_repo.commit("fix: add file2")?;
+        stack_manager.add_branch_to_stack(Some("feature-1")).await?;
+
+        // Stack-wide default, overridden for a single branch
+        stack_manager
+            .set_mr_template(Some("feature"), None, false)
+            .await?;
+        stack_manager
+            .set_mr_template(Some("bugfix"), Some("feature-2"), false)
+            .await?;
+        assert!(stack_manager
+            .set_mr_template(Some("missing"), None, false)
+            .await
+            .is_err());
+
+        stack_manager.push_stack().await?;
+
+        let mrs = mrs.lock().unwrap();
+        let description_for = |branch: &str| {
+            mrs.values()
+                .find(|mr| mr.source_branch == branch)
+                .and_then(|mr| mr.description.clone())
+                .unwrap_or_default()
+        };
+        assert!(description_for("feature-1").contains("## Feature checklist"));
+        assert!(description_for("feature-2").contains("## Bugfix checklist"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_review_notes_synced_to_mr() -> Result<()> {
+        let test_repo = TestRepo::new()?;
+        let mut config = TrainConfig::default();
+        config.conflict_resolution.auto_force_push_after_rebase = true;
+        config.git.verify_signatures = false;
+        config.mr.sync_review_notes = true;
+        let mock_gitlab = MockGitLab::new();
+        let mrs = mock_gitlab.merge_requests.clone();
+        let gitlab_client = Some(Box::new(mock_gitlab) as Box<dyn GitLabApi + Send + Sync>);
+        let mut stack_manager = StackManager::new_with_config(
+            config,
+            Some(test_repo.git_repo().clone()),
+            gitlab_client,
+        )
+        .await?;
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.create_file("file1.txt", "content1")?;
+        test_repo.commit("feat: add file1")?;
+        stack_manager.create_stack("my-stack").await?;
+        stack_manager.push_stack().await?;
+
+        stack_manager
+            .review_notes(
+                ReviewNoteAction::Add {
+                    text: "Run the migration on staging".to_string(),
+                    todo: true,
+                },
+                None,
+            )
+            .await?;
+        stack_manager
+            .review_notes(ReviewNoteAction::Check(1), Some("feature-1"))
+            .await?;
+        assert!(stack_manager
+            .review_notes(ReviewNoteAction::Remove(5), None)
+            .await
+            .is_err());
+
+        let stack = stack_manager.get_or_load_current_stack()?;
+        assert_eq!(stack.branches["feature-1"].todo_progress(), (1, 1));
+
+        let description = mrs.lock().unwrap()[&MrIid::from(1)]
+            .description
+            .clone()
+            .unwrap();
+        assert!(description.contains("- [x] Run the migration on staging"));
+        assert!(description.contains("git-train-stack-start"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_force_push_reports_remote_only_commits() -> Result<()> {
+        let (test_repo, mut stack_manager, _mrs) = setup().await?;
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.create_file("file1.txt", "content1")?;
+        test_repo.commit("feat: add file1")?;
+        stack_manager.create_stack("my-stack").await?;
+        stack_manager.push_stack().await?;
+
+        // A teammate pushes on top, then we drop their commit locally
+        test_repo.run(&[
+            "commit",
+            "--allow-empty",
+            "--author=Teammate <teammate@example.com>",
+            "-m",
+            "fix: teammate change",
+        ])?;
+        test_repo.run(&["push", "origin", "feature-1"])?;
+        test_repo.run(&["reset", "--hard", "HEAD~1"])?;
+
+        let discarded = test_repo
+            .git_repo()
+            .commits_only_in("feature-1", "origin/feature-1")?;
+        assert_eq!(discarded.len(), 1);
+        assert_eq!(discarded[0].author_email, "teammate@example.com");
+        assert_eq!(discarded[0].subject, "fix: teammate change");
+
+        // Rewriting only our own commits force-pushes without a prompt
+        test_repo.run(&["push", "--force", "origin", "feature-1"])?;
+        let before = test_repo
+            .git_repo()
+            .get_commit_hash_for_branch("feature-1")?;
+        test_repo.run(&["commit", "--amend", "--allow-empty", "-m", "feat: reworded"])?;
+        stack_manager.push_stack().await?;
+        test_repo.run(&["fetch", "origin"])?;
+        let remote = test_repo
+            .git_repo()
+            .get_commit_hash_for_branch("origin/feature-1")?;
+        assert_ne!(remote, before);
+        assert_eq!(
+            remote,
+            test_repo
+                .git_repo()
+                .get_commit_hash_for_branch("feature-1")?
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_switch_with_checkout() -> Result<()> {
+        let (test_repo, mut stack_manager, _mrs) = setup().await?;
+
+        test_repo.create_branch("alpha-1")?;
+        test_repo.create_file("alpha.txt", "alpha")?;
+        test_repo.commit("feat: alpha")?;
+        stack_manager.create_stack("alpha").await?;
+        let alpha_id = stack_manager.get_or_load_current_stack()?.id;
+
+        test_repo.checkout("main")?;
+        test_repo.create_branch("beta-1")?;
+        test_repo.create_file("beta.txt", "beta")?;
+        test_repo.commit("feat: beta")?;
+        stack_manager.create_stack("beta").await?;
+
+        // Metadata-only switch leaves HEAD alone
+        stack_manager.switch_stack(&alpha_id, Some(false)).await?;
+        assert_eq!(test_repo.git_repo().get_current_branch()?, "beta-1");
+
+        // Uncommitted changes are carried over with auto-stash
+        fs::write(test_repo.path().join("notes.txt"), "wip")?;
+        stack_manager.switch_stack(&alpha_id, Some(true)).await?;
+        assert_eq!(test_repo.git_repo().get_current_branch()?, "alpha-1");
+        assert!(test_repo.path().join("notes.txt").exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_current_branch_follows_head() -> Result<()> {
+        let (test_repo, mut stack_manager, _mrs) = setup().await?;
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.create_file("file1.txt", "content1")?;
+        test_repo.commit("feat: add file1")?;
+        stack_manager.create_stack("my-stack").await?;
+
+        test_repo.create_branch("feature-2")?;
+        test_repo.create_file("file2.txt", "content2")?;
+        test_repo.commit("feat: add file2")?;
+        stack_manager.add_branch_to_stack(Some("feature-1")).await?;
+        let stack_id = stack_manager.get_or_load_current_stack()?.id;
+        stack_manager.record_current_branch()?;
+        assert_eq!(
+            stack_manager.get_or_load_current_stack()?.current_branch,
+            Some("feature-2".into())
+        );
+
+        // A manual checkout is picked up on the next command
+        test_repo.checkout("feature-1")?;
+        stack_manager.record_current_branch()?;
+        assert_eq!(
+            stack_manager.get_or_load_current_stack()?.current_branch,
+            Some("feature-1".into())
+        );
+
+        // ...so switching back with --checkout returns to it
+        test_repo.checkout("main")?;
+        test_repo.create_branch("other-1")?;
+        test_repo.commit("feat: other")?;
+        stack_manager.create_stack("other").await?;
+        stack_manager.switch_stack(&stack_id, Some(true)).await?;
+        assert_eq!(test_repo.git_repo().get_current_branch()?, "feature-1");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_push_attaches_existing_mr() -> Result<()> {
+        let (test_repo, mut stack_manager, mrs) = setup().await?;
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.create_file("file1.txt", "content1")?;
+        test_repo.commit("feat: add file1")?;
+        stack_manager.create_stack("my-stack").await?;
+
+        // Someone opened an MR for the branch by hand
+        mrs.lock().unwrap().insert(
+            MrIid::from(42),
+            MergeRequest {
+                id: 42,
+                iid: MrIid::from(42),
+                title: "Manually opened".to_string(),
+                description: None,
+                source_branch: "feature-1".to_string(),
+                target_branch: "main".to_string(),
+                source_project_id: Some(1),
+                state: "opened".to_string(),
+                web_url: "http://gitlab.com/test-namespace/test-project/merge_requests/42"
+                    .to_string(),
+                head_pipeline: None,
+                has_conflicts: false,
+                detailed_merge_status: None,
+                sha: None,
+                milestone: None,
+            },
+        );
+
+        stack_manager.push_stack().await?;
+
+        assert_eq!(mrs.lock().unwrap().len(), 1);
+        let stack = stack_manager.get_or_load_current_stack()?;
+        assert_eq!(stack.branches["feature-1"].mr_iid, Some(MrIid::from(42)));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_undo_restores_mr_metadata_from_before_the_update() -> Result<()> {
+        let (test_repo, mut stack_manager, mrs) = setup().await?;
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.create_file("file1.txt", "content1")?;
+        test_repo.commit("feat: add file1")?;
+        stack_manager.create_stack("my-stack").await?;
+        test_repo.create_branch("feature-2")?;
+        test_repo.create_file("file2.txt", "content2")?;
+        test_repo.commit("feat: add file2")?;
+        stack_manager.add_branch_to_stack(Some("feature-1")).await?;
+
+        // Nothing to restore before git-train has touched any MR
+        stack_manager.push_stack().await?;
+        stack_manager.undo_mr_update().await?;
+        let iid = stack_manager.get_or_load_current_stack()?.branches["feature-2"]
+            .mr_iid
+            .unwrap();
+        assert!(mrs.lock().unwrap()[&iid]
+            .description
+            .as_deref()
+            .unwrap()
+            .contains("feature-1"));
+
+        // A description edited by hand is overwritten by the next update
+        mrs.lock().unwrap().get_mut(&iid).unwrap().description =
+            Some("Hand-written notes".to_string());
+        stack_manager.update_all_mrs(None).await?;
+        assert_ne!(
+            mrs.lock().unwrap()[&iid].description.as_deref(),
+            Some("Hand-written notes")
+        );
+
+        stack_manager.undo_mr_update().await?;
+        assert_eq!(
+            mrs.lock().unwrap()[&iid].description.as_deref(),
+            Some("Hand-written notes")
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_push_skips_mrs_for_branches_that_failed_to_push() -> Result<()> {
+        let (test_repo, mut stack_manager, mrs) = setup().await?;
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.create_file("file1.txt", "content1")?;
+        test_repo.commit("feat: add file1")?;
+        stack_manager.create_stack("my-stack").await?;
+        test_repo.create_branch("feature-2")?;
+        test_repo.create_file("file2.txt", "content2")?;
+        test_repo.commit("feat: add file2")?;
+        stack_manager.add_branch_to_stack(Some("feature-1")).await?;
+
+        // The remote refuses feature-2
+        let hook = test_repo.remote_dir.path().join("hooks/pre-receive");
+        fs::write(
+            &hook,
+            "#!/bin/sh\nwhile read old new ref; do\n  [ \"$ref\" = refs/heads/feature-2 ] && exit 1\ndone\nexit 0\n",
+        )?;
+        Command::new("chmod").arg("+x").arg(&hook).output()?;
+
+        stack_manager.push_stack().await?;
+
+        let stack = stack_manager.get_or_load_current_stack()?;
+        assert!(stack.branches["feature-1"].mr_iid.is_some());
+        assert!(stack.branches["feature-2"].mr_iid.is_none());
+        let mrs = mrs.lock().unwrap();
+        assert_eq!(mrs.len(), 1);
+        assert!(mrs.values().all(|mr| mr.source_branch == "feature-1"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fork_mrs_target_the_upstream_base() -> Result<()> {
+        let test_repo = TestRepo::new()?;
+        let mut mock = MockGitLab::new();
+        let mut fork = mock.project.clone();
+        fork.id = 2;
+        fork.namespace.path = "contributor".to_string();
+        mock.fork = Some(fork);
+        let mrs = mock.merge_requests.clone();
+        let mut stack_manager = StackManager::new_with_config(
+            TrainConfig::default(),
+            Some(test_repo.git_repo().clone()),
+            Some(Box::new(mock)),
+        )
+        .await?;
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.create_file("file1.txt", "content1")?;
+        test_repo.commit("feat: add file1")?;
+        stack_manager.create_stack("my-stack").await?;
+        test_repo.create_branch("feature-2")?;
+        test_repo.create_file("file2.txt", "content2")?;
+        test_repo.commit("feat: add file2")?;
+        stack_manager.add_branch_to_stack(Some("feature-1")).await?;
+
+        // Another fork's MR from a branch with the same name is not ours to attach
+        mrs.lock().unwrap().insert(
+            MrIid::from(42),
+            MergeRequest {
+                id: 42,
+                iid: MrIid::from(42),
+                title: "Someone else's branch".to_string(),
+                description: None,
+                source_branch: "feature-1".to_string(),
+                target_branch: "main".to_string(),
+                source_project_id: Some(3),
+                state: "opened".to_string(),
+                web_url: "http://gitlab.com/test-namespace/test-project/merge_requests/42"
+                    .to_string(),
+                head_pipeline: None,
+                has_conflicts: false,
+                detailed_merge_status: None,
+                sha: None,
+                milestone: None,
+            },
+        );
+
+        stack_manager.push_stack().await?;
+
+        let stack = stack_manager.get_or_load_current_stack()?;
+        let mrs = mrs.lock().unwrap();
+        for branch in ["feature-1", "feature-2"] {
+            let iid = stack.branches[branch].mr_iid.unwrap();
+            assert_ne!(iid, MrIid::from(42));
+            let mr = &mrs[&iid];
+            // The parent branch only exists in the fork, so every level targets main
+            assert_eq!(mr.target_branch, "main");
+            assert_eq!(mr.source_project_id, Some(2));
+            assert!(mr.description.as_deref().unwrap().contains("feature-1"));
+        }
+        assert!(mrs[&MrIid::from(42)].description.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_add_refuses_a_parent_that_is_not_an_ancestor() -> Result<()> {
+        let (test_repo, mut stack_manager, _) = setup().await?;
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.create_file("file1.txt", "content1")?;
+        test_repo.commit("feat: add file1")?;
+        stack_manager.create_stack("my-stack").await?;
+
+        test_repo.create_branch("feature-2")?;
+        test_repo.create_file("file2.txt", "content2")?;
+        test_repo.commit("feat: add file2")?;
+
+        // feature-1 moves on after feature-2 was cut from it
+        test_repo.checkout("feature-1")?;
+        test_repo.create_file("file1b.txt", "more")?;
+        test_repo.commit("feat: extend file1")?;
+        test_repo.checkout("feature-2")?;
+
+        // Without a confirmation the branch is left alone and not added
+        let before = test_repo.git_repo().get_current_commit_hash()?;
+        let error = stack_manager
+            .add_branch_to_stack(Some("feature-1"))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("git rebase feature-1"));
+        assert_eq!(test_repo.git_repo().get_current_commit_hash()?, before);
+        let stack = stack_manager.get_or_load_current_stack()?;
+        assert!(!stack.branches.contains_key("feature-2"));
+
+        // Once it is rebased, it is added as usual
+        test_repo.run(&["rebase", "feature-1"])?;
+        stack_manager.add_branch_to_stack(Some("feature-1")).await?;
+        let stack = stack_manager.get_or_load_current_stack()?;
+        assert!(stack.branches.contains_key("feature-2"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_top_and_bottom_follow_the_current_chain() -> Result<()> {
+        let (test_repo, mut stack_manager, _) = setup().await?;
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.create_file("file1.txt", "content1")?;
+        test_repo.commit("feat: add file1")?;
+        stack_manager.create_stack("my-stack").await?;
+        for (branch, parent) in [
+            ("feature-2", "feature-1"),
+            ("feature-3", "feature-2"),
+            ("side", "feature-1"),
+        ] {
+            test_repo.checkout(parent)?;
+            test_repo.create_branch(branch)?;
+            test_repo.create_file(&format!("{}.txt", branch), "content")?;
+            test_repo.commit(&format!("feat: add {}", branch))?;
+            stack_manager.add_branch_to_stack(Some(parent)).await?;
+        }
+
+        // The side branch forks off below feature-2, so it isn't a candidate
+        test_repo.checkout("feature-2")?;
+        assert_eq!(stack_manager.checkout_top().await?, "feature-3");
+        assert_eq!(test_repo.git_repo().get_current_branch()?, "feature-3");
+        assert_eq!(stack_manager.checkout_top().await?, "feature-3");
+
+        test_repo.checkout("side")?;
+        assert_eq!(stack_manager.checkout_bottom().await?, "feature-1");
+        assert_eq!(test_repo.git_repo().get_current_branch()?, "feature-1");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_server_side_conflicts_block_landing() -> Result<()> {
+        let (test_repo, mut stack_manager, mrs) = setup().await?;
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.create_file("file1.txt", "content1")?;
+        test_repo.commit("feat: add file1")?;
+        stack_manager.create_stack("my-stack").await?;
+        stack_manager.push_stack().await?;
+
+        let iid = stack_manager.get_or_load_current_stack()?.branches["feature-1"]
+            .mr_iid
+            .unwrap();
+        mrs.lock()
+            .unwrap()
+            .get_mut(&iid)
+            .unwrap()
+            .detailed_merge_status = Some("conflict".to_string());
+
+        stack_manager.show_status().await?;
+        let options = LandOptions {
+            squash_all: false,
+            per_level: false,
+            squash: false,
+            delete_source_branch: false,
+        };
+        let error = stack_manager.land_stack(&options).await.unwrap_err();
+        assert!(error.to_string().contains("not ready to land"));
+        assert_eq!(mrs.lock().unwrap()[&iid].state, "opened");
+
+        // Once GitLab sees the conflict resolved the level can land
+        mrs.lock()
+            .unwrap()
+            .get_mut(&iid)
+            .unwrap()
+            .detailed_merge_status = Some("mergeable".to_string());
+        stack_manager.land_stack(&options).await?;
+        assert_eq!(mrs.lock().unwrap()[&iid].state, "merged");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_amend_earlier_branch_without_checking_it_out() -> Result<()> {
+        let (test_repo, mut stack_manager, _) = setup().await?;
+        let repo = test_repo.git_repo();
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.create_file("file1.txt", "content1")?;
+        test_repo.commit("feat: add file1")?;
+        stack_manager.create_stack("my-stack").await?;
+        test_repo.create_branch("feature-2")?;
+        test_repo.create_file("file2.txt", "content2")?;
+        test_repo.commit("feat: add file2")?;
+        stack_manager.add_branch_to_stack(Some("feature-1")).await?;
+
+        // Stage a fix for feature-1 and leave an unrelated edit unstaged
+        test_repo.create_file("file1.txt", "fixed1")?;
+        fs::write(test_repo.path().join("file2.txt"), "work in progress")?;
+
+        stack_manager
+            .amend_branch("feature-1", Some("feat: add fixed file1"))
+            .await?;
+
+        assert_eq!(repo.get_current_branch()?, "feature-2");
+        assert_eq!(repo.run(&["show", "feature-1:file1.txt"])?, "fixed1");
+        assert_eq!(
+            repo.get_commit_message_for_branch("feature-1")?,
+            "feat: add fixed file1"
+        );
+        assert_eq!(repo.run(&["rev-list", "--count", "main..feature-1"])?, "1");
+        assert!(repo.is_ancestor("feature-1", "feature-2"));
+        assert_eq!(
+            repo.run(&["diff", "--name-only"])?,
+            "file2.txt",
+            "unstaged work should be restored"
+        );
+        assert!(repo.run(&["diff", "--cached", "--name-only"])?.is_empty());
+        assert!(repo.run(&["stash", "list"])?.is_empty());
+
+        let stack = stack_manager.get_or_load_current_stack()?;
+        assert_eq!(
+            stack.branches["feature-1"].commit_hash,
+            repo.get_commit_hash_for_branch("feature-1")?
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_push_sets_upstream_tracking() -> Result<()> {
+        let (test_repo, mut stack_manager, _) = setup().await?;
+        let repo = test_repo.git_repo();
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.create_file("file1.txt", "content1")?;
+        test_repo.commit("feat: add file1")?;
+        stack_manager.create_stack("my-stack").await?;
+        assert!(repo.run(&["config", "branch.feature-1.merge"]).is_err());
+
+        stack_manager.push_stack().await?;
+        assert_eq!(repo.run(&["config", "branch.feature-1.remote"])?, "origin");
+        assert_eq!(
+            repo.run(&["config", "branch.feature-1.merge"])?,
+            "refs/heads/feature-1"
+        );
+        assert_eq!(
+            repo.run(&["rev-parse", "--abbrev-ref", "feature-1@{upstream}"])?,
+            "origin/feature-1"
+        );
+
+        // Pushing again leaves the single config entry alone
+        stack_manager.push_stack().await?;
+        let entries = repo.run(&["config", "--get-all", "branch.feature-1.merge"])?;
+        assert_eq!(entries.lines().count(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_push_retries_after_stale_lease() -> Result<()> {
+        let (test_repo, mut stack_manager, _) = setup().await?;
+        let repo = test_repo.git_repo();
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.create_file("file1.txt", "content1")?;
+        test_repo.commit("feat: add file1")?;
+        stack_manager.create_stack("my-stack").await?;
+        stack_manager.push_stack().await?;
+
+        // Stop fetches from refreshing origin/feature-1, then move the remote branch so
+        // the lease git-train pushes with is stale
+        test_repo.run(&[
+            "config",
+            "remote.origin.fetch",
+            "+refs/heads/main:refs/remotes/origin/main",
+        ])?;
+        test_repo.create_branch("elsewhere")?;
+        test_repo.create_file("other.txt", "other")?;
+        test_repo.commit("feat: pushed from elsewhere")?;
+        test_repo.run(&["push", "origin", "elsewhere:feature-1"])?;
+        test_repo.checkout("feature-1")?;
+
+        test_repo.create_file("file1.txt", "amended")?;
+        stack_manager.amend_changes(None).await?;
+        stack_manager.push_stack().await?;
+
+        assert_eq!(
+            repo.run(&["ls-remote", "origin", "refs/heads/feature-1"])?
+                .split('\t')
+                .next()
+                .unwrap(),
+            repo.get_commit_hash_for_branch("feature-1")?
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_force_push_leases_on_the_last_pushed_commit() -> Result<()> {
+        let (test_repo, mut stack_manager, _) = setup().await?;
+        let repo = test_repo.git_repo();
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.create_file("file1.txt", "content1")?;
+        test_repo.commit("feat: add file1")?;
+        stack_manager.create_stack("my-stack").await?;
+        stack_manager.push_stack().await?;
+        let pushed = repo.get_commit_hash_for_branch("feature-1")?;
+        let remote_sha = |manager: &mut StackManager| -> Result<Option<String>> {
+            Ok(manager.get_or_load_current_stack()?.branches["feature-1"]
+                .remote_sha
+                .clone())
+        };
+        assert_eq!(remote_sha(&mut stack_manager)?, Some(pushed.clone()));
+
+        // Someone else pushes and an unrelated fetch refreshes origin/feature-1, which
+        // a bare --force-with-lease would accept without another look
+        test_repo.create_branch("elsewhere")?;
+        test_repo.create_file("other.txt", "other")?;
+        test_repo.commit("feat: pushed from elsewhere")?;
+        test_repo.run(&["push", "origin", "elsewhere:feature-1"])?;
+        test_repo.run(&["fetch", "origin"])?;
+        test_repo.checkout("feature-1")?;
+
+        test_repo.create_file("file1.txt", "amended")?;
+        stack_manager.amend_changes(None).await?;
+        let lease = format!("--force-with-lease=feature-1:{}", pushed);
+        assert!(repo
+            .run(&["push", &lease, "origin", "feature-1:feature-1"])
+            .unwrap_err()
+            .to_string()
+            .contains("stale info"));
+
+        stack_manager.push_stack().await?;
+        let amended = repo.get_commit_hash_for_branch("feature-1")?;
+        assert_eq!(remote_sha(&mut stack_manager)?, Some(amended.clone()));
+        assert!(repo
+            .run(&["ls-remote", "origin", "refs/heads/feature-1"])?
+            .starts_with(&amended));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_frozen_branch_follows_its_remote_tip() -> Result<()> {
+        let (test_repo, mut stack_manager, _) = setup().await?;
+        let repo = test_repo.git_repo();
+
+        test_repo.create_branch("shared")?;
+        test_repo.create_file("shared.txt", "shared")?;
+        test_repo.commit("feat: shared work")?;
+        stack_manager.create_stack("collab").await?;
+        test_repo.create_branch("mine")?;
+        test_repo.create_file("mine.txt", "mine")?;
+        test_repo.commit("feat: my work")?;
+        stack_manager.add_branch_to_stack(None).await?;
+        stack_manager.push_stack().await?;
+
+        assert!(stack_manager
+            .set_branch_frozen("unknown", true)
+            .await
+            .is_err());
+        stack_manager.set_branch_frozen("shared", true).await?;
+        assert_eq!(
+            stack_manager.get_or_load_current_stack()?.branches["shared"].state,
+            BranchState::Frozen
+        );
+
+        // A teammate pushes to the frozen branch
+        test_repo.checkout("shared")?;
+        test_repo.create_branch("teammate")?;
+        test_repo.create_file("teammate.txt", "teammate")?;
+        test_repo.commit("feat: teammate work")?;
+        test_repo.run(&["push", "origin", "teammate:shared"])?;
+        let teammate_tip = repo.get_commit_hash_for_branch("teammate")?;
+        test_repo.checkout("mine")?;
+
+        stack_manager.sync_with_remote().await?;
+        assert_eq!(repo.get_commit_hash_for_branch("shared")?, teammate_tip);
+        assert!(repo.is_ancestor(&teammate_tip, "mine"));
+        let stack = stack_manager.get_or_load_current_stack()?;
+        assert_eq!(stack.branches["shared"].commit_hash, teammate_tip);
+
+        // Frozen branches are never rewritten or pushed
+        test_repo.checkout("shared")?;
+        test_repo.create_file("shared.txt", "amended")?;
+        assert!(stack_manager.amend_changes(None).await.is_err());
+        test_repo.run(&["checkout", "--", "shared.txt"])?;
+        test_repo.create_file("local.txt", "local")?;
+        test_repo.commit("feat: local only")?;
+        stack_manager.push_stack().await?;
+        assert!(repo
+            .run(&["ls-remote", "origin", "refs/heads/shared"])?
+            .starts_with(&teammate_tip));
+
+        stack_manager.set_branch_frozen("shared", false).await?;
+        stack_manager.push_stack().await?;
+        let local_tip = repo.get_commit_hash_for_branch("shared")?;
+        assert!(repo
+            .run(&["ls-remote", "origin", "refs/heads/shared"])?
+            .starts_with(&local_tip));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_branch_lifecycle_follows_push_and_mr_state() -> Result<()> {
+        let (test_repo, mut stack_manager, mrs) = setup().await?;
+        let repo = test_repo.git_repo();
+        let state = |stack_manager: &mut StackManager, branch: &str| -> Result<BranchState> {
+            Ok(stack_manager.get_or_load_current_stack()?.branches[branch].state)
+        };
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.commit("feat: level 1")?;
+        stack_manager.create_stack("lifecycle").await?;
+        test_repo.create_branch("feature-2")?;
+        test_repo.commit("feat: level 2")?;
+        stack_manager.add_branch_to_stack(Some("feature-1")).await?;
+        assert_eq!(
+            state(&mut stack_manager, "feature-1")?,
+            BranchState::Unpushed
+        );
+
+        // Nothing is on the remote to hold back yet
+        let error = stack_manager
+            .set_branch_paused("feature-1", true)
+            .await
+            .unwrap_err();
+        assert!(
+            error.to_string().contains("can't become paused"),
+            "{}",
+            error
+        );
+
+        stack_manager
+            .push_stack_with_options(&PushOptions {
+                no_mr: true,
+                ..Default::default()
+            })
+            .await?;
+        assert_eq!(state(&mut stack_manager, "feature-1")?, BranchState::Pushed);
+        stack_manager.push_stack().await?;
+        assert_eq!(state(&mut stack_manager, "feature-1")?, BranchState::MrOpen);
+
+        // Marking the MR as a draft on GitLab shows up on the next refresh
+        let iid = |stack_manager: &mut StackManager, branch: &str| -> Result<MrIid> {
+            Ok(stack_manager.get_or_load_current_stack()?.branches[branch]
+                .mr_iid
+                .unwrap())
+        };
+        let top = iid(&mut stack_manager, "feature-2")?;
+        mrs.lock().unwrap().get_mut(&top).unwrap().title = "Draft: level 2".to_string();
+        stack_manager.show_status().await?;
+        assert_eq!(
+            state(&mut stack_manager, "feature-2")?,
+            BranchState::MrDraft
+        );
+
+        // A paused level keeps its remote tip until resumed
+        stack_manager.set_branch_paused("feature-1", true).await?;
+        assert!(stack_manager
+            .set_branch_frozen("feature-1", true)
+            .await
+            .is_err());
+        let pushed_tip = repo.get_commit_hash_for_branch("feature-1")?;
+        test_repo.checkout("feature-1")?;
+        test_repo.create_file("more.txt", "more")?;
+        test_repo.commit("feat: more level 1")?;
+        stack_manager.push_stack().await?;
+        assert!(repo
+            .run(&["ls-remote", "origin", "refs/heads/feature-1"])?
+            .starts_with(&pushed_tip));
+        assert_eq!(state(&mut stack_manager, "feature-1")?, BranchState::Paused);
+
+        stack_manager.set_branch_paused("feature-1", false).await?;
+        assert_eq!(state(&mut stack_manager, "feature-1")?, BranchState::MrOpen);
+        stack_manager.push_stack().await?;
+        let local_tip = repo.get_commit_hash_for_branch("feature-1")?;
+        assert!(repo
+            .run(&["ls-remote", "origin", "refs/heads/feature-1"])?
+            .starts_with(&local_tip));
+
+        // Merged is final
+        let bottom = iid(&mut stack_manager, "feature-1")?;
+        mrs.lock().unwrap().get_mut(&bottom).unwrap().state = "merged".to_string();
+        stack_manager.show_status().await?;
+        assert_eq!(
+            state(&mut stack_manager, "feature-1")?,
+            BranchState::MrMerged
+        );
+        assert!(stack_manager
+            .set_branch_paused("feature-1", true)
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_private_levels_stay_local_until_published() -> Result<()> {
+        let (test_repo, mut stack_manager, mrs) = setup().await?;
+        let git = test_repo.git_repo();
+
+        for level in 1..=3 {
+            test_repo.create_branch(&format!("feature-{}", level))?;
+            test_repo.commit(&format!("feat: level {}", level))?;
+            if level == 1 {
+                stack_manager.create_stack("private").await?;
+            } else {
+                let parent = format!("feature-{}", level - 1);
+                stack_manager.add_branch_to_stack(Some(&parent)).await?;
+            }
+        }
+
+        // Everything from feature-2 up stays local, new levels on top included
+        stack_manager.set_branch_private("feature-2").await?;
+        test_repo.create_branch("feature-4")?;
+        test_repo.commit("feat: level 4")?;
+        stack_manager.add_branch_to_stack(Some("feature-3")).await?;
+        stack_manager.push_stack().await?;
+
+        let stack = stack_manager.get_or_load_current_stack()?;
+        assert!(git.has_remote_branch("feature-1"));
+        for private in ["feature-2", "feature-3", "feature-4"] {
+            assert_eq!(stack.branches[private].state, BranchState::LocalOnly);
+            assert!(!git.has_remote_branch(private), "{}", private);
+            assert!(stack.branches[private].mr_iid.is_none(), "{}", private);
+        }
+        let bottom = stack.branches["feature-1"].mr_iid.unwrap();
+        let description = mrs.lock().unwrap()[&bottom].description.clone().unwrap();
+        assert!(!description.contains("feature-2"), "{}", description);
+
+        // Publishing feature-3 takes feature-2 along and leaves feature-4 private
+        stack_manager.publish_branch("feature-3").await?;
+        let stack = stack_manager.get_or_load_current_stack()?;
+        for published in ["feature-2", "feature-3"] {
+            assert_eq!(stack.branches[published].state, BranchState::MrOpen);
+            assert!(git.has_remote_branch(published));
+            assert!(stack.branches[published].mr_iid.is_some());
+        }
+        assert_eq!(stack.branches["feature-4"].state, BranchState::LocalOnly);
+        assert!(!git.has_remote_branch("feature-4"));
+
+        // A level with an open MR can't go private again
+        let error = stack_manager
+            .set_branch_private("feature-3")
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("already published"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mr_drift_between_local_state_and_gitlab() -> Result<()> {
+        let (test_repo, mut stack_manager, _) = setup().await?;
+        let repo = test_repo.git_repo();
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.create_file("file1.txt", "content1")?;
+        test_repo.commit("feat: add file1")?;
+        stack_manager.create_stack("my-stack").await?;
+        stack_manager.push_stack().await?;
+        let stack = stack_manager.get_or_load_current_stack()?;
+        let pushed = repo.get_commit_hash_for_branch("feature-1")?;
+        let in_sync = MrStatusInfo {
+            iid: stack.branches["feature-1"].mr_iid.unwrap(),
+            state: "opened".to_string(),
+            target_branch: Some("main".to_string()),
+            stale: false,
+            conflicts: false,
+            title: stack.branches["feature-1"].mr_title.clone(),
+            head_sha: Some(pushed.clone()),
+        };
+        assert!(stack_manager
+            .mr_drift(&stack, "feature-1", &in_sync)
+            .is_empty());
+
+        let edited = MrStatusInfo {
+            target_branch: Some("develop".to_string()),
+            title: Some("Edited on GitLab".to_string()),
+            ..in_sync.clone()
+        };
+        assert_eq!(
+            stack_manager.mr_drift(&stack, "feature-1", &edited),
+            [
+                MrDrift::Target {
+                    gitlab: "develop".to_string()
+                },
+                MrDrift::Title
+            ]
+        );
+        let merged = MrStatusInfo {
+            state: "merged".to_string(),
+            ..edited
+        };
+        assert!(stack_manager
+            .mr_drift(&stack, "feature-1", &merged)
+            .is_empty());
+
+        let pushed_elsewhere = MrStatusInfo {
+            head_sha: Some("0123456789abcdef0123456789abcdef01234567".to_string()),
+            ..in_sync.clone()
+        };
+        assert_eq!(
+            stack_manager.mr_drift(&stack, "feature-1", &pushed_elsewhere),
+            [MrDrift::Behind]
+        );
+
+        test_repo.create_file("file1.txt", "more")?;
+        test_repo.commit("feat: more")?;
+        assert_eq!(
+            stack_manager.mr_drift(&stack, "feature-1", &in_sync),
+            [MrDrift::Unpushed]
+        );
+
+        // Rewriting the pushed commit needs a force-push
+        test_repo.run(&["reset", "--hard", "HEAD~2"])?;
+        test_repo.create_file("file1.txt", "rewritten")?;
+        test_repo.commit("feat: rewritten file1")?;
+        assert_eq!(
+            stack_manager.mr_drift(&stack, "feature-1", &in_sync),
+            [MrDrift::Diverged]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_title_sync_modes() -> Result<()> {
+        // (mode, expected feature-1 title after a manual edit, expected feature-2 title
+        // after its commit is reworded)
+        let cases = [
+            (
+                TitleSync::FollowCommit,
+                "[Stack: my-stack] feat: add file1",
+                "[Stack: my-stack] feat: reworded file2",
+            ),
+            (
+                TitleSync::PrefixOnly,
+                "Hand-written title",
+                "[Stack: my-stack] feat: reworded file2",
+            ),
+            (
+                TitleSync::Manual,
+                "Hand-written title",
+                "[Stack: my-stack] feat: add file2",
+            ),
+        ];
+
+        for (mode, expected_1, expected_2) in cases {
+            let test_repo = TestRepo::new()?;
+            let mut config = TrainConfig::default();
+            config.conflict_resolution.auto_force_push_after_rebase = true;
+            config.editor.default_editor = "true".to_string();
+            config.mr.title_sync = mode;
+            let mock_gitlab = MockGitLab::new();
+            let mrs = mock_gitlab.merge_requests.clone();
+            let mut stack_manager = StackManager::new_with_config(
+                config,
+                Some(test_repo.git_repo().clone()),
+                Some(Box::new(mock_gitlab) as Box<dyn GitLabApi + Send + Sync>),
+            )
+            .await?;
+
+            test_repo.create_branch("feature-1")?;
+            test_repo.create_file("file1.txt", "content1")?;
+            test_repo.commit("feat: add file1")?;
+            stack_manager.create_stack("my-stack").await?;
+            test_repo.create_branch("feature-2")?;
+            test_repo.create_file("file2.txt", "content2")?;
+            test_repo.commit("feat: add file2")?;
+            stack_manager.add_branch_to_stack(Some("feature-1")).await?;
+            stack_manager.push_stack().await?;
+
+            let stack = stack_manager.get_or_load_current_stack()?;
+            let (iid_1, iid_2) = (
+                stack.branches["feature-1"].mr_iid.unwrap(),
+                stack.branches["feature-2"].mr_iid.unwrap(),
+            );
+            mrs.lock().unwrap().get_mut(&iid_1).unwrap().title = "Hand-written title".to_string();
+            stack_manager
+                .amend_changes(Some("feat: reworded file2"))
+                .await?;
+            stack_manager.push_stack().await?;
+
+            let mrs = mrs.lock().unwrap();
+            assert_eq!(mrs[&iid_1].title, expected_1, "{:?}", mode);
+            assert_eq!(mrs[&iid_2].title, expected_2, "{:?}", mode);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_status_lists_review_apps_in_tree_order() -> Result<()> {
+        let test_repo = TestRepo::new()?;
+        let mut config = TrainConfig::default();
+        config.conflict_resolution.auto_force_push_after_rebase = true;
+        config.editor.default_editor = "true".to_string();
+        let mock_gitlab = MockGitLab::new();
+        mock_gitlab.environments.lock().unwrap().insert(
+            "feature-2".to_string(),
+            vec![Environment {
+                name: "review/feature-2".to_string(),
+                external_url: Some("https://feature-2.review.example.com".to_string()),
+            }],
+        );
+        let queries = mock_gitlab.environment_queries.clone();
+        let mut stack_manager = StackManager::new_with_config(
+            config,
+            Some(test_repo.git_repo().clone()),
+            Some(Box::new(mock_gitlab) as Box<dyn GitLabApi + Send + Sync>),
+        )
+        .await?;
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.create_file("file1.txt", "content1")?;
+        test_repo.commit("feat: add file1")?;
+        stack_manager.create_stack("my-stack").await?;
+        test_repo.create_branch("feature-2")?;
+        test_repo.create_file("file2.txt", "content2")?;
+        test_repo.commit("feat: add file2")?;
+        stack_manager.add_branch_to_stack(Some("feature-1")).await?;
+
+        // Levels without MRs have nothing for reviewers to open yet
+        stack_manager.show_status().await?;
+        assert!(queries.lock().unwrap().is_empty());
+
+        stack_manager.push_stack().await?;
+        stack_manager.show_status().await?;
+        assert_eq!(*queries.lock().unwrap(), vec!["feature-1", "feature-2"]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_land_squash_all() -> Result<()> {
+        let test_repo = TestRepo::new()?;
+        let mut config = TrainConfig::default();
+        config.conflict_resolution.auto_force_push_after_rebase = true;
+        config.editor.default_editor = "true".to_string();
+        let mock_gitlab = MockGitLab::new();
+        let mrs = mock_gitlab.merge_requests.clone();
+        let unapproved = mock_gitlab.unapproved.clone();
+        let notes = mock_gitlab.notes.clone();
+        let mut stack_manager = StackManager::new_with_config(
+            config,
+            Some(test_repo.git_repo().clone()),
+            Some(Box::new(mock_gitlab) as Box<dyn GitLabApi + Send + Sync>),
+        )
+        .await?;
+        let repo = test_repo.git_repo();
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.create_file("file1.txt", "content1")?;
+        test_repo.commit("feat: add file1")?;
+        stack_manager.create_stack("my-stack").await?;
+        for level in 2..=3 {
+            let parent = format!("feature-{}", level - 1);
+            test_repo.create_branch(&format!("feature-{}", level))?;
+            test_repo.create_file(&format!("file{}.txt", level), "content")?;
+            test_repo.commit(&format!("feat: add file{}", level))?;
+            stack_manager.add_branch_to_stack(Some(&parent)).await?;
+        }
+        stack_manager.push_stack().await?;
+
+        let stack = stack_manager.get_or_load_current_stack()?;
+        let iid = |branch: &str| stack.branches[branch].mr_iid.unwrap();
+        let options = LandOptions {
+            squash_all: true,
+            per_level: false,
+            squash: false,
+            delete_source_branch: false,
+        };
+
+        // An unapproved level blocks the whole landing
+        unapproved.lock().unwrap().push(iid("feature-2"));
+        assert!(stack_manager.land_stack(&options).await.is_err());
+        assert!(mrs.lock().unwrap().values().all(|mr| mr.state == "opened"));
+
+        unapproved.lock().unwrap().clear();
+        stack_manager.land_stack(&options).await?;
+
+        // The bottom branch now holds the whole stack as one commit on the base
+        let count = repo.run(&["rev-list", "--count", "origin/main..origin/feature-1"])?;
+        assert_eq!(count.trim(), "1");
+        assert_eq!(
+            repo.get_commit_hash_for_branch("origin/feature-1^{tree}")?,
+            repo.get_commit_hash_for_branch("feature-3^{tree}")?
+        );
+
+        let mrs = mrs.lock().unwrap();
+        assert_eq!(mrs[&iid("feature-1")].state, "merged");
+        assert_eq!(mrs[&iid("feature-2")].state, "closed");
+        assert_eq!(mrs[&iid("feature-3")].state, "closed");
+        let reference = format!("Landed as part of !{}", iid("feature-1"));
+        let mut noted: Vec<MrIid> = notes
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, body)| body.contains(&reference))
+            .map(|(iid, _)| *iid)
+            .collect();
+        noted.sort();
+        let mut expected = vec![iid("feature-2"), iid("feature-3")];
+        expected.sort();
+        assert_eq!(noted, expected);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_branch_naming_scheme() -> Result<()> {
+        let test_repo = TestRepo::new()?;
+        let mut config = TrainConfig::default();
+        config.editor.default_editor = "true".to_string();
+        config.branch.name_template = Some("{user}/{stack}/{slug}".to_string());
+        config.branch.enforcement = NameEnforcement::Reject;
+        let mut stack_manager =
+            StackManager::new_offline(config.clone(), test_repo.git_repo().clone()).await?;
+
+        test_repo.create_branch("test/naming/base-work")?;
+        test_repo.commit("feat: base work")?;
+        stack_manager.create_stack("naming").await?;
+
+        // Generated names follow the scheme and stack on the current branch
+        stack_manager.create_branch("Add login page!", None).await?;
+        assert_eq!(
+            test_repo.git_repo().get_current_branch()?,
+            "test/naming/add-login-page"
+        );
+        let stack = stack_manager.get_or_load_current_stack()?;
+        assert_eq!(
+            stack.branches["test/naming/add-login-page"]
+                .parent
+                .as_deref(),
+            Some("test/naming/base-work")
+        );
+
+        // Hand-made names are rejected...
+        test_repo.create_branch("my_feature")?;
+        test_repo.commit("feat: more")?;
+        assert!(stack_manager.add_branch_to_stack(None).await.is_err());
+
+        // ...or renamed to fit
+        config.branch.enforcement = NameEnforcement::Fix;
+        let mut stack_manager =
+            StackManager::new_offline(config, test_repo.git_repo().clone()).await?;
+        stack_manager.add_branch_to_stack(None).await?;
+        assert_eq!(
+            test_repo.git_repo().get_current_branch()?,
+            "test/naming/my-feature"
+        );
+        let stack = stack_manager.get_or_load_current_stack()?;
+        assert!(stack.branches.contains_key("test/naming/my-feature"));
+        assert!(!stack.branches.contains_key("my_feature"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bump_adds_release_level_on_top() -> Result<()> {
+        let (test_repo, mut stack_manager, mrs) = setup().await?;
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.create_file(
+            "Cargo.toml",
+            "[package]\nname = \"app\"\nversion = \"1.2.3\"\n\n[dependencies]\nserde = { version = \"1.0\" }\n",
+        )?;
+        test_repo.create_file("package.json", "{\n  \"version\": \"1.2.3\"\n}\n")?;
+        test_repo.commit("feat: add manifests")?;
+        stack_manager.create_stack("release").await?;
+        stack_manager.push_stack().await?;
+
+        stack_manager.bump_version(BumpLevel::Minor, false).await?;
+
+        let branch = "test/release/release-1-3-0";
+        assert_eq!(test_repo.git_repo().get_current_branch()?, branch);
+        let cargo = fs::read_to_string(test_repo.path().join("Cargo.toml"))?;
+        assert!(cargo.contains("version = \"1.3.0\""));
+        assert!(cargo.contains("serde = { version = \"1.0\" }"));
+        let package = fs::read_to_string(test_repo.path().join("package.json"))?;
+        assert!(package.contains("\"version\": \"1.3.0\""));
+
+        let stack = stack_manager.get_or_load_current_stack()?;
+        assert_eq!(stack.branches[branch].parent.as_deref(), Some("feature-1"));
+        let iid = stack.branches[branch].mr_iid.expect("bump MR opened");
+        let mrs = mrs.lock().unwrap();
+        assert_eq!(mrs[&iid].title, "[Stack: release] chore: release 1.3.0");
+        assert_eq!(mrs[&iid].target_branch, "feature-1");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mr_target_history_and_manual_retarget() -> Result<()> {
+        let (test_repo, mut stack_manager, mrs) = setup().await?;
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.commit("feat: one")?;
+        stack_manager.create_stack("my-stack").await?;
+        test_repo.create_branch("feature-2")?;
+        test_repo.commit("feat: two")?;
+        stack_manager.add_branch_to_stack(Some("feature-1")).await?;
+        stack_manager.push_stack().await?;
+
+        let stack = stack_manager.get_or_load_current_stack()?;
+        let history = &stack.branches["feature-2"].target_history;
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].from, None);
+        assert_eq!(history[0].to, "feature-1");
+        assert!(stack_manager
+            .find_unexpected_retargets(&stack)
+            .await
+            .is_empty());
+
+        // Someone retargets the MR by hand on GitLab
+        let iid = stack.branches["feature-2"].mr_iid.unwrap();
+        mrs.lock().unwrap().get_mut(&iid).unwrap().target_branch = "main".to_string();
+
+        let retargets = stack_manager.find_unexpected_retargets(&stack).await;
+        assert_eq!(retargets.len(), 1);
+        assert_eq!(retargets[0].branch, "feature-2");
+        assert_eq!(retargets[0].expected, "feature-1");
+        assert_eq!(retargets[0].actual, "main");
+
+        // Pushing again records the manual target as acknowledged
+        stack_manager.push_stack().await?;
+        let stack = stack_manager.get_or_load_current_stack()?;
+        let last = stack.branches["feature-2"].target_history.last().unwrap();
+        assert_eq!(last.from.as_deref(), Some("feature-1"));
+        assert_eq!(last.to, "main");
+        assert!(stack_manager
+            .find_unexpected_retargets(&stack)
+            .await
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_verify_runs_every_level_in_a_worktree() -> Result<()> {
+        let (test_repo, mut stack_manager, _mrs) = setup().await?;
+
+        for level in 1..=3 {
+            test_repo.create_branch(&format!("feature-{}", level))?;
+            let file = if level == 2 { "bug.txt" } else { "ok.txt" };
+            test_repo.create_file(file, &format!("level {}", level))?;
+            test_repo.commit(&format!("feat: level {}", level))?;
+            if level == 1 {
+                stack_manager.create_stack("my-stack").await?;
+            } else {
+                let parent = format!("feature-{}", level - 1);
+                stack_manager.add_branch_to_stack(Some(&parent)).await?;
+            }
+        }
+        // Uncommitted work stays untouched by the worktree checkouts
+        test_repo.create_file("scratch.txt", "wip")?;
+
+        let err = stack_manager
+            .verify_stack(Some("test ! -f bug.txt"))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("2 of 3 stack levels failed"));
+        assert_eq!(test_repo.git_repo().get_current_branch()?, "feature-3");
+        assert!(test_repo.path().join("scratch.txt").exists());
+        let worktrees = test_repo.git_repo().run(&["worktree", "list"])?;
+        assert_eq!(worktrees.lines().count(), 1);
+
+        let results = stack_manager.verify_stack(Some("test -f ok.txt")).await?;
+        let branches: Vec<&str> = results.iter().map(|(b, _)| b.as_str()).collect();
+        assert_eq!(branches, ["feature-1", "feature-2", "feature-3"]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bisect_finds_offending_level() -> Result<()> {
+        let (test_repo, mut stack_manager, _mrs) = setup().await?;
+
+        for level in 1..=5 {
+            test_repo.create_branch(&format!("feature-{}", level))?;
+            let file = if level == 3 { "bug.txt" } else { "ok.txt" };
+            test_repo.create_file(file, &format!("level {}", level))?;
+            test_repo.commit(&format!("feat: level {}", level))?;
+            if level == 1 {
+                stack_manager.create_stack("my-stack").await?;
+            } else {
+                let parent = format!("feature-{}", level - 1);
+                stack_manager.add_branch_to_stack(Some(&parent)).await?;
+            }
+        }
+
+        let culprit = stack_manager
+            .bisect_stack("test ! -f bug.txt", None)
+            .await?;
+        assert_eq!(culprit.as_deref(), Some("feature-3"));
+        assert_eq!(test_repo.git_repo().get_current_branch()?, "feature-5");
+
+        let culprit = stack_manager
+            .bisect_stack("test ! -f bug.txt", Some("feature-2"))
+            .await?;
+        assert_eq!(culprit, None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_git_commands_are_audited() -> Result<()> {
+        let (test_repo, mut stack_manager, _mrs) = setup().await?;
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.create_file("file1.txt", "content1")?;
+        test_repo.commit("feat: add file1")?;
+        stack_manager.create_stack("my-stack").await?;
+        assert!(test_repo
+            .git_repo()
+            .run(&["rev-parse", "no-such-ref"])
+            .is_err());
+
+        let audit_log = test_repo.path().join(".git/train/audit.log");
+        assert_eq!(test_repo.git_repo().audit_log_path(), audit_log.as_path());
+        let audit = fs::read_to_string(audit_log)?;
+        assert!(audit.contains("exit=0"));
+        assert!(audit.contains("git checkout -b feature-1"));
+        assert!(audit
+            .lines()
+            .any(|line| line.contains("git rev-parse no-such-ref") && !line.contains("exit=0")));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_follow_head_to_owning_stack() -> Result<()> {
+        let test_repo = TestRepo::new()?;
+        let mut config = TrainConfig::default();
+        config.git.auto_switch_stack = AutoSwitchMode::Always;
+        let mut stack_manager = StackManager::new_with_config(
+            config,
+            Some(test_repo.git_repo().clone()),
+            Some(Box::new(MockGitLab::new()) as Box<dyn GitLabApi + Send + Sync>),
+        )
+        .await?;
+
+        test_repo.create_branch("owner-alpha-1")?;
+        test_repo.commit("feat: alpha")?;
+        stack_manager.create_stack("owner-alpha").await?;
+
+        test_repo.checkout("main")?;
+        test_repo.create_branch("owner-beta-1")?;
+        test_repo.commit("feat: beta")?;
+        stack_manager.create_stack("owner-beta").await?;
+
+        // HEAD moves to alpha's branch behind git-train's back
+        test_repo.checkout("owner-alpha-1")?;
+        stack_manager.follow_head_to_owning_stack()?;
+        assert_eq!(
+            stack_manager.get_or_load_current_stack()?.name,
+            "owner-alpha"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pinned_stack_leaves_active_stack_alone() -> Result<()> {
+        let test_repo = TestRepo::new()?;
+        let mut stack_manager =
+            StackManager::new_offline(TrainConfig::default(), test_repo.git_repo().clone()).await?;
+
+        test_repo.create_branch("pinned-alpha-1")?;
+        test_repo.commit("feat: alpha")?;
+        stack_manager.create_stack("pinned-alpha").await?;
+
+        test_repo.checkout("main")?;
+        test_repo.create_branch("pinned-beta-1")?;
+        test_repo.commit("feat: beta")?;
+        stack_manager.create_stack("pinned-beta").await?;
+
+        let current_file = test_repo.path().join(".git/train/current.json");
+        let active_id = fs::read_to_string(&current_file)?;
+
+        // A fresh process given `--stack pinned-alpha`
+        let mut scripted =
+            StackManager::new_offline(TrainConfig::default(), test_repo.git_repo().clone()).await?;
+        scripted.pin_stack("pinned-alpha")?;
+        assert_eq!(scripted.get_or_load_current_stack()?.name, "pinned-alpha");
+        scripted
+            .set_update_strategy(Some(UpdateStrategy::Merge))
+            .await?;
+        scripted.record_current_branch()?;
+
+        assert_eq!(fs::read_to_string(&current_file)?, active_id);
+        let alpha = scripted.get_or_load_current_stack()?;
+        assert_eq!(alpha.update_strategy, UpdateStrategy::Merge);
+        assert!(scripted.pin_stack("no-such-stack").is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fork_stack_copies_branches() -> Result<()> {
+        let (test_repo, mut stack_manager, mrs) = setup().await?;
+
+        test_repo.create_branch("fork-1")?;
+        test_repo.commit("feat: fork level 1")?;
+        stack_manager.create_stack("original").await?;
+        test_repo.create_branch("fork-2")?;
+        test_repo.commit("feat: fork level 2")?;
+        stack_manager.add_branch_to_stack(Some("fork-1")).await?;
+        stack_manager.push_stack().await?;
+        let original_mrs = mrs.lock().unwrap().len();
+
+        stack_manager.fork_stack("alt", None, None).await?;
+
+        let fork = stack_manager.get_or_load_current_stack()?;
+        assert_eq!(fork.name, "alt");
+        assert_eq!(test_repo.git_repo().get_current_branch()?, "fork-2-alt");
+        let level_2 = &fork.branches["fork-2-alt"];
+        assert_eq!(level_2.parent.as_deref(), Some("fork-1-alt"));
+        assert_eq!(level_2.mr_iid, None);
+        assert_eq!(
+            test_repo
+                .git_repo()
+                .get_commit_hash_for_branch("fork-2-alt")?,
+            test_repo.git_repo().get_commit_hash_for_branch("fork-2")?
+        );
+
+        // The original stack keeps its MRs, and the fork opens its own
+        stack_manager.switch_stack("original", None).await?;
+        let original = stack_manager.get_or_load_current_stack()?;
+        assert!(original.branches.values().all(|b| b.mr_iid.is_some()));
+        stack_manager.switch_stack("alt", Some(true)).await?;
+        stack_manager.push_stack().await?;
+        assert_eq!(mrs.lock().unwrap().len(), original_mrs + 2);
+
+        // Existing branch names are never clobbered
+        assert!(stack_manager
+            .fork_stack("again", Some(""), None)
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stack_is_archived_once_every_level_is_merged() -> Result<()> {
+        let (test_repo, mut stack_manager, mrs) = setup().await?;
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.commit("feat: first")?;
+        stack_manager.create_stack("done").await?;
+        test_repo.create_branch("feature-2")?;
+        test_repo.commit("feat: second")?;
+        stack_manager.add_branch_to_stack(Some("feature-1")).await?;
+        stack_manager.push_stack().await?;
+
+        let stack = stack_manager.get_or_load_current_stack()?;
+        let iid = |branch: &str| stack.branches[branch].mr_iid.unwrap();
+        mrs.lock()
+            .unwrap()
+            .get_mut(&iid("feature-1"))
+            .unwrap()
+            .state = "merged".to_string();
+        stack_manager.show_status().await?;
+        let progress = stack_manager.get_or_load_current_stack()?;
+        assert_eq!(progress.merge_progress(), (1, 2));
+        assert!(!progress.archived);
+
+        mrs.lock()
+            .unwrap()
+            .get_mut(&iid("feature-2"))
+            .unwrap()
+            .state = "merged".to_string();
+        stack_manager.show_status().await?;
+        let done = stack_manager.get_or_load_current_stack()?;
+        assert_eq!(done.merge_progress(), (2, 2));
+        assert!(done.archived);
+        stack_manager.list_stacks(&ListOptions::default()).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_serve_answers_json_requests_over_a_socket() -> Result<()> {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+        let (test_repo, mut stack_manager, _mrs) = setup().await?;
+        test_repo.create_branch("feature-1")?;
+        test_repo.commit("feat: first")?;
+        stack_manager.create_stack("served").await?;
+        test_repo.create_branch("feature-2")?;
+        test_repo.commit("feat: second")?;
+        stack_manager.add_branch_to_stack(Some("feature-1")).await?;
+
+        let (unknown, shutdown) =
+            serve::handle_line(&mut stack_manager, r#"{"id":1,"method":"bogus"}"#).await;
+        assert!(!shutdown);
+        assert!(unknown.error.unwrap().message.contains("Unknown method"));
+        let (invalid, _) = serve::handle_line(&mut stack_manager, "not json").await;
+        assert!(invalid.error.is_some());
+
+        let socket_dir = tempfile::tempdir()?;
+        let socket = socket_dir.path().join("serve.sock");
+        let client = async {
+            let stream = loop {
+                match tokio::net::UnixStream::connect(&socket).await {
+                    Ok(stream) => break stream,
+                    Err(_) => tokio::time::sleep(std::time::Duration::from_millis(10)).await,
+                }
+            };
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            let mut responses = Vec::new();
+            for request in [
+                r#"{"id":1,"method":"status"}"#,
+                r#"{"id":2,"method":"push","params":{"no_mr":true}}"#,
+                r#"{"id":3,"method":"shutdown"}"#,
+            ] {
+                writer
+                    .write_all(format!("{}\n", request).as_bytes())
+                    .await?;
+                let line = lines.next_line().await?.unwrap();
+                responses.push(serde_json::from_str::<serde_json::Value>(&line)?);
+            }
+            anyhow::Ok(responses)
+        };
+        let (served, responses) = tokio::join!(serve::serve(stack_manager, &socket), client);
+        served?;
+        let responses = responses?;
+
+        let status = &responses[0]["result"];
+        assert_eq!(responses[0]["id"], 1);
+        assert_eq!(status["name"], "served");
+        let names: Vec<&str> = status["branches"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|b| b["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["feature-1", "feature-2"]);
+        assert_eq!(status["branches"][1]["parent"], "feature-1");
+
+        assert_eq!(responses[1]["result"]["ok"], true);
+        assert!(test_repo.git_repo().has_remote_branch("feature-2"));
+        assert_eq!(responses[2]["id"], 3);
+        assert!(!socket.exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_status_watch_highlights_changes_between_refreshes() -> Result<()> {
+        let (test_repo, mut stack_manager, mrs) = setup().await?;
+        let interval = std::time::Duration::from_secs(10);
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.commit("feat: first")?;
+        stack_manager.create_stack("watched").await?;
+        stack_manager.push_stack().await?;
+
+        let (first, changes) = stack_manager.watch_frame(None, interval).await?;
+        assert!(changes.is_empty());
+        let (unchanged, changes) = stack_manager.watch_frame(Some(&first), interval).await?;
+        assert!(changes.is_empty(), "{:?}", changes);
+
+        // Someone lands work on the base, the MR merges and the level gets a child
+        test_repo.checkout("main")?;
+        test_repo.commit("chore: upstream work")?;
+        test_repo.run(&["push", "origin", "main"])?;
+        test_repo.checkout("feature-1")?;
+        let iid = stack_manager.get_or_load_current_stack()?.branches["feature-1"]
+            .mr_iid
+            .unwrap();
+        mrs.lock().unwrap().get_mut(&iid).unwrap().state = "merged".to_string();
+        test_repo.create_branch("feature-2")?;
+        test_repo.commit("feat: second")?;
+        stack_manager.add_branch_to_stack(Some("feature-1")).await?;
+
+        let (_, changes) = stack_manager
+            .watch_frame(Some(&unchanged), interval)
+            .await?;
+        assert_eq!(
+            changes,
+            [
+                "1 new commit on base 'main'".to_string(),
+                format!("MR !{} for 'feature-1': opened → merged", iid),
+                "'feature-2' joined the stack".to_string(),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_merged_levels_retarget_children_before_deletion() -> Result<()> {
+        let (test_repo, mut stack_manager, mrs) = setup().await?;
+        let interval = std::time::Duration::from_secs(10);
+
+        for level in 1..=3 {
+            test_repo.create_branch(&format!("feature-{}", level))?;
+            test_repo.commit(&format!("feat: level {}", level))?;
+            if level == 1 {
+                stack_manager.create_stack("retarget").await?;
+            } else {
+                let parent = format!("feature-{}", level - 1);
+                stack_manager.add_branch_to_stack(Some(&parent)).await?;
+            }
+        }
+        stack_manager.push_stack().await?;
+        let stack = stack_manager.get_or_load_current_stack()?;
+        let iid = |branch: &str| stack.branches[branch].mr_iid.unwrap();
+
+        // Landing the bottom level retargets its child, then deletes the branch
+        stack_manager
+            .land_stack(&LandOptions {
+                delete_source_branch: true,
+                ..Default::default()
+            })
+            .await?;
+        assert_eq!(mrs.lock().unwrap()[&iid("feature-1")].state, "merged");
+        assert_eq!(mrs.lock().unwrap()[&iid("feature-2")].target_branch, "main");
+        assert!(!test_repo.git_repo().has_remote_branch("feature-1"));
+        assert_eq!(
+            stack_manager.get_or_load_current_stack()?.branches["feature-1"].state,
+            BranchState::MrMerged
+        );
+
+        // A merge done in the GitLab UI is picked up by the next watch refresh
+        let (first, _) = stack_manager.watch_frame(None, interval).await?;
+        mrs.lock()
+            .unwrap()
+            .get_mut(&iid("feature-2"))
+            .unwrap()
+            .state = "merged".to_string();
+        let (_, changes) = stack_manager.watch_frame(Some(&first), interval).await?;
+        assert_eq!(mrs.lock().unwrap()[&iid("feature-3")].target_branch, "main");
+        assert!(
+            changes.contains(
+                &"Retargeted MR for 'feature-3' to 'main' after 'feature-2' merged".to_string()
+            ),
+            "{:?}",
+            changes
+        );
+        let stack = stack_manager.get_or_load_current_stack()?;
+        assert_eq!(stack.branches["feature-3"].last_set_target(), Some("main"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_codeowners_become_reviewers_of_new_mrs() -> Result<()> {
+        let test_repo = TestRepo::new()?;
+        let mut config = TrainConfig::default();
+        config.mr.codeowner_reviewers = 2;
+        let gitlab = MockGitLab::new();
+        let reviewers = gitlab.reviewers.clone();
+        let mut stack_manager = StackManager::new_with_config(
+            config,
+            Some(test_repo.git_repo().clone()),
+            Some(Box::new(gitlab)),
+        )
+        .await?;
+
+        fs::create_dir(test_repo.path().join(".gitlab"))?;
+        test_repo.create_file(
+            ".gitlab/CODEOWNERS",
+            "* @test-user\n*.rs @backend/team @alice @bob @carol\n\n[Docs]\ndocs/ @writer\n",
+        )?;
+        test_repo.commit("chore: add code owners")?;
+        test_repo.run(&["push", "origin", "main"])?;
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.create_file("lib.rs", "fn main() {}")?;
+        test_repo.commit("feat: code")?;
+        stack_manager.create_stack("owners").await?;
+        test_repo.create_branch("feature-2")?;
+        test_repo.create_file("README.md", "readme")?;
+        test_repo.commit("docs: readme")?;
+        stack_manager.add_branch_to_stack(Some("feature-1")).await?;
+        stack_manager.push_stack().await?;
+
+        let stack = stack_manager.get_or_load_current_stack()?;
+        let reviewers = reviewers.lock().unwrap();
+        // The group and the over-the-cap owner are skipped
+        let iid = stack.branches["feature-1"].mr_iid.unwrap();
+        assert_eq!(reviewers[&iid], [2, 3]);
+        // The only owner of README.md is the author, who isn't asked to review
+        let iid = stack.branches["feature-2"].mr_iid.unwrap();
+        assert!(reviewers[&iid].is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_dependency_links_between_stacked_mrs() -> Result<()> {
+        let test_repo = TestRepo::new()?;
+        let mut config = TrainConfig::default();
+        config.conflict_resolution.auto_force_push_after_rebase = true;
+        config.mr.dependency_links = DependencyLinks::Description;
+        let mock = MockGitLab::new();
+        let mrs = mock.merge_requests.clone();
+        let blocks = mock.blocks.clone();
+        let mut stack_manager = StackManager::new_with_config(
+            config.clone(),
+            Some(test_repo.git_repo().clone()),
+            Some(Box::new(mock)),
+        )
+        .await?;
+
+        test_repo.create_branch("feature-1")?;
+        test_repo.commit("feat: first")?;
+        stack_manager.create_stack("linked").await?;
+        test_repo.create_branch("feature-2")?;
+        test_repo.commit("feat: second")?;
+        stack_manager.add_branch_to_stack(Some("feature-1")).await?;
+        stack_manager.push_stack().await?;
+
+        let stack = stack_manager.get_or_load_current_stack()?;
+        let iid = |branch: &str| stack.branches[branch].mr_iid.unwrap();
+        let description = |iid: MrIid| mrs.lock().unwrap()[&iid].description.clone().unwrap();
+        assert!(
+            description(iid("feature-2")).contains(&format!("Depends on !{}", iid("feature-1")))
+        );
+        assert!(
+            description(iid("feature-1")).contains(&format!("Required by !{}", iid("feature-2")))
+        );
+        assert!(blocks.lock().unwrap().is_empty());
+
+        // Switching to GitLab links drops the description references
+        config.mr.dependency_links = DependencyLinks::Api;
+        let mut stack_manager = StackManager::new_with_config(
+            config,
+            Some(test_repo.git_repo().clone()),
+            Some(Box::new(MockGitLab {
+                merge_requests: mrs.clone(),
+                blocks: blocks.clone(),
+                ..MockGitLab::new()
+            })),
+        )
+        .await?;
+        stack_manager.push_stack().await?;
+        stack_manager.push_stack().await?;
+        assert!(!description(iid("feature-2")).contains("Depends on"));
+        assert_eq!(
+            blocks.lock().unwrap().get(&iid("feature-2")),
+            Some(&vec![iid("feature-1")])
+        );
+        assert!(!blocks.lock().unwrap().contains_key(&iid("feature-1")));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_adopt_existing_branch_chain() -> Result<()> {
+        let (test_repo, mut stack_manager, _mrs) = setup().await?;
+
+        test_repo.create_branch("part-1")?;
+        test_repo.commit("feat: part 1")?;
+        test_repo.create_branch("part-2")?;
+        test_repo.commit("feat: part 2")?;
+        test_repo.create_branch("part-3")?;
+        test_repo.commit("feat: part 3")?;
+        // A branch on the base and one that forks off part-1 aren't levels of part-3
+        test_repo.checkout("main")?;
+        test_repo.create_branch("unrelated")?;
+        test_repo.commit("feat: unrelated")?;
+        test_repo.checkout("part-1")?;
+        test_repo.create_branch("side")?;
+        test_repo.commit("feat: side")?;
+        test_repo.checkout("part-3")?;
+
+        assert_eq!(stack_manager.detect_base_branch().as_deref(), Some("main"));
+        let chain = stack_manager.detect_branch_chain("part-3", "main")?;
+        assert_eq!(chain, ["part-1", "part-2", "part-3"]);
+
+        test_repo.checkout("side")?;
+        stack_manager
+            .adopt_branch_chain("adopted", &chain, "main")
+            .await?;
+        assert_eq!(test_repo.git_repo().get_current_branch()?, "side");
+
+        let stack = stack_manager.get_or_load_current_stack()?;
+        assert_eq!(stack.name, "adopted");
+        assert_eq!(stack.base_branch, "main");
+        assert_eq!(stack.branches["part-1"].parent.as_deref(), Some("main"));
+        assert_eq!(stack.branches["part-2"].parent.as_deref(), Some("part-1"));
+        assert_eq!(stack.branches["part-3"].parent.as_deref(), Some("part-2"));
+
+        // Adopted branches aren't offered again
+        assert!(stack_manager.detect_branch_chain("part-3", "main").is_err());
+        assert_eq!(stack_manager.detect_branch_chain("side", "main")?, ["side"]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_recover_stack_from_mr_description() -> Result<()> {
+        let (test_repo, mut stack_manager, mrs) = setup().await?;
+
+        test_repo.create_branch("level-1")?;
+        test_repo.commit("feat: level 1")?;
+        stack_manager.create_stack("recoverable").await?;
+        test_repo.create_branch("level-2")?;
+        test_repo.commit("feat: level 2")?;
+        stack_manager.add_branch_to_stack(None).await?;
+        stack_manager.push_stack().await?;
+        let original = stack_manager.get_or_load_current_stack()?;
+        let top_iid = original.branches["level-2"].mr_iid.unwrap();
+
+        // Lose every bit of local metadata, leaving the empty directory a fresh
+        // git-train process would create
+        let train_dir = test_repo.path().join(".git/train");
+        fs::remove_dir_all(&train_dir)?;
+        fs::create_dir_all(&train_dir)?;
+        stack_manager.forget_current_stack();
+        assert!(stack_manager.get_or_load_current_stack().is_err());
+
+        stack_manager.recover_from_mr(top_iid, None).await?;
+        let stack = stack_manager.get_or_load_current_stack()?;
+        assert_eq!(stack.name, "recoverable");
+        assert_eq!(stack.base_branch, "main");
+        assert_eq!(stack.current_branch.as_deref(), Some("level-2"));
+        for (name, parent) in [("level-1", "main"), ("level-2", "level-1")] {
+            let branch = &stack.branches[name];
+            assert_eq!(branch.parent.as_deref(), Some(parent));
+            assert_eq!(branch.mr_iid, original.branches[name].mr_iid);
+            assert_eq!(branch.commit_hash, original.branches[name].commit_hash);
+        }
+        assert_eq!(stack.branches["level-1"].children, ["level-2"]);
+
+        // Recovering twice would put the branches in two stacks
+        assert!(stack_manager.recover_from_mr(top_iid, None).await.is_err());
+
+        // An MR without a stack table has nothing to recover from
+        mrs.lock().unwrap().get_mut(&top_iid).unwrap().description = None;
+        let error = stack_manager
+            .recover_from_mr(top_iid, Some("other"))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("no git-train stack table"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_recover_keeps_tip_of_merged_level_without_branch() -> Result<()> {
+        let (test_repo, mut stack_manager, mrs) = setup().await?;
+
+        test_repo.create_branch("level-1")?;
+        test_repo.commit("feat: level 1")?;
+        stack_manager.create_stack("half-merged").await?;
+        test_repo.create_branch("level-2")?;
+        test_repo.commit("feat: level 2")?;
+        stack_manager.add_branch_to_stack(None).await?;
+        stack_manager.push_stack().await?;
+        let original = stack_manager.get_or_load_current_stack()?;
+        let bottom_iid = original.branches["level-1"].mr_iid.unwrap();
+        let top_iid = original.branches["level-2"].mr_iid.unwrap();
+        let tip = original.branches["level-1"].commit_hash.clone();
+
+        // level-1 was merged and GitLab deleted its branch
+        {
+            let mut mrs = mrs.lock().unwrap();
+            let mr = mrs.get_mut(&bottom_iid).unwrap();
+            mr.state = "merged".to_string();
+            mr.sha = Some(tip.clone());
+        }
+        test_repo.run(&["push", "origin", "--delete", "level-1"])?;
+        test_repo.run(&["branch", "-D", "level-1"])?;
+
+        let train_dir = test_repo.path().join(".git/train");
+        fs::remove_dir_all(&train_dir)?;
+        fs::create_dir_all(&train_dir)?;
+        stack_manager.forget_current_stack();
+
+        stack_manager.recover_from_mr(top_iid, None).await?;
+        let stack = stack_manager.get_or_load_current_stack()?;
+        assert_eq!(stack.branches["level-1"].commit_hash, tip);
+        assert_eq!(stack.branches["level-1"].state, BranchState::MrMerged);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stacks_record_their_owner() -> Result<()> {
+        let (test_repo, mut stack_manager, _mrs) = setup().await?;
+
+        test_repo.create_branch("mine-1")?;
+        test_repo.commit("feat: mine")?;
+        stack_manager.create_stack("mine").await?;
+        assert_eq!(
+            stack_manager.get_or_load_current_stack()?.owner.as_deref(),
+            Some("test@example.com")
+        );
+
+        test_repo.run(&["config", "user.email", "teammate@example.com"])?;
+        test_repo.checkout("main")?;
+        test_repo.create_branch("theirs-1")?;
+        test_repo.commit("feat: theirs")?;
+        stack_manager.create_stack("theirs").await?;
+        assert_eq!(
+            stack_manager.get_or_load_current_stack()?.owner.as_deref(),
+            Some("teammate@example.com")
+        );
+
+        stack_manager
+            .list_stacks(&ListOptions {
+                mine: true,
+                ..Default::default()
+            })
+            .await?;
+        stack_manager
+            .list_stacks(&ListOptions {
+                author: Some("TEST@example.com".to_string()),
+                ..Default::default()
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_detached_head_is_reported() -> Result<()> {
+        let (test_repo, mut stack_manager, _mrs) = setup().await?;
+
+        test_repo.create_branch("detached-1")?;
+        test_repo.commit("feat: detached level")?;
+        stack_manager.create_stack("detached").await?;
+        test_repo.git_repo().run(&["checkout", "--detach"])?;
+
+        // Read-only commands work from the saved metadata
+        stack_manager.show_status().await?;
+        stack_manager.list_stacks(&ListOptions::default()).await?;
+        stack_manager.record_current_branch()?;
+        assert_eq!(
+            stack_manager
+                .get_or_load_current_stack()?
+                .current_branch
+                .as_deref(),
+            Some("detached-1")
+        );
+
+        // Anything that needs a branch explains how to get back onto one
+        let err = stack_manager
+            .commit_changes(Some("wip"), false)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("HEAD is detached"), "{}", err);
+        assert!(err.to_string().contains("git switch"), "{}", err);
+        assert!(test_repo.git_repo().detached_head()?.is_some());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hooks_keep_metadata_fresh() -> Result<()> {
+        let (test_repo, mut stack_manager, _mrs) = setup().await?;
+        let hooks_dir = test_repo.path().join(".git/hooks");
+        fs::create_dir_all(&hooks_dir)?;
+        fs::write(hooks_dir.join("post-checkout"), "#!/bin/sh\necho mine\n")?;
+
+        let installed = hooks::install(test_repo.git_repo(), false)?;
+        assert!(installed.contains(&("post-commit".to_string(), HookInstall::Installed)));
+        assert!(installed.contains(&("post-checkout".to_string(), HookInstall::Skipped)));
+        let installed = hooks::install(test_repo.git_repo(), true)?;
+        assert!(installed.contains(&("post-commit".to_string(), HookInstall::Updated)));
+        assert!(installed.contains(&("post-checkout".to_string(), HookInstall::Replaced)));
+        assert!(hooks_dir.join("post-checkout.pre-git-train").exists());
+        assert!(fs::read_to_string(hooks_dir.join("post-rewrite"))?.contains("git-train hook"));
+
+        test_repo.create_branch("hooked-1")?;
+        test_repo.commit("feat: hooked 1")?;
+        stack_manager.create_stack("hooked").await?;
+        test_repo.create_branch("hooked-2")?;
+        test_repo.commit("feat: hooked 2")?;
+        stack_manager.add_branch_to_stack(Some("hooked-1")).await?;
+        let recorded_1 = test_repo
+            .git_repo()
+            .get_commit_hash_for_branch("hooked-1")?;
+
+        // A plain commit on the top branch is picked up
+        let new_tip = test_repo.commit("feat: hooked 2 follow-up")?;
+        stack_manager.refresh_from_hook("post-commit")?;
+        let stack = stack_manager.get_or_load_current_stack()?;
+        assert_eq!(stack.branches["hooked-2"].commit_hash, new_tip);
+
+        // Amending the lower branch keeps the commit its child was built on
+        test_repo.checkout("hooked-1")?;
+        test_repo.git_repo().run(&[
+            "commit",
+            "--amend",
+            "--allow-empty",
+            "-m",
+            "feat: hooked 1 v2",
+        ])?;
+        stack_manager.refresh_from_hook("post-rewrite")?;
+        let stack = stack_manager.get_or_load_current_stack()?;
+        assert_eq!(stack.branches["hooked-1"].commit_hash, recorded_1);
+        assert_eq!(stack.current_branch.as_deref(), Some("hooked-1"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_remote_only_base_branch() -> Result<()> {
+        let (test_repo, mut stack_manager, _mrs) = setup().await?;
+        let git = test_repo.git_repo();
+
+        test_repo.create_branch("remote-base-1")?;
+        test_repo.commit("feat: remote base 1")?;
+        git.run(&["branch", "-D", "main"])?;
+        assert!(!git.has_local_branch("main"));
+
+        stack_manager
+            .create_stack_with_base("remote-base", Some("origin/main"))
+            .await?;
+        assert_eq!(
+            stack_manager.get_or_load_current_stack()?.base_branch,
+            "main"
+        );
+        test_repo.create_branch("remote-base-2")?;
+        test_repo.commit("feat: remote base 2")?;
+        stack_manager.add_branch_to_stack(None).await?;
+        stack_manager.show_status().await?;
+        assert!(!git.has_local_branch("main"));
+
+        // Move origin/main on without a local main
+        git.run(&["checkout", "-b", "upstream-work", "origin/main"])?;
+        let upstream = test_repo.commit("chore: upstream change")?;
+        git.run(&["push", "origin", "upstream-work:main"])?;
+        test_repo.checkout("remote-base-2")?;
+        git.run(&["branch", "-D", "upstream-work"])?;
+
+        // Sync creates the local base only because it has to update it
+        stack_manager.sync_with_remote().await?;
+        assert!(git.has_local_branch("main"));
+        assert!(git.is_ancestor(&upstream, "remote-base-1"));
+        assert!(git.is_ancestor(&upstream, "remote-base-2"));
+
+        assert!(stack_manager
+            .create_stack_with_base("missing-base", Some("origin/nope"))
+            .await
+            .is_err());
+
+        // A base that was never fetched is looked up on origin
+        git.run(&["push", "origin", "remote-base-1:release/1.2"])?;
+        git.run(&["update-ref", "-d", "refs/remotes/origin/release/1.2"])?;
+        test_repo.create_branch("release-fix")?;
+        test_repo.commit("fix: on release")?;
+        stack_manager
+            .create_stack_with_base("release-stack", Some("release/1.2"))
+            .await?;
+        assert_eq!(
+            stack_manager.get_or_load_current_stack()?.base_branch,
+            "release/1.2"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_oversized_levels_are_flagged() -> Result<()> {
+        let test_repo = TestRepo::new()?;
+        let mut config = TrainConfig::default();
+        config.mr.max_changed_lines = 5;
+        let mut stack_manager =
+            StackManager::new_offline(config, test_repo.git_repo().clone()).await?;
+
+        test_repo.create_branch("size-small")?;
+        test_repo.create_file("small.txt", "one line\n")?;
+        test_repo.commit("feat: small")?;
+        stack_manager.create_stack("sizes").await?;
+
+        test_repo.create_branch("size-big")?;
+        fs::create_dir_all(test_repo.path().join("api"))?;
+        fs::create_dir_all(test_repo.path().join("ui"))?;
+        test_repo.create_file("api/handlers.txt", &"handler\n".repeat(6))?;
+        test_repo.create_file("ui/view.txt", "view\n")?;
+        test_repo.commit("feat: big")?;
+        stack_manager
+            .add_branch_to_stack(Some("size-small"))
+            .await?;
+
+        let stack = stack_manager.get_or_load_current_stack()?;
+        let oversized = stack_manager.oversized_levels(&stack);
+        assert_eq!(oversized.len(), 1);
+        let (branch, files) = &oversized[0];
+        assert_eq!(branch, "size-big");
+        assert_eq!(files.len(), 2);
+        stack_manager.show_status().await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_continue_reports_interrupted_command() -> Result<()> {
+        let (test_repo, mut stack_manager, _) = setup().await?;
+
+        let record = InterruptedCommand {
+            interrupted_at: chrono::Utc::now(),
+            command: "git-train sync".to_string(),
+            git_command: "git fetch origin".to_string(),
+        };
+        let journal = test_repo.path().join(".git/train/interrupted.json");
+        fs::write(&journal, serde_json::to_string(&record)?)?;
+
+        // Nothing was left half-done, so continue only reports and clears the record
+        stack_manager.continue_conflict_resolution().await?;
+        assert!(!journal.exists());
+        assert!(test_repo.git_repo().take_interrupted()?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_commit_trailers_record_stack_position() -> Result<()> {
+        let test_repo = TestRepo::new()?;
+        let mut config = TrainConfig::default();
+        config.git.commit_trailers = true;
+        config.editor.default_editor = "true".to_string();
+        let mut stack_manager =
+            StackManager::new_offline(config, test_repo.git_repo().clone()).await?;
+
+        test_repo.create_branch("trailer-1")?;
+        test_repo.commit("feat: first")?;
+        stack_manager.create_stack("trailers").await?;
+        test_repo.create_branch("trailer-2")?;
+        test_repo.commit("feat: second")?;
+        stack_manager.add_branch_to_stack(Some("trailer-1")).await?;
+
+        test_repo.create_file("second.txt", "second")?;
+        stack_manager
+            .commit_changes(Some("feat: more second"), false)
+            .await?;
+
+        let stack = stack_manager.get_or_load_current_stack()?;
+        let trailers = |repo: &TestRepo| {
+            repo.git_repo()
+                .run(&["log", "-1", "--format=%(trailers:only)"])
+                .unwrap()
+        };
+        let expected = format!("Stack-Id: {}\nStack-Position: 2", stack.id);
+        assert_eq!(trailers(&test_repo).trim(), expected);
+
+        // Amending replaces the trailers instead of appending a second set
+        test_repo.create_file("second-more.txt", "more")?;
+        stack_manager.amend_changes(None).await?;
+        assert_eq!(trailers(&test_repo).trim(), expected);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_commit_reword_starts_from_previous_message() -> Result<()> {
+        let (test_repo, mut stack_manager, _mrs) = setup().await?;
+
+        test_repo.create_branch("tmpl-1")?;
+        test_repo.commit("feat: first")?;
+        stack_manager.create_stack("templates").await?;
+        test_repo.create_branch("tmpl-2")?;
+        test_repo.commit("feat: second")?;
+        stack_manager.add_branch_to_stack(Some("tmpl-1")).await?;
+
+        // Accept the template untouched: the stack comment lines must not end up in the commit
+        test_repo.run(&["config", "core.editor", "true"])?;
+
+        test_repo.create_file("second.txt", "second")?;
+        stack_manager.commit_changes(None, true).await?;
+
+        let messages = test_repo
+            .git_repo()
+            .run(&["log", "-2", "--format=%s", "tmpl-2"])?;
+        assert_eq!(messages.trim(), "feat: second\nfeat: second");
+        let message = test_repo.git_repo().run(&["log", "-1", "--format=%B"])?;
+        assert!(!message.contains('#'));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_conflict_detection() -> Result<()> {
+        // Custom setup for this test to control config
+        let test_repo = TestRepo::new()?;
+        let mut config = TrainConfig::default();
+        config.conflict_resolution.auto_resolve_strategy = AutoResolveStrategy::Never;
+        config.conflict_resolution.auto_force_push_after_rebase = true;
+        config.git.verify_signatures = false;
+        config.editor.default_editor = "true".to_string();
+        let mock_gitlab = MockGitLab::new();
+        let _mrs = mock_gitlab.merge_requests.clone();
+
+        let gitlab_client = Some(Box::new(mock_gitlab) as Box<dyn GitLabApi + Send + Sync>);
+        let mut stack_manager = StackManager::new_with_config(
+            config,
+            Some(test_repo.git_repo().clone()),
+            gitlab_client,
+        )
+        .await?;
+
+        // 1. Setup a stack
+        test_repo.create_branch("feature-1")?;
+        test_repo.create_file("file.txt", "line 1\nline 2\nline 3")?;
+        test_repo.commit("feat: add file")?;
+        stack_manager.create_stack("my-stack").await?;
+
+        // 2. Modify file on main to create a conflict on a specific line
+        test_repo.checkout("main")?;
+        test_repo.create_file("file.txt", "line 1\nline 2 - main\nline 3")?;
+        test_repo.commit("refactor: update file on main")?;
+        test_repo.run(&["push", "origin", "main"])?;
+
+        // 3. Modify the same line on the feature branch
+        test_repo.checkout("feature-1")?;
+        test_repo.create_file("file.txt", "line 1\nline 2 - feature\nline 3")?;
+        test_repo.commit("feat: update file on feature")?;
+
+        // 4. Try to sync by propagating changes from main, which should cause a conflict
+        let result = stack_manager.sync_with_remote().await;
+
+        // 5. Assert that a conflict was detected and the correct error is returned
+        assert!(result.is_err());
+        if let Some(err) = result.err() {
             let err_string = err.to_string();
             assert!(err_string.contains("Manual conflict resolution required"));
             assert!(err_string.contains("rebase of feature-1 onto main"));
         }
 
-        // 6. Check git state
+        // 6. Check git state: the conflicting rebase is left in progress
         let conflict_resolver = stack_manager.get_conflict_resolver();
         let state = conflict_resolver.get_git_state()?;
-        assert!(matches!(state, gittrain::conflict::GitState::Conflicted));
+        assert!(matches!(state, gittrain::conflict::GitState::Rebasing));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_repo_discovery_from_subdirectory() -> Result<()> {
+        let (test_repo, _stack_manager, _mrs) = setup().await?;
+        let nested = test_repo.path().join("src").join("deep");
+        fs::create_dir_all(&nested)?;
+
+        let repo = GitRepository::discover(&nested)?;
+        let root = fs::canonicalize(test_repo.path())?;
+        assert_eq!(fs::canonicalize(repo.root())?, root);
+        assert!(repo.git_dir().is_absolute());
+        assert_eq!(fs::canonicalize(repo.train_dir())?, root.join(".git/train"));
+
+        // State written through the discovered repo lands in the repo, not the cwd
+        let mut config = TrainConfig::default();
+        config.editor.default_editor = "true".to_string();
+        let mut stack_manager = StackManager::new_offline(config, repo).await?;
+        test_repo.create_branch("discovered-1")?;
+        test_repo.commit("discovered commit")?;
+        stack_manager.create_stack("discovered-stack").await?;
+        let stack_id = fs::read_to_string(root.join(".git/train/current.json"))?;
+        let stack_json = fs::read_to_string(root.join(format!(".git/train/{}.json", stack_id)))?;
+        assert!(stack_json.contains("discovered-stack"));
+
+        let outside = tempfile::tempdir()?;
+        assert!(GitRepository::discover(outside.path()).is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_linked_worktrees_keep_their_own_current_stack() -> Result<()> {
+        let (test_repo, mut stack_manager, _mrs) = setup().await?;
+        test_repo.create_branch("main-1")?;
+        test_repo.commit("feat: main worktree")?;
+        stack_manager.create_stack("main-stack").await?;
+
+        let worktree_dir = tempfile::tempdir()?;
+        let worktree_path = worktree_dir.path().join("linked");
+        test_repo.run(&[
+            "worktree",
+            "add",
+            "-b",
+            "linked-1",
+            &worktree_path.to_string_lossy(),
+            "main",
+        ])?;
+        let linked = GitRepository::new(&worktree_path)?;
+        assert_eq!(
+            fs::canonicalize(linked.train_dir())?,
+            fs::canonicalize(test_repo.git_repo().train_dir())?
+        );
+        assert_ne!(
+            linked.current_stack_file(),
+            test_repo.git_repo().current_stack_file()
+        );
+
+        let mut config = TrainConfig::default();
+        config.editor.default_editor = "true".to_string();
+        let mut linked_manager = StackManager::new_offline(config.clone(), linked.clone()).await?;
+        // A new worktree starts without an active stack instead of borrowing the main one
+        assert!(linked_manager.get_or_load_current_stack().is_err());
+
+        fs::write(worktree_path.join("linked.txt"), "linked")?;
+        linked.run(&["add", "linked.txt"])?;
+        linked.run(&["commit", "-m", "feat: linked worktree"])?;
+        linked_manager.create_stack("linked-stack").await?;
+
+        // Each worktree keeps its own active stack, while the stacks themselves are shared
+        let mut main_manager =
+            StackManager::new_offline(config.clone(), test_repo.git_repo().clone()).await?;
+        assert_eq!(main_manager.get_or_load_current_stack()?.name, "main-stack");
+        let mut linked_manager = StackManager::new_offline(config, linked).await?;
+        assert_eq!(
+            linked_manager.get_or_load_current_stack()?.name,
+            "linked-stack"
+        );
+        let shared = fs::read_dir(test_repo.git_repo().train_dir())?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().extension().is_some_and(|e| e == "json"))
+            .count();
+        assert_eq!(
+            shared, 3,
+            "two stacks plus the main worktree's current.json"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_benchmark_large_stack() -> Result<()> {
+        let mut config = TrainConfig::default();
+        config.editor.default_editor = "true".to_string();
+
+        let report = gittrain::bench::run_benchmark(&config, 12).await?;
+
+        assert_eq!(report.branches, 12);
+        let phases: Vec<&str> = report.timings.iter().map(|(p, _)| p.as_str()).collect();
+        assert_eq!(
+            phases,
+            vec!["build", "status", "push", "sync", "push (after sync)"]
+        );
+
+        // Generous ceiling to catch pathological regressions (e.g. quadratic git calls)
+        let sync = report.timings[3].1;
+        assert!(
+            sync < std::time::Duration::from_secs(120),
+            "sync of 12 branches took {:?}",
+            sync
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_base_branch_probes_remote_head_then_candidates() -> Result<()> {
+        let test_repo = TestRepo::new()?;
+        let mut config = TrainConfig::default();
+        config.git.base_branch_candidates = vec!["trunk".to_string(), "release/*".to_string()];
+
+        test_repo.create_branch("release/1.0")?;
+        test_repo.commit("chore: cut release")?;
+        test_repo.create_branch("develop")?;
+        test_repo.commit("chore: start develop")?;
+        test_repo.run(&["push", "origin", "develop"])?;
+        test_repo.run(&["branch", "-D", "main"])?;
+
+        let mut stack_manager = StackManager::new_with_config(
+            config,
+            Some(test_repo.git_repo().clone()),
+            Some(Box::new(MockGitLab::new())),
+        )
+        .await?;
+
+        // No remote HEAD and no 'trunk': the glob candidate wins over origin/main
+        test_repo.create_branch("feature-a")?;
+        test_repo.commit("feat: a")?;
+        stack_manager.create_stack("from-glob").await?;
+        let stack = stack_manager.get_or_load_current_stack()?;
+        assert_eq!(stack.base_branch, "release/1.0");
+
+        // origin's HEAD takes precedence over the candidate list
+        test_repo.run(&[
+            "symbolic-ref",
+            "refs/remotes/origin/HEAD",
+            "refs/remotes/origin/develop",
+        ])?;
+        test_repo.checkout("develop")?;
+        test_repo.create_branch("feature-b")?;
+        test_repo.commit("feat: b")?;
+        stack_manager.create_stack("from-head").await?;
+        let stack = stack_manager.get_or_load_current_stack()?;
+        assert_eq!(stack.base_branch, "develop");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cwd_flag_acts_on_other_repository() -> Result<()> {
+        let (test_repo, mut stack_manager, _mrs) = setup().await?;
+        test_repo.create_branch("feature-1")?;
+        test_repo.commit("feat: one")?;
+        stack_manager.create_stack("elsewhere").await?;
+        let stack_id = stack_manager.get_or_load_current_stack()?.id;
+
+        // Run from a directory outside the repository, with its own empty home
+        let outside = tempfile::tempdir()?;
+        let output = Command::new(env!("CARGO_BIN_EXE_git-train"))
+            .arg("--cwd")
+            .arg(test_repo.path())
+            .arg("list")
+            .current_dir(outside.path())
+            .env("HOME", outside.path())
+            .env("XDG_CONFIG_HOME", outside.path())
+            .output()?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(
+            output.status.success(),
+            "git-train failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert!(
+            stdout.contains(stack_id.short()),
+            "unexpected output: {}",
+            stdout
+        );
 
         Ok(())
     }